@@ -0,0 +1,121 @@
+// alert_history.rs - Window with the history of triggered alerts: filters by symbol and direction, re-arm
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::alerts::direction;
+use crate::axes_util::format_price;
+use crate::db::{AlertEvent, Database};
+use crate::symbol_info::guess_price_decimals;
+use chrono::{Local, TimeZone};
+use eframe::egui;
+
+pub enum HistoryAction {
+    Rearm(AlertEvent),
+    Clear,
+}
+
+#[derive(Default)]
+pub struct AlertHistory {
+    pub open: bool,
+    events: Vec<AlertEvent>,
+    symbol_filter: Option<String>,
+    // Some(true) - only crossings upwards
+    direction_filter: Option<bool>,
+}
+
+fn format_time(ts: i64) -> String {
+    Local
+        .timestamp_millis_opt(ts)
+        .single()
+        .map(|dt| dt.format("%d %b %Y %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+fn price(value: f64) -> String {
+    format_price(value, guess_price_decimals(value))
+}
+
+impl AlertHistory {
+    pub fn refresh(&mut self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        self.events = db.get_alert_history()?;
+        Ok(())
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.events.iter().map(|e| e.symbol.clone()).collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    /// Triggered alerts, newest first. Returns the action chosen by the user.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<HistoryAction> {
+        let mut action = None;
+        let mut open = self.open;
+        let symbols = self.symbols();
+        egui::Window::new("Alert history")
+            .open(&mut open)
+            .default_height(360.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("alert_history_symbol")
+                        .selected_text(self.symbol_filter.as_deref().unwrap_or("all symbols"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.symbol_filter, None, "all symbols");
+                            for symbol in symbols {
+                                ui.selectable_value(
+                                    &mut self.symbol_filter,
+                                    Some(symbol.clone()),
+                                    symbol,
+                                );
+                            }
+                        });
+                    ui.selectable_value(&mut self.direction_filter, None, "both");
+                    ui.selectable_value(&mut self.direction_filter, Some(true), "above");
+                    ui.selectable_value(&mut self.direction_filter, Some(false), "below");
+                    ui.separator();
+                    if ui
+                        .add_enabled(!self.events.is_empty(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        action = Some(HistoryAction::Clear);
+                    }
+                });
+                ui.separator();
+                let events: Vec<&AlertEvent> = self
+                    .events
+                    .iter()
+                    .rev()
+                    .filter(|e| self.symbol_filter.as_ref().is_none_or(|s| *s == e.symbol))
+                    .filter(|e| self.direction_filter.is_none_or(|above| above == e.above))
+                    .collect();
+                if events.is_empty() {
+                    ui.label("No triggered alerts");
+                    return;
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("alert_history")
+                        .striped(true)
+                        .num_columns(6)
+                        .show(ui, |ui| {
+                            for event in events {
+                                ui.label(format_time(event.time));
+                                ui.label(&event.symbol);
+                                ui.label(direction(event));
+                                ui.label(price(event.level));
+                                ui.label(format!("last {}", price(event.price)));
+                                if ui
+                                    .small_button("re-arm")
+                                    .on_hover_text("Set this alert again")
+                                    .clicked()
+                                {
+                                    action = Some(HistoryAction::Rearm(event.clone()));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        self.open = open;
+        action
+    }
+}
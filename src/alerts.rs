@@ -0,0 +1,288 @@
+// alerts.rs - Price alert lines: stored per symbol, triggered on a close crossing, draggable on the chart
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::format_price;
+use crate::datawindow::DataWindow;
+use crate::db::{AlertEvent, Database, PriceAlert};
+use crate::drawing_util;
+use crate::symbol_info::{guess_price_decimals, SymbolInfo};
+use eframe::egui::{self, Align2, Color32, FontId, Rect, Stroke};
+use std::error::Error;
+
+// расстояние до линии в пикселях, с которого ее можно захватить
+const GRAB_DISTANCE: f32 = 4.0;
+
+/// Price with the decimals of the symbol tick size, when it is known.
+fn format_alert_price(info: Option<&SymbolInfo>, price: f64) -> String {
+    info.map_or_else(
+        || format_price(price, guess_price_decimals(price)),
+        |info| info.format_price(price),
+    )
+}
+
+/// Price of the y coordinate in the price area (inverse of `create_scale_price_fn`).
+fn price_at_y(y: f32, rect: Rect, data_window: &DataWindow) -> f64 {
+    let (min_price, max_price) = data_window.price;
+    let range = (max_price - min_price).max(1e-9);
+    min_price + (rect.bottom() - y) as f64 / rect.height() as f64 * range
+}
+
+pub fn direction(event: &AlertEvent) -> &'static str {
+    if event.above {
+        "above"
+    } else {
+        "below"
+    }
+}
+
+#[derive(Default)]
+pub struct PriceAlerts {
+    alerts: Vec<PriceAlert>,
+    // символ, для которого загружены алерты
+    loaded_for: Option<String>,
+    // (index, price under the cursor) while a line is dragged
+    dragging: Option<(usize, f64)>,
+    last_close: Option<f64>,
+    // changed by the user, saved on the next refresh
+    dirty: bool,
+    // tick size of the symbol, alert prices are rounded to it
+    info: Option<SymbolInfo>,
+}
+
+impl PriceAlerts {
+    /// Metadata of the chart symbol; new and moved alerts snap to its tick size.
+    pub fn set_symbol_info(&mut self, info: Option<SymbolInfo>) {
+        self.info = info;
+    }
+
+    fn round(&self, price: f64) -> f64 {
+        self.info
+            .as_ref()
+            .map_or(price, |info| info.round_price(price))
+    }
+
+    /// Loads the alerts of `symbol`, saves edits and fires alerts crossed by the last
+    /// close since the previous call. Triggered alerts go to the alert history; returns
+    /// their messages.
+    pub fn refresh(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        data_window: &DataWindow,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        if self.loaded_for.as_deref() != Some(symbol) {
+            self.alerts = db.get_alerts(symbol)?;
+            self.loaded_for = Some(symbol.to_string());
+            self.dragging = None;
+            self.last_close = None;
+            self.dirty = false;
+        }
+        let mut messages = Vec::new();
+        let mut events = Vec::new();
+        let close = data_window.bars.last().map(|b| b.close);
+        // пока линию тащат, индексы алертов не должны меняться - проверка откладывается
+        let previous = self.last_close.filter(|_| self.dragging.is_none());
+        if let (Some(previous), Some(close)) = (previous, close) {
+            self.alerts.retain(|alert| {
+                let crossed = previous != close
+                    && alert.price >= previous.min(close)
+                    && alert.price <= previous.max(close);
+                if crossed {
+                    let event = AlertEvent {
+                        symbol: symbol.to_string(),
+                        level: alert.price,
+                        above: close > previous,
+                        time: chrono::Utc::now().timestamp_millis(),
+                        price: close,
+                    };
+                    messages.push(format!(
+                        "Alert: {} crossed {} {} (last {})",
+                        symbol,
+                        direction(&event),
+                        format_alert_price(self.info.as_ref(), alert.price),
+                        format_alert_price(self.info.as_ref(), close)
+                    ));
+                    events.push(event);
+                }
+                !crossed
+            });
+            if !events.is_empty() {
+                self.dirty = true;
+                db.add_alert_events(&events)?;
+            }
+        }
+        if self.dragging.is_none() {
+            self.last_close = close;
+        }
+        if self.dirty && self.dragging.is_none() {
+            db.set_alerts(symbol, &self.alerts)?;
+            self.dirty = false;
+        }
+        Ok(messages)
+    }
+
+    pub fn add(&mut self, price: f64) {
+        self.alerts.push(PriceAlert {
+            price: self.round(price),
+            created: chrono::Utc::now().timestamp_millis(),
+        });
+        self.dirty = true;
+    }
+
+    /// Sets the alert of a history entry again, for the chart symbol or stored for another one.
+    pub fn rearm(&mut self, db: &Database, event: &AlertEvent) -> Result<(), Box<dyn Error>> {
+        if self.loaded_for.as_deref() == Some(event.symbol.as_str()) {
+            self.add(event.level);
+            return Ok(());
+        }
+        let mut alerts = db.get_alerts(&event.symbol)?;
+        alerts.push(PriceAlert {
+            price: event.level,
+            created: chrono::Utc::now().timestamp_millis(),
+        });
+        db.set_alerts(&event.symbol, &alerts)
+    }
+
+    fn line_at(&self, y: f32, scale_price: &impl Fn(f64) -> f32) -> Option<usize> {
+        self.alerts
+            .iter()
+            .enumerate()
+            .map(|(i, alert)| (i, (scale_price(alert.price) - y).abs()))
+            .filter(|(_, distance)| *distance <= GRAB_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Alt+click adds an alert at the clicked price; dragging a line moves it, the
+    /// stored alert is updated on release. With `magnet` the price snaps to the OHLC
+    /// of the bar under the cursor. Returns `true` when the input was used.
+    pub fn handle_input(
+        &mut self,
+        response: &egui::Response,
+        price_rect: Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+        magnet: bool,
+    ) -> bool {
+        let pointer = response.interact_pointer_pos().or(response.hover_pos());
+        let price_at = |pos: egui::Pos2| {
+            let y = pos.y.clamp(price_rect.top(), price_rect.bottom());
+            magnet
+                .then(|| drawing_util::snap_to_ohlc(pos, price_rect, data_window, scale_price))
+                .flatten()
+                .unwrap_or_else(|| price_at_y(y, price_rect, data_window))
+        };
+        let alt = response.ctx.input(|i| i.modifiers.alt);
+        if let (true, true, Some(pos)) = (alt, response.clicked(), pointer) {
+            if price_rect.contains(pos) {
+                self.add(price_at(pos));
+                return true;
+            }
+        }
+
+        if response.drag_started() {
+            let origin = response.ctx.input(|i| i.pointer.press_origin());
+            self.dragging = origin
+                .filter(|pos| price_rect.contains(*pos))
+                .and_then(|pos| self.line_at(pos.y, scale_price))
+                .map(|i| (i, self.alerts[i].price));
+        }
+        if let Some((index, _)) = self.dragging {
+            if response.dragged() {
+                if let Some(pos) = pointer {
+                    self.dragging = Some((index, self.round(price_at(pos))));
+                    response.ctx.request_repaint();
+                }
+            } else {
+                if let (Some(alert), Some((_, price))) = (self.alerts.get_mut(index), self.dragging)
+                {
+                    alert.price = price;
+                    self.dirty = true;
+                }
+                self.dragging = None;
+            }
+            return true;
+        }
+
+        if let Some(pos) = pointer.filter(|pos| price_rect.contains(*pos)) {
+            if self.line_at(pos.y, scale_price).is_some() {
+                response
+                    .ctx
+                    .set_cursor_icon(egui::CursorIcon::ResizeVertical);
+            }
+        }
+        false
+    }
+
+    /// Dashed lines with the alert price; the dragged line shows the price under the
+    /// cursor and its distance from the last close.
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        scale_price: &impl Fn(f64) -> f32,
+        font: &FontId,
+    ) {
+        let painter = ui.painter();
+        let color = Color32::from_rgb(255, 170, 60);
+        for (index, alert) in self.alerts.iter().enumerate() {
+            let dragged = self.dragging.filter(|(i, _)| *i == index);
+            let price = dragged.map_or(alert.price, |(_, price)| price);
+            let y = scale_price(price);
+            if y < rect.top() || y > rect.bottom() {
+                continue;
+            }
+            painter.extend(egui::Shape::dashed_line(
+                &[egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                Stroke::new(if dragged.is_some() { 1.5 } else { 1.0 }, color),
+                8.0,
+                4.0,
+            ));
+            let mut label = format!("alert {}", format_alert_price(self.info.as_ref(), price));
+            if let (Some(_), Some(close)) = (dragged, self.last_close) {
+                if close > 0.0 {
+                    label.push_str(&format!(" ({:+.2}%)", (price - close) / close * 100.0));
+                }
+            }
+            painter.text(
+                egui::pos2(rect.left() + 4.0, y - 2.0),
+                Align2::LEFT_BOTTOM,
+                label,
+                font.clone(),
+                color,
+            );
+        }
+    }
+
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .add_enabled(
+                self.last_close.is_some(),
+                egui::Button::new("Add at last price"),
+            )
+            .clicked()
+        {
+            if let Some(close) = self.last_close {
+                self.add(close);
+            }
+        }
+        ui.label("Alt+click on the chart adds an alert, drag a line to move it");
+        ui.separator();
+        let mut remove = None;
+        for (index, alert) in self.alerts.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format_alert_price(self.info.as_ref(), alert.price));
+                if ui.small_button("×").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove {
+            self.alerts.remove(index);
+            self.dirty = true;
+        }
+        if self.alerts.is_empty() {
+            ui.label("No alerts");
+        }
+    }
+}
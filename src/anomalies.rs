@@ -0,0 +1,176 @@
+// anomalies.rs - Scan of stored 1m klines for suspicious data: zero-volume spans, spikes, duplicates, misaligned blocks
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::compress;
+use crate::db::Database;
+use crate::fetch::KLine;
+use crate::settings::{ANOMALY_SPIKE_RATIO, ANOMALY_ZERO_VOLUME_MINUTES};
+use std::error::Error;
+
+const MINUTE_MS: i64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// At least `ANOMALY_ZERO_VOLUME_MINUTES` minutes in a row without volume.
+    ZeroVolume,
+    /// A wick further than `ANOMALY_SPIKE_RATIO` from the bar body or a jump of the
+    /// open from the previous close.
+    Spike,
+    /// The same minute stored twice, or minutes out of order.
+    DuplicateTime,
+    /// A block key off the block grid, or klines outside their block window / not on a minute.
+    Misaligned,
+}
+
+impl AnomalyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnomalyKind::ZeroVolume => "zero volume",
+            AnomalyKind::Spike => "spike",
+            AnomalyKind::DuplicateTime => "duplicate time",
+            AnomalyKind::Misaligned => "misaligned",
+        }
+    }
+}
+
+/// Suspicious range of one stored block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// Key timestamp of the block holding the range.
+    pub block_start: i64,
+    /// Open time of the first and last affected minute, ms.
+    pub from: i64,
+    pub to: i64,
+    pub detail: String,
+}
+
+fn price(value: u64) -> f64 {
+    value as f64 / 10f64.powi(crate::fetch::PRICE_MULTIPLIER as i32)
+}
+
+fn check_block(block_start: i64, block_ms: i64, klines: &[KLine], anomalies: &mut Vec<Anomaly>) {
+    let mut push = |kind, from, to, detail: String| {
+        anomalies.push(Anomaly {
+            kind,
+            block_start,
+            from,
+            to,
+            detail,
+        })
+    };
+    if block_start.rem_euclid(block_ms) != 0 {
+        let (from, to) = klines
+            .first()
+            .zip(klines.last())
+            .map_or((block_start, block_start), |(f, l)| {
+                (f.open_time, l.open_time)
+            });
+        push(
+            AnomalyKind::Misaligned,
+            from,
+            to,
+            "block key off the block grid".to_string(),
+        );
+    }
+    let outside = klines
+        .iter()
+        .filter(|k| {
+            k.open_time.rem_euclid(MINUTE_MS) != 0
+                || k.open_time < block_start
+                || k.open_time >= block_start + block_ms
+        })
+        .count();
+    if outside > 0 {
+        push(
+            AnomalyKind::Misaligned,
+            block_start,
+            block_start + block_ms - MINUTE_MS,
+            format!(
+                "{} klines outside the block window or off the minute",
+                outside
+            ),
+        );
+    }
+
+    let mut zero_start: Option<usize> = None;
+    for (i, kline) in klines.iter().enumerate() {
+        if let Some(previous) = i.checked_sub(1).map(|p| &klines[p]) {
+            if kline.open_time <= previous.open_time {
+                push(
+                    AnomalyKind::DuplicateTime,
+                    previous.open_time,
+                    kline.open_time,
+                    if kline.open_time == previous.open_time {
+                        "minute stored twice".to_string()
+                    } else {
+                        "minutes out of order".to_string()
+                    },
+                );
+            }
+            let previous_close = price(previous.close);
+            let open = price(kline.open);
+            if previous_close > 0.0
+                && (open - previous_close).abs() / previous_close > ANOMALY_SPIKE_RATIO
+            {
+                push(
+                    AnomalyKind::Spike,
+                    previous.open_time,
+                    kline.open_time,
+                    format!("open {} after close {}", open, previous_close),
+                );
+            }
+        }
+
+        let body_high = price(kline.open.max(kline.close));
+        let body_low = price(kline.open.min(kline.close));
+        let (high, low) = (price(kline.high), price(kline.low));
+        if body_low > 0.0
+            && ((high - body_high) / body_high > ANOMALY_SPIKE_RATIO
+                || (body_low - low) / body_low > ANOMALY_SPIKE_RATIO)
+        {
+            push(
+                AnomalyKind::Spike,
+                kline.open_time,
+                kline.open_time,
+                format!("high {} low {} body {}-{}", high, low, body_low, body_high),
+            );
+        }
+
+        if kline.volume == 0 {
+            zero_start.get_or_insert(i);
+        }
+        let span_ends = kline.volume != 0 || i + 1 == klines.len();
+        if let (true, Some(start)) = (span_ends, zero_start) {
+            let end = if kline.volume == 0 { i } else { i - 1 };
+            if end + 1 - start >= ANOMALY_ZERO_VOLUME_MINUTES {
+                push(
+                    AnomalyKind::ZeroVolume,
+                    klines[start].open_time,
+                    klines[end].open_time,
+                    format!("{} minutes without volume", end + 1 - start),
+                );
+            }
+            zero_start = None;
+        }
+    }
+}
+
+/// Checks every stored block of `symbol`. Blocks that cannot be read (checksum
+/// mismatch) are left to the coverage map.
+pub fn scan(db: &Database, symbol: &str) -> Result<Vec<Anomaly>, Box<dyn Error>> {
+    let block_ms = db.block_size() as i64 * MINUTE_MS;
+    let mut anomalies = Vec::new();
+    for block_start in db.block_timestamps(symbol)? {
+        let klines = match db.get_block(symbol, block_start) {
+            Ok(Some(data)) => compress::decompress_klines(&data)?,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("Skipping block {}_{}: {}", symbol, block_start, e);
+                continue;
+            }
+        };
+        check_block(block_start, block_ms, &klines, &mut anomalies);
+    }
+    Ok(anomalies)
+}
@@ -0,0 +1,152 @@
+// anomaly_report.rs - Data anomaly report window: scan of a stored symbol, re-fetch of affected ranges
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::anomalies::{self, Anomaly};
+use crate::db::Database;
+use chrono::{Local, TimeZone};
+use eframe::egui::{self, Color32};
+use std::collections::BTreeMap;
+
+/// Ranges to download again: (stale block key, first minute, last minute).
+pub struct RefetchRequest {
+    pub symbol: String,
+    pub ranges: Vec<(i64, i64, i64)>,
+}
+
+#[derive(Default)]
+pub struct AnomalyReport {
+    pub open: bool,
+    symbol: String,
+    symbols: Vec<String>,
+    anomalies: Vec<Anomaly>,
+    // символ последней проверки
+    scanned: Option<String>,
+    /// A re-fetch started from the report is running.
+    pub refetching: bool,
+}
+
+fn format_time(ts: i64) -> String {
+    Local
+        .timestamp_millis_opt(ts)
+        .single()
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn range(anomaly: &Anomaly) -> (i64, i64, i64) {
+    (anomaly.block_start, anomaly.from, anomaly.to)
+}
+
+impl AnomalyReport {
+    /// Lists the stored symbols, preselecting `symbol`.
+    pub fn refresh_symbols(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.symbols = db.symbols()?;
+        if self.symbol.is_empty() || !self.symbols.contains(&self.symbol) {
+            self.symbol = symbol.to_string();
+        }
+        Ok(())
+    }
+
+    /// Checks the blocks of the selected symbol.
+    pub fn scan(&mut self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        self.anomalies = anomalies::scan(db, &self.symbol)?;
+        self.scanned = Some(self.symbol.clone());
+        Ok(())
+    }
+
+    /// Symbol of the last scan, rescanned after a re-fetch.
+    pub fn scanned(&self) -> Option<&str> {
+        self.scanned.as_deref()
+    }
+
+    /// Returns whether a scan was requested, and the ranges to re-fetch if any.
+    pub fn show(&mut self, ctx: &egui::Context) -> (bool, Option<RefetchRequest>) {
+        let mut scan = false;
+        let mut refetch = None;
+        let mut open = self.open;
+        egui::Window::new("Data anomalies")
+            .open(&mut open)
+            .default_height(360.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("anomaly_symbol")
+                        .selected_text(&self.symbol)
+                        .show_ui(ui, |ui| {
+                            for symbol in &self.symbols {
+                                ui.selectable_value(&mut self.symbol, symbol.clone(), symbol);
+                            }
+                        });
+                    scan = ui.button("Scan").clicked();
+                    let can_refetch = !self.refetching
+                        && !self.anomalies.is_empty()
+                        && self.scanned.as_ref() == Some(&self.symbol);
+                    if ui
+                        .add_enabled(can_refetch, egui::Button::new("Re-fetch all"))
+                        .clicked()
+                    {
+                        // one range per block, covering all its anomalies
+                        let mut blocks: BTreeMap<i64, (i64, i64)> = BTreeMap::new();
+                        for anomaly in &self.anomalies {
+                            let entry = blocks
+                                .entry(anomaly.block_start)
+                                .or_insert((anomaly.from, anomaly.to));
+                            *entry = (entry.0.min(anomaly.from), entry.1.max(anomaly.to));
+                        }
+                        let ranges = blocks
+                            .into_iter()
+                            .map(|(block, (from, to))| (block, from, to))
+                            .collect();
+                        refetch = Some(RefetchRequest {
+                            symbol: self.symbol.clone(),
+                            ranges,
+                        });
+                    }
+                    if self.refetching {
+                        ui.spinner();
+                    }
+                });
+                ui.separator();
+                let Some(scanned) = &self.scanned else {
+                    ui.label("Scan checks the stored 1m data for gaps in volume, spikes, duplicates and misaligned blocks");
+                    return;
+                };
+                if self.anomalies.is_empty() {
+                    ui.label(format!("No anomalies in {}", scanned));
+                    return;
+                }
+                ui.label(format!("{}: {} anomalies", scanned, self.anomalies.len()));
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("anomalies")
+                        .striped(true)
+                        .num_columns(4)
+                        .show(ui, |ui| {
+                            for anomaly in &self.anomalies {
+                                ui.label(format!(
+                                    "{} - {}",
+                                    format_time(anomaly.from),
+                                    format_time(anomaly.to)
+                                ));
+                                ui.colored_label(Color32::from_rgb(230, 160, 60), anomaly.kind.name());
+                                ui.label(&anomaly.detail);
+                                if ui
+                                    .add_enabled(!self.refetching, egui::Button::new("re-fetch").small())
+                                    .clicked()
+                                {
+                                    refetch = Some(RefetchRequest {
+                                        symbol: scanned.clone(),
+                                        ranges: vec![range(anomaly)],
+                                    });
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        self.open = open;
+        (scan, refetch)
+    }
+}
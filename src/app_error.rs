@@ -0,0 +1,88 @@
+// app_error.rs - Errors of the data layer reported to the GUI message center, with retry actions
+// See CONVENTIONS.md for project structure and workflow
+
+use std::sync::Mutex;
+
+/// Failures that do not abort the current operation but should be visible to the
+/// user. Background threads report them with `report`, the GUI collects them with
+/// `take_reported` and shows them in the status list.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AppError {
+    #[error("Aggregation of {symbol} failed: {message}")]
+    Aggregation { symbol: String, message: String },
+    #[error("Block {symbol}_{block_start} is unreadable: {message}")]
+    CorruptedBlock {
+        symbol: String,
+        block_start: i64,
+        message: String,
+    },
+    #[error("Dropped unreadable block {key} during migration: {message}")]
+    DroppedBlock { key: String, message: String },
+    #[error("Unable to load the chart of {symbol}: {message}")]
+    DataWindow { symbol: String, message: String },
+    #[error("Live update of {symbol} failed: {message}")]
+    LiveUpdate { symbol: String, message: String },
+    #[error("Recent data of {symbol}: {message}")]
+    RecentData { symbol: String, message: String },
+    #[error("Sync of {symbol} failed: {message}")]
+    Sync { symbol: String, message: String },
+}
+
+/// What the retry button of an error does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryAction {
+    Aggregate(String),
+    RefetchBlock {
+        symbol: String,
+        block_start: i64,
+    },
+    ReloadChart,
+    /// Fetches the missing blocks of the chart symbol again.
+    SyncChart,
+}
+
+impl AppError {
+    pub fn retry(&self) -> Option<RetryAction> {
+        match self {
+            AppError::Aggregation { symbol, .. } => Some(RetryAction::Aggregate(symbol.clone())),
+            AppError::CorruptedBlock {
+                symbol,
+                block_start,
+                ..
+            } => Some(RetryAction::RefetchBlock {
+                symbol: symbol.clone(),
+                block_start: *block_start,
+            }),
+            AppError::DataWindow { .. } => Some(RetryAction::ReloadChart),
+            AppError::Sync { .. } => Some(RetryAction::SyncChart),
+            AppError::DroppedBlock { .. }
+            | AppError::LiveUpdate { .. }
+            | AppError::RecentData { .. } => None,
+        }
+    }
+}
+
+/// Entry of the status list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusMessage {
+    pub text: String,
+    pub retry: Option<RetryAction>,
+}
+
+static REPORTED: Mutex<Vec<AppError>> = Mutex::new(Vec::new());
+
+/// Logs the error and queues it for the GUI.
+pub fn report(error: AppError) {
+    tracing::warn!("{}", error);
+    if let Ok(mut reported) = REPORTED.lock() {
+        reported.push(error);
+    }
+}
+
+/// Errors reported since the last call.
+pub fn take_reported() -> Vec<AppError> {
+    REPORTED
+        .lock()
+        .map(|mut reported| std::mem::take(&mut *reported))
+        .unwrap_or_default()
+}
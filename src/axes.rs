@@ -1,15 +1,29 @@
 //axes.rs
 use crate::axes_util::{
-    choose_time_interval, deduplicate_price_labels, format_time_label, generate_price_labels,
+    choose_bar_step, choose_time_interval, deduplicate_price_labels, format_time_label,
+    generate_price_labels, AxisMode, AxisTimezone,
 };
 use crate::datawindow::DataWindow;
-use chrono::{DateTime, Datelike, Utc};
-use eframe::egui::{self, Color32, Rect, Ui};
+use crate::render::Renderer;
+use crate::settings::TimeLabelFormat;
+use crate::theme::Theme;
+use chrono::Datelike;
+use eframe::egui::{self, Color32, Rect, Stroke};
 
-pub fn draw(ui: &mut Ui, rect: Rect, data_window: &DataWindow, scale_price: &impl Fn(f64) -> f32) {
-    let painter = ui.painter();
-    let text_color = ui.style().visuals.text_color();
-    let grid_color = Color32::from_gray(60);
+#[allow(clippy::too_many_arguments)]
+pub fn draw(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &DataWindow,
+    scale_price: &impl Fn(f64) -> f32,
+    time_zone: AxisTimezone,
+    time_format: &TimeLabelFormat,
+    axis_mode: AxisMode,
+    theme: &Theme,
+) {
+    let text_color = renderer.text_color();
+    let font = theme.chart_font();
+    let grid_color = theme.grid;
 
     let volume_height = rect.height() * data_window.volume_height_ratio;
     let price_rect =
@@ -26,36 +40,29 @@ pub fn draw(ui: &mut Ui, rect: Rect, data_window: &DataWindow, scale_price: &imp
         &scale_price,
         price_rect.top(),
         price_rect.bottom(),
+        data_window.price_decimals(),
     );
-    deduplicate_price_labels(&mut price_labels_info);
+    deduplicate_price_labels(&mut price_labels_info, data_window.price_decimals());
 
     for (_price, label_text, y) in &price_labels_info {
-        painter.line_segment(
+        renderer.line_segment(
             [egui::pos2(rect.left(), *y), egui::pos2(rect.right(), *y)],
-            (0.5, grid_color),
+            Stroke::new(0.5, grid_color),
         );
 
-        let galley = painter.layout_no_wrap(
-            label_text.clone(),
-            egui::FontId::proportional(10.0),
-            text_color,
-        );
+        let text_size = renderer.text_size(label_text, &font);
         let text_rect = Rect::from_min_size(
-            egui::pos2(rect.left() + 5.0, *y - 2.0 - galley.size().y),
-            galley.size() + egui::vec2(4.0, 4.0),
+            egui::pos2(rect.left() + 5.0, *y - 2.0 - text_size.y),
+            text_size + egui::vec2(4.0, 4.0),
         );
 
         if text_rect.min.y >= price_rect.top() && text_rect.max.y <= price_rect.bottom() {
-            painter.rect_filled(
-                text_rect,
-                0.0,
-                Color32::from_rgba_premultiplied(20, 20, 20, 220),
-            );
-            painter.text(
+            renderer.rect_filled(text_rect, 0.0, theme.price_label_background);
+            renderer.text(
                 egui::pos2(rect.left() + 7.0, *y - 2.0),
                 egui::Align2::LEFT_BOTTOM,
-                label_text.clone(),
-                egui::FontId::proportional(10.0),
+                label_text,
+                &font,
                 text_color,
             );
         }
@@ -73,6 +80,19 @@ pub fn draw(ui: &mut Ui, rect: Rect, data_window: &DataWindow, scale_price: &imp
         return;
     }
 
+    if axis_mode == AxisMode::BarIndex {
+        draw_bar_labels(
+            renderer,
+            rect,
+            data_window,
+            (start as usize, end as usize),
+            time_zone,
+            time_format,
+            theme,
+        );
+        return;
+    }
+
     let time_span_ms = visible_slice.last().map(|bar| bar.time).unwrap_or(0)
         - visible_slice.first().map(|bar| bar.time).unwrap_or(0);
 
@@ -89,11 +109,14 @@ pub fn draw(ui: &mut Ui, rect: Rect, data_window: &DataWindow, scale_price: &imp
     let time_interval_ms = choose_time_interval(time_span_ms, target_lines);
 
     let first_time = visible_slice.first().map(|bar| bar.time).unwrap_or(0);
-    let first_time_rounded = first_time - first_time % time_interval_ms.max(1);
+    // Round in the display timezone so day ticks fall on its midnight
+    let offset_ms = time_zone.offset_ms(first_time);
+    let first_time_rounded =
+        first_time - (first_time + offset_ms).rem_euclid(time_interval_ms.max(1));
     let last_time = first_time + time_span_ms;
 
-    let first_dt = DateTime::<Utc>::from_timestamp_millis(first_time).unwrap_or_else(Utc::now);
-    let last_dt = DateTime::<Utc>::from_timestamp_millis(last_time).unwrap_or_else(Utc::now);
+    let first_dt = time_zone.datetime(first_time);
+    let last_dt = time_zone.datetime(last_time);
     let has_two_years = first_dt.year() != last_dt.year();
     let has_two_months = first_dt.month() != last_dt.month() || first_dt.year() != last_dt.year();
     let has_two_days = first_dt.ordinal() != last_dt.ordinal() || first_dt.year() != last_dt.year();
@@ -129,7 +152,7 @@ pub fn draw(ui: &mut Ui, rect: Rect, data_window: &DataWindow, scale_price: &imp
             if x >= rect.left() + left_margin && x <= rect.right() - right_margin {
                 if labels
                     .last()
-                    .map_or(true, |l| (x - l.2).abs() >= min_pixel_gap * 0.8)
+                    .is_none_or(|l| (x - l.2).abs() >= min_pixel_gap * 0.8)
                 {
                     labels.push((current_time_check, bar_idx, x));
                 }
@@ -147,39 +170,202 @@ pub fn draw(ui: &mut Ui, rect: Rect, data_window: &DataWindow, scale_price: &imp
         current_time_check += time_interval_ms;
     }
 
+    draw_period_boundaries(
+        renderer,
+        rect,
+        data_window,
+        start as usize,
+        end as usize,
+        time_zone,
+    );
+
     let mut last_drawn_x: Option<f32> = None;
 
     for (time_ms, _bar_idx, x) in &labels {
-        if last_drawn_x.map_or(false, |last_x| (*x - last_x).abs() < min_pixel_gap) {
+        if last_drawn_x.is_some_and(|last_x| (*x - last_x).abs() < min_pixel_gap) {
             continue;
         }
 
-        painter.line_segment(
+        renderer.line_segment(
             [egui::pos2(*x, rect.top()), egui::pos2(*x, rect.bottom())],
-            (0.5, grid_color),
+            Stroke::new(0.5, grid_color),
         );
 
-        let dt = DateTime::<Utc>::from_timestamp_millis(*time_ms).unwrap_or_else(Utc::now);
+        let dt = time_zone.datetime(*time_ms);
         let label = format_time_label(
             dt,
             time_interval_ms,
             has_two_years,
             has_two_months,
             has_two_days,
+            time_format,
         );
 
-        let galley =
-            painter.layout_no_wrap(label.clone(), egui::FontId::proportional(10.0), text_color);
-        let text_x = x - galley.size().x / 2.0;
+        let text_x = x - renderer.text_size(&label, &font).x / 2.0;
 
-        painter.text(
+        renderer.text(
             egui::pos2(text_x, rect.bottom() + 2.0),
             egui::Align2::CENTER_TOP,
-            label,
-            egui::FontId::proportional(10.0),
+            &label,
+            &font,
             text_color,
         );
 
         last_drawn_x = Some(*x);
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PeriodBoundary {
+    Day,
+    Week,
+    Month,
+}
+
+/// Draws emphasized vertical lines where a visible bar starts a new day, week
+/// (Monday) or month in the display timezone. Finer boundaries are skipped when
+/// they would be denser than `MIN_BOUNDARY_GAP` pixels on average.
+fn draw_period_boundaries(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &DataWindow,
+    start: usize,
+    end: usize,
+    time_zone: AxisTimezone,
+) {
+    const DAY_MS: i64 = 86_400_000;
+    const MIN_BOUNDARY_GAP: f32 = 12.0;
+
+    let bars = &data_window.bars[start..end];
+    let Some(first) = bars.first() else {
+        return;
+    };
+    // One offset for the whole view is enough to find day changes
+    let offset_ms = time_zone.offset_ms(first.time);
+    // day of the session open for markets with trading hours
+    let calendar = &data_window.calendar;
+    let day_of = |time: i64| {
+        let time = if calendar.is_continuous() {
+            time
+        } else {
+            calendar.session_start(time).unwrap_or(time)
+        };
+        (time + offset_ms).div_euclid(DAY_MS)
+    };
+    // 1970-01-01 was a Thursday, shift so weeks start on Monday
+    let week_of = |day: i64| (day + 3).div_euclid(7);
+
+    let mut boundaries: Vec<(usize, PeriodBoundary)> = Vec::new();
+    let mut prev_day = day_of(first.time);
+    let mut prev_month = time_zone.datetime(first.time).month();
+    for (i, bar) in bars.iter().enumerate().skip(1) {
+        let day = day_of(bar.time);
+        if day == prev_day {
+            continue;
+        }
+        let month = time_zone.datetime(bar.time).month();
+        let kind = if month != prev_month {
+            PeriodBoundary::Month
+        } else if week_of(day) != week_of(prev_day) {
+            PeriodBoundary::Week
+        } else {
+            PeriodBoundary::Day
+        };
+        boundaries.push((i, kind));
+        prev_day = day;
+        prev_month = month;
+    }
+
+    // Pick the finest boundary kind that still leaves enough room between lines
+    let min_kind = [
+        PeriodBoundary::Day,
+        PeriodBoundary::Week,
+        PeriodBoundary::Month,
+    ]
+    .into_iter()
+    .find(|kind| {
+        let count = boundaries.iter().filter(|(_, k)| k >= kind).count();
+        count == 0 || rect.width() / count as f32 >= MIN_BOUNDARY_GAP
+    });
+    let Some(min_kind) = min_kind else {
+        return;
+    };
+
+    let visible_count = bars.len() as f32;
+    for (i, kind) in boundaries {
+        if kind < min_kind {
+            continue;
+        }
+        let x = rect.left() + (i as f32 / visible_count) * rect.width() + data_window.pixel_offset;
+        if x < rect.left() || x > rect.right() {
+            continue;
+        }
+        let stroke = match kind {
+            PeriodBoundary::Day => Stroke::new(0.8, Color32::from_gray(75)),
+            PeriodBoundary::Week => Stroke::new(1.0, Color32::from_gray(95)),
+            PeriodBoundary::Month => Stroke::new(1.3, Color32::from_rgb(90, 110, 150)),
+        };
+        renderer.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            stroke,
+        );
+    }
+}
+
+/// Labels every N-th bar with its open time, N from the 1-2-5 sequence so labels
+/// stay `MIN_LABEL_GAP` apart. Steps of a whole number of bars keep the labels on the
+/// same bars while scrolling.
+fn draw_bar_labels(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &DataWindow,
+    (start, end): (usize, usize),
+    time_zone: AxisTimezone,
+    time_format: &TimeLabelFormat,
+    theme: &Theme,
+) {
+    const MIN_LABEL_GAP: f32 = 100.0;
+
+    let bars = &data_window.bars[start..end];
+    let (Some(first), Some(last)) = (bars.first(), bars.last()) else {
+        return;
+    };
+    let visible_count = bars.len();
+    let bar_width = rect.width() / visible_count as f32;
+    let step = choose_bar_step(bar_width, MIN_LABEL_GAP);
+    // средний интервал между подписями определяет формат, разрывы в данных не учитываются
+    let interval_ms = (last.time - first.time) / (visible_count.max(2) - 1) as i64 * step as i64;
+    let first_dt = time_zone.datetime(first.time);
+    let last_dt = time_zone.datetime(last.time);
+    let has_two_years = first_dt.year() != last_dt.year();
+    let has_two_months = has_two_years || first_dt.month() != last_dt.month();
+    let has_two_days = has_two_years || first_dt.ordinal() != last_dt.ordinal();
+    let font = theme.chart_font();
+    let text_color = renderer.text_color();
+
+    for index in (start.div_ceil(step) * step..end).step_by(step) {
+        let x = rect.left() + (index - start) as f32 * bar_width + data_window.pixel_offset;
+        if x < rect.left() + 5.0 || x > rect.right() - 5.0 {
+            continue;
+        }
+        renderer.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            Stroke::new(0.5, theme.grid),
+        );
+        let label = format_time_label(
+            time_zone.datetime(data_window.bars[index].time),
+            interval_ms,
+            has_two_years,
+            has_two_months,
+            has_two_days,
+            time_format,
+        );
+        renderer.text(
+            egui::pos2(x, rect.bottom() + 2.0),
+            egui::Align2::CENTER_TOP,
+            &label,
+            &font,
+            text_color,
+        );
+    }
+}
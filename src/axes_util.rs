@@ -1,6 +1,59 @@
 //axes_util.rs
-use crate::{datawindow::DataWindow, settings};
-use chrono::{DateTime, Datelike, Timelike, Utc};
+use crate::datawindow::DataWindow;
+use crate::settings::TimeLabelFormat;
+use chrono::{DateTime, FixedOffset, Local, Offset, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// Timezone used for time axis labels and the crosshair readout.
+/// Independent of aggregation, whose tiers are always aligned to UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AxisTimezone {
+    #[default]
+    Local,
+    Utc,
+    Named(Tz),
+}
+
+impl AxisTimezone {
+    /// Parses "Local", "UTC" or an IANA name such as "America/New_York".
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            n if n.eq_ignore_ascii_case("local") => Some(AxisTimezone::Local),
+            n if n.eq_ignore_ascii_case("utc") => Some(AxisTimezone::Utc),
+            n => n.parse::<Tz>().ok().map(AxisTimezone::Named),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            AxisTimezone::Local => "Local".to_string(),
+            AxisTimezone::Utc => "UTC".to_string(),
+            AxisTimezone::Named(tz) => tz.name().to_string(),
+        }
+    }
+
+    /// Converts a Unix millisecond timestamp into this timezone.
+    pub fn datetime(&self, timestamp_ms: i64) -> DateTime<FixedOffset> {
+        let utc = DateTime::<Utc>::from_timestamp_millis(timestamp_ms).unwrap_or_else(Utc::now);
+        match self {
+            AxisTimezone::Local => utc.with_timezone(&Local).fixed_offset(),
+            AxisTimezone::Utc => utc.fixed_offset(),
+            AxisTimezone::Named(tz) => utc.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// UTC offset in milliseconds at the given moment.
+    pub fn offset_ms(&self, timestamp_ms: i64) -> i64 {
+        let utc = DateTime::<Utc>::from_timestamp_millis(timestamp_ms).unwrap_or_else(Utc::now);
+        let seconds = match self {
+            AxisTimezone::Local => Local.offset_from_utc_datetime(&utc.naive_utc()).fix(),
+            AxisTimezone::Utc => Utc.fix(),
+            AxisTimezone::Named(tz) => tz.offset_from_utc_datetime(&utc.naive_utc()).fix(),
+        }
+        .local_minus_utc();
+        seconds as i64 * 1000
+    }
+}
 
 pub fn create_scale_price_fn(data_window: &DataWindow, rect: egui::Rect) -> impl Fn(f64) -> f32 {
     let (min_price, max_price) = data_window.price;
@@ -11,45 +64,18 @@ pub fn create_scale_price_fn(data_window: &DataWindow, rect: egui::Rect) -> impl
     move |price: f64| -> f32 { bottom - ((price - min_price) / range) as f32 * height }
 }
 
-pub fn format_price(price: f64) -> String {
-    let abs_price = price.abs();
-    let (value, suffix, decimals): (f64, &str, usize) = if abs_price >= 1_000_000.0 {
-        (price / 1_000_000.0, "m", 1)
-    } else if abs_price >= 1_000.0 {
-        (price / 1_000.0, "k", 1)
-    } else {
-        (price, "", 2)
-    };
-
-    let tolerance = if suffix.is_empty() {
-        1e-9
-    } else {
-        10f64.powi(-(decimals as i32 + 1))
-    };
-    let is_round = value.fract().abs() < tolerance;
-
-    if is_round {
-        format!("{:.0}{}", value, suffix)
-    } else if suffix.is_empty()
-        && abs_price > 1.0
-        && value.fract().abs() < settings::PRICE_FRACTION_THRESHOLD
-    {
-        format!("{:.0}", value)
-    } else {
-        format!("{:.prec$}{}", value, suffix, prec = decimals)
-    }
+/// Price with `decimals` decimals (see `DataWindow::price_decimals`), without k/m
+/// suffixes, so low-priced alts and indices keep their significant digits.
+pub fn format_price(price: f64, decimals: usize) -> String {
+    format!("{:.*}", decimals, price)
 }
 
-pub fn format_price_high_precision(price: f64) -> String {
-    let abs_price = price.abs();
-    let (value, suffix, decimals): (f64, &str, usize) = if abs_price >= 1_000_000.0 {
-        (price / 1_000_000.0, "m", 2)
-    } else if abs_price >= 1_000.0 {
-        (price / 1_000.0, "k", 2)
-    } else {
-        (price, "", 3)
-    };
-    format!("{:.prec$}{}", value, suffix, prec = decimals)
+/// Decimals an axis step needs (0.05 -> 2, 500 -> 0), at most `max_decimals`.
+fn step_decimals(step: f64, max_decimals: usize) -> usize {
+    if step >= 1.0 {
+        return 0;
+    }
+    ((-step.log10() - 1e-9).ceil().max(0.0) as usize).min(max_decimals)
 }
 
 pub fn nice_range(min: f64, max: f64, ticks: usize) -> (f64, f64, f64) {
@@ -80,12 +106,15 @@ pub fn nice_range(min: f64, max: f64, ticks: usize) -> (f64, f64, f64) {
     (nice_min, nice_max, nice_tick)
 }
 
+/// Price axis labels; `decimals` is the price precision of the symbol, labels show
+/// only as many as the step between them needs.
 pub fn generate_price_labels(
     min: f64,
     max: f64,
     scale_price: &impl Fn(f64) -> f32,
     height_limit_top: f32,
     height_limit_bottom: f32,
+    decimals: usize,
 ) -> Vec<(f64, String, f32)> {
     let price_range = (max - min).max(1e-9);
     let (nice_min, nice_max, tick_spacing) =
@@ -96,6 +125,7 @@ pub fn generate_price_labels(
     }
 
     let tick_count = (((nice_max - nice_min) / tick_spacing).round() as i32).min(100);
+    let label_decimals = step_decimals(tick_spacing, decimals);
     let mut labels = vec![];
 
     for i in 0..=tick_count {
@@ -104,13 +134,14 @@ pub fn generate_price_labels(
         if y < height_limit_top - 10.0 || y > height_limit_bottom {
             continue;
         }
-        labels.push((price, format_price(price), y));
+        labels.push((price, format_price(price, label_decimals), y));
     }
 
     labels
 }
 
-pub fn deduplicate_price_labels(labels: &mut Vec<(f64, String, f32)>) {
+/// Labels rounded to the same text get the full `decimals` of the symbol.
+pub fn deduplicate_price_labels(labels: &mut [(f64, String, f32)], decimals: usize) {
     if labels.len() < 2 {
         return;
     }
@@ -129,9 +160,33 @@ pub fn deduplicate_price_labels(labels: &mut Vec<(f64, String, f32)>) {
     if has_duplicates {
         for i in 0..labels.len() {
             if needs_update[i] {
-                labels[i].1 = format_price_high_precision(labels[i].0);
+                labels[i].1 = format_price(labels[i].0, decimals);
+            }
+        }
+    }
+}
+
+/// Labeling of the time axis. Bars are spaced equally by index in both modes, so
+/// gaps in the data (weekends of imported equity data, renko/tick bars) take no room;
+/// `BarIndex` also places the labels on every N-th bar instead of wall-clock intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AxisMode {
+    #[default]
+    Time,
+    BarIndex,
+}
+
+/// Smallest 1-2-5 step of bars whose labels are at least `min_gap` pixels apart.
+pub fn choose_bar_step(bar_width: f32, min_gap: f32) -> usize {
+    let mut magnitude = 1;
+    loop {
+        for multiple in [1, 2, 5] {
+            let step = multiple * magnitude;
+            if step as f32 * bar_width >= min_gap {
+                return step;
             }
         }
+        magnitude *= 10;
     }
 }
 
@@ -169,24 +224,27 @@ pub fn choose_time_interval(time_span_ms: i64, target_lines: usize) -> i64 {
 }
 
 pub fn format_time_label(
-    dt: DateTime<Utc>,
+    dt: DateTime<FixedOffset>,
     interval_ms: i64,
     has_two_years: bool,
     has_two_months: bool,
     has_two_days: bool,
+    format: &TimeLabelFormat,
 ) -> String {
     match interval_ms {
         i if i >= 31_536_000_000 && has_two_years => dt.format("%Y").to_string(),
         i if i >= 2_592_000_000 && has_two_months => dt.format("%b").to_string(),
-        i if i >= 604_800_000 && has_two_days => format!("{}.{}", dt.day(), dt.month()),
+        i if i >= 604_800_000 && has_two_days => {
+            dt.format(format.numeric_day_month_pattern()).to_string()
+        }
         i if i >= 86_400_000 && has_two_days => dt.format("%d").to_string(),
         i if i >= 900_000 => {
             if dt.hour() == 0 && dt.minute() == 0 && has_two_days {
-                dt.format("%d %b").to_string()
+                dt.format(format.day_month_pattern()).to_string()
             } else {
-                dt.format("%H:%M").to_string()
+                dt.format(format.time_pattern(false)).to_string()
             }
         }
-        _ => dt.format("%H:%M:%S").to_string(),
+        _ => dt.format(format.time_pattern(true)).to_string(),
     }
 }
@@ -0,0 +1,324 @@
+// backup.rs - Incremental backup of raw blocks to an S3-compatible bucket
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::app_error::{self, AppError};
+use crate::compress;
+use crate::db::{Database, WriteBatch, KLINE_FORMAT};
+use crate::settings::{
+    BACKUP_ACCESS_KEY_ENV, BACKUP_BUCKET, BACKUP_ENDPOINT, BACKUP_REGION, BACKUP_SECRET_KEY_ENV,
+};
+use crate::timeframe::Timeframe;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// URI encoding of an object key for SigV4: everything but unreserved characters
+/// and `/` is percent-encoded, e.g. the `:` of futures symbols (`F:BTCUSDT`).
+fn uri_encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Layout the blocks of a symbol were uploaded in. Indexes written before the
+/// fields were added are a bare block list and read with both set to 0.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct BackupIndex {
+    kline_format: u64,
+    block_size: usize,
+    blocks: Vec<(i64, u32)>,
+}
+
+impl BackupIndex {
+    fn parse(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data).or_else(|e| match serde_json::from_slice(data) {
+            Ok(blocks) => Ok(Self {
+                kline_format: 0,
+                block_size: 0,
+                blocks,
+            }),
+            Err(_) => Err(e),
+        })
+    }
+}
+
+/// Bucket objects:
+///   {symbol}/{timestamp}  -> block as stored: encrypted when the DB is (see crypto.rs),
+///                            so it restores only into a DB with the same key (a copy)
+///   {symbol}/index        -> JSON `BackupIndex`: kline format and block size of the
+///                            blocks, [timestamp, crc32 of the stored bytes] per block
+pub struct S3Backup {
+    client: Client,
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Backup {
+    /// Target from settings.rs and credentials from the environment;
+    /// `None` when backup is not configured.
+    pub fn from_settings() -> Option<Self> {
+        if BACKUP_ENDPOINT.is_empty() {
+            return None;
+        }
+        Some(Self {
+            client: Client::new(),
+            endpoint: BACKUP_ENDPOINT.trim_end_matches('/').to_string(),
+            bucket: BACKUP_BUCKET.to_string(),
+            region: BACKUP_REGION.to_string(),
+            access_key: std::env::var(BACKUP_ACCESS_KEY_ENV).ok()?,
+            secret_key: std::env::var(BACKUP_SECRET_KEY_ENV).ok()?,
+        })
+    }
+
+    /// Sends a path-style request signed with AWS Signature V4.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+        let host = self
+            .endpoint
+            .split("://")
+            .nth(1)
+            .unwrap_or(&self.endpoint)
+            .to_string();
+        let path = format!("/{}/{}", uri_encode_key(&self.bucket), uri_encode_key(key));
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex(&Sha256::digest(&body));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method, path, host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac(&signing_key, part);
+        }
+        let signature = hex(&hmac(&signing_key, &string_to_sign));
+
+        let response = self
+            .client
+            .request(method, format!("{}{}", self.endpoint, path))
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    self.access_key, scope, signature
+                ),
+            )
+            .body(body)
+            .send()?;
+        Ok(response)
+    }
+
+    fn put_object(&self, key: &str, body: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        let response = self.request(reqwest::Method::PUT, key, body)?;
+        if !response.status().is_success() {
+            return Err(format!("backup upload of {} failed: {}", key, response.status()).into());
+        }
+        Ok(())
+    }
+
+    fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let response = self.request(reqwest::Method::GET, key, Vec::new())?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("backup download of {} failed: {}", key, response.status()).into());
+        }
+        Ok(Some(response.bytes()?.to_vec()))
+    }
+
+    fn get_index(&self, symbol: &str) -> Result<Option<BackupIndex>, Box<dyn Error>> {
+        match self.get_object(&format!("{}/index", symbol))? {
+            Some(data) => Ok(Some(BackupIndex::parse(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Uploads blocks that are new or changed since the last upload; returns their number.
+    /// Blocks are uploaded as stored, an encrypted DB is never sent in plaintext.
+    /// A backup in another kline format or block size is overwritten as a whole.
+    pub fn push(&self, db: &Database, symbol: &str) -> Result<usize, Box<dyn Error>> {
+        let mut index: std::collections::BTreeMap<i64, u32> = match self.get_index(symbol)? {
+            Some(index)
+                if index.kline_format == KLINE_FORMAT && index.block_size == db.block_size() =>
+            {
+                index.blocks.into_iter().collect()
+            }
+            _ => Default::default(),
+        };
+        let mut uploaded = 0;
+        for timestamp in db.block_timestamps(symbol)? {
+            let Some(payload) = db.get_stored_block(symbol, timestamp)? else {
+                continue;
+            };
+            let checksum = crc32fast::hash(&payload);
+            if db.get_backup_mark(symbol, timestamp)? == Some(checksum)
+                && index.get(&timestamp) == Some(&checksum)
+            {
+                continue;
+            }
+            self.put_object(&format!("{}/{}", symbol, timestamp), payload)?;
+            db.set_backup_mark(symbol, timestamp, checksum)?;
+            index.insert(timestamp, checksum);
+            uploaded += 1;
+        }
+        if uploaded > 0 {
+            let index = BackupIndex {
+                kline_format: KLINE_FORMAT,
+                block_size: db.block_size(),
+                blocks: index.into_iter().collect(),
+            };
+            self.put_object(&format!("{}/index", symbol), serde_json::to_vec(&index)?)?;
+        }
+        Ok(uploaded)
+    }
+
+    /// Downloads blocks missing or different locally and merges them with local data;
+    /// returns the number of blocks written. Aggregation tiers are rebuilt after.
+    /// Blocks in an older kline format are converted; a backup without the format,
+    /// in a newer one or with another block size is rejected.
+    pub fn pull(&self, db: &Database, symbol: &str) -> Result<usize, Box<dyn Error>> {
+        let Some(index) = self.get_index(symbol)? else {
+            return Ok(0);
+        };
+        if index.kline_format == 0 {
+            return Err(format!(
+                "backup of {} records no kline format or block size; push it again first",
+                symbol
+            )
+            .into());
+        }
+        if index.kline_format > KLINE_FORMAT {
+            return Err(format!(
+                "backup of {} is in kline format {}, this version reads up to {}",
+                symbol, index.kline_format, KLINE_FORMAT
+            )
+            .into());
+        }
+        if index.block_size != db.block_size() {
+            return Err(format!(
+                "backup of {} has blocks of {} minutes, the database uses {}",
+                symbol,
+                index.block_size,
+                db.block_size()
+            )
+            .into());
+        }
+        let same_format = index.kline_format == KLINE_FORMAT;
+        let mut batch = WriteBatch::default();
+        for (timestamp, checksum) in index.blocks {
+            let local = db.get_stored_block(symbol, timestamp)?;
+            if same_format
+                && local
+                    .as_ref()
+                    .is_some_and(|stored| crc32fast::hash(stored) == checksum)
+            {
+                continue;
+            }
+            let Some(remote) = self.get_object(&format!("{}/{}", symbol, timestamp))? else {
+                continue;
+            };
+            let remote_klines = db
+                .convert_klines(&remote, index.kline_format)
+                .map_err(|e| {
+                    format!(
+                        "backup block {}/{} does not open with this database's key: {}",
+                        symbol, timestamp, e
+                    )
+                })?;
+            match local {
+                Some(local) => {
+                    let merged = Timeframe::merge_klines(
+                        compress::decompress_klines(&db.open_payload(&local)?)?,
+                        remote_klines,
+                    );
+                    batch.insert_block(symbol, timestamp, &compress::compress_klines(&merged)?);
+                }
+                None if same_format => batch.insert_stored_block(symbol, timestamp, &remote),
+                None => batch.insert_block(
+                    symbol,
+                    timestamp,
+                    &compress::compress_klines(&remote_klines)?,
+                ),
+            }
+        }
+        let written = batch.block_count();
+        db.apply_batch(batch)?;
+
+        // Restored blocks may lie before the last aggregated record
+        if written > 0 {
+            db.invalidate_aggregation(symbol)?;
+            if let Err(e) = db.aggregate_ohlcv_data(symbol) {
+                app_error::report(AppError::Aggregation {
+                    symbol: symbol.to_string(),
+                    message: e.to_string(),
+                });
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_records_the_block_layout() {
+        let index = BackupIndex {
+            kline_format: KLINE_FORMAT,
+            block_size: 1440,
+            blocks: vec![(0, 1), (86_400_000, 2)],
+        };
+        let data = serde_json::to_vec(&index).unwrap();
+        assert_eq!(BackupIndex::parse(&data).unwrap(), index);
+    }
+
+    #[test]
+    fn bare_block_list_is_read_as_unversioned() {
+        let index = BackupIndex::parse(b"[[0,1],[60000,2]]").unwrap();
+        assert_eq!(index.kline_format, 0);
+        assert_eq!(index.block_size, 0);
+        assert_eq!(index.blocks, vec![(0, 1), (60_000, 2)]);
+        assert!(BackupIndex::parse(b"{\"blocks\":[]}").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// bookmarks.rs - Labeled time bookmarks per symbol: list window, Ctrl+B to add, [ and ] to jump between them
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::AxisTimezone;
+use crate::datawindow::DataWindow;
+use crate::db::{Database, TimeBookmark};
+use crate::drawing_util::calculate_bar_center_x;
+use eframe::egui::{self, Align2, Color32, FontId, Rect};
+use std::error::Error;
+
+#[derive(Default)]
+pub struct Bookmarks {
+    pub open: bool,
+    items: Vec<TimeBookmark>,
+    // символ, для которого загружены закладки
+    loaded_for: Option<String>,
+    // changed by the user, saved on the next refresh
+    dirty: bool,
+    label: String,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks of `symbol` and saves edits.
+    pub fn refresh(&mut self, db: &Database, symbol: &str) -> Result<(), Box<dyn Error>> {
+        if self.loaded_for.as_deref() != Some(symbol) {
+            self.items = db.get_bookmarks(symbol)?;
+            self.loaded_for = Some(symbol.to_string());
+            self.dirty = false;
+        }
+        if self.dirty {
+            db.set_bookmarks(symbol, &self.items)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Adds a bookmark, kept in time order.
+    pub fn add(&mut self, time: i64, label: &str) {
+        let index = self.items.partition_point(|b| b.time <= time);
+        self.items.insert(
+            index,
+            TimeBookmark {
+                time,
+                label: label.trim().to_string(),
+            },
+        );
+        self.dirty = true;
+    }
+
+    fn previous(&self, time: i64) -> Option<i64> {
+        self.items.iter().rev().map(|b| b.time).find(|t| *t < time)
+    }
+
+    fn next(&self, time: i64) -> Option<i64> {
+        self.items.iter().map(|b| b.time).find(|t| *t > time)
+    }
+
+    /// Ctrl+B bookmarks the bar in the middle of the view; `[` and `]` return the
+    /// previous / next bookmark relative to it, to jump to.
+    pub fn handle_keys(&mut self, ctx: &egui::Context, data_window: &DataWindow) -> Option<i64> {
+        if ctx.wants_keyboard_input() {
+            return None;
+        }
+        let center = data_window.center_time()?;
+        let (add, previous, next) = ctx.input_mut(|i| {
+            (
+                i.consume_shortcut(&egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND,
+                    egui::Key::B,
+                )),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::OpenBracket),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::CloseBracket),
+            )
+        });
+        if add {
+            self.add(center, "");
+        }
+        if previous {
+            self.previous(center)
+        } else if next {
+            self.next(center)
+        } else {
+            None
+        }
+    }
+
+    /// Dotted vertical line with the label at the bar of each visible bookmark.
+    pub fn draw(&self, ui: &mut egui::Ui, rect: Rect, data_window: &DataWindow, font: &FontId) {
+        let bars = &data_window.bars;
+        let (start, end) = data_window.visible_range;
+        let end = end.min(bars.len() as i64);
+        if start < 0 || start >= end {
+            return;
+        }
+        let painter = ui.painter();
+        let color = Color32::from_rgb(120, 200, 200);
+        for bookmark in &self.items {
+            let Some(index) = bars
+                .partition_point(|b| b.time <= bookmark.time)
+                .checked_sub(1)
+            else {
+                continue;
+            };
+            if (index as i64) < start || index as i64 >= end {
+                continue;
+            }
+            let x = calculate_bar_center_x(
+                index - start as usize,
+                (end - start) as usize,
+                rect,
+                data_window.pixel_offset,
+            );
+            painter.extend(egui::Shape::dotted_line(
+                &[egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                color.gamma_multiply(0.7),
+                6.0,
+                1.0,
+            ));
+            if !bookmark.label.is_empty() {
+                painter.text(
+                    egui::pos2(x + 3.0, rect.top() + 2.0),
+                    Align2::LEFT_TOP,
+                    &bookmark.label,
+                    font.clone(),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Bookmark list of the chart symbol. Returns the time of a clicked bookmark.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        data_window: &DataWindow,
+        time_zone: AxisTimezone,
+    ) -> Option<i64> {
+        let mut jump = None;
+        let mut open = self.open;
+        egui::Window::new("Bookmarks")
+            .open(&mut open)
+            .default_height(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.label)
+                            .hint_text("label")
+                            .desired_width(160.0),
+                    );
+                    let center = data_window.center_time();
+                    if ui
+                        .add_enabled(center.is_some(), egui::Button::new("Add at view center"))
+                        .clicked()
+                    {
+                        if let Some(time) = center {
+                            let label = std::mem::take(&mut self.label);
+                            self.add(time, &label);
+                        }
+                    }
+                });
+                ui.label("Ctrl+B adds a bookmark at the view center, [ and ] jump between them");
+                ui.separator();
+                if self.items.is_empty() {
+                    ui.label("No bookmarks");
+                    return;
+                }
+                let mut remove = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("bookmarks")
+                        .striped(true)
+                        .num_columns(3)
+                        .show(ui, |ui| {
+                            for (index, bookmark) in self.items.iter_mut().enumerate() {
+                                let time = time_zone
+                                    .datetime(bookmark.time)
+                                    .format("%Y-%m-%d %H:%M")
+                                    .to_string();
+                                if ui.link(time).clicked() {
+                                    jump = Some(bookmark.time);
+                                }
+                                if ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut bookmark.label)
+                                            .desired_width(160.0),
+                                    )
+                                    .changed()
+                                {
+                                    self.dirty = true;
+                                }
+                                if ui.small_button("×").clicked() {
+                                    remove = Some(index);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+                if let Some(index) = remove {
+                    self.items.remove(index);
+                    self.dirty = true;
+                }
+            });
+        self.open = open;
+        jump
+    }
+}
@@ -0,0 +1,125 @@
+// bybit.rs - Bybit v5 spot market API client: klines, server time, symbols
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::exchange::Exchange;
+use crate::fetch::{convert_to_u64, KLine, PRICE_MULTIPLIER, VOLUME_MULTIPLIER};
+use crate::settings::{API_TIMEOUT_SECS, BYBIT_API_URL};
+use reqwest::blocking::Client;
+use std::error::Error;
+use std::time::Duration;
+
+/// Bybit spot market. Klines come newest first and are reversed here.
+pub struct Bybit;
+
+/// Bybit interval code of a Binance-style interval ("1m" -> "1", "4h" -> "240", "1d" -> "D").
+fn interval_code(interval: &str) -> Result<String, Box<dyn Error>> {
+    let (count, unit) = interval.split_at(interval.len().saturating_sub(1));
+    let count: u32 = count
+        .parse()
+        .map_err(|_| format!("unsupported interval {}", interval))?;
+    Ok(match (unit, count) {
+        ("m", _) => count.to_string(),
+        ("h", _) => (count * 60).to_string(),
+        ("d", 1) => "D".to_string(),
+        ("w", 1) => "W".to_string(),
+        ("M", 1) => "M".to_string(),
+        _ => return Err(format!("unsupported interval {}", interval).into()),
+    })
+}
+
+/// `result` of a v5 response, failing on a non-zero `retCode`.
+fn get_result(client: &Client, path_and_query: &str) -> Result<serde_json::Value, Box<dyn Error>> {
+    let response = client
+        .get(format!("{}{}", BYBIT_API_URL, path_and_query))
+        .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+        .send()?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    let mut value: serde_json::Value = response.json()?;
+    let code = value.get("retCode").and_then(|c| c.as_i64()).unwrap_or(-1);
+    if code != 0 {
+        let message = value.get("retMsg").and_then(|m| m.as_str()).unwrap_or("");
+        return Err(format!("Bybit error {}: {}", code, message).into());
+    }
+    Ok(value
+        .get_mut("result")
+        .map(serde_json::Value::take)
+        .unwrap_or_default())
+}
+
+impl Exchange for Bybit {
+    fn name(&self) -> &'static str {
+        "bybit"
+    }
+
+    fn fetch_klines(
+        &self,
+        client: &Client,
+        symbol: &str,
+        interval: &str,
+        limit: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        let mut url = format!(
+            "/v5/market/kline?category=spot&symbol={}&interval={}&limit={}",
+            symbol,
+            interval_code(interval)?,
+            limit
+        );
+        if let Some(start) = start_time {
+            url.push_str(&format!("&start={}", start));
+        }
+        if let Some(end) = end_time {
+            url.push_str(&format!("&end={}", end));
+        }
+        let result = get_result(client, &url)?;
+        let text = |row: &serde_json::Value, i: usize| {
+            row.get(i)
+                .and_then(|v| v.as_str())
+                .unwrap_or("0")
+                .to_string()
+        };
+        let mut klines: Vec<KLine> = result
+            .get("list")
+            .and_then(|l| l.as_array())
+            .ok_or("no kline list in Bybit response")?
+            .iter()
+            .map(|row| KLine {
+                open_time: text(row, 0).parse().unwrap_or(0),
+                open: convert_to_u64(&text(row, 1), PRICE_MULTIPLIER),
+                high: convert_to_u64(&text(row, 2), PRICE_MULTIPLIER),
+                low: convert_to_u64(&text(row, 3), PRICE_MULTIPLIER),
+                close: convert_to_u64(&text(row, 4), PRICE_MULTIPLIER),
+                volume: convert_to_u64(&text(row, 5), VOLUME_MULTIPLIER),
+            })
+            .collect();
+        klines.reverse();
+        Ok(klines)
+    }
+
+    fn server_time(&self, client: &Client) -> Result<i64, Box<dyn Error>> {
+        let result = get_result(client, "/v5/market/time")?;
+        let seconds = result
+            .get("timeSecond")
+            .and_then(|t| t.as_str())
+            .and_then(|t| t.parse::<i64>().ok())
+            .ok_or("no time in Bybit response")?;
+        Ok(seconds * 1000)
+    }
+
+    fn symbols(&self, client: &Client) -> Result<Vec<String>, Box<dyn Error>> {
+        let result = get_result(client, "/v5/market/instruments-info?category=spot")?;
+        let mut symbols: Vec<String> = result
+            .get("list")
+            .and_then(|l| l.as_array())
+            .ok_or("no symbols in Bybit response")?
+            .iter()
+            .filter(|s| s.get("status").and_then(|v| v.as_str()) == Some("Trading"))
+            .filter_map(|s| Some(s.get("symbol")?.as_str()?.to_string()))
+            .collect();
+        symbols.sort();
+        Ok(symbols)
+    }
+}
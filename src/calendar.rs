@@ -0,0 +1,143 @@
+// calendar.rs - Trading calendars (session hours, weekends, holidays) for non-crypto data
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::settings::{SYMBOL_CALENDARS, US_MARKET_HOLIDAYS};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+const DAY_MINUTES: u32 = 1440;
+
+/// Trading hours of a market. Sessions open at `open` and close at `close` minutes of
+/// local time; `open > close` is an overnight session that closes the next day.
+/// Weekdays and holidays refer to the date of the session open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingCalendar {
+    pub name: &'static str,
+    pub time_zone: Tz,
+    pub open: u32,
+    pub close: u32,
+    /// Monday first.
+    pub weekdays: [bool; 7],
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for TradingCalendar {
+    fn default() -> Self {
+        Self::continuous()
+    }
+}
+
+fn us_holidays() -> Vec<NaiveDate> {
+    US_MARKET_HOLIDAYS
+        .iter()
+        .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .collect()
+}
+
+impl TradingCalendar {
+    /// Crypto: always open, bars are cut by time only.
+    pub fn continuous() -> Self {
+        Self {
+            name: "24/7",
+            time_zone: Tz::UTC,
+            open: 0,
+            close: DAY_MINUTES,
+            weekdays: [true; 7],
+            holidays: Vec::new(),
+        }
+    }
+
+    /// NYSE/Nasdaq regular hours 9:30-16:00 New York time.
+    pub fn nyse() -> Self {
+        Self {
+            name: "NYSE",
+            time_zone: Tz::America__New_York,
+            open: 570,
+            close: 960,
+            weekdays: [true, true, true, true, true, false, false],
+            holidays: us_holidays(),
+        }
+    }
+
+    /// CME Globex: 17:00-16:00 Chicago time, opening Sunday through Thursday.
+    pub fn cme_globex() -> Self {
+        Self {
+            name: "CME Globex",
+            time_zone: Tz::America__Chicago,
+            open: 1020,
+            close: 960,
+            weekdays: [true, true, true, true, false, false, true],
+            // сессия накануне праздника
+            holidays: us_holidays()
+                .into_iter()
+                .filter_map(|date| date.pred_opt())
+                .collect(),
+        }
+    }
+
+    pub fn presets() -> Vec<Self> {
+        vec![Self::continuous(), Self::nyse(), Self::cme_globex()]
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        Self::presets()
+            .into_iter()
+            .find(|calendar| calendar.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Calendar of a symbol from `SYMBOL_CALENDARS`, 24/7 for the rest.
+    pub fn for_symbol(symbol: &str) -> Self {
+        SYMBOL_CALENDARS
+            .iter()
+            .find(|(s, _)| *s == symbol)
+            .and_then(|(_, name)| Self::by_name(name))
+            .unwrap_or_default()
+    }
+
+    pub fn is_continuous(&self) -> bool {
+        self.open == 0
+            && self.close == DAY_MINUTES
+            && self.weekdays.iter().all(|d| *d)
+            && self.holidays.is_empty()
+    }
+
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.weekdays[date.weekday().num_days_from_monday() as usize]
+            && !self.holidays.contains(&date)
+    }
+
+    /// Local time `minutes` after the midnight of `date` as a timestamp, ms.
+    fn local_ms(&self, date: NaiveDate, minutes: u32) -> Option<i64> {
+        let naive = date.and_time(NaiveTime::MIN) + Duration::minutes(minutes as i64);
+        self.time_zone
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.timestamp_millis())
+    }
+
+    /// `(start, end)` of the session containing `timestamp_ms`, `None` outside sessions.
+    /// For the 24/7 calendar sessions are UTC days.
+    pub fn session_at(&self, timestamp_ms: i64) -> Option<(i64, i64)> {
+        let date = chrono::DateTime::from_timestamp_millis(timestamp_ms)?
+            .with_timezone(&self.time_zone)
+            .date_naive();
+        let overnight = self.open >= self.close;
+        // ночная сессия могла открыться накануне
+        [date.pred_opt(), Some(date)]
+            .into_iter()
+            .flatten()
+            .filter(|day| self.is_trading_day(*day))
+            .filter_map(|day| {
+                let close_day = if overnight { day.succ_opt()? } else { day };
+                Some((
+                    self.local_ms(day, self.open)?,
+                    self.local_ms(close_day, self.close)?,
+                ))
+            })
+            .find(|(start, end)| (*start..*end).contains(&timestamp_ms))
+    }
+
+    pub fn session_start(&self, timestamp_ms: i64) -> Option<i64> {
+        self.session_at(timestamp_ms).map(|(start, _)| start)
+    }
+}
@@ -0,0 +1,616 @@
+// cli.rs - Command line: config overrides, headless sync, export/import, aggregation and verification of stored symbols without the GUI
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::anomalies;
+use crate::compress::{self, Codec};
+use crate::config::Config;
+use crate::datawindow::DataWindow;
+use crate::db::{AggrPeriod, Database};
+use crate::export::{self, ExportFormat};
+use crate::settings::{AGGREGATION_PERIODS, CONFIG_FILE, EXPORT_DIR, SYNC_REQUEST_PAUSE};
+use crate::timeframe::{SyncProgress, Timeframe};
+use chrono::{TimeZone, Utc};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::error::Error;
+use std::path::Path;
+use std::time::Instant;
+
+// параметры командной строки, заменяющие ключи файла настроек
+const CONFIG_OPTIONS: [(&str, &str); 9] = [
+    ("symbol", "symbol"),
+    ("timeframe", "timeframe"),
+    ("db", "db_path"),
+    ("block-codec", "block_codec"),
+    ("initial-load-days", "initial_load_days"),
+    ("zoom-sensitivity", "zoom_sensitivity"),
+    ("window-width", "window_width"),
+    ("window-height", "window_height"),
+    ("fullscreen", "fullscreen"),
+];
+// блоков в bench-codecs по умолчанию (самые новые)
+const BENCH_BLOCKS: usize = 100;
+
+/// Command line as declared for clap; turned into [`Cli`] by [`Cli::parse`].
+#[derive(Debug, Parser)]
+#[command(
+    name = "n-ohlcv",
+    about = "OHLCV collector and chart. Without a command the chart window is opened.",
+    disable_help_subcommand = true
+)]
+struct Args {
+    /// Config file, written with the defaults if missing
+    #[arg(long, global = true, value_name = "PATH", default_value = CONFIG_FILE)]
+    config: String,
+    /// Chart symbol at startup (instead of the last one)
+    // свое имя: глобальный `symbol` перехватил бы SYMBOL команд
+    #[arg(long = "symbol", global = true, value_name = "SYMBOL")]
+    chart_symbol: Option<String>,
+    /// Chart timeframe at startup (instead of the last one)
+    #[arg(long, global = true, value_name = "MINUTES")]
+    timeframe: Option<String>,
+    /// Database directory (db_path)
+    #[arg(long, global = true, value_name = "PATH")]
+    db: Option<String>,
+    /// Codec of newly written blocks: lzma, zstd or none
+    #[arg(long, global = true, value_name = "CODEC")]
+    block_codec: Option<String>,
+    /// Days of history fetched for a new symbol
+    #[arg(long, global = true, value_name = "N")]
+    initial_load_days: Option<String>,
+    /// Share of the visible bars zoomed per wheel step
+    #[arg(long, global = true, value_name = "X")]
+    zoom_sensitivity: Option<String>,
+    /// Window size and mode until the window geometry is remembered
+    #[arg(long, global = true, value_name = "W")]
+    window_width: Option<String>,
+    #[arg(long, global = true, value_name = "H")]
+    window_height: Option<String>,
+    #[arg(long, global = true, value_name = "true|false")]
+    fullscreen: Option<String>,
+    /// Same as the bench-codecs command with the default block count
+    #[arg(long, value_name = "SYMBOL", value_parser = symbol)]
+    bench_codecs: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Args {
+    /// Values of the config options in `CONFIG_OPTIONS` order.
+    fn config_values(&self) -> [&Option<String>; 9] {
+        [
+            &self.chart_symbol,
+            &self.timeframe,
+            &self.db,
+            &self.block_codec,
+            &self.initial_load_days,
+            &self.zoom_sensitivity,
+            &self.window_width,
+            &self.window_height,
+            &self.fullscreen,
+        ]
+    }
+}
+
+fn symbol(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err("empty symbol".to_string());
+    }
+    Ok(value.to_uppercase())
+}
+
+fn positive(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or(format!("{} is not a positive number", value))
+}
+
+/// Subcommand of the command line.
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Fetch missing 1m klines of the last N days
+    Sync {
+        /// Symbol, e.g. BTCUSDT or F:BTCUSDT for the perpetual futures
+        #[arg(value_parser = symbol)]
+        symbol: String,
+        /// Days to sync, `initial_load_days` of the config when not given
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(i64).range(1..))]
+        days: Option<i64>,
+    },
+    /// Export the 1m history as csv or parquet, or the hourly aggregates as csv, json or txt
+    Export {
+        /// Symbol, e.g. BTCUSDT or F:BTCUSDT for the perpetual futures
+        #[arg(value_parser = symbol)]
+        symbol: String,
+        #[arg(long, value_name = "FORMAT", default_value = "csv", value_parser = |f: &str| Ok::<_, String>(f.to_lowercase()))]
+        format: String,
+        /// Export the hourly aggregates instead of the 1m history
+        #[arg(long)]
+        hourly: bool,
+        /// Export directory
+        #[arg(long, value_name = "DIR", default_value = EXPORT_DIR)]
+        dir: String,
+    },
+    /// Store DIR/SYMBOL_1m.parquet over the stored minutes
+    Import {
+        /// Symbol, e.g. BTCUSDT or F:BTCUSDT for the perpetual futures
+        #[arg(value_parser = symbol)]
+        symbol: String,
+        /// Directory of the parquet file
+        #[arg(long, value_name = "DIR", default_value = EXPORT_DIR)]
+        dir: String,
+    },
+    /// Bring the aggregation tiers up to date
+    Aggregate {
+        /// Symbol, e.g. BTCUSDT or F:BTCUSDT for the perpetual futures
+        #[arg(value_parser = symbol)]
+        symbol: String,
+        /// Rebuild the tiers from scratch
+        #[arg(long)]
+        rebuild: bool,
+    },
+    /// Report missing minutes, corrupted blocks and data anomalies
+    Verify {
+        /// Symbol, e.g. BTCUSDT or F:BTCUSDT for the perpetual futures
+        #[arg(value_parser = symbol)]
+        symbol: String,
+    },
+    /// Size, ratio and speed of every block codec over the newest stored blocks
+    BenchCodecs {
+        /// Symbol, e.g. BTCUSDT or F:BTCUSDT for the perpetual futures
+        #[arg(value_parser = symbol)]
+        symbol: String,
+        /// Number of the newest blocks to re-encode
+        #[arg(long, value_name = "N", default_value_t = BENCH_BLOCKS, value_parser = positive)]
+        blocks: usize,
+    },
+}
+
+/// Parsed command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cli {
+    pub config_path: String,
+    /// Config keys with the values given on the command line.
+    pub overrides: Vec<(&'static str, String)>,
+    /// `None` opens the chart window.
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Parses the arguments after the program name. `--help` comes back as an error
+    /// too; `clap::Error::exit` prints it with the right exit code.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, clap::Error> {
+        let args = Args::try_parse_from(std::iter::once("n-ohlcv".to_string()).chain(args))?;
+        let mut overrides = Vec::new();
+        for ((option, key), value) in CONFIG_OPTIONS.into_iter().zip(args.config_values()) {
+            if let Some(value) = value {
+                // проверка значения до загрузки файла
+                if let Err(e) = Config::default().set(key, value) {
+                    return Err(Args::command()
+                        .error(ErrorKind::InvalidValue, format!("--{}: {}", option, e)));
+                }
+                overrides.push((key, value.clone()));
+            }
+        }
+        let command = match (args.bench_codecs, args.command) {
+            (Some(_), Some(_)) => {
+                return Err(Args::command().error(
+                    ErrorKind::ArgumentConflict,
+                    "--bench-codecs cannot be combined with a command",
+                ))
+            }
+            (Some(symbol), None) => Some(Command::BenchCodecs {
+                symbol,
+                blocks: BENCH_BLOCKS,
+            }),
+            (None, command) => command,
+        };
+        Ok(Self {
+            config_path: args.config,
+            overrides,
+            command,
+        })
+    }
+
+    /// The config file (created with the defaults if missing) with the command line
+    /// overrides applied.
+    pub fn config(&self) -> Config {
+        let mut config = Config::load_or_default(Path::new(&self.config_path));
+        for (key, value) in &self.overrides {
+            if let Err(e) = config.set(key, value) {
+                tracing::warn!("Ignoring --{}: {}", key, e);
+            }
+        }
+        config
+    }
+}
+
+impl Command {
+    /// Runs the command, printing results to stdout. Returns whether it succeeded
+    /// (`verify` fails when problems were found).
+    pub fn run(&self, config: &Config) -> Result<bool, Box<dyn Error>> {
+        let db = Database::new(&config.db_path)?;
+        let ok = match self {
+            Command::Sync { symbol, days } => {
+                sync(&db, symbol, days.unwrap_or(config.initial_load_days))?
+            }
+            Command::Export {
+                symbol,
+                format,
+                hourly,
+                dir,
+            } => export(&db, symbol, format, *hourly, dir)?,
+            Command::Import { symbol, dir } => {
+                let (rows, blocks) = export::import_history_parquet(&db, symbol, dir)?;
+                println!(
+                    "Imported {} klines of {} ({} blocks written)",
+                    rows, symbol, blocks
+                );
+                true
+            }
+            Command::Aggregate { symbol, rebuild } => aggregate(&db, symbol, *rebuild)?,
+            Command::Verify { symbol } => verify(&db, symbol)?,
+            Command::BenchCodecs { symbol, blocks } => bench_codecs(&db, symbol, *blocks)?,
+        };
+        db.flush()?;
+        Ok(ok)
+    }
+}
+
+fn format_time(ms: i64) -> String {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Fetches the blocks after the last stored one and, when the history starts later
+/// than `days` ago, the older blocks. The unfinished block tail is saved like on GUI exit.
+fn sync(db: &Database, symbol: &str, days: i64) -> Result<bool, Box<dyn Error>> {
+    let end_time = Utc::now().timestamp_millis();
+    let start_time = end_time - chrono::Duration::days(days).num_milliseconds();
+    let mut window = DataWindow::default();
+    Timeframe::restore_recent(symbol, db, &mut window)?;
+    let mut report = |progress: &SyncProgress| match &progress.last_error {
+        Some(e) => eprintln!("{} {}: {}", symbol, format_time(progress.current_time), e),
+        None => println!(
+            "{} {} {}/{}",
+            symbol,
+            format_time(progress.current_time),
+            progress.blocks_done,
+            progress.blocks_total
+        ),
+    };
+    Timeframe::sync_data(
+        SYNC_REQUEST_PAUSE,
+        db,
+        symbol,
+        start_time,
+        end_time,
+        &mut window,
+        &mut report,
+    )?;
+    db.save_recent(symbol, &window.recent_data)?;
+    let first = db.get_first_timestamp(symbol)?;
+    if first > db.block_start(start_time) {
+        let missing_days = ((first - start_time) as u64).div_ceil(86_400_000) as i64;
+        let blocks =
+            Timeframe::backfill_history(SYNC_REQUEST_PAUSE, db, symbol, missing_days, &mut report)?;
+        println!("Fetched {} older blocks of {}", blocks, symbol);
+    }
+    println!(
+        "{} stored from {} to {}",
+        symbol,
+        format_time(db.get_first_timestamp(symbol)?),
+        format_time(db.get_last_timestamp(symbol)?)
+    );
+    Ok(true)
+}
+
+fn export(
+    db: &Database,
+    symbol: &str,
+    format: &str,
+    hourly: bool,
+    dir: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let (path, rows) = match (format, hourly) {
+        ("csv", false) => export::export_history_csv(db, symbol, dir)?,
+        ("parquet", false) => export::export_history_parquet(db, symbol, dir)?,
+        (_, false) => {
+            return Err(format!("1m history is exported as csv or parquet, not {}", format).into())
+        }
+        (format, true) => {
+            let format = match format {
+                "csv" => ExportFormat::Csv,
+                "json" => ExportFormat::Json,
+                "txt" | "text" => ExportFormat::Text,
+                other => {
+                    return Err(format!(
+                        "hourly aggregates are exported as csv, json or txt, not {}",
+                        other
+                    )
+                    .into())
+                }
+            };
+            export::export_aggr(db, symbol, dir, format)?
+        }
+    };
+    println!("Exported {} records to {}", rows, path.display());
+    Ok(true)
+}
+
+fn aggregate(db: &Database, symbol: &str, rebuild: bool) -> Result<bool, Box<dyn Error>> {
+    if rebuild {
+        db.invalidate_aggregation(symbol)?;
+    }
+    db.aggregate_ohlcv_data(symbol)?;
+    for minutes in AGGREGATION_PERIODS {
+        let (first, last) = db.get_aggr_info(symbol, AggrPeriod { minutes })?;
+        if last == 0 {
+            println!("{}m: empty", minutes);
+        } else {
+            println!(
+                "{}m: {} - {}",
+                minutes,
+                format_time(first),
+                format_time(last)
+            );
+        }
+    }
+    Ok(true)
+}
+
+fn verify(db: &Database, symbol: &str) -> Result<bool, Box<dyn Error>> {
+    let report = db.verify(symbol)?;
+    if report.blocks == 0 {
+        println!("No stored data of {}", symbol);
+        return Ok(true);
+    }
+    for issue in &report.issues {
+        println!("{}", issue.describe(format_time));
+    }
+    let anomalies = anomalies::scan(db, symbol)?;
+    for anomaly in &anomalies {
+        println!(
+            "{} {} - {}: {}",
+            anomaly.kind.name(),
+            format_time(anomaly.from),
+            format_time(anomaly.to),
+            anomaly.detail
+        );
+    }
+    if let (Some(first), Some(last)) = (report.first, report.last) {
+        println!("{} - {}", format_time(first), format_time(last));
+    }
+    println!("{}, {} anomalies", report.summary(symbol), anomalies.len());
+    Ok(report.is_ok() && anomalies.is_empty())
+}
+
+/// Re-encodes the newest `blocks` stored blocks of `symbol` with every codec of this
+/// build. Speeds are of the serialized (uncompressed) bytes.
+fn bench_codecs(db: &Database, symbol: &str, blocks: usize) -> Result<bool, Box<dyn Error>> {
+    let mut samples = Vec::new();
+    for timestamp in db.block_timestamps(symbol)?.into_iter().rev().take(blocks) {
+        if let Some(data) = db.get_block(symbol, timestamp)? {
+            samples.push(compress::decompress_klines(&data)?);
+        }
+    }
+    if samples.is_empty() {
+        println!("No stored data of {}", symbol);
+        return Ok(true);
+    }
+    let mut raw_size = 0;
+    for klines in &samples {
+        raw_size += compress::compress_klines_with(klines, Codec::None)?.len() - 1;
+    }
+    println!(
+        "{}: {} blocks, {} klines, {} bytes serialized",
+        symbol,
+        samples.len(),
+        samples.iter().map(Vec::len).sum::<usize>(),
+        raw_size
+    );
+    println!(
+        "{:<6} {:>12} {:>7} {:>12} {:>12}",
+        "codec", "bytes", "ratio", "encode MB/s", "decode MB/s"
+    );
+    let mb_per_sec = |seconds: f64| raw_size as f64 / 1e6 / seconds.max(1e-9);
+    for codec in Codec::ALL {
+        if !codec.is_available() {
+            println!("{:<6} not in this build", codec.name());
+            continue;
+        }
+        let started = Instant::now();
+        let mut encoded = Vec::with_capacity(samples.len());
+        for klines in &samples {
+            encoded.push(compress::compress_klines_with(klines, codec)?);
+        }
+        let encode_time = started.elapsed().as_secs_f64();
+        let started = Instant::now();
+        for block in &encoded {
+            compress::decompress_klines(block)?;
+        }
+        let decode_time = started.elapsed().as_secs_f64();
+        let size: usize = encoded.iter().map(Vec::len).sum();
+        println!(
+            "{:<6} {:>12} {:>7.2} {:>12.1} {:>12.1}",
+            codec.name(),
+            size,
+            raw_size as f64 / size as f64,
+            mb_per_sec(encode_time),
+            mb_per_sec(decode_time)
+        );
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_open_the_window() {
+        let cli = parse(&[]).unwrap();
+        assert_eq!(cli.config_path, CONFIG_FILE);
+        assert!(cli.overrides.is_empty());
+        assert_eq!(cli.command, None);
+    }
+
+    #[test]
+    fn config_options_become_overrides() {
+        let cli = parse(&[
+            "--config",
+            "other.toml",
+            "--db=/tmp/db",
+            "--symbol",
+            "ethusdt",
+            "--fullscreen",
+            "true",
+        ])
+        .unwrap();
+        assert_eq!(cli.config_path, "other.toml");
+        assert_eq!(
+            cli.overrides,
+            [
+                ("symbol", "ethusdt".to_string()),
+                ("db_path", "/tmp/db".to_string()),
+                ("fullscreen", "true".to_string()),
+            ]
+        );
+        assert_eq!(cli.command, None);
+        // параметры настроек допустимы и после команды (cron-строки)
+        let cli = parse(&["verify", "BTCUSDT", "--db", "/data"]).unwrap();
+        assert_eq!(cli.overrides, [("db_path", "/data".to_string())]);
+    }
+
+    #[test]
+    fn invalid_config_values_are_rejected() {
+        let err = parse(&["--fullscreen", "maybe"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+        assert!(parse(&["--initial-load-days", "-3"]).is_err());
+    }
+
+    #[test]
+    fn commands() {
+        let cli = parse(&["sync", "btcusdt", "--days", "90"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Sync {
+                symbol: "BTCUSDT".into(),
+                days: Some(90),
+            })
+        );
+        assert!(cli.overrides.is_empty());
+        assert_eq!(
+            parse(&["--db", "x", "sync", "F:btcusdt"]).unwrap().command,
+            Some(Command::Sync {
+                symbol: "F:BTCUSDT".into(),
+                days: None,
+            })
+        );
+        assert_eq!(
+            parse(&["export", "BTCUSDT", "--format", "Parquet"])
+                .unwrap()
+                .command,
+            Some(Command::Export {
+                symbol: "BTCUSDT".into(),
+                format: "parquet".into(),
+                hourly: false,
+                dir: EXPORT_DIR.into(),
+            })
+        );
+        assert_eq!(
+            parse(&["export", "BTCUSDT", "--hourly", "--dir", "out"])
+                .unwrap()
+                .command,
+            Some(Command::Export {
+                symbol: "BTCUSDT".into(),
+                format: "csv".into(),
+                hourly: true,
+                dir: "out".into(),
+            })
+        );
+        assert_eq!(
+            parse(&["import", "BTCUSDT"]).unwrap().command,
+            Some(Command::Import {
+                symbol: "BTCUSDT".into(),
+                dir: EXPORT_DIR.into(),
+            })
+        );
+        assert_eq!(
+            parse(&["aggregate", "BTCUSDT", "--rebuild"])
+                .unwrap()
+                .command,
+            Some(Command::Aggregate {
+                symbol: "BTCUSDT".into(),
+                rebuild: true,
+            })
+        );
+        assert_eq!(
+            parse(&["verify", "BTCUSDT"]).unwrap().command,
+            Some(Command::Verify {
+                symbol: "BTCUSDT".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn bench_codecs_command_and_flag() {
+        assert_eq!(
+            parse(&["bench-codecs", "BTCUSDT", "--blocks", "5"])
+                .unwrap()
+                .command,
+            Some(Command::BenchCodecs {
+                symbol: "BTCUSDT".into(),
+                blocks: 5,
+            })
+        );
+        assert_eq!(
+            parse(&["--bench-codecs", "btcusdt"]).unwrap().command,
+            Some(Command::BenchCodecs {
+                symbol: "BTCUSDT".into(),
+                blocks: BENCH_BLOCKS,
+            })
+        );
+        assert_eq!(
+            parse(&["--bench-codecs", "BTCUSDT", "verify", "BTCUSDT"])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::ArgumentConflict
+        );
+        assert!(parse(&["bench-codecs", "BTCUSDT", "--blocks", "0"]).is_err());
+    }
+
+    #[test]
+    fn bad_command_lines() {
+        let kind = |args: &[&str]| parse(args).unwrap_err().kind();
+        assert_eq!(kind(&["frobnicate"]), ErrorKind::InvalidSubcommand);
+        assert_eq!(kind(&["sync"]), ErrorKind::MissingRequiredArgument);
+        assert_eq!(
+            kind(&["sync", "BTCUSDT", "ETHUSDT"]),
+            ErrorKind::UnknownArgument
+        );
+        assert_eq!(
+            kind(&["sync", "BTCUSDT", "--days", "0"]),
+            ErrorKind::ValueValidation
+        );
+        assert_eq!(
+            kind(&["verify", "BTCUSDT", "--hourly"]),
+            ErrorKind::UnknownArgument
+        );
+        assert_eq!(kind(&["--help"]), ErrorKind::DisplayHelp);
+        assert_eq!(kind(&["sync", "--help"]), ErrorKind::DisplayHelp);
+    }
+
+    #[test]
+    fn clap_definition_is_consistent() {
+        Args::command().debug_assert();
+    }
+}
@@ -0,0 +1,139 @@
+// coinbase.rs - Coinbase Exchange public API client: candles, server time, products
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::exchange::Exchange;
+use crate::fetch::{convert_to_u64, KLine, PRICE_MULTIPLIER, VOLUME_MULTIPLIER};
+use crate::settings::{API_TIMEOUT_SECS, COINBASE_API_URL};
+use chrono::{TimeZone, Utc};
+use reqwest::blocking::Client;
+use std::error::Error;
+use std::time::Duration;
+
+// Coinbase returns at most 300 candles per request
+const CANDLES_PER_REQUEST: i64 = 300;
+// the API rejects requests without a User-Agent
+const USER_AGENT: &str = concat!("n-ohlcv/", env!("CARGO_PKG_VERSION"));
+
+/// Coinbase spot. Symbols are product ids ("BTC-USD"). Candles are requested by a
+/// time window instead of a count, newest first, and minutes without trades are
+/// missing; windows longer than 300 candles are split here.
+pub struct Coinbase;
+
+/// Granularity in seconds of a Binance-style interval; Coinbase has 1m, 5m, 15m, 1h, 6h and 1d.
+fn granularity(interval: &str) -> Result<i64, Box<dyn Error>> {
+    Ok(match interval {
+        "1m" => 60,
+        "5m" => 300,
+        "15m" => 900,
+        "1h" => 3600,
+        "6h" => 21_600,
+        "1d" => 86_400,
+        _ => return Err(format!("unsupported Coinbase interval {}", interval).into()),
+    })
+}
+
+fn get_json(client: &Client, path_and_query: &str) -> Result<serde_json::Value, Box<dyn Error>> {
+    let response = client
+        .get(format!("{}{}", COINBASE_API_URL, path_and_query))
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+        .send()?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    Ok(response.json()?)
+}
+
+fn iso_time(ms: i64) -> String {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// `[time, low, high, open, close, volume]` row with numeric fields.
+fn parse_candle(row: &serde_json::Value) -> Option<KLine> {
+    // числа, а не строки: фиксированная точка через 8 знаков после запятой
+    let fixed = |i: usize, decimals: u32| {
+        let value = row.get(i)?.as_f64()?;
+        Some(convert_to_u64(&format!("{:.8}", value), decimals))
+    };
+    Some(KLine {
+        open_time: row.get(0)?.as_i64()? * 1000,
+        open: fixed(3, PRICE_MULTIPLIER)?,
+        high: fixed(2, PRICE_MULTIPLIER)?,
+        low: fixed(1, PRICE_MULTIPLIER)?,
+        close: fixed(4, PRICE_MULTIPLIER)?,
+        volume: fixed(5, VOLUME_MULTIPLIER)?,
+    })
+}
+
+impl Exchange for Coinbase {
+    fn name(&self) -> &'static str {
+        "coinbase"
+    }
+
+    fn fetch_klines(
+        &self,
+        client: &Client,
+        symbol: &str,
+        interval: &str,
+        limit: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        let step = granularity(interval)? * 1000;
+        let end = end_time.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let start = start_time.unwrap_or(end - (limit - 1) * step);
+        let mut klines = Vec::new();
+        let mut window_start = start;
+        while window_start <= end && (klines.len() as i64) < limit {
+            let window_end = (window_start + (CANDLES_PER_REQUEST - 1) * step).min(end);
+            let value = get_json(
+                client,
+                &format!(
+                    "/products/{}/candles?granularity={}&start={}&end={}",
+                    symbol,
+                    step / 1000,
+                    iso_time(window_start),
+                    iso_time(window_end)
+                ),
+            )?;
+            let mut page: Vec<KLine> = value
+                .as_array()
+                .ok_or("unexpected Coinbase candles response")?
+                .iter()
+                .filter_map(parse_candle)
+                .filter(|k| k.open_time >= window_start && k.open_time <= window_end)
+                .collect();
+            page.reverse();
+            klines.extend(page);
+            window_start = window_end + step;
+        }
+        klines.truncate(limit.max(0) as usize);
+        Ok(klines)
+    }
+
+    fn server_time(&self, client: &Client) -> Result<i64, Box<dyn Error>> {
+        let value = get_json(client, "/time")?;
+        let epoch = value
+            .get("epoch")
+            .and_then(|e| e.as_f64())
+            .ok_or("no epoch in Coinbase time response")?;
+        Ok((epoch * 1000.0) as i64)
+    }
+
+    fn symbols(&self, client: &Client) -> Result<Vec<String>, Box<dyn Error>> {
+        let value = get_json(client, "/products")?;
+        let mut symbols: Vec<String> = value
+            .as_array()
+            .ok_or("unexpected Coinbase products response")?
+            .iter()
+            .filter(|p| p.get("status").and_then(|v| v.as_str()) == Some("online"))
+            .filter(|p| p.get("trading_disabled").and_then(|v| v.as_bool()) != Some(true))
+            .filter_map(|p| Some(p.get("id")?.as_str()?.to_string()))
+            .collect();
+        symbols.sort();
+        Ok(symbols)
+    }
+}
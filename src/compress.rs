@@ -4,7 +4,108 @@ use std::io::{self, Read, Write};
 use xz2::read::XzDecoder;
 use xz2::write::XzEncoder;
 use xz2::stream::{Check, Filters, LzmaOptions, Stream};
-use crate::fetch::KLine;
+use crate::config;
+use crate::fetch::{KLine, VOLUME_MULTIPLIER};
+
+// KLine layout used before volume became fixed-point
+#[derive(bincode::Decode)]
+struct LegacyKLine {
+    open_time: i64,
+    open: u64,
+    high: u64,
+    low: u64,
+    close: u64,
+    volume: f64,
+}
+
+/// Header byte of a stored block naming the codec of the rest of the payload.
+pub const CODEC_NONE: u8 = 0;
+pub const CODEC_LZMA: u8 = 1;
+pub const CODEC_ZSTD: u8 = 2;
+/// Header bit set when the klines are stored as delta columns (`encode_columns`)
+/// instead of bincode.
+pub const DELTA_FLAG: u8 = 0x80;
+// first byte of an xz stream: blocks written before the codec header
+const XZ_MAGIC: u8 = 0xFD;
+// шаг open_time 1m свечей: хранится только отклонение от него
+const KLINE_STEP_MS: i64 = 60_000;
+// уровень zstd: скорость распаковки от уровня почти не зависит, поэтому сжатие сильное
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 19;
+
+/// Codec of the serialized klines of a block. New blocks use the `block_codec` of the
+/// config; blocks already stored keep theirs until rewritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    /// LZMA2 preset 9: smallest blocks, slowest to decode.
+    Lzma,
+    /// Needs the `zstd` feature; decodes several times faster than LZMA.
+    Zstd,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 3] = [Codec::None, Codec::Lzma, Codec::Zstd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::None => "none",
+            Codec::Lzma => "lzma",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// Codec by its `name()`.
+    pub fn by_name(name: &str) -> Option<Codec> {
+        Self::ALL
+            .into_iter()
+            .find(|codec| codec.name() == name.trim().to_lowercase())
+    }
+
+    pub fn header(self) -> u8 {
+        match self {
+            Codec::None => CODEC_NONE,
+            Codec::Lzma => CODEC_LZMA,
+            Codec::Zstd => CODEC_ZSTD,
+        }
+    }
+
+    fn from_header(header: u8) -> Option<Codec> {
+        Self::ALL.into_iter().find(|codec| codec.header() == header)
+    }
+
+    /// Whether this build can encode and decode the codec.
+    pub fn is_available(self) -> bool {
+        self != Codec::Zstd || cfg!(feature = "zstd")
+    }
+
+    fn compress(self, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            Codec::None => Ok(data.to_vec()),
+            Codec::Lzma => compress_lzma2_max(data),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => zstd::stream::encode_all(data, ZSTD_LEVEL),
+            #[cfg(not(feature = "zstd"))]
+            Codec::Zstd => Err(zstd_unavailable()),
+        }
+    }
+
+    fn decompress(self, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            Codec::None => Ok(data.to_vec()),
+            Codec::Lzma => decompress_lzma2(data),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => zstd::stream::decode_all(data),
+            #[cfg(not(feature = "zstd"))]
+            Codec::Zstd => Err(zstd_unavailable()),
+        }
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unavailable() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "built without the zstd feature")
+}
 
 // bincode configuration
 fn bincode_config() -> impl bincode::config::Config {
@@ -13,29 +114,192 @@ fn bincode_config() -> impl bincode::config::Config {
         .with_little_endian()
 }
 
+/// Serializes and compresses klines with the `block_codec` of the config.
 pub fn compress_klines(klines: &[KLine]) -> Result<Vec<u8>, io::Error> {
-    let serialized = bincode::encode_to_vec(klines, bincode_config())
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    compress_klines_with(klines, config::get().block_codec)
+}
 
-    let compressed = compress_lzma2_max(&serialized)?;
-    
-    println!(
-        "Compressed from {} to {} bytes (ratio: {:.2})",
+pub fn compress_klines_with(klines: &[KLine], codec: Codec) -> Result<Vec<u8>, io::Error> {
+    let serialized = encode_columns(klines);
+
+    let mut compressed = vec![codec.header() | DELTA_FLAG];
+    compressed.extend(codec.compress(&serialized)?);
+
+    tracing::debug!(
+        "Compressed from {} to {} bytes with {} (ratio: {:.2})",
         serialized.len(),
         compressed.len(),
+        codec.name(),
         serialized.len() as f32 / compressed.len() as f32
     );
-    
+
     Ok(compressed)
 }
 
+/// Decodes a block by its codec header; headerless xz blocks of older databases
+/// (and backups) are read as LZMA.
 pub fn decompress_klines(data: &[u8]) -> Result<Vec<KLine>, io::Error> {
-    let decompressed = decompress_lzma2(data)?;
+    let (decompressed, delta) = match data.split_first() {
+        Some((&XZ_MAGIC, _)) => (decompress_lzma2(data)?, false),
+        Some((&header, rest)) => match Codec::from_header(header & !DELTA_FLAG) {
+            Some(codec) => (codec.decompress(rest)?, header & DELTA_FLAG != 0),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown block codec {}", header),
+                ))
+            }
+        },
+        None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "empty block")),
+    };
+    if delta {
+        return decode_columns(&decompressed);
+    }
     let (result, _) = bincode::decode_from_slice(&decompressed, bincode_config())
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     Ok(result)
 }
 
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_signed(out: &mut Vec<u8>, value: i64) {
+    write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, io::Error> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "truncated delta columns")
+        })?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "varint too long",
+    ))
+}
+
+fn read_signed(data: &[u8], pos: &mut usize) -> Result<i64, io::Error> {
+    let value = read_varint(data, pos)?;
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+/// Klines as columns of zigzag varints, each column whole before the next: open time
+/// minus the previous one plus a minute, open minus the previous close, close minus
+/// open, high above and low below the body, volume. Consecutive 1m klines differ
+/// little, so most values take one byte and the columns compress better than bincode.
+/// Differences wrap, so any values round-trip.
+pub fn encode_columns(klines: &[KLine]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(klines.len() * 8);
+    write_varint(&mut out, klines.len() as u64);
+    let mut previous_time = -KLINE_STEP_MS;
+    for kline in klines {
+        write_signed(
+            &mut out,
+            kline
+                .open_time
+                .wrapping_sub(previous_time.wrapping_add(KLINE_STEP_MS)),
+        );
+        previous_time = kline.open_time;
+    }
+    let mut previous_close = 0u64;
+    for kline in klines {
+        write_signed(&mut out, kline.open.wrapping_sub(previous_close) as i64);
+        previous_close = kline.close;
+    }
+    for kline in klines {
+        write_signed(&mut out, kline.close.wrapping_sub(kline.open) as i64);
+    }
+    for kline in klines {
+        write_signed(
+            &mut out,
+            kline.high.wrapping_sub(kline.open.max(kline.close)) as i64,
+        );
+    }
+    for kline in klines {
+        write_signed(
+            &mut out,
+            kline.open.min(kline.close).wrapping_sub(kline.low) as i64,
+        );
+    }
+    for kline in klines {
+        write_varint(&mut out, kline.volume);
+    }
+    out
+}
+
+/// Inverse of `encode_columns`.
+pub fn decode_columns(data: &[u8]) -> Result<Vec<KLine>, io::Error> {
+    let mut pos = 0;
+    let count = read_varint(data, &mut pos)? as usize;
+    // каждое значение занимает хотя бы байт
+    if count > data.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "bad delta column count",
+        ));
+    }
+    let column = |pos: &mut usize| -> Result<Vec<i64>, io::Error> {
+        (0..count).map(|_| read_signed(data, pos)).collect()
+    };
+    let times = column(&mut pos)?;
+    let opens = column(&mut pos)?;
+    let closes = column(&mut pos)?;
+    let highs = column(&mut pos)?;
+    let lows = column(&mut pos)?;
+    let mut klines = Vec::with_capacity(count);
+    let mut previous_time = -KLINE_STEP_MS;
+    let mut previous_close = 0u64;
+    for i in 0..count {
+        let open_time = previous_time
+            .wrapping_add(KLINE_STEP_MS)
+            .wrapping_add(times[i]);
+        let open = previous_close.wrapping_add(opens[i] as u64);
+        let close = open.wrapping_add(closes[i] as u64);
+        klines.push(KLine {
+            open_time,
+            open,
+            high: open.max(close).wrapping_add(highs[i] as u64),
+            low: open.min(close).wrapping_sub(lows[i] as u64),
+            close,
+            volume: read_varint(data, &mut pos)?,
+        });
+        previous_time = open_time;
+        previous_close = close;
+    }
+    Ok(klines)
+}
+
+/// Decodes a block written with the legacy `f64` volume layout.
+pub fn decompress_legacy_klines(data: &[u8]) -> Result<Vec<KLine>, io::Error> {
+    let decompressed = decompress_lzma2(data)?;
+    let (legacy, _): (Vec<LegacyKLine>, usize) =
+        bincode::decode_from_slice(&decompressed, bincode_config())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(legacy
+        .into_iter()
+        .map(|k| KLine {
+            open_time: k.open_time,
+            open: k.open,
+            high: k.high,
+            low: k.low,
+            close: k.close,
+            volume: (k.volume * 10f64.powi(VOLUME_MULTIPLIER as i32)).round() as u64,
+        })
+        .collect())
+}
+
 fn compress_lzma2_max(data: &[u8]) -> Result<Vec<u8>, io::Error> {
     let mut opts = LzmaOptions::new_preset(9)?; // maximum compress level
     
@@ -57,3 +321,128 @@ fn decompress_lzma2(data: &[u8]) -> Result<Vec<u8>, io::Error> {
     decoder.read_to_end(&mut buf)?;
     Ok(buf)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline(open_time: i64, open: u64, high: u64, low: u64, close: u64, volume: u64) -> KLine {
+        KLine {
+            open_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+
+    fn sample() -> Vec<KLine> {
+        vec![
+            kline(1_700_000_000_000, 100_000, 101_000, 99_000, 100_500, 12_345),
+            // цена падает: отрицательные разности open/close
+            kline(1_700_000_060_000, 100_400, 100_400, 95_000, 95_100, 0),
+            // пропущенные минуты
+            kline(1_700_000_300_000, 95_200, 96_000, 95_200, 96_000, 7),
+            // время назад
+            kline(1_699_999_940_000, 96_000, 96_000, 96_000, 96_000, u64::MAX),
+        ]
+    }
+
+    fn bincode_bytes(klines: &[KLine]) -> Vec<u8> {
+        bincode::encode_to_vec(klines, bincode_config()).unwrap()
+    }
+
+    #[test]
+    fn columns_round_trip() {
+        let klines = sample();
+        assert_eq!(decode_columns(&encode_columns(&klines)).unwrap(), klines);
+    }
+
+    #[test]
+    fn columns_round_trip_extreme_values() {
+        let klines = vec![
+            kline(i64::MIN, u64::MAX, u64::MAX, 0, 0, u64::MAX),
+            kline(i64::MAX, 0, u64::MAX, 0, u64::MAX, 0),
+            kline(-60_000, 1, 1, 1, 1, 1),
+        ];
+        assert_eq!(decode_columns(&encode_columns(&klines)).unwrap(), klines);
+    }
+
+    #[test]
+    fn zigzag_signed_values() {
+        for value in [0, 1, -1, 63, -64, 64, -65, i64::MAX, i64::MIN] {
+            let mut out = Vec::new();
+            write_signed(&mut out, value);
+            let mut pos = 0;
+            assert_eq!(read_signed(&out, &mut pos).unwrap(), value);
+            assert_eq!(pos, out.len());
+        }
+        let mut out = Vec::new();
+        write_signed(&mut out, -1);
+        assert_eq!(out, [1]);
+    }
+
+    #[test]
+    fn empty_block_round_trip() {
+        assert_eq!(encode_columns(&[]), [0]);
+        assert!(decode_columns(&encode_columns(&[])).unwrap().is_empty());
+        let stored = compress_klines_with(&[], Codec::Lzma).unwrap();
+        assert!(decompress_klines(&stored).unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_columns_are_rejected() {
+        let encoded = encode_columns(&sample());
+        assert!(decode_columns(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode_columns(&[0xFF]).is_err());
+        // count larger than the data
+        assert!(decode_columns(&[100, 0]).is_err());
+    }
+
+    #[test]
+    fn codecs_round_trip_with_delta_flag() {
+        let klines = sample();
+        for codec in Codec::ALL.into_iter().filter(|c| c.is_available()) {
+            let stored = compress_klines_with(&klines, codec).unwrap();
+            assert_eq!(stored[0], codec.header() | DELTA_FLAG);
+            assert_eq!(
+                decompress_klines(&stored).unwrap(),
+                klines,
+                "{}",
+                codec.name()
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_headerless_xz_block() {
+        let klines = sample();
+        let stored = compress_lzma2_max(&bincode_bytes(&klines)).unwrap();
+        assert_eq!(stored[0], XZ_MAGIC);
+        assert_eq!(decompress_klines(&stored).unwrap(), klines);
+    }
+
+    #[test]
+    fn header_without_delta_flag_is_bincode() {
+        let klines = sample();
+        let mut stored = vec![CODEC_NONE];
+        stored.extend(bincode_bytes(&klines));
+        assert_eq!(decompress_klines(&stored).unwrap(), klines);
+        let mut stored = vec![CODEC_LZMA];
+        stored.extend(compress_lzma2_max(&bincode_bytes(&klines)).unwrap());
+        assert_eq!(decompress_klines(&stored).unwrap(), klines);
+    }
+
+    #[test]
+    fn bad_blocks_are_rejected() {
+        assert_eq!(
+            decompress_klines(&[]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            decompress_klines(&[0x7F, 1, 2]).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
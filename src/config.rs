@@ -0,0 +1,277 @@
+// config.rs - Startup configuration from the TOML `CONFIG_FILE` (written with the defaults on first run), overridable on the command line
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::compress::Codec;
+use crate::settings::{
+    BLOCK_CODEC, DB_PATH, DEFAULT_SYMBOL, DEFAULT_TIMEFRAME, INITIAL_LOAD_DAYS, WINDOW_FULLSCREEN,
+    WINDOW_SIZE, ZOOM_SENSITIVITY,
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use toml_edit::{DocumentMut, Item, Value};
+
+static CONFIG: RwLock<Option<Loaded>> = RwLock::new(None);
+
+/// The running config and what it was built from, for `reload`.
+struct Loaded {
+    config: Arc<Config>,
+    path: Option<PathBuf>,
+    overrides: Vec<(&'static str, String)>,
+}
+
+/// Settings a user may change without rebuilding; the defaults are the `settings.rs`
+/// constants of the same name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Chart symbol at startup.
+    pub symbol: String,
+    /// Chart timeframe at startup, minutes.
+    pub timeframe: i32,
+    pub db_path: String,
+    /// Codec of newly written blocks.
+    pub block_codec: Codec,
+    pub initial_load_days: i64,
+    pub zoom_sensitivity: f64,
+    /// Window size before `WINDOW_FILE` remembers one.
+    pub window_width: f32,
+    pub window_height: f32,
+    pub fullscreen: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            symbol: DEFAULT_SYMBOL.to_string(),
+            timeframe: DEFAULT_TIMEFRAME,
+            db_path: DB_PATH.to_string(),
+            block_codec: Codec::by_name(BLOCK_CODEC).unwrap_or(Codec::Lzma),
+            initial_load_days: INITIAL_LOAD_DAYS,
+            zoom_sensitivity: ZOOM_SENSITIVITY,
+            window_width: WINDOW_SIZE[0],
+            window_height: WINDOW_SIZE[1],
+            fullscreen: WINDOW_FULLSCREEN,
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid {} value {:?}", key, value))
+}
+
+fn positive<T: PartialOrd + Default>(key: &str, value: T) -> Result<T, String> {
+    if value > T::default() {
+        Ok(value)
+    } else {
+        Err(format!("{} must be positive", key))
+    }
+}
+
+impl Config {
+    /// Sets `key` from its text form, as given in the file or on the command line.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "symbol" => {
+                let symbol = value.trim().to_uppercase();
+                if symbol.is_empty() {
+                    return Err("symbol is empty".into());
+                }
+                self.symbol = symbol;
+            }
+            "timeframe" => self.timeframe = positive(key, parse(key, value)?)?,
+            "db_path" => self.db_path = value.to_string(),
+            "block_codec" => {
+                let codec =
+                    Codec::by_name(value).ok_or(format!("unknown block codec {:?}", value))?;
+                if !codec.is_available() {
+                    return Err(format!(
+                        "block codec {} needs the {} feature",
+                        value,
+                        codec.name()
+                    ));
+                }
+                self.block_codec = codec;
+            }
+            "initial_load_days" => self.initial_load_days = positive(key, parse(key, value)?)?,
+            "zoom_sensitivity" => self.zoom_sensitivity = positive(key, parse(key, value)?)?,
+            "window_width" => self.window_width = positive(key, parse(key, value)?)?,
+            "window_height" => self.window_height = positive(key, parse(key, value)?)?,
+            "fullscreen" => self.fullscreen = parse(key, value)?,
+            _ => return Err(format!("unknown config key {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Config of a TOML document; missing keys keep their defaults.
+    pub fn from_toml(text: &str) -> Result<Self, Box<dyn Error>> {
+        let document: DocumentMut = text.parse()?;
+        let mut config = Self::default();
+        for (key, item) in document.iter() {
+            let value = match item {
+                Item::Value(Value::String(s)) => s.value().clone(),
+                Item::Value(Value::Integer(i)) => i.value().to_string(),
+                Item::Value(Value::Float(f)) => f.value().to_string(),
+                Item::Value(Value::Boolean(b)) => b.value().to_string(),
+                _ => return Err(format!("unsupported value of {}", key).into()),
+            };
+            config.set(key, &value)?;
+        }
+        Ok(config)
+    }
+
+    /// TOML text with every key, commented.
+    pub fn to_toml(&self) -> String {
+        let mut document = DocumentMut::new();
+        document["symbol"] = toml_edit::value(self.symbol.as_str());
+        document["timeframe"] = toml_edit::value(self.timeframe as i64);
+        document["db_path"] = toml_edit::value(self.db_path.as_str());
+        document["block_codec"] = toml_edit::value(self.block_codec.name());
+        document["initial_load_days"] = toml_edit::value(self.initial_load_days);
+        document["zoom_sensitivity"] = toml_edit::value(self.zoom_sensitivity);
+        document["window_width"] = toml_edit::value(self.window_width as f64);
+        document["window_height"] = toml_edit::value(self.window_height as f64);
+        document["fullscreen"] = toml_edit::value(self.fullscreen);
+        for (key, comment) in [
+            (
+                "symbol",
+                "# Chart symbol and timeframe (minutes) at the first start, later the last ones are restored\n",
+            ),
+            ("db_path", "# Database directory\n"),
+            (
+                "block_codec",
+                "# Codec of newly written blocks: lzma (smallest), zstd (faster to read, needs the zstd feature) or none\n",
+            ),
+            (
+                "initial_load_days",
+                "# Days of 1m history fetched for a new symbol\n",
+            ),
+            (
+                "zoom_sensitivity",
+                "# Share of the visible bars zoomed per wheel step\n",
+            ),
+            (
+                "window_width",
+                "# Window size and mode until the window geometry is remembered\n",
+            ),
+        ] {
+            if let Some(mut key) = document.key_mut(key) {
+                key.leaf_decor_mut().set_prefix(comment);
+            }
+        }
+        document.to_string()
+    }
+
+    /// Reads `path`, writing the defaults there when it does not exist yet. Errors
+    /// are logged and the defaults used.
+    pub fn load_or_default(path: &Path) -> Self {
+        if !path.exists() {
+            let config = Self::default();
+            match std::fs::write(path, config.to_toml()) {
+                Ok(()) => tracing::info!("Wrote the default config to {}", path.display()),
+                Err(e) => tracing::warn!("Unable to write {}: {}", path.display(), e),
+            }
+            return config;
+        }
+        match std::fs::read_to_string(path)
+            .map_err(|e| e.into())
+            .and_then(|text| Self::from_toml(&text))
+        {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Unable to load {}, using defaults: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Makes `config` the one returned by `get`. It was read from `path` with the command
+/// line `overrides` applied on top; `reload` repeats that.
+pub fn init(config: Config, path: &Path, overrides: Vec<(&'static str, String)>) {
+    let mut loaded = CONFIG.write().unwrap_or_else(PoisonError::into_inner);
+    if loaded.is_some() {
+        tracing::warn!("Config already initialized");
+        return;
+    }
+    *loaded = Some(Loaded {
+        config: Arc::new(config),
+        path: Some(path.to_path_buf()),
+        overrides,
+    });
+}
+
+/// The current config, the defaults unless `init` was called. Read it where it is
+/// used rather than keeping a copy, so that a reload takes effect.
+pub fn get() -> Arc<Config> {
+    if let Some(loaded) = CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return loaded.config.clone();
+    }
+    CONFIG
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(|| Loaded {
+            config: Arc::new(Config::default()),
+            path: None,
+            overrides: Vec::new(),
+        })
+        .config
+        .clone()
+}
+
+/// File the config was loaded from, if any.
+pub fn path() -> Option<PathBuf> {
+    CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .and_then(|loaded| loaded.path.clone())
+}
+
+/// Outcome of `reload`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reloaded {
+    /// Some live setting changed (block codec, initial load days, zoom sensitivity).
+    pub changed: bool,
+    /// Startup settings changed in the file (symbol, timeframe, DB path, window); they
+    /// are kept until the next start.
+    pub needs_restart: bool,
+}
+
+/// Reads the config file again and applies the live settings, keeping the ones used
+/// only at startup. A broken file leaves the current config in place.
+pub fn reload() -> Result<Reloaded, Box<dyn Error>> {
+    let mut loaded = CONFIG.write().unwrap_or_else(PoisonError::into_inner);
+    let loaded = loaded.as_mut().ok_or("config is not initialized")?;
+    let path = loaded
+        .path
+        .as_ref()
+        .ok_or("config was not loaded from a file")?;
+    let mut config = Config::from_toml(&std::fs::read_to_string(path)?)?;
+    for (key, value) in &loaded.overrides {
+        // значения проверены при разборе командной строки
+        let _ = config.set(key, value);
+    }
+    let current = &loaded.config;
+    let live = Config {
+        symbol: current.symbol.clone(),
+        timeframe: current.timeframe,
+        db_path: current.db_path.clone(),
+        window_width: current.window_width,
+        window_height: current.window_height,
+        fullscreen: current.fullscreen,
+        ..config.clone()
+    };
+    let reloaded = Reloaded {
+        changed: live != **current,
+        needs_restart: live != config,
+    };
+    loaded.config = Arc::new(live);
+    Ok(reloaded)
+}
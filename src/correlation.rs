@@ -0,0 +1,237 @@
+// correlation.rs - Correlation matrix of hourly returns across the stored symbols over a trailing window
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::db::{AggrPeriod, Database};
+use crate::fetch::KLine;
+use crate::settings::CORRELATION_HOURS;
+use eframe::egui::{self, Align2, Color32, FontId, Rect, Stroke};
+use std::collections::HashMap;
+use std::error::Error;
+
+const CELL: f32 = 44.0;
+const LABEL_WIDTH: f32 = 80.0;
+// меньше общих часов - корреляция не показывается
+const MIN_COMMON_HOURS: usize = 24;
+
+/// Close-to-close returns keyed by the open time of the later record; only
+/// consecutive hours count, so gaps in the data do not produce multi-hour returns.
+fn hourly_returns(records: &[KLine]) -> HashMap<i64, f64> {
+    records
+        .windows(2)
+        .filter(|pair| {
+            pair[0].close > 0
+                && pair[1].open_time - pair[0].open_time == AggrPeriod::HOUR.duration_ms()
+        })
+        .map(|pair| {
+            (
+                pair[1].open_time,
+                pair[1].close as f64 / pair[0].close as f64 - 1.0,
+            )
+        })
+        .collect()
+}
+
+/// Pearson correlation over the hours present in both series.
+pub fn correlation(a: &HashMap<i64, f64>, b: &HashMap<i64, f64>) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = a
+        .iter()
+        .filter_map(|(time, x)| b.get(time).map(|y| (*x, *y)))
+        .collect();
+    if pairs.len() < MIN_COMMON_HOURS {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in &pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    (var_x > 0.0 && var_y > 0.0).then(|| cov / (var_x * var_y).sqrt())
+}
+
+/// Red for -1 through transparent to green for +1.
+fn correlation_color(value: f64) -> Color32 {
+    let intensity = value.abs().min(1.0) as f32;
+    if value < 0.0 {
+        Color32::from_rgb(200, 60, 60).gamma_multiply(intensity)
+    } else {
+        Color32::from_rgb(60, 180, 60).gamma_multiply(intensity)
+    }
+}
+
+pub struct CorrelationMatrix {
+    pub open: bool,
+    pub hours: i64,
+    symbols: Vec<String>,
+    matrix: Vec<Vec<Option<f64>>>,
+    computed: bool,
+}
+
+impl Default for CorrelationMatrix {
+    fn default() -> Self {
+        Self {
+            open: false,
+            hours: CORRELATION_HOURS,
+            symbols: Vec::new(),
+            matrix: Vec::new(),
+            computed: false,
+        }
+    }
+}
+
+impl CorrelationMatrix {
+    /// Correlates the last `hours` hourly records of every symbol in the DB, ending
+    /// at the latest record of any of them.
+    pub fn compute(&mut self, db: &Database) -> Result<(), Box<dyn Error>> {
+        let mut symbols = Vec::new();
+        let mut last_times = Vec::new();
+        for symbol in db.symbols()? {
+            let (_, last) = db.get_aggr_info(&symbol, AggrPeriod::HOUR)?;
+            if last > 0 {
+                symbols.push(symbol);
+                last_times.push(last);
+            }
+        }
+        let last = last_times.into_iter().max().unwrap_or(0);
+        let from = last - self.hours * AggrPeriod::HOUR.duration_ms();
+        let returns = symbols
+            .iter()
+            .map(|symbol| {
+                db.get_aggr_records(symbol, AggrPeriod::HOUR, from, last)
+                    .map(|records| hourly_returns(&records))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut matrix = vec![vec![None; symbols.len()]; symbols.len()];
+        for i in 0..symbols.len() {
+            matrix[i][i] = Some(1.0);
+            for j in i + 1..symbols.len() {
+                let value = correlation(&returns[i], &returns[j]);
+                matrix[i][j] = value;
+                matrix[j][i] = value;
+            }
+        }
+        self.symbols = symbols;
+        self.matrix = matrix;
+        self.computed = true;
+        Ok(())
+    }
+
+    /// Draws the matrix window; returns the symbol clicked to open its chart.
+    pub fn show(&mut self, ctx: &egui::Context, db: &Database) -> Option<String> {
+        if !self.computed {
+            if let Err(e) = self.compute(db) {
+                tracing::warn!("Correlation failed: {}", e);
+                self.computed = true;
+            }
+        }
+        let mut selected = None;
+        let mut open = self.open;
+        egui::Window::new("Correlation matrix")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Hourly returns over the last");
+                    ui.add(egui::DragValue::new(&mut self.hours).range(48..=8760));
+                    ui.label("hours");
+                    if ui.button("compute").clicked() {
+                        self.computed = false;
+                    }
+                });
+                if self.symbols.len() < 2 {
+                    ui.label("Need at least two symbols with hourly data");
+                    return;
+                }
+                egui::ScrollArea::both().show(ui, |ui| {
+                    selected = self.draw_matrix(ui);
+                });
+            });
+        self.open = open;
+        selected
+    }
+
+    fn draw_matrix(&self, ui: &mut egui::Ui) -> Option<String> {
+        let count = self.symbols.len();
+        let size = egui::vec2(
+            LABEL_WIDTH + CELL * count as f32,
+            CELL * 0.5 + CELL * count as f32,
+        );
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(10.0);
+        let grid_top = rect.top() + CELL * 0.5;
+        let pointer = response.hover_pos();
+        let mut hovered = None;
+        let mut selected = None;
+
+        for (i, symbol) in self.symbols.iter().enumerate() {
+            // сокращенные подписи столбцов, полные - у строк
+            let short: String = symbol.trim_end_matches("USDT").chars().take(6).collect();
+            painter.text(
+                egui::pos2(
+                    rect.left() + LABEL_WIDTH + (i as f32 + 0.5) * CELL,
+                    rect.top(),
+                ),
+                Align2::CENTER_TOP,
+                short,
+                font.clone(),
+                text_color,
+            );
+            let row = Rect::from_min_size(
+                egui::pos2(rect.left(), grid_top + i as f32 * CELL),
+                egui::vec2(LABEL_WIDTH, CELL),
+            );
+            painter.text(
+                row.left_center(),
+                Align2::LEFT_CENTER,
+                symbol,
+                font.clone(),
+                text_color,
+            );
+            if pointer.is_some_and(|pos| row.contains(pos)) {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                if response.clicked() {
+                    selected = Some(symbol.clone());
+                }
+            }
+            for (j, value) in self.matrix[i].iter().enumerate() {
+                let cell = Rect::from_min_size(
+                    egui::pos2(rect.left() + LABEL_WIDTH + j as f32 * CELL, row.top()),
+                    egui::vec2(CELL, CELL),
+                );
+                if let Some(value) = value {
+                    painter.rect_filled(cell.shrink(0.5), 0.0, correlation_color(*value));
+                    painter.text(
+                        cell.center(),
+                        Align2::CENTER_CENTER,
+                        format!("{:.2}", value),
+                        font.clone(),
+                        text_color,
+                    );
+                }
+                if pointer.is_some_and(|pos| cell.contains(pos)) {
+                    painter.rect_stroke(
+                        cell,
+                        0.0,
+                        Stroke::new(1.0, text_color),
+                        egui::StrokeKind::Inside,
+                    );
+                    hovered = Some((i, j, *value));
+                }
+            }
+        }
+        if let Some((i, j, value)) = hovered {
+            let value = value.map_or("not enough common hours".to_string(), |v| {
+                format!("{:.3}", v)
+            });
+            response.on_hover_text(format!(
+                "{} / {}: {}",
+                self.symbols[i], self.symbols[j], value
+            ));
+        }
+        selected
+    }
+}
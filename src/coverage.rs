@@ -0,0 +1,248 @@
+// coverage.rs - Data coverage map: per-symbol timeline of stored, missing and corrupted blocks
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::db::{BlockStatus, Database};
+use chrono::{Local, NaiveDateTime, TimeZone};
+use eframe::egui::{self, Color32, Rect, Sense};
+
+const ROW_HEIGHT: f32 = 14.0;
+const TIMELINE_WIDTH: f32 = 480.0;
+
+struct CoverageRow {
+    symbol: String,
+    blocks: Vec<(i64, BlockStatus)>,
+}
+
+/// Data removal confirmed by the user in the coverage window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeleteRequest {
+    Symbol(String),
+    Range { symbol: String, from: i64, to: i64 },
+}
+
+pub enum CoverageAction {
+    Refresh,
+    Delete(DeleteRequest),
+}
+
+#[derive(Default)]
+pub struct CoverageMap {
+    pub open: bool,
+    rows: Vec<CoverageRow>,
+    block_ms: i64,
+    confirm: Option<DeleteRequest>,
+    range_symbol: String,
+    range_from: String,
+    range_to: String,
+    range_error: Option<String>,
+}
+
+fn status_color(status: BlockStatus) -> Color32 {
+    match status {
+        BlockStatus::Present => Color32::from_rgb(60, 160, 90),
+        BlockStatus::Missing => Color32::from_gray(70),
+        BlockStatus::Corrupted => Color32::from_rgb(200, 60, 60),
+    }
+}
+
+fn parse_time(text: &str) -> Option<i64> {
+    let naive = NaiveDateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M")
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+}
+
+fn format_time(ts: i64) -> String {
+    Local
+        .timestamp_millis_opt(ts)
+        .single()
+        .map(|dt| dt.format("%d %b %Y %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+impl CoverageMap {
+    /// Re-reads block keys of all symbols in the DB.
+    pub fn refresh(&mut self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        self.block_ms = db.block_size() as i64 * 60_000;
+        self.rows.clear();
+        for symbol in db.symbols()? {
+            let blocks = db.coverage(&symbol)?;
+            self.rows.push(CoverageRow { symbol, blocks });
+        }
+        Ok(())
+    }
+
+    /// Draws the coverage window and the deletion confirmation dialog.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<CoverageAction> {
+        let mut action = None;
+        let mut open = self.open;
+        egui::Window::new("Data coverage")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("refresh").clicked() {
+                        action = Some(CoverageAction::Refresh);
+                    }
+                    for (status, name) in [
+                        (BlockStatus::Present, "present"),
+                        (BlockStatus::Missing, "missing"),
+                        (BlockStatus::Corrupted, "corrupted"),
+                    ] {
+                        ui.colored_label(status_color(status), "■");
+                        ui.label(name);
+                    }
+                });
+                if self.rows.is_empty() {
+                    ui.label("No data in DB");
+                    return;
+                }
+
+                // все символы на общей шкале времени
+                let start = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.blocks.first().map(|b| b.0))
+                    .min()
+                    .unwrap_or(0);
+                let end = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.blocks.last().map(|b| b.0 + self.block_ms))
+                    .max()
+                    .unwrap_or(start + self.block_ms);
+                let span = (end - start).max(1) as f32;
+
+                egui::Grid::new("coverage_rows")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for row in &self.rows {
+                            ui.label(&row.symbol);
+                            let (rect, response) = ui.allocate_exact_size(
+                                egui::vec2(TIMELINE_WIDTH, ROW_HEIGHT),
+                                Sense::hover(),
+                            );
+                            let painter = ui.painter_at(rect);
+                            painter.rect_filled(rect, 0.0, Color32::from_gray(30));
+                            let x_at =
+                                |ts: i64| rect.left() + (ts - start) as f32 / span * rect.width();
+                            for &(ts, status) in &row.blocks {
+                                let block_rect = Rect::from_min_max(
+                                    egui::pos2(x_at(ts), rect.top()),
+                                    // не меньше пикселя, чтобы одиночные дыры были видны
+                                    egui::pos2(
+                                        x_at(ts + self.block_ms).max(x_at(ts) + 1.0),
+                                        rect.bottom(),
+                                    ),
+                                );
+                                painter.rect_filled(block_rect, 0.0, status_color(status));
+                            }
+                            if let Some(pos) = response.hover_pos() {
+                                let ts =
+                                    start + ((pos.x - rect.left()) / rect.width() * span) as i64;
+                                let hovered = row
+                                    .blocks
+                                    .iter()
+                                    .find(|(block, _)| ts >= *block && ts < block + self.block_ms);
+                                let text = match hovered {
+                                    Some((block, status)) => format!(
+                                        "{} - {}: {:?}",
+                                        format_time(*block),
+                                        format_time(block + self.block_ms),
+                                        status
+                                    ),
+                                    None => format!("{}: no data", format_time(ts)),
+                                };
+                                response.on_hover_text_at_pointer(text);
+                            }
+                            if ui.small_button("delete").clicked() {
+                                self.confirm = Some(DeleteRequest::Symbol(row.symbol.clone()));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.label(format!("{} - {}", format_time(start), format_time(end)));
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("coverage_range_symbol")
+                        .selected_text(&self.range_symbol)
+                        .show_ui(ui, |ui| {
+                            for row in &self.rows {
+                                ui.selectable_value(
+                                    &mut self.range_symbol,
+                                    row.symbol.clone(),
+                                    &row.symbol,
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.range_from)
+                            .hint_text("YYYY-MM-DD HH:MM")
+                            .desired_width(110.0),
+                    );
+                    ui.label("-");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.range_to)
+                            .hint_text("YYYY-MM-DD HH:MM")
+                            .desired_width(110.0),
+                    );
+                    if ui.button("clear range").clicked() {
+                        self.range_error = None;
+                        match (parse_time(&self.range_from), parse_time(&self.range_to)) {
+                            _ if self.range_symbol.is_empty() => {
+                                self.range_error = Some("Select a symbol".to_string())
+                            }
+                            (Some(from), Some(to)) if from < to => {
+                                self.confirm = Some(DeleteRequest::Range {
+                                    symbol: self.range_symbol.clone(),
+                                    from,
+                                    to,
+                                })
+                            }
+                            _ => self.range_error = Some("Invalid time range".to_string()),
+                        }
+                    }
+                });
+                if let Some(error) = &self.range_error {
+                    ui.colored_label(Color32::LIGHT_RED, error);
+                }
+            });
+        self.open = open;
+
+        if let Some(request) = self.confirm.clone() {
+            let text = match &request {
+                DeleteRequest::Symbol(symbol) => format!("Delete all data of {}?", symbol),
+                DeleteRequest::Range { symbol, from, to } => format!(
+                    "Delete {} data from {} to {}?",
+                    symbol,
+                    format_time(*from),
+                    format_time(*to)
+                ),
+            };
+            egui::Window::new("Confirm deletion")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(text);
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            action = Some(CoverageAction::Delete(request));
+                            self.confirm = None;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm = None;
+                        }
+                    });
+                });
+        }
+        action
+    }
+}
@@ -1,13 +1,38 @@
 // crosshair.rs
+use crate::axes_util::{self, format_price, AxisTimezone};
 use crate::datawindow::DataWindow;
 use crate::drawing_util; // Добавлен импорт для drawing_util
-use chrono::{DateTime, Utc};
-use eframe::egui::Rect;
+use crate::render::Renderer;
+use crate::settings::TimeLabelFormat;
+use crate::theme::Theme;
+use eframe::egui::{Rect, Stroke};
+
+/// Compact volume for the readout: 2/1/0 decimals with k/m suffixes.
+pub fn format_volume(volume: f64) -> String {
+    let (base, unit) = if volume < 1000.0 {
+        (1.0, "")
+    } else if volume < 1_000_000.0 {
+        (1000.0, "k")
+    } else {
+        (1_000_000.0, "m")
+    };
+    let value = volume / base;
+    let decimals = if value < 10.0 {
+        2
+    } else if value < 100.0 {
+        1
+    } else {
+        0
+    };
+    format!("{:.*}{}", decimals, value, unit)
+}
 
 #[derive(Default)]
 pub struct Crosshair {
     rect: Option<egui::Rect>, // Private field for chart area
-    cached_bar_index: Option<usize>,
+    /// Bar of the keyboard crosshair (absolute index), shown instead of the mouse one.
+    pub key_bar: Option<usize>,
+    cached_bar_index: Option<(usize, AxisTimezone, TimeLabelFormat, usize)>,
     cached_bar_info: Option<String>,
 }
 
@@ -30,7 +55,7 @@ impl Crosshair {
 
         let adjusted_x = mouse_pos.x - data_window.pixel_offset;
         let normalized_x = (adjusted_x - chart_left) / chart_width;
-        if normalized_x < 0.0 || normalized_x >= 1.0 {
+        if !(0.0..1.0).contains(&normalized_x) {
             return None;
         }
         let index_float = normalized_x * visible_slice.len() as f32;
@@ -42,15 +67,120 @@ impl Crosshair {
         Some((actual_index, &visible_slice[index]))
     }
 
+    /// Chart area of the current frame; the keyboard crosshair needs it before the mouse
+    /// ever hovers the chart.
+    pub fn set_rect(&mut self, rect: Rect) {
+        self.rect = Some(rect);
+    }
+
+    /// Arrow keys move the keyboard crosshair one bar (ten with shift), Home/End jump
+    /// to the first/last bar, Escape releases it. The view scrolls to keep the bar
+    /// visible. The first arrow press starts at the bar under the mouse or the last one.
+    pub fn handle_keys(&mut self, ctx: &egui::Context, data_window: &mut DataWindow) {
+        let len = data_window.bars.len();
+        if ctx.wants_keyboard_input() || len == 0 {
+            return;
+        }
+        let (left, right, home, end, escape, shift) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowLeft),
+                i.key_pressed(egui::Key::ArrowRight),
+                i.key_pressed(egui::Key::Home),
+                i.key_pressed(egui::Key::End),
+                i.key_pressed(egui::Key::Escape),
+                i.modifiers.shift,
+            )
+        });
+        if escape {
+            self.key_bar = None;
+            return;
+        }
+        if !(left || right || home || end) {
+            return;
+        }
+        let current = self.key_bar.or_else(|| {
+            ctx.pointer_hover_pos()
+                .and_then(|pos| self.bar_at(pos, data_window))
+                .or(Some(
+                    (data_window.visible_range.1 as usize).clamp(1, len) - 1,
+                ))
+        });
+        let step = if shift { 10 } else { 1 };
+        let Some(mut bar) = current else {
+            return;
+        };
+        if self.key_bar.is_some() {
+            if left {
+                bar = bar.saturating_sub(step);
+            }
+            if right {
+                bar += step;
+            }
+        }
+        if home {
+            bar = 0;
+        }
+        if end {
+            bar = len - 1;
+        }
+        let bar = bar.min(len - 1);
+        self.key_bar = Some(bar);
+
+        // прокрутка, чтобы бар остался в видимой области
+        let (start, end) = data_window.visible_range;
+        let count = (end - start).max(1);
+        let bar = bar as i64;
+        let new_start = if bar < start {
+            bar
+        } else if bar >= end {
+            bar + 1 - count
+        } else {
+            start
+        };
+        if new_start != start {
+            let new_start = new_start.clamp(0, (len as i64 - count).max(0));
+            data_window.visible_range = (new_start, (new_start + count).min(len as i64));
+            data_window.pixel_offset = 0.0;
+        }
+    }
+
+    fn bar_at(&self, pos: egui::Pos2, data_window: &DataWindow) -> Option<usize> {
+        let rect = self.rect?;
+        self.get_bar_under_cursor_data(pos, data_window, rect)
+            .map(|(index, _)| index)
+    }
+
+    /// Crosshair position of the keyboard bar (its center at the close), `None`
+    /// when it is not set or outside the view of the last drawn chart.
+    pub fn key_position(&self, data_window: &DataWindow) -> Option<egui::Pos2> {
+        let rect = self.rect?;
+        let index = self.key_bar?;
+        let bar = data_window.bars.get(index)?;
+        let (start, end) = data_window.visible_range;
+        if (index as i64) < start || index as i64 >= end {
+            return None;
+        }
+        let volume_height = rect.height() * data_window.volume_height_ratio;
+        let price_rect =
+            egui::Rect::from_min_max(rect.min, egui::pos2(rect.max.x, rect.max.y - volume_height));
+        let x = drawing_util::calculate_bar_center_x(
+            index - start as usize,
+            (end - start) as usize,
+            price_rect,
+            data_window.pixel_offset,
+        );
+        let y = axes_util::create_scale_price_fn(data_window, price_rect)(bar.close);
+        Some(egui::pos2(x, y))
+    }
+
     pub fn get_bar_info(
         &mut self,
         mouse_pos: egui::Pos2,
         data_window: &DataWindow,
+        time_zone: AxisTimezone,
+        time_format: TimeLabelFormat,
     ) -> Option<String> {
-        let chart_area_rect = match self.rect {
-            Some(rect) => rect,
-            None => return None, // Область графика не определена
-        };
+        let chart_area_rect = self.rect?;
 
         // Определяем price_rect для информации о баре (исключая область объема)
         let volume_height = chart_area_rect.height() * data_window.volume_height_ratio;
@@ -64,59 +194,61 @@ impl Crosshair {
             self.get_bar_under_cursor_data(mouse_pos, data_window, price_rect)?;
 
         // Проверяем, есть ли уже информация об этом баре в кеше
+        let decimals = data_window.price_decimals();
         if let Some(cached_index) = self.cached_bar_index {
-            if cached_index == actual_index {
+            if cached_index == (actual_index, time_zone, time_format, decimals) {
                 return self.cached_bar_info.clone();
             }
         }
 
-        let dt = DateTime::<Utc>::from_timestamp_millis(bar.time).unwrap_or(Utc::now());
-        let volume_str = {
-            let volume = bar.volume;
-            let (base, unit) = if volume < 1000.0 {
-                (1.0, "")
-            } else if volume < 1_000_000.0 {
-                (1000.0, "k")
-            } else {
-                (1_000_000.0, "m")
-            };
-            let value = volume / base;
-            let decimals = if value < 10.0 {
-                2
-            } else if value < 100.0 {
-                1
-            } else {
-                0
-            };
-            format!("{:.*}{}", decimals, value, unit)
-        };
+        let dt = time_zone.datetime(bar.time);
+        let volume_str = format_volume(bar.volume);
         let bar_info = format!(
-            "{} | o {:.2} h {:.2} l {:.2} c {:.2} v {}",
-            dt.format("%H:%M"),
-            bar.open,
-            bar.high,
-            bar.low,
-            bar.close,
+            "{} | o {} h {} l {} c {} v {}",
+            dt.format(time_format.time_pattern(false)),
+            format_price(bar.open, decimals),
+            format_price(bar.high, decimals),
+            format_price(bar.low, decimals),
+            format_price(bar.close, decimals),
             volume_str
         );
 
         // Кешируем результат
-        self.cached_bar_index = Some(actual_index);
+        self.cached_bar_index = Some((actual_index, time_zone, time_format, decimals));
         self.cached_bar_info = Some(bar_info.clone());
 
         Some(bar_info)
     }
 
+    /// Price level under the cursor, `None` outside the price area of the last drawn chart.
+    pub fn price_at(&self, mouse_pos: egui::Pos2, data_window: &DataWindow) -> Option<f64> {
+        let chart_area_rect = self.rect?;
+        let volume_height = chart_area_rect.height() * data_window.volume_height_ratio;
+        let price_rect = egui::Rect::from_min_max(
+            chart_area_rect.min,
+            egui::pos2(chart_area_rect.max.x, chart_area_rect.max.y - volume_height),
+        );
+        if !price_rect.contains(mouse_pos) {
+            return None;
+        }
+        let (min_price, max_price) = data_window.price;
+        let range = (max_price - min_price).max(1e-9);
+        Some(
+            min_price
+                + (price_rect.bottom() - mouse_pos.y) as f64 / price_rect.height() as f64 * range,
+        )
+    }
+
     pub fn highlight_bar(
         &self,
-        ui: &mut egui::Ui,
+        renderer: &mut impl Renderer,
         rect: Rect, // Это общий прямоугольник области графика
         data_window: &DataWindow,
         mouse_pos: egui::Pos2,
         scale_price: &impl Fn(f64) -> f32,
+        theme: &Theme,
     ) {
-        let painter = ui.painter();
-        let highlight_color = egui::Color32::from_rgb(100, 100, 100);
+        let highlight_color = theme.bar_highlight;
 
         let volume_height = rect.height() * data_window.volume_height_ratio;
         let price_rect =
@@ -147,7 +279,7 @@ impl Crosshair {
             egui::pos2(x_right + 0.5, low_y + 0.5), // Сдвиг вправо-вниз на 0.5px
         );
         // Отрисовка закрашенного прямоугольника
-        painter.rect_filled(
+        renderer.rect_filled(
             expanded_rect,
             1.0, // Скругление углов
             highlight_color,
@@ -156,28 +288,28 @@ impl Crosshair {
 
     pub fn draw(
         &mut self,
-        ui: &mut egui::Ui,
+        renderer: &mut impl Renderer,
         rect: Rect,
         _data_window: &DataWindow,
         mouse_pos: egui::Pos2,
+        theme: &Theme,
     ) {
         self.rect = Some(rect);
-        let painter = ui.painter();
-        let color = egui::Color32::from_rgba_unmultiplied(255, 255, 255, 100);
+        let color = theme.crosshair;
 
-        painter.line_segment(
+        renderer.line_segment(
             [
                 egui::pos2(mouse_pos.x, rect.top()),
                 egui::pos2(mouse_pos.x, rect.bottom()),
             ],
-            (1.0, color),
+            Stroke::new(1.0, color),
         );
-        painter.line_segment(
+        renderer.line_segment(
             [
                 egui::pos2(rect.left(), mouse_pos.y),
                 egui::pos2(rect.right(), mouse_pos.y),
             ],
-            (1.0, color),
+            Stroke::new(1.0, color),
         );
     }
 }
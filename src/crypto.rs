@@ -0,0 +1,71 @@
+// crypto.rs - Optional encryption at rest of stored payloads (XChaCha20-Poly1305)
+// See CONVENTIONS.md for project structure and workflow
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::error::Error;
+
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+// Encrypted with the derived key and stored to detect a wrong passphrase
+const CHECK_PLAINTEXT: &[u8] = b"n-ohlcv";
+
+#[derive(Clone)]
+pub struct BlockCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl BlockCipher {
+    /// Derives the key from a passphrase with Argon2id.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("key derivation failed: {}", e))?;
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(&key.into()),
+        })
+    }
+
+    pub fn random_salt() -> [u8; SALT_LEN] {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+
+    /// Returns `nonce || ciphertext`; a fresh random nonce is used for every call.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "encryption failed")?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        if sealed.len() < NONCE_LEN {
+            return Err("encrypted payload is too short".into());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "decryption failed (wrong passphrase or damaged data)")?;
+        Ok(plaintext)
+    }
+
+    /// Value stored next to the salt to verify the passphrase on open.
+    pub fn check_value(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.encrypt(CHECK_PLAINTEXT)
+    }
+
+    pub fn verify(&self, check_value: &[u8]) -> bool {
+        self.decrypt(check_value)
+            .is_ok_and(|plaintext| plaintext == CHECK_PLAINTEXT)
+    }
+}
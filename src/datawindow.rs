@@ -1,13 +1,19 @@
+use crate::app_error::{self, AppError};
+use crate::calendar::TradingCalendar;
 use crate::compress;
-use crate::db::Database;
-use crate::fetch::KLine;
-use crate::rsi::WilderRSI;
+use crate::db::{AggrPeriod, Database, DbError, StoredBlock};
+use crate::fetch::{KLine, PRICE_MULTIPLIER};
+use crate::settings::{
+    AGGREGATION_PERIODS, MAX_CHART_BARS, REFETCH_CORRUPTED_BLOCKS, SYNC_REQUEST_PAUSE,
+};
+use crate::symbol_info::guess_price_decimals;
 use crate::timeframe;
-use crate::timeframe::Bar;
+use crate::timeframe::{Bar, BarType, MONTH_MINUTES};
+use crate::volume_profile::VolumeProfile;
 use chrono::Timelike;
 use std::error::Error;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct DataWindow {
     pub bars: Vec<Bar>,
     pub visible_range: (i64, i64),
@@ -15,16 +21,36 @@ pub struct DataWindow {
     pub min_indexes: Option<Vec<usize>>,
     pub max_indexes: Option<Vec<usize>>,
     pub recent_data: Vec<KLine>,
+    /// The minute still open on the exchange (`KlineStream`); completes the last time
+    /// bar on append but is never stored.
+    pub forming: Option<KLine>,
     pub timeframe_remainder: Vec<KLine>,
     pub volume_height_ratio: f32,
+    /// Share of the chart height taken by each indicator pane.
+    pub pane_height_ratio: f32,
     pub pixel_offset: f32,
     pub cached_visible_range: Option<(i64, i64)>,
     pub cached_max_volume: Option<f64>,
+    /// Volume profile of the visible bars and the visible range it was built for.
+    pub cached_volume_profile: Option<((i64, i64), VolumeProfile)>,
+    /// New klines were stored or added to `recent_data` since the bars were built.
+    pub dirty: bool,
+    /// Session hours of the symbol; bars never span two sessions.
+    pub calendar: TradingCalendar,
+    /// Decimals of the symbol tick size (`SymbolInfo`), `None` until it is known.
+    pub tick_decimals: Option<usize>,
+    /// Time buckets, or Renko/range bars built from the 1m klines.
+    pub bar_type: BarType,
+    /// Box or range in price of the Renko/range bars, fixed when the bars are loaded.
+    pub bar_size: f64,
+    /// Tier period the bars are built with instead of the requested timeframe, when
+    /// the loaded range is too long for it (`MAX_CHART_BARS`).
+    pub coarsened_timeframe: Option<i32>,
 }
 
-pub const BLOCK_SIZE: usize = 1000;
-
 impl DataWindow {
+    /// Fetches the missing blocks of `symbol` (blocking), then builds the bars.
+    #[tracing::instrument(level = "debug", skip(db, data_window))]
     pub fn get_data_window(
         db: &Database,
         symbol: &str,
@@ -33,24 +59,394 @@ impl DataWindow {
         timeframe_minutes: i32,
         data_window: &mut DataWindow,
     ) -> Result<(), Box<dyn Error>> {
-        println!(
+        tracing::debug!(
             "get_data_window: symbol = {}, start_time = {}, end_time = {}, timeframe = {}",
-            symbol, start_time, end_time, timeframe_minutes
+            symbol,
+            start_time,
+            end_time,
+            timeframe_minutes
+        );
+        timeframe::Timeframe::sync_data(
+            SYNC_REQUEST_PAUSE,
+            db,
+            symbol,
+            start_time,
+            end_time,
+            data_window,
+            &mut |_| {},
+        )?;
+        Self::load_bars(
+            db,
+            symbol,
+            start_time,
+            end_time,
+            timeframe_minutes,
+            data_window,
+        )
+    }
+
+    /// `get_data_window` without fetching: builds the bars from the data already stored.
+    pub fn load_bars(
+        db: &Database,
+        symbol: &str,
+        start_time: i64,
+        end_time: i64,
+        timeframe_minutes: i32,
+        data_window: &mut DataWindow,
+    ) -> Result<(), Box<dyn Error>> {
+        // тиры агрегации выровнены по UTC, а не по открытию сессий
+        data_window.coarsened_timeframe =
+            if data_window.bar_type == BarType::Time && data_window.calendar.is_continuous() {
+                Self::coarser_source(db, symbol, end_time - start_time, timeframe_minutes)?
+            } else {
+                None
+            };
+        let timeframe_minutes = data_window.coarsened_timeframe.unwrap_or(timeframe_minutes);
+        let tier = if data_window.calendar.is_continuous() {
+            Self::aggregation_tier(db, symbol, timeframe_minutes)?
+        } else {
+            None
+        };
+        let bars = match (data_window.bar_type, tier) {
+            (BarType::Time, _) if timeframe_minutes == MONTH_MINUTES => {
+                Self::month_bars(db, symbol, start_time, end_time, data_window)?
+            }
+            (BarType::Time, Some(tier)) => Self::tier_bars(
+                db,
+                symbol,
+                start_time,
+                end_time,
+                timeframe_minutes,
+                tier,
+                data_window,
+            )?,
+            (BarType::Time, None) => Self::minute_bars(
+                db,
+                symbol,
+                start_time,
+                end_time,
+                timeframe_minutes,
+                data_window,
+            )?,
+            (bar_type, _) => {
+                let klines = data_window.load_minute_klines(db, symbol, start_time, end_time)?;
+                data_window.timeframe_remainder.clear();
+                let last_close = klines.last().map_or(0.0, |k| {
+                    k.close as f64 / 10f64.powi(PRICE_MULTIPLIER as i32)
+                });
+                data_window.bar_size = bar_type.size_at(last_close);
+                data_window.price_bars(&klines, None)
+            }
+        };
+        data_window.bars = bars;
+        data_window.cached_volume_profile = None;
+        tracing::debug!("data_window.bars.len: {}", data_window.bars.len());
+        let len = data_window.bars.len() as i64;
+        let window_size = 200.min(data_window.bars.len()) as i64;
+        data_window.visible_range = (
+            (len - window_size).max(0), // start
+            len,                        // end
+        );
+        data_window.build_extrema_indexes();
+        data_window.update_price_range_extrema();
+        /*for bar in  &data_window.bars[data_window.bars.len()-50 ..] {
+            println!("{:?}", bar);
+        }*/
+        Ok(())
+    }
+
+    /// Decimals for prices of the chart: those of the tick size, or a guess from the
+    /// last close while the symbol metadata is not loaded.
+    pub fn price_decimals(&self) -> usize {
+        self.tick_decimals.unwrap_or_else(|| {
+            guess_price_decimals(self.bars.last().map_or(self.price.1, |bar| bar.close))
+        })
+    }
+
+    /// Close time of the newest minute: the end of the `recent_data` tail or, without
+    /// a tail, of the last bar (capped at now for a bar still in progress).
+    pub fn latest_data_time(&self, timeframe_minutes: i32) -> Option<i64> {
+        let timeframe_minutes = self.coarsened_timeframe.unwrap_or(timeframe_minutes);
+        if let Some(kline) = self.recent_data.last() {
+            return Some(kline.open_time + 60_000);
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        self.bars
+            .last()
+            .map(|bar| (bar.time + timeframe_minutes as i64 * 60_000).min(now))
+    }
+
+    /// Moves the visible range, keeping its width, so that the bar containing `time`
+    /// is in the middle. Returns `false` when `time` is outside the loaded bars.
+    pub fn center_on_time(&mut self, time: i64) -> bool {
+        let len = self.bars.len() as i64;
+        let Some(first) = self.bars.first() else {
+            return false;
+        };
+        if time < first.time {
+            return false;
+        }
+        let index = self.bars.partition_point(|b| b.time <= time) as i64 - 1;
+        let (start, end) = self.visible_range;
+        let count = (end - start).clamp(2.min(len), len);
+        let start = (index - count / 2).clamp(0, len - count);
+        self.visible_range = (start, start + count);
+        self.pixel_offset = 0.0;
+        true
+    }
+
+    /// Time of the bar in the middle of the visible range.
+    pub fn center_time(&self) -> Option<i64> {
+        let (start, end) = self.visible_range;
+        let index = ((start + end) / 2).clamp(0, self.bars.len() as i64 - 1);
+        self.bars.get(index as usize).map(|b| b.time)
+    }
+
+    /// Open times of the first and last visible bar.
+    pub fn visible_times(&self) -> Option<(i64, i64)> {
+        let (start, end) = self.visible_range;
+        let first = self.bars.get(start.max(0) as usize)?;
+        let last = self.bars.get((end - 1).max(0) as usize)?;
+        Some((first.time, last.time))
+    }
+
+    /// Visible range of the bars from `first` to `last` open time (as returned by
+    /// `visible_times`), moved to end at the newest bar when `at_last_bar`.
+    pub fn set_visible_times(&mut self, first: i64, last: i64, at_last_bar: bool) {
+        let len = self.bars.len() as i64;
+        if len == 0 {
+            return;
+        }
+        let start = self.bars.partition_point(|b| b.time < first) as i64;
+        let end = self.bars.partition_point(|b| b.time <= last) as i64;
+        let count = (end - start).clamp(2.min(len), len);
+        let start = if at_last_bar {
+            len - count
+        } else {
+            start.clamp(0, len - count)
+        };
+        self.visible_range = (start, start + count);
+        self.pixel_offset = 0.0;
+    }
+
+    /// Rebuilds the bars from the start of the last (possibly partial) bar with the
+    /// 1m data stored since, instead of reconverting the whole window. Returns the
+    /// number of bars added.
+    #[tracing::instrument(level = "debug", skip(self, db))]
+    pub fn append_new_bars(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        timeframe_minutes: i32,
+    ) -> Result<usize, Box<dyn Error>> {
+        self.dirty = false;
+        let timeframe_minutes = self.coarsened_timeframe.unwrap_or(timeframe_minutes);
+        let Some(from) = self.bars.last().map(|b| b.time) else {
+            return Ok(0);
+        };
+        let now = chrono::Utc::now().timestamp_millis();
+        if self.bar_type != BarType::Time {
+            return self.append_price_bars(db, symbol, from, now);
+        }
+        if timeframe_minutes == MONTH_MINUTES {
+            // последний месяц пересобирается из дневных баров
+            let remainder = std::mem::take(&mut self.timeframe_remainder);
+            let tail = Self::month_bars(db, symbol, from, now, self);
+            self.timeframe_remainder = remainder;
+            let tail = tail?;
+            let old_len = self.bars.len();
+            self.bars.truncate(old_len - 1);
+            self.bars.extend(tail);
+            self.update_extrema_indexes(old_len - 1);
+            self.cached_visible_range = None;
+            self.cached_max_volume = None;
+            self.cached_volume_profile = None;
+            return Ok(self.bars.len().saturating_sub(old_len));
+        }
+        let mut klines = self.load_minute_klines(db, symbol, from, now)?;
+        let last_minute = klines.last().map_or(from - 60_000, |k| k.open_time);
+        if let Some(forming) = self.forming.as_ref().filter(|k| k.open_time > last_minute) {
+            klines.push(forming.clone());
+        }
+        if klines.is_empty() {
+            return Ok(0);
+        }
+        // остаток незавершенного бара полной загрузки здесь не нужен
+        let remainder = std::mem::take(&mut self.timeframe_remainder);
+        let tail =
+            timeframe::Timeframe::convert_to_timeframe(klines, timeframe_minutes, 1, true, self);
+        self.timeframe_remainder = remainder;
+        let tail = tail?;
+        let old_len = self.bars.len();
+        self.bars.truncate(old_len - 1);
+        self.bars.extend(tail);
+        self.update_extrema_indexes(old_len - 1);
+        self.cached_visible_range = None;
+        self.cached_max_volume = None;
+        self.cached_volume_profile = None;
+        Ok(self.bars.len().saturating_sub(old_len))
+    }
+
+    /// Renko or range bars of `klines` with the current `bar_size`; Renko continues
+    /// after the brick `last`.
+    fn price_bars(&self, klines: &[KLine], last: Option<&Bar>) -> Vec<Bar> {
+        match self.bar_type {
+            BarType::Renko(_) => timeframe::Timeframe::renko_bars(klines, self.bar_size, last),
+            _ => timeframe::Timeframe::range_bars(klines, self.bar_size),
+        }
+    }
+
+    /// `append_new_bars` for Renko/range bars: bricks continue after the minute of the
+    /// last brick, range bars are rebuilt from the start of the last (unfinished) bar.
+    fn append_price_bars(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        last_time: i64,
+        now: i64,
+    ) -> Result<usize, Box<dyn Error>> {
+        let old_len = self.bars.len();
+        let (keep, from) = match self.bar_type {
+            BarType::Renko(_) => (old_len, last_time + 60_000),
+            _ => (old_len - 1, last_time),
+        };
+        let klines = self.load_minute_klines(db, symbol, from, now)?;
+        if klines.is_empty() {
+            return Ok(0);
+        }
+        let tail = self.price_bars(&klines, self.bars.last());
+        self.bars.truncate(keep);
+        self.bars.extend(tail);
+        self.update_extrema_indexes(keep);
+        self.cached_visible_range = None;
+        self.cached_max_volume = None;
+        self.cached_volume_profile = None;
+        Ok(self.bars.len().saturating_sub(old_len))
+    }
+
+    /// Month bars from daily bars: the 1d tier when it has data, else 1m blocks.
+    fn month_bars(
+        db: &Database,
+        symbol: &str,
+        start_time: i64,
+        end_time: i64,
+        data_window: &mut DataWindow,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        let day = AggrPeriod { minutes: 1440 };
+        let daily = if data_window.calendar.is_continuous() && db.get_aggr_info(symbol, day)?.1 != 0
+        {
+            Self::tier_bars(db, symbol, start_time, end_time, 1440, day, data_window)?
+        } else {
+            Self::minute_bars(db, symbol, start_time, end_time, 1440, data_window)?
+        };
+        // блоки 1m могут начинаться раньше запрошенного времени
+        let first = daily.partition_point(|b| b.time < start_time);
+        Ok(timeframe::Timeframe::month_bars(&daily[first..]))
+    }
+
+    /// Tier period to build the bars with instead of `timeframe_minutes` when the
+    /// range would give more than `MAX_CHART_BARS` bars: the smallest coarser tier
+    /// with data that fits, else the largest one. `None` keeps the timeframe.
+    fn coarser_source(
+        db: &Database,
+        symbol: &str,
+        range_ms: i64,
+        timeframe_minutes: i32,
+    ) -> Result<Option<i32>, Box<dyn Error>> {
+        let bar_count = |minutes: i64| range_ms / (minutes * 60_000);
+        if timeframe_minutes == MONTH_MINUTES
+            || bar_count(timeframe_minutes as i64) <= MAX_CHART_BARS as i64
+        {
+            return Ok(None);
+        }
+        let mut coarser = None;
+        for minutes in AGGREGATION_PERIODS {
+            if minutes <= timeframe_minutes as i64
+                || db.get_aggr_info(symbol, AggrPeriod { minutes })?.1 == 0
+            {
+                continue;
+            }
+            coarser = Some(minutes as i32);
+            if bar_count(minutes) <= MAX_CHART_BARS as i64 {
+                break;
+            }
+        }
+        Ok(coarser)
+    }
+
+    /// Largest aggregation tier with data whose period divides `timeframe_minutes`.
+    fn aggregation_tier(
+        db: &Database,
+        symbol: &str,
+        timeframe_minutes: i32,
+    ) -> Result<Option<AggrPeriod>, Box<dyn Error>> {
+        for minutes in AGGREGATION_PERIODS.iter().rev() {
+            let tier = AggrPeriod { minutes: *minutes };
+            if timeframe_minutes as i64 % tier.minutes == 0
+                && db.get_aggr_info(symbol, tier)?.1 != 0
+            {
+                return Ok(Some(tier));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Bars built from aggregated records; minutes after the last complete tier
+    /// record (stored blocks and `recent_data`) are combined into partial records.
+    #[allow(clippy::too_many_arguments)]
+    fn tier_bars(
+        db: &Database,
+        symbol: &str,
+        start_time: i64,
+        end_time: i64,
+        timeframe_minutes: i32,
+        tier: AggrPeriod,
+        data_window: &mut DataWindow,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
+        // first bar starts on a timeframe boundary, as with the "hh:00" cut for 1m data
+        let bar_period = AggrPeriod {
+            minutes: timeframe_minutes as i64,
+        };
+        let from = bar_period.bucket_start(start_time + bar_period.duration_ms() - 1);
+        let tail_start = (db.get_aggr_info(symbol, tier)?.1 + tier.duration_ms()).max(from);
+        let mut records = db.get_aggr_records(symbol, tier, from, tail_start - 1)?;
+        let tail = data_window.load_minute_klines(db, symbol, tail_start, end_time)?;
+        records.extend(tier.combine(&tail));
+        tracing::debug!(
+            "{} records of {}m tier, {} minutes after it",
+            records.len(),
+            tier.minutes,
+            tail.len()
         );
-        timeframe::Timeframe::sync_data(3, db, symbol, start_time, end_time, data_window)?;
 
+        data_window.timeframe_remainder.clear();
+        timeframe::Timeframe::convert_to_timeframe(
+            records,
+            timeframe_minutes,
+            tier.minutes as i32,
+            true,
+            data_window,
+        )
+    }
+
+    /// Bars converted from 1m blocks, completed with `recent_data`.
+    fn minute_bars(
+        db: &Database,
+        symbol: &str,
+        start_time: i64,
+        end_time: i64,
+        timeframe_minutes: i32,
+        data_window: &mut DataWindow,
+    ) -> Result<Vec<Bar>, Box<dyn Error>> {
         let mut bars = Vec::new();
-        let mut current_block_start = timeframe::Timeframe::get_dbtimestamp(start_time);
-        let period = 14;
-        let mut rsi_calculator = WilderRSI::new(period);
-        while current_block_start <= end_time {
-            println!("Get block from db, timestamp: {}", current_block_start);
-            if let Some(compressed_data) = db.get_block(symbol, current_block_start)? {
-                let mut block = compress::decompress_klines(&compressed_data)?;
+        for result in db.get_blocks_in_range(symbol, start_time, end_time) {
+            if let Some((block_start, mut block)) =
+                Self::checked_block(db, symbol, result, Some(data_window))?
+            {
                 if bars.is_empty() {
                     if let Some(i) = block.iter().position(|k| {
                         chrono::DateTime::from_timestamp_millis(k.open_time)
-                            .map_or(false, |dt| dt.minute() == 0)
+                            .is_some_and(|dt| dt.minute() == 0)
                     }) {
                         block = block.split_off(i); // cut  "hh:00"
                     }
@@ -58,48 +454,97 @@ impl DataWindow {
                 let converted = timeframe::Timeframe::convert_to_timeframe(
                     block,
                     timeframe_minutes,
+                    1,
                     false,
                     data_window,
-                    &mut rsi_calculator,
                 )?;
-                println!(
+                tracing::debug!(
                     "Block at {} has {} bars after conversion, remainder.len: {}",
-                    current_block_start,
+                    block_start,
                     converted.len(),
                     data_window.timeframe_remainder.len()
                 );
                 bars.extend(converted);
-            } else {
-                println!("No data for block at {}", current_block_start);
             }
-            current_block_start += BLOCK_SIZE as i64 * 60_000;
         }
-        println!("bars.len: {}", bars.len());
-        println!(
+        tracing::debug!("bars.len: {}", bars.len());
+        tracing::debug!(
             "data_window.recent_data (minutes): {}",
             data_window.recent_data.len()
         );
         bars.extend(timeframe::Timeframe::convert_to_timeframe(
             data_window.recent_data.to_vec(),
             timeframe_minutes,
+            1,
             true,
             data_window,
-            &mut rsi_calculator,
         )?);
-        data_window.bars = bars;
-        println!("data_window.bars.len: {}", data_window.bars.len());
-        let len = data_window.bars.len() as i64;
-        let window_size = 200.min(data_window.bars.len()) as i64;
-        data_window.visible_range = (
-            (len - window_size).max(0), // start
-            len,                        // end
+        Ok(bars)
+    }
+
+    /// A block of `Database::get_blocks_in_range`. A corrupted block is reported and
+    /// skipped or, when a window is given and `REFETCH_CORRUPTED_BLOCKS` is set,
+    /// re-fetched from the API.
+    fn checked_block(
+        db: &Database,
+        symbol: &str,
+        block: Result<StoredBlock, Box<dyn Error>>,
+        refetch_into: Option<&mut DataWindow>,
+    ) -> Result<Option<StoredBlock>, Box<dyn Error>> {
+        let e = match block {
+            Ok(block) => return Ok(Some(block)),
+            Err(e) => e,
+        };
+        let Some(&DbError::CorruptedBlock {
+            timestamp: block_start,
+            ..
+        }) = e.downcast_ref::<DbError>()
+        else {
+            return Err(e);
+        };
+        match refetch_into {
+            Some(dw) if REFETCH_CORRUPTED_BLOCKS => {
+                timeframe::Timeframe::refetch_block(db, symbol, block_start, dw)?;
+                match db.get_block(symbol, block_start)? {
+                    Some(data) => Ok(Some((block_start, compress::decompress_klines(&data)?))),
+                    None => Ok(None),
+                }
+            }
+            _ => {
+                app_error::report(AppError::CorruptedBlock {
+                    symbol: symbol.to_string(),
+                    block_start,
+                    message: e.to_string(),
+                });
+                Ok(None)
+            }
+        }
+    }
+
+    /// Loads raw 1m klines for `[start_time, end_time]` from stored blocks,
+    /// completed with the not yet stored `recent_data` tail.
+    pub fn load_minute_klines(
+        &self,
+        db: &Database,
+        symbol: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        let in_range = |k: &KLine| k.open_time >= start_time && k.open_time <= end_time;
+        let mut klines = Vec::new();
+        for result in db.get_blocks_in_range(symbol, start_time, end_time) {
+            if let Some((_, block)) = Self::checked_block(db, symbol, result, None)? {
+                klines.extend(block.into_iter().filter(in_range));
+            }
+        }
+        let last_stored = klines.last().map_or(i64::MIN, |k| k.open_time);
+        klines.extend(
+            self.recent_data
+                .iter()
+                .filter(|k| k.open_time > last_stored && in_range(k))
+                .cloned(),
         );
-        data_window.build_extrema_indexes();
-        data_window.update_price_range_extrema();
-        /*for bar in  &data_window.bars[data_window.bars.len()-50 ..] {
-            println!("{:?}", bar);
-        }*/
-        Ok(())
+        Ok(klines)
     }
 
     pub fn update_price_range_extrema(&mut self) {
@@ -196,6 +641,24 @@ impl DataWindow {
         max_volume
     }
 
+    /// Volume by price of the visible bars in `bin_count` bins, rebuilt when the
+    /// visible range changes.
+    pub fn visible_volume_profile(&mut self, bin_count: usize) -> Option<&VolumeProfile> {
+        let cached = self.cached_volume_profile.as_ref();
+        if cached.map(|(range, p)| (*range, p.bins.len())) != Some((self.visible_range, bin_count))
+        {
+            let (start, end) = self.visible_range;
+            let start = start.max(0) as usize;
+            let end = end.min(self.bars.len() as i64).max(0) as usize;
+            self.cached_volume_profile = self
+                .bars
+                .get(start..end)
+                .and_then(|bars| VolumeProfile::from_bars(bars, bin_count))
+                .map(|profile| (self.visible_range, profile));
+        }
+        self.cached_volume_profile.as_ref().map(|(_, p)| p)
+    }
+
     fn build_extrema_indexes(&mut self) {
         let mut mins: Vec<usize> = (0..self.bars.len()).collect();
         let mut maxs: Vec<usize> = (0..self.bars.len()).collect();
@@ -207,4 +670,24 @@ impl DataWindow {
         self.min_indexes = Some(mins);
         self.max_indexes = Some(maxs);
     }
+
+    /// Keeps the extrema indexes sorted after the bars from `from` on were replaced:
+    /// the old entries are dropped and the new bars are inserted by binary search,
+    /// without re-sorting the whole list.
+    fn update_extrema_indexes(&mut self, from: usize) {
+        let (Some(mins), Some(maxs)) = (self.min_indexes.as_mut(), self.max_indexes.as_mut())
+        else {
+            self.build_extrema_indexes();
+            return;
+        };
+        let bars = &self.bars;
+        mins.retain(|&i| i < from);
+        maxs.retain(|&i| i < from);
+        for (i, bar) in bars.iter().enumerate().skip(from) {
+            let pos = mins.partition_point(|&j| bars[j].low <= bar.low);
+            mins.insert(pos, i);
+            let pos = maxs.partition_point(|&j| bars[j].high >= bar.high);
+            maxs.insert(pos, i);
+        }
+    }
 }
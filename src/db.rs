@@ -1,91 +1,1346 @@
 // db.rs - Database operations, data aggregation system, OHLCV storage
 // See CONVENTIONS.md for project structure and workflow
 
+use crate::app_error::{self, AppError};
+use crate::compress;
+use crate::crypto::{BlockCipher, SALT_LEN};
+use crate::export;
 use crate::fetch::KLine;
-use crate::fetch::PRICE_MULTIPLIER;
-use crate::settings::AGGREGATION_VERSION;
-use chrono::{DateTime, Local, TimeZone, Timelike};
+use crate::fetch::{PRICE_MULTIPLIER, VOLUME_MULTIPLIER};
+use crate::settings::{
+    AGGREGATION_PERIODS, AGGREGATION_VERSION, ALERT_HISTORY_LIMIT, BLOCK_SIZE, DB_CACHE_CAPACITY,
+    DB_FLUSH_INTERVAL_MS, DB_USE_COMPRESSION, ENCRYPTION_PASSPHRASE_ENV,
+};
+use crate::symbol_info::{SymbolInfo, SymbolList};
+use crate::timeframe::{BarType, Timeframe};
+use chrono::{Local, TimeZone};
 use sled;
+use sled::transaction::{ConflictableTransactionError, TransactionError};
 use std::collections::BTreeMap;
 use std::error::Error;
 
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    #[error(
+        "block {symbol}_{timestamp} is corrupted (checksum {stored:08x}, actual {actual:08x})"
+    )]
+    CorruptedBlock {
+        symbol: String,
+        timestamp: i64,
+        stored: u32,
+        actual: u32,
+    },
+    #[error("key {key} holds {len} bytes instead of a big-endian timestamp")]
+    CorruptedKey { key: String, len: usize },
+}
+
+/// Start time and decoded klines of a stored 1m block.
+pub type StoredBlock = (i64, Vec<KLine>);
+
+/// State of one block window in the coverage map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    Present,
+    Missing,
+    Corrupted,
+}
+
+/// Problem found by `Database::verify`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyIssue {
+    /// Minutes missing inside a block or between blocks: open times of the first
+    /// and last missing minute.
+    Gap { from: i64, to: i64 },
+    /// A block failing its checksum or its decoding.
+    BadBlock { block_start: i64, message: String },
+}
+
+impl VerifyIssue {
+    /// One line describing the issue, times formatted with `format_time`.
+    pub fn describe(&self, format_time: impl Fn(i64) -> String) -> String {
+        match self {
+            VerifyIssue::Gap { from, to } => format!(
+                "gap {} - {} ({} minutes)",
+                format_time(*from),
+                format_time(*to),
+                (to - from) / 60_000 + 1
+            ),
+            VerifyIssue::BadBlock {
+                block_start,
+                message,
+            } => format!("bad block {}: {}", format_time(*block_start), message),
+        }
+    }
+}
+
+/// Result of `Database::verify`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VerifyReport {
+    pub blocks: usize,
+    pub klines: usize,
+    /// Open times of the first and last stored kline.
+    pub first: Option<i64>,
+    pub last: Option<i64>,
+    /// Sorted by time.
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn summary(&self, symbol: &str) -> String {
+        let (gaps, missing) = self
+            .issues
+            .iter()
+            .filter_map(|issue| match issue {
+                VerifyIssue::Gap { from, to } => Some((to - from) / 60_000 + 1),
+                VerifyIssue::BadBlock { .. } => None,
+            })
+            .fold((0, 0), |(gaps, missing), minutes| {
+                (gaps + 1, missing + minutes)
+            });
+        format!(
+            "{}: {} blocks, {} klines, {} gaps ({} missing minutes), {} bad blocks",
+            symbol,
+            self.blocks,
+            self.klines,
+            gaps,
+            missing,
+            self.issues.len() - gaps
+        )
+    }
+
+    // минуты между `previous` и `next`, кроме записанных простоев биржи
+    fn add_gap(&mut self, previous: Option<i64>, next: i64, downtime: &[(i64, i64)]) {
+        let Some(previous) = previous else {
+            return;
+        };
+        let (from, to) = (previous + 60_000, next - 60_000);
+        if from > to
+            || downtime
+                .iter()
+                .any(|(down_from, down_to)| *down_from <= from && to < *down_to)
+        {
+            return;
+        }
+        self.issues.push(VerifyIssue::Gap { from, to });
+    }
+}
+
+/// Price alert line of a symbol, stored with `Database::set_alerts`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PriceAlert {
+    pub price: f64,
+    /// Creation time, ms.
+    pub created: i64,
+}
+
+/// Labeled timestamp of a symbol, stored with `Database::set_bookmarks`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TimeBookmark {
+    /// Bookmarked time, ms.
+    pub time: i64,
+    pub label: String,
+}
+
+/// Triggered price alert, kept in the alert history (`Database::add_alert_event`).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AlertEvent {
+    pub symbol: String,
+    /// Price of the alert line.
+    pub level: f64,
+    /// The close crossed the line upwards.
+    pub above: bool,
+    /// Trigger time, ms.
+    pub time: i64,
+    /// Last close at the trigger.
+    pub price: f64,
+}
+
+/// Chart view of the last session, saved on exit with `Database::set_ui_state`
+/// and restored at startup.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UiState {
+    pub symbol: String,
+    pub timeframe: i32,
+    /// Open times of the first and last visible bar.
+    pub visible_times: Option<(i64, i64)>,
+    /// The newest bar was visible: the restored view ends at the newest bar.
+    pub at_last_bar: bool,
+    pub show_candles: bool,
+    pub bar_type: BarType,
+    pub volume_height_ratio: f32,
+    pub pane_height_ratio: f32,
+}
+
+/// Target period of an aggregation tier stored as `{symbol}_aggr{...}` records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggrPeriod {
+    pub minutes: i64,
+}
+
+impl AggrPeriod {
+    pub const HOUR: AggrPeriod = AggrPeriod { minutes: 60 };
+
+    pub fn duration_ms(&self) -> i64 {
+        self.minutes * 60_000
+    }
+
+    /// Series name of the tier; the hourly tier keeps the original `{symbol}_aggr`.
+    pub fn series(&self, symbol: &str) -> String {
+        if *self == Self::HOUR {
+            format!("{}_aggr", symbol)
+        } else {
+            format!("{}_aggr{}m", symbol, self.minutes)
+        }
+    }
+
+    /// Start of the period containing `timestamp_ms`, aligned to UTC.
+    /// Weekly periods start on Monday (1970-01-01 was a Thursday).
+    pub fn bucket_start(&self, timestamp_ms: i64) -> i64 {
+        let offset = if self.minutes % 10080 == 0 {
+            4 * 86_400_000
+        } else {
+            0
+        };
+        timestamp_ms - (timestamp_ms - offset).rem_euclid(self.duration_ms())
+    }
+
+    /// Combines time-sorted klines into one record per period; the last record is
+    /// partial when the klines end inside a period.
+    pub fn combine(&self, klines: &[KLine]) -> Vec<KLine> {
+        let mut groups: BTreeMap<i64, Vec<&KLine>> = BTreeMap::new();
+        for kline in klines {
+            groups
+                .entry(self.bucket_start(kline.open_time))
+                .or_default()
+                .push(kline);
+        }
+        groups
+            .into_iter()
+            .map(|(bucket, group)| KLine {
+                open_time: bucket,
+                open: group.first().unwrap().open,
+                high: group.iter().map(|k| k.high).max().unwrap_or(0),
+                low: group.iter().map(|k| k.low).min().unwrap_or(u64::MAX),
+                close: group.last().unwrap().close,
+                volume: group
+                    .iter()
+                    .fold(0u64, |sum, k| sum.saturating_add(k.volume)),
+            })
+            .collect()
+    }
+
+    /// Whole-hour tiers above 1h are built from the hourly tier instead of 1m blocks.
+    pub fn from_hourly(&self) -> bool {
+        *self != Self::HOUR && self.minutes % 60 == 0
+    }
+}
+
+// Block size of databases created before it was stored in `meta_block_size`
+const LEGACY_BLOCK_SIZE: usize = 1000;
+// Layout of stored klines: 1 - f64 volume, 2 - fixed-point u64 volume,
+// 3 - prices scaled by 10^PRICE_MULTIPLIER instead of 10^LEGACY_PRICE_MULTIPLIER,
+// 4 - codec header byte before the compressed payload (`compress::CODEC_*`),
+// 5 - volume scaled by 10^VOLUME_MULTIPLIER instead of 10^LEGACY_VOLUME_MULTIPLIER
+pub const KLINE_FORMAT: u64 = 5;
+const LEGACY_PRICE_MULTIPLIER: u32 = 2;
+const LEGACY_VOLUME_MULTIPLIER: u32 = 8;
+// Layout of block keys: 1 - "{series}_{timestamp}" text, 2 - binary `block_key`
+const KEY_FORMAT: u64 = 2;
+
+/// Key of a raw block or aggregated record of `series`: the name, a zero byte and the
+/// start time as big-endian bytes with the sign bit flipped, so the keys of a series
+/// sort by time and a scan can be bounded with `sled::Db::range`.
+fn block_key(series: &str, timestamp: i64) -> Vec<u8> {
+    let mut key = series_prefix(series);
+    key.extend_from_slice(&((timestamp as u64) ^ (1 << 63)).to_be_bytes());
+    key
+}
+
+fn series_prefix(series: &str) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(series.len() + 9);
+    prefix.extend_from_slice(series.as_bytes());
+    prefix.push(0);
+    prefix
+}
+
+/// Start time of a `block_key` of the series with `prefix`.
+fn key_timestamp(key: &[u8], prefix: &[u8]) -> Option<i64> {
+    let bytes: [u8; 8] = key.strip_prefix(prefix)?.try_into().ok()?;
+    Some((u64::from_be_bytes(bytes) ^ (1 << 63)) as i64)
+}
+
+fn crc_key(block_key: &[u8]) -> Vec<u8> {
+    [b"crc_".as_slice(), block_key].concat()
+}
+
+#[derive(Clone)]
 pub struct Database {
     db: sled::Db,
+    block_size: usize,
+    // set when block payloads are encrypted at rest
+    cipher: Option<BlockCipher>,
 }
 
-impl Database {
-    pub fn new(path: &str) -> Result<Self, sled::Error> {
-        let config = sled::Config::default()
-            .path(path)
-            .cache_capacity(4 * 1024 * 1024)
-            .use_compression(false);
-        config.open().map(|db| Self { db })
+impl Database {
+    /// Opens the DB, taking the encryption passphrase (if any) from `ENCRYPTION_PASSPHRASE_ENV`.
+    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
+        let passphrase = std::env::var(ENCRYPTION_PASSPHRASE_ENV).ok();
+        Self::open(path, passphrase.as_deref().filter(|p| !p.is_empty()))
+    }
+
+    /// Opens the DB. An encrypted DB requires `passphrase`; passing one for an
+    /// unencrypted DB encrypts all stored payloads.
+    pub fn open(path: &str, passphrase: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let config = sled::Config::default()
+            .path(path)
+            .cache_capacity(DB_CACHE_CAPACITY)
+            .use_compression(DB_USE_COMPRESSION)
+            .flush_every_ms(DB_FLUSH_INTERVAL_MS);
+        let db = config.open()?;
+        let block_size = match db.get(b"meta_block_size")? {
+            Some(bytes) => u64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])) as usize,
+            None => LEGACY_BLOCK_SIZE,
+        };
+        let key_format = match db.get(b"meta_key_format")? {
+            Some(bytes) => u64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
+            None => 1,
+        };
+        let kline_format = match db.get(b"meta_kline_format")? {
+            Some(bytes) => u64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
+            None => 1,
+        };
+        let cipher = match db.get(b"meta_encryption")? {
+            Some(meta) => {
+                let Some(passphrase) = passphrase else {
+                    return Err(format!(
+                        "database is encrypted, set {} to open it",
+                        ENCRYPTION_PASSPHRASE_ENV
+                    )
+                    .into());
+                };
+                let (salt, check) = meta.split_at(SALT_LEN.min(meta.len()));
+                let cipher = BlockCipher::from_passphrase(passphrase, salt)?;
+                if !cipher.verify(check) {
+                    return Err("wrong database passphrase".into());
+                }
+                Some(cipher)
+            }
+            None => None,
+        };
+        let mut database = Self {
+            db,
+            block_size,
+            cipher,
+        };
+        if key_format < KEY_FORMAT {
+            database.migrate_key_format()?;
+            database
+                .db
+                .insert(b"meta_key_format", &KEY_FORMAT.to_be_bytes())?;
+        }
+        if kline_format < KLINE_FORMAT {
+            database.migrate_kline_format(kline_format)?;
+            database
+                .db
+                .insert(b"meta_kline_format", &KLINE_FORMAT.to_be_bytes())?;
+        }
+        if database.block_size != BLOCK_SIZE {
+            database.migrate_block_size(BLOCK_SIZE)?;
+        }
+        database
+            .db
+            .insert(b"meta_block_size", &(BLOCK_SIZE as u64).to_be_bytes())?;
+        if let (None, Some(passphrase)) = (&database.cipher, passphrase) {
+            database.enable_encryption(passphrase)?;
+        }
+        Ok(database)
+    }
+
+    /// Decrypts a stored payload; a no-op for unencrypted databases.
+    pub fn open_payload(&self, stored: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(stored),
+            None => Ok(stored.to_vec()),
+        }
+    }
+
+    fn seal_payload(&self, payload: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(payload),
+            None => Ok(payload.to_vec()),
+        }
+    }
+
+    /// Encrypts every raw block, aggregated record and saved tail in one batch.
+    fn enable_encryption(&mut self, passphrase: &str) -> Result<(), Box<dyn Error>> {
+        tracing::info!("Encrypting database payloads");
+        let salt = BlockCipher::random_salt();
+        let cipher = BlockCipher::from_passphrase(passphrase, &salt)?;
+        let mut batch = WriteBatch::default();
+        for symbol in self.symbols()? {
+            let tiers = AGGREGATION_PERIODS.map(|minutes| AggrPeriod { minutes }.series(&symbol));
+            for series in std::iter::once(symbol.clone()).chain(tiers) {
+                for result in self.scan_blocks(&series, i64::MIN, i64::MAX) {
+                    let (timestamp, data) = result?;
+                    batch.insert_block(&series, timestamp, &data);
+                }
+            }
+            let recent_key = format!("recent_{}", symbol);
+            if let Some(recent) = self.db.get(recent_key.as_bytes())? {
+                batch
+                    .inserts
+                    .push((recent_key.into_bytes(), cipher.encrypt(&recent)?));
+            }
+        }
+        let mut meta = salt.to_vec();
+        meta.extend(cipher.check_value()?);
+        batch.inserts.push((b"meta_encryption".to_vec(), meta));
+        self.cipher = Some(cipher);
+        self.apply_batch(batch)
+    }
+
+    /// Number of 1m klines per stored block.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Start of the block window containing `timestamp_ms`.
+    pub fn block_start(&self, timestamp_ms: i64) -> i64 {
+        timestamp_ms.saturating_sub(timestamp_ms.rem_euclid(self.block_size as i64 * 60_000))
+    }
+
+    /// Symbols with raw 1m data (derived from `last_{symbol}` keys).
+    pub fn symbols(&self) -> Result<Vec<String>, sled::Error> {
+        let mut symbols = Vec::new();
+        for result in self.db.scan_prefix(b"last_") {
+            let (key, _) = result?;
+            let key_str = String::from_utf8_lossy(&key);
+            if let Some(symbol) = key_str.strip_prefix("last_") {
+                if !symbol.contains("_aggr") {
+                    symbols.push(symbol.to_string());
+                }
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Moves the blocks and aggregated records of every symbol, with their checksums,
+    /// from the text keys `{series}_{timestamp}` to `block_key`. Payloads are kept as is.
+    fn migrate_key_format(&self) -> Result<(), Box<dyn Error>> {
+        for symbol in self.symbols()? {
+            tracing::info!("Converting {} block keys to the binary layout", symbol);
+            let tiers = AGGREGATION_PERIODS.map(|minutes| AggrPeriod { minutes }.series(&symbol));
+            for series in std::iter::once(symbol.clone()).chain(tiers) {
+                let prefix = format!("{}_", series);
+                let mut batch = sled::Batch::default();
+                for result in self.db.scan_prefix(prefix.as_bytes()) {
+                    let (key, data) = result?;
+                    let key_str = String::from_utf8_lossy(&key);
+                    let Some(timestamp) = key_str
+                        .strip_prefix(&prefix)
+                        .and_then(|s| s.parse::<i64>().ok())
+                    else {
+                        continue;
+                    };
+                    let new_key = block_key(&series, timestamp);
+                    let old_crc_key = format!("crc_{}", key_str);
+                    if let Some(crc) = self.db.get(old_crc_key.as_bytes())? {
+                        batch.remove(old_crc_key.as_bytes());
+                        batch.insert(crc_key(&new_key), crc);
+                    }
+                    batch.remove(key);
+                    batch.insert(new_key, data);
+                }
+                self.db.apply_batch(batch)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stored blocks of `series` (a symbol or an aggregation tier) with
+    /// `from <= start <= to`, ascending, as `(start, stored bytes)`. Only keys inside
+    /// the bounds are read.
+    fn scan_blocks(
+        &self,
+        series: &str,
+        from: i64,
+        to: i64,
+    ) -> impl Iterator<Item = Result<(i64, sled::IVec), sled::Error>> {
+        let prefix = series_prefix(series);
+        self.db
+            .range(block_key(series, from)..=block_key(series, to))
+            .filter_map(move |result| match result {
+                Ok((key, data)) => {
+                    key_timestamp(&key, &prefix).map(|timestamp| Ok((timestamp, data)))
+                }
+                Err(e) => Some(Err(e)),
+            })
+    }
+
+    /// Rewrites blocks and saved tails stored in an older kline layout using the current
+    /// one; aggregation tiers are rebuilt from them on the next aggregation.
+    fn migrate_kline_format(&self, from_format: u64) -> Result<(), Box<dyn Error>> {
+        for symbol in self.symbols()? {
+            tracing::info!(
+                "Converting {} blocks from kline format {} to {}",
+                symbol,
+                from_format,
+                KLINE_FORMAT
+            );
+            let mut batch = WriteBatch::default();
+            for result in self.scan_blocks(&symbol, i64::MIN, i64::MAX) {
+                let (timestamp, data) = result?;
+                match self.convert_klines(&data, from_format) {
+                    Ok(klines) => {
+                        batch.insert_block(
+                            &symbol,
+                            timestamp,
+                            &compress::compress_klines(&klines)?,
+                        );
+                    }
+                    Err(e) => {
+                        app_error::report(AppError::DroppedBlock {
+                            key: format!("{}_{}", symbol, timestamp),
+                            message: e.to_string(),
+                        });
+                        batch.remove_block(&symbol, timestamp);
+                    }
+                }
+            }
+            self.apply_batch(batch)?;
+            let recent_key = format!("recent_{}", symbol);
+            if let Some(data) = self.db.get(recent_key.as_bytes())? {
+                match self.convert_klines(&data, from_format) {
+                    Ok(klines) => self.save_recent(&symbol, &klines)?,
+                    Err(_) => {
+                        self.db.remove(recent_key.as_bytes())?;
+                    }
+                }
+            }
+            self.invalidate_aggregation(&symbol)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes stored klines written in kline format `from_format`, scaled as now.
+    pub fn convert_klines(
+        &self,
+        stored: &[u8],
+        from_format: u64,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        let data = self.open_payload(stored)?;
+        let mut klines = if from_format < 2 {
+            // f64 volumes are scaled by VOLUME_MULTIPLIER on decoding
+            compress::decompress_legacy_klines(&data)?
+        } else {
+            let mut klines = compress::decompress_klines(&data)?;
+            if from_format < 5 {
+                let volume_scale = 10u64.pow(LEGACY_VOLUME_MULTIPLIER - VOLUME_MULTIPLIER);
+                for kline in &mut klines {
+                    kline.volume /= volume_scale;
+                }
+            }
+            klines
+        };
+        if from_format < 3 {
+            let price_scale = 10u64.pow(PRICE_MULTIPLIER - LEGACY_PRICE_MULTIPLIER);
+            for kline in &mut klines {
+                kline.open *= price_scale;
+                kline.high *= price_scale;
+                kline.low *= price_scale;
+                kline.close *= price_scale;
+            }
+        }
+        Ok(klines)
+    }
+
+    /// Re-chunks the stored 1m blocks of every symbol into windows of `new_size`
+    /// minutes. Windows that are still open or reach past the old blocks go to the
+    /// saved tail (`save_recent`) with it; the next sync or chart load stores them.
+    fn migrate_block_size(&mut self, new_size: usize) -> Result<(), Box<dyn Error>> {
+        tracing::info!(
+            "Block size changed from {} to {}, migrating stored blocks",
+            self.block_size,
+            new_size
+        );
+        let old_block_ms = self.block_size as i64 * 60_000;
+        let block_ms = new_size as i64 * 60_000;
+        let now = chrono::Utc::now().timestamp_millis();
+        for symbol in self.symbols()? {
+            let mut old_blocks = Vec::new();
+            let mut klines = Vec::new();
+            for result in self.scan_blocks(&symbol, i64::MIN, i64::MAX) {
+                let (timestamp, data) = result?;
+                old_blocks.push(timestamp);
+                klines.extend(compress::decompress_klines(&self.open_payload(&data)?)?);
+            }
+            let covered_to = old_blocks
+                .last()
+                .map_or(i64::MIN, |last| last + old_block_ms);
+
+            let mut chunks: BTreeMap<i64, Vec<KLine>> = BTreeMap::new();
+            for kline in klines {
+                let start = kline.open_time - kline.open_time.rem_euclid(block_ms);
+                chunks.entry(start).or_default().push(kline);
+            }
+            let mut batch = WriteBatch::default();
+            for timestamp in &old_blocks {
+                batch.remove_block(&symbol, *timestamp);
+            }
+            let mut tail = Vec::new();
+            for (start, mut chunk) in chunks {
+                chunk.sort_by_key(|k| k.open_time);
+                chunk.dedup_by_key(|k| k.open_time);
+                if start + block_ms > covered_to || start + block_ms + 60_000 > now {
+                    tail.extend(chunk);
+                    continue;
+                }
+                batch.insert_block(&symbol, start, &compress::compress_klines(&chunk)?);
+            }
+            let tail = Timeframe::merge_klines(tail, self.load_recent(&symbol)?);
+            // last_ must be able to move backwards here
+            self.db.remove(format!("last_{}", symbol).as_bytes())?;
+            tracing::info!(
+                "  {}: {} blocks rewritten as {}, {} minutes kept as the tail",
+                symbol,
+                old_blocks.len(),
+                batch.block_count(),
+                tail.len()
+            );
+            self.apply_batch(batch)?;
+            self.save_recent(&symbol, &tail)?;
+        }
+        self.block_size = new_size;
+        Ok(())
+    }
+
+    pub fn insert_block(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+        data: &[u8],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut batch = WriteBatch::default();
+        batch.insert_block(symbol, timestamp, data);
+        self.apply_batch(batch)
+    }
+
+    /// Commits all staged writes in one sled transaction, so an interrupted
+    /// multi-block sync never leaves blocks without their metadata.
+    pub fn apply_batch(&self, batch: WriteBatch) -> Result<(), Box<dyn Error>> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut sled_batch = sled::Batch::default();
+        for key in &batch.removes {
+            sled_batch.remove(key.as_slice());
+        }
+        for (key, value) in &batch.inserts {
+            sled_batch.insert(key.as_slice(), value.as_slice());
+        }
+        // Checksums cover the stored (encrypted) bytes
+        for (key, payload) in &batch.payloads {
+            let stored = self.seal_payload(payload)?;
+            sled_batch.insert(crc_key(key), &crc32fast::hash(&stored).to_be_bytes());
+            sled_batch.insert(key.as_slice(), stored);
+        }
+        for (key, stored) in &batch.stored {
+            sled_batch.insert(crc_key(key), &crc32fast::hash(stored).to_be_bytes());
+            sled_batch.insert(key.as_slice(), stored.as_slice());
+        }
+
+        let result = self.db.transaction(|tx| {
+            tx.apply_batch(&sled_batch)?;
+
+            // Only move last timestamps forward so that re-inserting an
+            // older block stays idempotent
+            for (symbol, timestamp) in &batch.last {
+                let last_key = format!("last_{}", symbol);
+                let last = match tx.get(last_key.as_bytes())? {
+                    Some(bytes) => match <[u8; 8]>::try_from(bytes.as_ref()) {
+                        Ok(bytes) => i64::from_be_bytes(bytes),
+                        // прерывает транзакцию: блоки не пишутся поверх испорченного last_
+                        Err(_) => {
+                            return Err(ConflictableTransactionError::Abort(
+                                DbError::CorruptedKey {
+                                    len: bytes.len(),
+                                    key: last_key,
+                                },
+                            ))
+                        }
+                    },
+                    None => 0,
+                };
+                if *timestamp > last {
+                    tx.insert(last_key.as_bytes(), &timestamp.to_be_bytes())?;
+                }
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => Ok(()),
+            Err(TransactionError::Abort(e)) => Err(e.into()),
+            Err(TransactionError::Storage(e)) => Err(e.into()),
+        }
+    }
+
+    /// Reads a block, verifies its checksum and decrypts it if the DB is encrypted.
+    /// Blocks written before checksums were introduced are returned unverified.
+    pub fn get_block(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+    ) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let key = block_key(symbol, timestamp);
+        let Some(data) = self.db.get(&key)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.verified_payload(symbol, timestamp, &data)?))
+    }
+
+    /// Reads a block as stored (still encrypted if the DB is) after verifying its checksum.
+    pub fn get_stored_block(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+    ) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let key = block_key(symbol, timestamp);
+        let Some(data) = self.db.get(&key)? else {
+            return Ok(None);
+        };
+        self.verify_checksum(symbol, timestamp, &data)?;
+        Ok(Some(data.to_vec()))
+    }
+
+    fn verify_checksum(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+        data: &[u8],
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(stored) = self.db.get(crc_key(&block_key(symbol, timestamp)))? {
+            let stored = u32::from_be_bytes(stored.as_ref().try_into().unwrap_or([0; 4]));
+            let actual = crc32fast::hash(data);
+            if stored != actual {
+                return Err(DbError::CorruptedBlock {
+                    symbol: symbol.to_string(),
+                    timestamp,
+                    stored,
+                    actual,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn verified_payload(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.verify_checksum(symbol, timestamp, data)?;
+        self.open_payload(data)
+    }
+
+    /// Decoded 1m blocks of `symbol` that may hold klines of `[start, end]`, ascending,
+    /// as `(block start, klines)`. Only the stored blocks inside the window are read.
+    /// A block failing its checksum yields `DbError::CorruptedBlock` and the
+    /// iteration goes on with the next one.
+    pub fn get_blocks_in_range(
+        &self,
+        symbol: &str,
+        start: i64,
+        end: i64,
+    ) -> impl Iterator<Item = Result<StoredBlock, Box<dyn Error>>> + '_ {
+        let symbol = symbol.to_string();
+        self.scan_blocks(&symbol, self.block_start(start), end)
+            .map(move |result| {
+                let (timestamp, data) = result?;
+                let payload = self.verified_payload(&symbol, timestamp, &data)?;
+                Ok((timestamp, compress::decompress_klines(&payload)?))
+            })
+    }
+
+    /// Removes a block and its checksum, e.g. a corrupted block before re-fetching it.
+    pub fn remove_block(&self, symbol: &str, timestamp: i64) -> Result<(), sled::Error> {
+        let key = block_key(symbol, timestamp);
+        self.db.remove(crc_key(&key))?;
+        self.db.remove(key)?;
+        Ok(())
+    }
+
+    /// Start timestamps of the stored raw blocks of a symbol, ascending.
+    pub fn block_timestamps(&self, symbol: &str) -> Result<Vec<i64>, sled::Error> {
+        self.scan_blocks(symbol, i64::MIN, i64::MAX)
+            .map(|result| result.map(|(timestamp, _)| timestamp))
+            .collect()
+    }
+
+    /// Checksum of the block version last uploaded to the backup target.
+    pub fn get_backup_mark(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+    ) -> Result<Option<u32>, sled::Error> {
+        Ok(self
+            .db
+            .get(format!("backup_{}_{}", symbol, timestamp).as_bytes())?
+            .map(|bytes| u32::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 4]))))
+    }
+
+    pub fn set_backup_mark(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+        checksum: u32,
+    ) -> Result<(), sled::Error> {
+        self.db.insert(
+            format!("backup_{}_{}", symbol, timestamp).as_bytes(),
+            &checksum.to_be_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Stores points of a derivatives metric (`funding`, `oi`) as plain big-endian f64
+    /// under `{metric}_{symbol}_{ts}`; existing points are overwritten.
+    pub fn insert_metric_points(
+        &self,
+        metric: &str,
+        symbol: &str,
+        points: &[(i64, f64)],
+    ) -> Result<(), sled::Error> {
+        let mut batch = sled::Batch::default();
+        for (timestamp, value) in points {
+            batch.insert(
+                format!("{}_{}_{}", metric, symbol, timestamp).as_bytes(),
+                &value.to_be_bytes(),
+            );
+        }
+        self.db.apply_batch(batch)
+    }
+
+    /// Metric points with `from <= ts <= to`, sorted by time.
+    pub fn get_metric_points(
+        &self,
+        metric: &str,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<(i64, f64)>, sled::Error> {
+        let prefix = format!("{}_{}_", metric, symbol);
+        let mut points = Vec::new();
+        for result in self.db.scan_prefix(prefix.as_bytes()) {
+            let (key, value) = result?;
+            let Some(timestamp) = String::from_utf8_lossy(&key)
+                .strip_prefix(&prefix)
+                .and_then(|ts| ts.parse::<i64>().ok())
+            else {
+                continue;
+            };
+            if timestamp < from || timestamp > to {
+                continue;
+            }
+            if let Ok(bytes) = value.as_ref().try_into() {
+                points.push((timestamp, f64::from_be_bytes(bytes)));
+            }
+        }
+        points.sort_by_key(|(timestamp, _)| *timestamp);
+        Ok(points)
+    }
+
+    /// Drops the stored points of a metric when they were fetched by another
+    /// `version` (kept in `version_{metric}_{symbol}`), like the aggregation tiers.
+    /// Returns `true` when the points were dropped and need a full re-fetch.
+    pub fn ensure_metric_version(
+        &self,
+        metric: &str,
+        symbol: &str,
+        version: i64,
+    ) -> Result<bool, sled::Error> {
+        let version_key = format!("version_{}_{}", metric, symbol);
+        let current_version = match self.db.get(version_key.as_bytes())? {
+            Some(bytes) => i64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
+            None => 0,
+        };
+        if current_version == version {
+            return Ok(false);
+        }
+        tracing::info!(
+            "New {} version ({}) detected, re-fetching {}",
+            metric,
+            version,
+            symbol
+        );
+        let mut batch = sled::Batch::default();
+        let prefix = format!("{}_{}_", metric, symbol);
+        for key in self.db.scan_prefix(prefix.as_bytes()).keys() {
+            batch.remove(key?);
+        }
+        batch.insert(version_key.as_bytes(), &version.to_be_bytes());
+        self.db.apply_batch(batch)?;
+        Ok(true)
+    }
+
+    /// Time of the newest stored point of a metric.
+    pub fn last_metric_time(&self, metric: &str, symbol: &str) -> Result<Option<i64>, sled::Error> {
+        let prefix = format!("{}_{}_", metric, symbol);
+        let mut last = None;
+        for result in self.db.scan_prefix(prefix.as_bytes()).keys() {
+            let key = result?;
+            if let Some(timestamp) = String::from_utf8_lossy(&key)
+                .strip_prefix(&prefix)
+                .and_then(|ts| ts.parse::<i64>().ok())
+            {
+                last = last.max(Some(timestamp));
+            }
+        }
+        Ok(last)
+    }
+
+    /// Stores a forced liquidation as `[side, price f64 BE, quantity f64 BE]` under
+    /// `liq_{symbol}_{ts}_{seq}`; `seq` separates events of the same millisecond.
+    pub fn insert_liquidation(
+        &self,
+        symbol: &str,
+        timestamp: i64,
+        seq: u32,
+        long: bool,
+        price: f64,
+        quantity: f64,
+    ) -> Result<(), sled::Error> {
+        let mut value = Vec::with_capacity(17);
+        value.push(long as u8);
+        value.extend_from_slice(&price.to_be_bytes());
+        value.extend_from_slice(&quantity.to_be_bytes());
+        self.db.insert(
+            format!("liq_{}_{}_{}", symbol, timestamp, seq).as_bytes(),
+            value,
+        )?;
+        Ok(())
+    }
+
+    /// Liquidations with `from <= ts <= to` as `(ts, long, price, quantity)`, sorted by time.
+    pub fn get_liquidations(
+        &self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<(i64, bool, f64, f64)>, sled::Error> {
+        let prefix = format!("liq_{}_", symbol);
+        let mut events = Vec::new();
+        for result in self.db.scan_prefix(prefix.as_bytes()) {
+            let (key, value) = result?;
+            let Some(timestamp) = String::from_utf8_lossy(&key)
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.split('_').next()?.parse::<i64>().ok())
+            else {
+                continue;
+            };
+            if timestamp < from || timestamp > to || value.len() != 17 {
+                continue;
+            }
+            let price = f64::from_be_bytes(value[1..9].try_into().unwrap_or([0; 8]));
+            let quantity = f64::from_be_bytes(value[9..17].try_into().unwrap_or([0; 8]));
+            events.push((timestamp, value[0] == 1, price, quantity));
+        }
+        events.sort_by_key(|event| event.0);
+        Ok(events)
+    }
+
+    /// Removes all raw and aggregated data of a symbol together with its metadata.
+    pub fn delete_symbol(&self, symbol: &str) -> Result<usize, Box<dyn Error>> {
+        let mut batch = sled::Batch::default();
+        let mut blocks = 0;
+        let tiers = AGGREGATION_PERIODS.map(|minutes| AggrPeriod { minutes }.series(symbol));
+        for series in std::iter::once(symbol.to_string()).chain(tiers) {
+            for result in self.scan_blocks(&series, i64::MIN, i64::MAX) {
+                let (timestamp, _) = result?;
+                if series == symbol {
+                    blocks += 1;
+                }
+                let key = block_key(&series, timestamp);
+                batch.remove(crc_key(&key));
+                batch.remove(key);
+            }
+        }
+        for prefix in [
+            format!("gap_{}_", symbol),
+            format!("backup_{}_", symbol),
+            format!("funding_{}_", symbol),
+            format!("oi_{}_", symbol),
+            format!("liq_{}_", symbol),
+        ] {
+            for key in self.db.scan_prefix(prefix.as_bytes()).keys() {
+                batch.remove(key?);
+            }
+        }
+        batch.remove(format!("last_{}", symbol).as_bytes());
+        batch.remove(format!("recent_{}", symbol).as_bytes());
+        batch.remove(format!("alerts_{}", symbol).as_bytes());
+        batch.remove(format!("info_{}", symbol).as_bytes());
+        batch.remove(format!("bookmarks_{}", symbol).as_bytes());
+        batch.remove(format!("version_funding_{}", symbol).as_bytes());
+        batch.remove(format!("version_oi_{}", symbol).as_bytes());
+        for minutes in AGGREGATION_PERIODS {
+            let series = AggrPeriod { minutes }.series(symbol);
+            for key in [
+                format!("last_{}", series),
+                format!("first_{}", series),
+                format!("version_{}", series),
+            ] {
+                batch.remove(key.as_bytes());
+            }
+        }
+        self.db.apply_batch(batch)?;
+        Ok(blocks)
+    }
+
+    /// Removes 1m klines with `from <= open_time < to`. Partially covered blocks and the
+    /// saved tail are rewritten, `last_{symbol}` moves back if needed and aggregation
+    /// tiers are rebuilt.
+    /// Sync does not re-download the cleared range unless it is after the new last block.
+    pub fn delete_range(&self, symbol: &str, from: i64, to: i64) -> Result<usize, Box<dyn Error>> {
+        let block_ms = self.block_size as i64 * 60_000;
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        let mut last_kept = 0;
+        for result in self.scan_blocks(symbol, i64::MIN, i64::MAX) {
+            let (timestamp, data) = result?;
+            if timestamp + block_ms <= from || timestamp >= to {
+                last_kept = last_kept.max(timestamp);
+                continue;
+            }
+            let klines = compress::decompress_klines(&self.open_payload(&data)?)?;
+            let before = klines.len();
+            let kept: Vec<KLine> = klines
+                .into_iter()
+                .filter(|k| k.open_time < from || k.open_time >= to)
+                .collect();
+            removed += before - kept.len();
+            if kept.is_empty() {
+                batch.remove_block(symbol, timestamp);
+            } else {
+                batch.insert_block(symbol, timestamp, &compress::compress_klines(&kept)?);
+                last_kept = last_kept.max(timestamp);
+            }
+        }
+        batch.clear_gaps(self, symbol, from, to)?;
+
+        // the saved tail must not bring the minutes back on the next load
+        let recent = self.load_recent(symbol)?;
+        let recent_len = recent.len();
+        let recent: Vec<KLine> = recent
+            .into_iter()
+            .filter(|k| k.open_time < from || k.open_time >= to)
+            .collect();
+        if recent.len() != recent_len {
+            removed += recent_len - recent.len();
+            let recent_key = format!("recent_{}", symbol).into_bytes();
+            if recent.is_empty() {
+                batch.removes.push(recent_key);
+            } else {
+                let stored = self.seal_payload(&compress::compress_klines(&recent)?)?;
+                batch.inserts.push((recent_key, stored));
+            }
+        }
+
+        // in the same transaction as the blocks, so a crash cannot leave last_ behind them
+        let last_key = format!("last_{}", symbol).into_bytes();
+        if last_kept == 0 {
+            batch.removes.push(last_key);
+        } else if self.get_last_timestamp(symbol)? > last_kept {
+            batch
+                .inserts
+                .push((last_key, last_kept.to_be_bytes().to_vec()));
+        }
+        self.apply_batch(batch)?;
+        // aggregated records of the range are stale
+        self.invalidate_aggregation(symbol)?;
+        Ok(removed)
+    }
+
+    /// Forces a rebuild of every aggregation tier of `symbol` on the next aggregation,
+    /// e.g. after data before the last aggregated record changed.
+    pub fn invalidate_aggregation(&self, symbol: &str) -> Result<(), Box<dyn Error>> {
+        for minutes in AGGREGATION_PERIODS {
+            self.db
+                .remove(format!("version_{}", AggrPeriod { minutes }.series(symbol)).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes all pending changes to disk, e.g. before the application exits.
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Stores the unfinished block tail (`DataWindow::recent_data`) of a symbol.
+    #[tracing::instrument(level = "debug", skip(self, klines), fields(klines = klines.len()))]
+    pub fn save_recent(&self, symbol: &str, klines: &[KLine]) -> Result<(), Box<dyn Error>> {
+        let key = format!("recent_{}", symbol);
+        if klines.is_empty() {
+            self.db.remove(key.as_bytes())?;
+        } else {
+            let stored = self.seal_payload(&compress::compress_klines(klines)?)?;
+            self.db.insert(key.as_bytes(), stored)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the tail saved by `save_recent`, empty if there is none.
+    pub fn load_recent(&self, symbol: &str) -> Result<Vec<KLine>, Box<dyn Error>> {
+        match self.db.get(format!("recent_{}", symbol).as_bytes())? {
+            Some(data) => Ok(compress::decompress_klines(&self.open_payload(&data)?)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Price alerts of a symbol, stored as JSON under `alerts_{symbol}`.
+    pub fn get_alerts(&self, symbol: &str) -> Result<Vec<PriceAlert>, Box<dyn Error>> {
+        match self.db.get(format!("alerts_{}", symbol).as_bytes())? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn set_alerts(&self, symbol: &str, alerts: &[PriceAlert]) -> Result<(), Box<dyn Error>> {
+        let key = format!("alerts_{}", symbol);
+        if alerts.is_empty() {
+            self.db.remove(key.as_bytes())?;
+        } else {
+            self.db
+                .insert(key.as_bytes(), serde_json::to_vec(alerts)?)?;
+        }
+        Ok(())
     }
 
-    pub fn insert_block(
+    /// Time bookmarks of a symbol, stored as JSON under `bookmarks_{symbol}`.
+    pub fn get_bookmarks(&self, symbol: &str) -> Result<Vec<TimeBookmark>, Box<dyn Error>> {
+        match self.db.get(format!("bookmarks_{}", symbol).as_bytes())? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn set_bookmarks(
         &self,
         symbol: &str,
-        timestamp: i64,
-        data: &[u8],
+        bookmarks: &[TimeBookmark],
     ) -> Result<(), Box<dyn Error>> {
-        let key = format!("{}_{}", symbol, timestamp);
-        self.db.transaction(|tx| {
-            // First insert
-            match tx.insert(key.as_bytes(), data) {
-                Ok(_) => {}
-                Err(e) => return Err(sled::transaction::ConflictableTransactionError::Abort(e)),
-            }
+        let key = format!("bookmarks_{}", symbol);
+        if bookmarks.is_empty() {
+            self.db.remove(key.as_bytes())?;
+        } else {
+            self.db
+                .insert(key.as_bytes(), serde_json::to_vec(bookmarks)?)?;
+        }
+        Ok(())
+    }
 
-            // Second insert
-            match tx.insert(
-                format!("last_{}", symbol).as_bytes(),
-                &timestamp.to_be_bytes(),
-            ) {
-                Ok(_) => {}
-                Err(e) => return Err(sled::transaction::ConflictableTransactionError::Abort(e)),
-            }
+    /// Triggered alerts of all symbols, oldest first, stored as JSON under `alert_history`.
+    pub fn get_alert_history(&self) -> Result<Vec<AlertEvent>, Box<dyn Error>> {
+        match self.db.get(b"alert_history")? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
 
-            Ok(())
-        })?;
+    /// Appends triggered alerts; only the last `ALERT_HISTORY_LIMIT` are kept.
+    pub fn add_alert_events(&self, events: &[AlertEvent]) -> Result<(), Box<dyn Error>> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let mut history = self.get_alert_history()?;
+        history.extend_from_slice(events);
+        let excess = history.len().saturating_sub(ALERT_HISTORY_LIMIT);
+        history.drain(..excess);
+        self.db
+            .insert(b"alert_history", serde_json::to_vec(&history)?)?;
+        Ok(())
+    }
 
+    pub fn clear_alert_history(&self) -> Result<(), Box<dyn Error>> {
+        self.db.remove(b"alert_history")?;
         Ok(())
     }
 
-    pub fn get_block(&self, symbol: &str, timestamp: i64) -> Result<Option<Vec<u8>>, sled::Error> {
-        let key = format!("{}_{}", symbol, timestamp);
-        match self.db.get(key.as_bytes())? {
-            Some(data) => Ok(Some(data.to_vec())),
+    /// Exchange metadata of a symbol, stored as JSON under `info_{symbol}`.
+    pub fn get_symbol_info(&self, symbol: &str) -> Result<Option<SymbolInfo>, Box<dyn Error>> {
+        match self.db.get(format!("info_{}", symbol).as_bytes())? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_symbol_info(&self, info: &SymbolInfo) -> Result<(), Box<dyn Error>> {
+        self.db.insert(
+            format!("info_{}", info.symbol).as_bytes(),
+            serde_json::to_vec(info)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn get_symbol_list(&self) -> Result<Option<SymbolList>, Box<dyn Error>> {
+        match self.db.get(b"symbol_list")? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_symbol_list(&self, list: &SymbolList) -> Result<(), Box<dyn Error>> {
+        self.db.insert(b"symbol_list", serde_json::to_vec(list)?)?;
+        Ok(())
+    }
+
+    /// Watchlist symbols in display order, stored as JSON under `watchlist`.
+    pub fn get_watchlist(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        match self.db.get(b"watchlist")? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn set_watchlist(&self, symbols: &[String]) -> Result<(), Box<dyn Error>> {
+        self.db.insert(b"watchlist", serde_json::to_vec(symbols)?)?;
+        Ok(())
+    }
+
+    /// Chart view of the last session, stored as JSON under `ui_state`.
+    pub fn get_ui_state(&self) -> Result<Option<UiState>, Box<dyn Error>> {
+        match self.db.get(b"ui_state")? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
             None => Ok(None),
         }
     }
 
-    pub fn get_last_timestamp(&self, symbol: &str) -> Result<i64, sled::Error> {
-        match self.db.get(format!("last_{}", symbol))? {
-            Some(bytes) => Ok(i64::from_be_bytes(bytes.as_ref().try_into().unwrap())),
+    pub fn set_ui_state(&self, state: &UiState) -> Result<(), Box<dyn Error>> {
+        self.db.insert(b"ui_state", serde_json::to_vec(state)?)?;
+        Ok(())
+    }
+
+    /// Start of the newest stored block (`last_{symbol}`), 0 when there is none.
+    pub fn get_last_timestamp(&self, symbol: &str) -> Result<i64, Box<dyn Error>> {
+        let key = format!("last_{}", symbol);
+        match self.db.get(key.as_bytes())? {
+            Some(bytes) => {
+                let bytes: [u8; 8] =
+                    bytes
+                        .as_ref()
+                        .try_into()
+                        .map_err(|_| DbError::CorruptedKey {
+                            len: bytes.len(),
+                            key,
+                        })?;
+                Ok(i64::from_be_bytes(bytes))
+            }
             None => Ok(0),
         }
     }
 
     pub fn get_first_timestamp(&self, symbol: &str) -> Result<i64, sled::Error> {
-        let prefix = format!("{}_", symbol);
-        let iter = self.db.scan_prefix(prefix.as_bytes());
+        match self.scan_blocks(symbol, i64::MIN, i64::MAX).next() {
+            Some(result) => Ok(result?.0),
+            None => Ok(0),
+        }
+    }
 
-        for result in iter {
-            match result {
-                Ok((key, _)) => {
-                    let key_str = String::from_utf8_lossy(&key);
-                    if let Some(timestamp_str) = key_str.strip_prefix(&prefix) {
-                        if let Ok(timestamp) = timestamp_str.parse::<i64>() {
-                            return Ok(timestamp);
-                        }
+    /// Returns recorded missing ranges `[from, to)` sorted by start time.
+    pub fn get_gaps(&self, symbol: &str) -> Result<Vec<(i64, i64)>, sled::Error> {
+        let prefix = format!("gap_{}_", symbol);
+        let mut gaps = Vec::new();
+        for result in self.db.scan_prefix(prefix.as_bytes()) {
+            let (key, value) = result?;
+            let key_str = String::from_utf8_lossy(&key);
+            if let Some(from) = key_str
+                .strip_prefix(&prefix)
+                .and_then(|s| s.parse::<i64>().ok())
+            {
+                let to = i64::from_be_bytes(value.as_ref().try_into().unwrap_or([0; 8]));
+                gaps.push((from, to));
+            }
+        }
+        gaps.sort_unstable();
+        Ok(gaps)
+    }
+
+    /// Status of every block window from the first stored block to `last_{symbol}`,
+    /// sorted by time. Checksums are verified, so this reads every block.
+    pub fn coverage(&self, symbol: &str) -> Result<Vec<(i64, BlockStatus)>, Box<dyn Error>> {
+        let mut stored = BTreeMap::new();
+        for timestamp in self.block_timestamps(symbol)? {
+            let status = match self.get_block(symbol, timestamp) {
+                Ok(_) => BlockStatus::Present,
+                Err(e) if e.downcast_ref::<DbError>().is_some() => BlockStatus::Corrupted,
+                Err(e) => return Err(e),
+            };
+            stored.insert(timestamp, status);
+        }
+
+        let (Some(&first), Some(&last)) = (stored.keys().next(), stored.keys().next_back()) else {
+            return Ok(Vec::new());
+        };
+        let block_ms = self.block_size as i64 * 60_000;
+        let mut coverage = Vec::new();
+        let mut timestamp = first;
+        while timestamp <= last {
+            let status = stored
+                .get(&timestamp)
+                .copied()
+                .unwrap_or(BlockStatus::Missing);
+            coverage.push((timestamp, status));
+            timestamp += block_ms;
+        }
+        Ok(coverage)
+    }
+
+    /// Walks the stored 1m blocks of `symbol` in order, decoding each one, and reports
+    /// blocks failing their checksum or decoding and minutes missing inside or between
+    /// blocks. Recorded exchange downtime (`get_gaps`) is not a gap; the window of a bad
+    /// block is reported as the block only. The unsaved tail after the last block is
+    /// not checked.
+    pub fn verify(&self, symbol: &str) -> Result<VerifyReport, Box<dyn Error>> {
+        let block_ms = self.block_size as i64 * 60_000;
+        let downtime = self.get_gaps(symbol)?;
+        let mut report = VerifyReport::default();
+        // последняя минута, уже учтенная в отчете
+        let mut previous: Option<i64> = None;
+        for result in self.scan_blocks(symbol, i64::MIN, i64::MAX) {
+            let (block_start, data) = result?;
+            report.blocks += 1;
+            let decoded = self
+                .verified_payload(symbol, block_start, &data)
+                .and_then(|payload| Ok(compress::decompress_klines(&payload)?));
+            match decoded {
+                Ok(klines) => {
+                    for kline in &klines {
+                        report.add_gap(previous, kline.open_time, &downtime);
+                        previous = previous.max(Some(kline.open_time));
+                        report.first = report.first.or(Some(kline.open_time));
+                        report.last = report.last.max(Some(kline.open_time));
                     }
+                    report.klines += klines.len();
+                }
+                Err(e) => {
+                    report.add_gap(previous, block_start, &downtime);
+                    report.issues.push(VerifyIssue::BadBlock {
+                        block_start,
+                        message: e.to_string(),
+                    });
+                    previous = previous.max(Some(block_start + block_ms - 60_000));
                 }
-                Err(e) => return Err(e),
             }
         }
-        Ok(0)
+        Ok(report)
     }
 
+    /// 1m klines with `start_time <= open_time <= end_time`, read from the raw blocks.
     pub fn get_range_data(
         &self,
         symbol: &str,
@@ -93,34 +1348,33 @@ impl Database {
         end_time: i64,
     ) -> Result<Vec<KLine>, Box<dyn Error>> {
         let mut klines = Vec::new();
-        let prefix = format!("{}_", symbol);
-        let iter = self.db.scan_prefix(prefix.as_bytes());
-
-        for result in iter {
-            match result {
-                Ok((key, data)) => {
-                    let key_str = String::from_utf8_lossy(&key);
-                    if let Some(timestamp_str) = key_str.strip_prefix(&prefix) {
-                        if let Ok(timestamp) = timestamp_str.parse::<i64>() {
-                            if timestamp >= start_time && timestamp <= end_time {
-                                let kline: KLine =
-                                    bincode::decode_from_slice(&data, bincode::config::standard())?
-                                        .0;
-                                klines.push(kline);
-                            }
-                        }
-                    }
-                }
-                Err(e) => return Err(e.into()),
-            }
+        for result in self.get_blocks_in_range(symbol, start_time, end_time) {
+            let (_, block) = result?;
+            klines.extend(
+                block
+                    .into_iter()
+                    .filter(|k| k.open_time >= start_time && k.open_time <= end_time),
+            );
         }
 
         klines.sort_by_key(|k| k.open_time);
         Ok(klines)
     }
 
+    /// Updates every tier from `AGGREGATION_PERIODS`, in order.
+    #[tracing::instrument(skip(self))]
     pub fn aggregate_ohlcv_data(&self, symbol: &str) -> Result<(), Box<dyn Error>> {
-        let aggr_symbol = format!("{}_aggr", symbol);
+        for minutes in AGGREGATION_PERIODS {
+            self.aggregate_period(symbol, AggrPeriod { minutes })?;
+        }
+        Ok(())
+    }
+
+    /// Incrementally builds complete buckets of one tier. Whole-hour tiers are built
+    /// from the hourly tier, the hourly tier itself from 1m blocks.
+    #[tracing::instrument(skip(self), fields(period = period.minutes))]
+    pub fn aggregate_period(&self, symbol: &str, period: AggrPeriod) -> Result<(), Box<dyn Error>> {
+        let aggr_symbol = period.series(symbol);
         let version_key = format!("version_{}", aggr_symbol);
 
         // Проверяем текущую версию
@@ -131,25 +1385,18 @@ impl Database {
 
         // Если версия новая - переделываем полностью
         if current_version != AGGREGATION_VERSION {
-            println!(
+            tracing::info!(
                 "New aggregation version ({}) detected, rebuilding data {}",
-                AGGREGATION_VERSION, aggr_symbol
+                AGGREGATION_VERSION,
+                aggr_symbol
             );
 
-            // Удаляем старые агрегированные данные
-            let aggr_prefix = format!("{}_", aggr_symbol);
-            let keys_to_delete: Vec<_> = self
-                .db
-                .scan_prefix(aggr_prefix.as_bytes())
-                .filter_map(|result| result.ok())
-                .map(|(key, _)| key)
-                .collect();
-
-            for key in keys_to_delete {
-                self.db.remove(key)?;
+            // Удаляем старые агрегированные данные и метаданные
+            let mut batch = WriteBatch::default();
+            for timestamp in self.block_timestamps(&aggr_symbol)? {
+                batch.remove_block(&aggr_symbol, timestamp);
             }
-
-            // Удаляем метаданные
+            self.apply_batch(batch)?;
             self.db.remove(format!("last_{}", aggr_symbol).as_bytes())?;
             self.db
                 .remove(format!("first_{}", aggr_symbol).as_bytes())?;
@@ -159,59 +1406,42 @@ impl Database {
                 .insert(version_key.as_bytes(), &AGGREGATION_VERSION.to_be_bytes())?;
         }
 
-        // Получаем границы исходных данных
-        let first_timestamp = self.get_first_timestamp(symbol)?;
-        let last_timestamp = self.get_last_timestamp(symbol)?;
-
-        if first_timestamp == 0 || last_timestamp == 0 {
-            println!("No data available for symbol {}", symbol);
+        // Границы исходных данных: [first, end)
+        let (source_first, source_end) = if period.from_hourly() {
+            let (first, last) = self.get_aggr_info(symbol, AggrPeriod::HOUR)?;
+            (first, last + AggrPeriod::HOUR.duration_ms())
+        } else {
+            let last = self.get_last_timestamp(symbol)?;
+            (
+                self.get_first_timestamp(symbol)?,
+                last + self.block_size as i64 * 60_000,
+            )
+        };
+        if source_first == 0 || source_end <= source_first {
+            tracing::info!("No data available for {} aggregation", aggr_symbol);
             return Ok(());
         }
 
         // Получаем последний timestamp агрегированных данных
         let last_aggr_timestamp = self.get_last_timestamp(&aggr_symbol)?;
-
         let start_time = if last_aggr_timestamp == 0 {
-            // Первый запуск - начинаем с начала данных, выравниваем по часам
-            let first_dt = DateTime::from_timestamp_millis(first_timestamp).unwrap_or_default();
-            let local_dt = first_dt.with_timezone(&Local);
-            let hour_aligned = local_dt
-                .with_minute(0)
-                .unwrap()
-                .with_second(0)
-                .unwrap()
-                .with_nanosecond(0)
-                .unwrap();
-            hour_aligned.timestamp_millis()
+            // Первый запуск - начинаем с начала данных
+            period.bucket_start(source_first)
         } else {
-            // Инкрементальное обновление - начинаем со следующего часа
-            last_aggr_timestamp + 3600000 // +1 час в миллисекундах
+            // Инкрементальное обновление - начинаем со следующего периода
+            last_aggr_timestamp + period.duration_ms()
         };
+        // Только завершенные периоды (end_time не включается)
+        let end_time = period.bucket_start(source_end);
 
-        // Округляем end_time до полного часа
-        let end_dt = DateTime::from_timestamp_millis(last_timestamp).unwrap_or_default();
-        let local_end_dt = end_dt.with_timezone(&Local);
-        let end_time = local_end_dt
-            .with_minute(0)
-            .unwrap()
-            .with_second(0)
-            .unwrap()
-            .with_nanosecond(0)
-            .unwrap()
-            .timestamp_millis();
-
-        if start_time > end_time {
-            if last_aggr_timestamp == 0 {
-                println!("No complete hour data available for aggregation {}", symbol);
-            } else {
-                println!("No new complete hour data for {}", symbol);
-            }
+        if start_time >= end_time {
+            tracing::info!("No new complete data for {}", aggr_symbol);
             return Ok(());
         }
 
-        println!(
+        tracing::info!(
             "Starting aggregation for {} from {} to {}",
-            symbol,
+            aggr_symbol,
             Local
                 .timestamp_millis_opt(start_time)
                 .unwrap()
@@ -223,172 +1453,568 @@ impl Database {
         );
 
         // Получаем данные для агрегации
-        let klines = self.get_range_data(symbol, start_time, end_time + 3599999)?; // +59:59.999 до конца часа
+        let klines = if period.from_hourly() {
+            self.get_aggr_records(symbol, AggrPeriod::HOUR, start_time, end_time - 1)?
+        } else {
+            self.get_range_data(symbol, start_time, end_time - 1)?
+        };
 
         if klines.is_empty() {
-            println!("No data available for aggregation in specified range");
+            tracing::info!("No data available for aggregation in specified range");
             return Ok(());
         }
 
-        // Группируем по часам
-        let mut hourly_groups: BTreeMap<i64, Vec<&KLine>> = BTreeMap::new();
+        // Создаем агрегированные записи одной транзакцией
+        let records = period.combine(&klines);
+        let first_aggr_timestamp = records.first().map_or(0, |k| k.open_time);
+        let last_processed_timestamp = records.last().map_or(0, |k| k.open_time);
+        let mut batch = WriteBatch::default();
+        for aggregated in &records {
+            let data = bincode::encode_to_vec(aggregated, bincode::config::standard())?;
+            batch.insert_block(&aggr_symbol, aggregated.open_time, &data);
+        }
+        let records_created = batch.block_count();
+        self.apply_batch(batch)?;
 
-        for kline in &klines {
-            let dt = DateTime::from_timestamp_millis(kline.open_time).unwrap_or_default();
-            let local_dt = dt.with_timezone(&Local);
-            let hour_timestamp = local_dt
-                .with_minute(0)
-                .unwrap()
-                .with_second(0)
+        // Сохраняем метаданные о первом timestamp для aggr
+        let first_key = format!("first_{}", aggr_symbol);
+        let current_first = match self.db.get(first_key.as_bytes())? {
+            Some(bytes) => i64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
+            None => 0,
+        };
+        let final_first = if current_first == 0 || first_aggr_timestamp < current_first {
+            self.db
+                .insert(first_key.as_bytes(), &first_aggr_timestamp.to_be_bytes())?;
+            first_aggr_timestamp
+        } else {
+            current_first
+        };
+
+        tracing::info!("Aggregation {} completed successfully:", aggr_symbol);
+        tracing::info!("  Created records: {}", records_created);
+        tracing::info!(
+            "  First data: {}",
+            Local
+                .timestamp_millis_opt(final_first)
                 .unwrap()
-                .with_nanosecond(0)
+                .format("%H:%M %d %b %Y")
+        );
+        tracing::info!(
+            "  Last data: {}",
+            Local
+                .timestamp_millis_opt(last_processed_timestamp)
                 .unwrap()
-                .timestamp_millis();
+                .format("%H:%M %d %b %Y")
+        );
+
+        // Выводим 5 последних записей для проверки
+        self.print_last_aggregated_records(symbol, period, 5)?;
+
+        Ok(())
+    }
+
+    /// First and last bucket of a tier, 0 when the tier is empty.
+    pub fn get_aggr_info(
+        &self,
+        symbol: &str,
+        period: AggrPeriod,
+    ) -> Result<(i64, i64), Box<dyn Error>> {
+        let aggr_symbol = period.series(symbol);
 
-            hourly_groups.entry(hour_timestamp).or_default().push(kline);
+        let first_timestamp = match self.db.get(format!("first_{}", aggr_symbol).as_bytes())? {
+            Some(bytes) => i64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
+            None => 0,
+        };
+
+        let last_timestamp = self.get_last_timestamp(&aggr_symbol)?;
+
+        Ok((first_timestamp, last_timestamp))
+    }
+
+    /// Aggregated records of a tier with `from <= open_time <= to`, sorted by time.
+    pub fn get_aggr_records(
+        &self,
+        symbol: &str,
+        period: AggrPeriod,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        let mut records = Vec::new();
+        if from > to {
+            return Ok(records);
         }
+        for result in self.scan_blocks(&period.series(symbol), from, to) {
+            let (_, data) = result?;
+            let kline: KLine = bincode::decode_from_slice(
+                &self.open_payload(&data)?,
+                bincode::config::standard(),
+            )?
+            .0;
+            records.push(kline);
+        }
+        Ok(records)
+    }
 
-        let mut records_created = 0;
-        let mut first_aggr_timestamp = 0i64;
-        let mut last_processed_timestamp = 0i64;
+    fn print_last_aggregated_records(
+        &self,
+        symbol: &str,
+        period: AggrPeriod,
+        count: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let records = self.get_aggr_records(symbol, period, i64::MIN, i64::MAX)?;
+        let last_records = &records[records.len().saturating_sub(count)..];
 
-        // Создаем агрегированные записи
-        for (hour_timestamp, group) in hourly_groups {
-            if group.is_empty() {
-                continue;
+        if !last_records.is_empty() {
+            tracing::debug!("\n  Last {} records:", last_records.len());
+            for kline in last_records {
+                tracing::debug!("    {}", export::format_record(kline));
             }
+        }
 
-            // Создаем агрегированную OHLCV запись
-            let aggregated = KLine {
-                open_time: hour_timestamp,
-                open: group.first().unwrap().open,
-                high: group.iter().map(|k| k.high).max().unwrap_or(0),
-                low: group.iter().map(|k| k.low).min().unwrap_or(u64::MAX),
-                close: group.last().unwrap().close,
-                volume: group.iter().map(|k| k.volume).sum(),
-            };
+        Ok(())
+    }
+}
 
-            // Сохраняем агрегированные данные
-            let data = bincode::encode_to_vec(&aggregated, bincode::config::standard())?;
-            self.insert_block(&aggr_symbol, hour_timestamp, &data)?;
+/// Writes staged for a single atomic commit via `Database::apply_batch`.
+#[derive(Default)]
+pub struct WriteBatch {
+    inserts: Vec<(Vec<u8>, Vec<u8>)>,
+    // block payloads, encrypted and checksummed on apply
+    payloads: Vec<(Vec<u8>, Vec<u8>)>,
+    // blocks already in their stored form, only checksummed on apply
+    stored: Vec<(Vec<u8>, Vec<u8>)>,
+    removes: Vec<Vec<u8>>,
+    last: BTreeMap<String, i64>,
+    blocks: usize,
+}
+
+impl WriteBatch {
+    pub fn is_empty(&self) -> bool {
+        self.inserts.is_empty()
+            && self.removes.is_empty()
+            && self.payloads.is_empty()
+            && self.stored.is_empty()
+    }
+
+    /// Number of staged blocks.
+    pub fn block_count(&self) -> usize {
+        self.blocks
+    }
+
+    /// Stages a block together with its checksum and the `last_{symbol}` update.
+    pub fn insert_block(&mut self, symbol: &str, timestamp: i64, data: &[u8]) {
+        self.payloads
+            .push((block_key(symbol, timestamp), data.to_vec()));
+        let last = self.last.entry(symbol.to_string()).or_insert(timestamp);
+        *last = (*last).max(timestamp);
+        self.blocks += 1;
+    }
+
+    /// Stages a block in its stored form (`Database::get_stored_block`, e.g. from a
+    /// backup of this DB): written as is, without encrypting it again.
+    pub fn insert_stored_block(&mut self, symbol: &str, timestamp: i64, stored: &[u8]) {
+        self.stored
+            .push((block_key(symbol, timestamp), stored.to_vec()));
+        let last = self.last.entry(symbol.to_string()).or_insert(timestamp);
+        *last = (*last).max(timestamp);
+        self.blocks += 1;
+    }
+
+    /// Stages removal of a block and its checksum.
+    pub fn remove_block(&mut self, symbol: &str, timestamp: i64) {
+        let key = block_key(symbol, timestamp);
+        self.removes.push(crc_key(&key));
+        self.removes.push(key);
+    }
+
+    /// Stages known missing ranges `[from, to)` (exchange downtime) for a symbol.
+    pub fn insert_gaps(&mut self, symbol: &str, gaps: &[(i64, i64)]) {
+        for (from, to) in gaps {
+            self.inserts.push((
+                format!("gap_{}_{}", symbol, from).into_bytes(),
+                to.to_be_bytes().to_vec(),
+            ));
+        }
+    }
 
-            if first_aggr_timestamp == 0 {
-                first_aggr_timestamp = hour_timestamp;
+    /// Stages removal of recorded gaps starting inside `[from, to)`, e.g. before a block is rewritten.
+    pub fn clear_gaps(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<(), sled::Error> {
+        for (gap_from, _) in db.get_gaps(symbol)? {
+            if gap_from >= from && gap_from < to {
+                self.removes
+                    .push(format!("gap_{}_{}", symbol, gap_from).into_bytes());
             }
-            last_processed_timestamp = hour_timestamp;
-            records_created += 1;
         }
+        Ok(())
+    }
+}
 
-        // Сохраняем метаданные о первом timestamp для aggr
-        if first_aggr_timestamp > 0 {
-            let current_first = match self.db.get(format!("first_{}", aggr_symbol).as_bytes())? {
-                Some(bytes) => i64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
-                None => 0,
-            };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-            if current_first == 0 || first_aggr_timestamp < current_first {
-                self.db.insert(
-                    format!("first_{}", aggr_symbol).as_bytes(),
-                    &first_aggr_timestamp.to_be_bytes(),
-                )?;
-            }
+    const MINUTE: i64 = 60_000;
+    // начало блока при BLOCK_SIZE 1000, ноябрь 2023
+    const BASE: i64 = 28_333 * 1000 * MINUTE;
+
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+    /// Directory of a test DB, removed on drop; declare it before the `Database`.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "n-ohlcv-test-{}-{}",
+                std::process::id(),
+                NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
         }
 
-        if records_created > 0 {
-            let final_first = match self.db.get(format!("first_{}", aggr_symbol).as_bytes())? {
-                Some(bytes) => i64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
-                None => first_aggr_timestamp,
-            };
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
 
-            println!("Aggregation {} completed successfully:", aggr_symbol);
-            println!("  Created records: {}", records_created);
-            println!(
-                "  First data: {}",
-                Local
-                    .timestamp_millis_opt(final_first)
-                    .unwrap()
-                    .format("%H:%M %d %b %Y")
-            );
-            println!(
-                "  Last data: {}",
-                Local
-                    .timestamp_millis_opt(last_processed_timestamp)
-                    .unwrap()
-                    .format("%H:%M %d %b %Y")
-            );
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn minutes(from: i64, count: usize) -> Vec<KLine> {
+        (0..count as u64)
+            .map(|i| KLine {
+                open_time: from + i as i64 * MINUTE,
+                open: 100 + i,
+                high: 110 + i,
+                low: 90 + i,
+                close: 101 + i,
+                volume: i,
+            })
+            .collect()
+    }
 
-            // Выводим 5 последних записей для проверки
-            self.print_last_aggregated_records(&aggr_symbol, 5)?;
+    /// Stores klines as sync does, one block per `block_start` window.
+    fn store(db: &Database, symbol: &str, klines: Vec<KLine>) {
+        let mut blocks: BTreeMap<i64, Vec<KLine>> = BTreeMap::new();
+        for kline in klines {
+            blocks
+                .entry(db.block_start(kline.open_time))
+                .or_default()
+                .push(kline);
+        }
+        let mut batch = WriteBatch::default();
+        for (start, block) in blocks {
+            batch.insert_block(symbol, start, &compress::compress_klines(&block).unwrap());
         }
+        db.apply_batch(batch).unwrap();
+    }
 
-        Ok(())
+    #[test]
+    fn delete_range_moves_last_back() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        let block_ms = db.block_size() as i64 * MINUTE;
+        store(&db, "BTCUSDT", minutes(BASE, 3 * db.block_size()));
+        assert_eq!(
+            db.get_last_timestamp("BTCUSDT").unwrap(),
+            BASE + 2 * block_ms
+        );
+
+        let removed = db
+            .delete_range("BTCUSDT", BASE + 2 * block_ms, BASE + 3 * block_ms)
+            .unwrap();
+        assert_eq!(removed, db.block_size());
+        assert_eq!(db.get_last_timestamp("BTCUSDT").unwrap(), BASE + block_ms);
+        assert_eq!(
+            db.block_timestamps("BTCUSDT").unwrap(),
+            [BASE, BASE + block_ms]
+        );
+
+        db.delete_range("BTCUSDT", BASE, BASE + 3 * block_ms)
+            .unwrap();
+        assert_eq!(db.get_last_timestamp("BTCUSDT").unwrap(), 0);
+        assert!(db.block_timestamps("BTCUSDT").unwrap().is_empty());
     }
 
-    pub fn get_aggr_info(&self, symbol: &str) -> Result<(i64, i64), Box<dyn Error>> {
-        let aggr_symbol = format!("{}_aggr", symbol);
+    #[test]
+    fn delete_range_trims_the_saved_tail() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        let block_ms = db.block_size() as i64 * MINUTE;
+        store(&db, "BTCUSDT", minutes(BASE, db.block_size()));
+        db.save_recent("BTCUSDT", &minutes(BASE + block_ms, 10))
+            .unwrap();
 
-        let first_timestamp = match self.db.get(format!("first_{}", aggr_symbol).as_bytes())? {
-            Some(bytes) => i64::from_be_bytes(bytes.as_ref().try_into().unwrap_or([0; 8])),
-            None => 0,
-        };
+        let removed = db
+            .delete_range("BTCUSDT", BASE + block_ms + 4 * MINUTE, BASE + 2 * block_ms)
+            .unwrap();
+        assert_eq!(removed, 6);
+        assert_eq!(
+            db.load_recent("BTCUSDT").unwrap(),
+            minutes(BASE + block_ms, 4)
+        );
+        assert_eq!(db.block_timestamps("BTCUSDT").unwrap(), [BASE]);
 
-        let last_timestamp = self.get_last_timestamp(&aggr_symbol)?;
+        db.delete_range("BTCUSDT", BASE, BASE + 2 * block_ms)
+            .unwrap();
+        assert!(db.load_recent("BTCUSDT").unwrap().is_empty());
+    }
 
-        Ok((first_timestamp, last_timestamp))
+    #[test]
+    fn delete_symbol_removes_the_saved_tail() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        store(&db, "BTCUSDT", minutes(BASE, db.block_size()));
+        let tail = minutes(BASE + db.block_size() as i64 * MINUTE, 10);
+        db.save_recent("BTCUSDT", &tail).unwrap();
+        db.save_recent("ETHUSDT", &tail).unwrap();
+
+        assert_eq!(db.delete_symbol("BTCUSDT").unwrap(), 1);
+        assert!(db.block_timestamps("BTCUSDT").unwrap().is_empty());
+        assert!(db.load_recent("BTCUSDT").unwrap().is_empty());
+        assert_eq!(db.load_recent("ETHUSDT").unwrap(), tail);
     }
 
-    fn print_last_aggregated_records(
-        &self,
-        aggr_symbol: &str,
-        count: usize,
-    ) -> Result<(), Box<dyn Error>> {
-        // Получаем все записи агрегированного символа и сортируем по времени
-        let prefix = format!("{}_", aggr_symbol);
-        let iter = self.db.scan_prefix(prefix.as_bytes());
+    #[test]
+    fn corrupted_last_timestamp_is_an_error() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        db.db.insert(b"last_BTCUSDT", b"bad").unwrap();
+        let error = db.get_last_timestamp("BTCUSDT").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DbError>(),
+            Some(DbError::CorruptedKey { len: 3, .. })
+        ));
+    }
 
-        let mut records = Vec::new();
+    #[test]
+    fn batch_over_corrupted_last_timestamp_is_aborted() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        db.db.insert(b"last_BTCUSDT", b"bad").unwrap();
+        let klines = minutes(BASE, db.block_size());
+        let mut batch = WriteBatch::default();
+        batch.insert_block(
+            "BTCUSDT",
+            BASE,
+            &compress::compress_klines(&klines).unwrap(),
+        );
+        let error = db.apply_batch(batch).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DbError>(),
+            Some(DbError::CorruptedKey { len: 3, .. })
+        ));
+        // ни блок, ни last_ не записаны
+        assert!(db.block_timestamps("BTCUSDT").unwrap().is_empty());
+        assert_eq!(
+            db.db.get(b"last_BTCUSDT").unwrap().unwrap().as_ref(),
+            b"bad"
+        );
+    }
 
-        for result in iter {
-            match result {
-                Ok((key, data)) => {
-                    let key_str = String::from_utf8_lossy(&key);
-                    if let Some(timestamp_str) = key_str.strip_prefix(&prefix) {
-                        if let Ok(timestamp) = timestamp_str.parse::<i64>() {
-                            let kline: KLine =
-                                bincode::decode_from_slice(&data, bincode::config::standard())?.0;
-                            records.push((timestamp, kline));
-                        }
-                    }
-                }
-                Err(e) => return Err(e.into()),
-            }
+    #[test]
+    fn block_size_migration_keeps_the_tail() {
+        let dir = TempDir::new();
+        let mut db = Database::open(dir.path(), None).unwrap();
+        let old_blocks = minutes(BASE, 3 * db.block_size());
+        let recent = minutes(BASE + 3 * db.block_size() as i64 * MINUTE, 100);
+        store(&db, "BTCUSDT", old_blocks.clone());
+        db.save_recent("BTCUSDT", &recent).unwrap();
+
+        // 3000 минут не делятся на окна по 900: последнее окно выходит за старые блоки
+        db.migrate_block_size(900).unwrap();
+        let block_ms = 900 * MINUTE;
+        let starts = db.block_timestamps("BTCUSDT").unwrap();
+        let partial_start = (BASE + 3000 * MINUTE).div_euclid(block_ms) * block_ms;
+        assert_eq!(starts.len(), 3);
+        assert!(starts.iter().all(|start| start % block_ms == 0));
+        assert_eq!(*starts.last().unwrap(), partial_start - block_ms);
+        assert_eq!(
+            db.get_last_timestamp("BTCUSDT").unwrap(),
+            partial_start - block_ms
+        );
+
+        let tail = db.load_recent("BTCUSDT").unwrap();
+        assert_eq!(tail.first().unwrap().open_time, partial_start);
+        let mut all = db.get_range_data("BTCUSDT", i64::MIN, i64::MAX).unwrap();
+        assert!(all.last().unwrap().open_time < partial_start);
+        all.extend(tail);
+        let expected: Vec<KLine> = old_blocks.into_iter().chain(recent).collect();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn block_keys_sort_by_time() {
+        let times = [i64::MIN, -BASE, -1, 0, 1, BASE, i64::MAX];
+        let keys: Vec<Vec<u8>> = times.iter().map(|t| block_key("BTCUSDT", *t)).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(sorted, keys);
+        let prefix = series_prefix("BTCUSDT");
+        for (key, time) in keys.iter().zip(times) {
+            assert_eq!(key_timestamp(key, &prefix), Some(time));
+        }
+        // ключи другой серии с тем же началом имени не попадают в диапазон
+        assert_eq!(key_timestamp(&block_key("BTCUSDT_aggr", 0), &prefix), None);
+        assert!(block_key("BTC", i64::MAX) < block_key("BTCUSDT", i64::MIN));
+    }
+
+    #[test]
+    fn scan_is_bounded_and_ordered_across_zero() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        let block_ms = db.block_size() as i64 * MINUTE;
+        let starts = [-2 * block_ms, -block_ms, 0, block_ms, 2 * block_ms];
+        let mut batch = WriteBatch::default();
+        for start in starts.iter().rev() {
+            let klines = minutes(*start, 2);
+            batch.insert_block(
+                "BTCUSDT",
+                *start,
+                &compress::compress_klines(&klines).unwrap(),
+            );
+            batch.insert_block(
+                "BTCUSDT_aggr",
+                *start,
+                &compress::compress_klines(&klines).unwrap(),
+            );
         }
+        batch.insert_block(
+            "BTC",
+            0,
+            &compress::compress_klines(&minutes(0, 1)).unwrap(),
+        );
+        db.apply_batch(batch).unwrap();
 
-        // Сортируем по времени и берем последние записи
-        records.sort_by_key(|(timestamp, _)| *timestamp);
-        let last_records: Vec<_> = records.into_iter().rev().take(count).collect();
+        assert_eq!(db.block_timestamps("BTCUSDT").unwrap(), starts);
+        let in_range: Vec<i64> = db
+            .get_blocks_in_range("BTCUSDT", -block_ms + MINUTE, block_ms)
+            .map(|result| result.unwrap().0)
+            .collect();
+        assert_eq!(in_range, [-block_ms, 0, block_ms]);
+    }
 
-        if !last_records.is_empty() {
-            println!("\n  Last {} records:", last_records.len());
-            for (timestamp, kline) in last_records.iter().rev() {
-                let datetime = Local.timestamp_millis_opt(*timestamp).unwrap();
-                let open = kline.open as f64 / (10_u64.pow(PRICE_MULTIPLIER) as f64);
-                let high = kline.high as f64 / (10_u64.pow(PRICE_MULTIPLIER) as f64);
-                let low = kline.low as f64 / (10_u64.pow(PRICE_MULTIPLIER) as f64);
-                let close = kline.close as f64 / (10_u64.pow(PRICE_MULTIPLIER) as f64);
-
-                println!(
-                    "    {} | O:{:.2} H:{:.2} L:{:.2} C:{:.2} V:{:.3}",
-                    datetime.format("%H:%M %d.%m.%y"),
-                    open,
-                    high,
-                    low,
-                    close,
-                    kline.volume
-                );
-            }
+    #[test]
+    fn legacy_text_keys_are_migrated() {
+        let dir = TempDir::new();
+        let block = minutes(BASE, 10);
+        let payload = compress::compress_klines(&block).unwrap();
+        let aggr = compress::compress_klines(&AggrPeriod::HOUR.combine(&block)).unwrap();
+        {
+            let raw = sled::Config::default()
+                .path(dir.path())
+                .use_compression(DB_USE_COMPRESSION)
+                .open()
+                .unwrap();
+            raw.insert(b"meta_block_size", &(BLOCK_SIZE as u64).to_be_bytes())
+                .unwrap();
+            raw.insert(b"meta_kline_format", &KLINE_FORMAT.to_be_bytes())
+                .unwrap();
+            raw.insert(format!("BTCUSDT_{}", BASE), payload.as_slice())
+                .unwrap();
+            raw.insert(
+                format!("crc_BTCUSDT_{}", BASE),
+                &crc32fast::hash(&payload).to_be_bytes(),
+            )
+            .unwrap();
+            raw.insert(format!("BTCUSDT_aggr_{}", BASE), aggr.as_slice())
+                .unwrap();
+            raw.insert(b"last_BTCUSDT", &BASE.to_be_bytes()).unwrap();
+            raw.flush().unwrap();
         }
 
-        Ok(())
+        let db = Database::open(dir.path(), None).unwrap();
+        assert_eq!(db.block_timestamps("BTCUSDT").unwrap(), [BASE]);
+        let stored = db.get_block("BTCUSDT", BASE).unwrap().unwrap();
+        assert_eq!(compress::decompress_klines(&stored).unwrap(), block);
+        assert!(db
+            .db
+            .get(crc_key(&block_key("BTCUSDT", BASE)))
+            .unwrap()
+            .is_some());
+        let tier: Vec<i64> = db
+            .scan_blocks("BTCUSDT_aggr", i64::MIN, i64::MAX)
+            .map(|result| result.unwrap().0)
+            .collect();
+        assert_eq!(tier, [BASE]);
+        for old_key in [
+            format!("BTCUSDT_{}", BASE),
+            format!("crc_BTCUSDT_{}", BASE),
+            format!("BTCUSDT_aggr_{}", BASE),
+        ] {
+            assert!(
+                db.db.get(old_key.as_bytes()).unwrap().is_none(),
+                "{}",
+                old_key
+            );
+        }
+        assert_eq!(
+            db.db.get(b"meta_key_format").unwrap().unwrap().as_ref(),
+            KEY_FORMAT.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn verify_reports_bad_blocks_and_gaps() {
+        let dir = TempDir::new();
+        let db = Database::open(dir.path(), None).unwrap();
+        let size = db.block_size();
+        let block_ms = size as i64 * MINUTE;
+        let mut klines = minutes(BASE, 5 * size);
+        // минута внутри последнего блока
+        klines.remove(4 * size + 10);
+        store(&db, "BTCUSDT", klines);
+        let missing = BASE + 4 * block_ms + 10 * MINUTE;
+        let missing_minute = VerifyIssue::Gap {
+            from: missing,
+            to: missing,
+        };
+        assert_eq!(
+            db.verify("BTCUSDT").unwrap().issues,
+            std::slice::from_ref(&missing_minute)
+        );
+
+        let corrupted = BASE + block_ms;
+        let key = block_key("BTCUSDT", corrupted);
+        let mut stored = db.db.get(&key).unwrap().unwrap().to_vec();
+        let last = stored.len() - 1;
+        stored[last] ^= 0xFF;
+        db.db.insert(&key, stored).unwrap();
+        db.remove_block("BTCUSDT", BASE + 3 * block_ms).unwrap();
+
+        let report = db.verify("BTCUSDT").unwrap();
+        assert_eq!(report.blocks, 4);
+        assert_eq!(report.klines, 3 * size - 1);
+        assert_eq!(report.first, Some(BASE));
+        assert_eq!(report.last, Some(BASE + 5 * block_ms - MINUTE));
+        assert_eq!(report.issues.len(), 3, "{:?}", report.issues);
+        assert!(matches!(
+            &report.issues[0],
+            VerifyIssue::BadBlock { block_start, message }
+                if *block_start == corrupted && message.contains("checksum")
+        ));
+        assert_eq!(
+            report.issues[1],
+            VerifyIssue::Gap {
+                from: BASE + 3 * block_ms,
+                to: BASE + 4 * block_ms - MINUTE,
+            }
+        );
+        assert_eq!(report.issues[2], missing_minute);
+        assert!(report
+            .summary("BTCUSDT")
+            .contains("2 gaps (1001 missing minutes), 1 bad blocks"));
     }
 }
@@ -0,0 +1,363 @@
+// derivatives.rs - Funding rate and open interest of USD-M futures: fetching, storage and sub-chart pane (gui feature)
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::db::Database;
+use crate::settings::{
+    API_TIMEOUT_SECS, DERIVATIVES_HISTORY_DAYS, DERIVATIVES_OI_PERIOD, DERIVATIVES_VERSION,
+    FUTURES_API_URL,
+};
+use reqwest::blocking::Client;
+use std::error::Error;
+use std::time::Duration;
+// панель графика - только с feature gui
+#[cfg(feature = "gui")]
+use crate::{datawindow::DataWindow, drawing_util, settings::DERIVATIVES_SYNC_SECS};
+#[cfg(feature = "gui")]
+use eframe::egui::{self, Align2, Color32, FontId, Stroke};
+#[cfg(feature = "gui")]
+use std::{thread::JoinHandle, time::Instant};
+
+pub const FUNDING: &str = "funding";
+pub const OPEN_INTEREST: &str = "oi";
+
+const FUNDING_LIMIT: usize = 1000;
+const OI_LIMIT: i64 = 500;
+const DAY_MS: i64 = 86_400_000;
+
+fn get_json(client: &Client, path_and_query: &str) -> Result<serde_json::Value, Box<dyn Error>> {
+    let response = client
+        .get(format!("{}{}", FUTURES_API_URL, path_and_query))
+        .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+        .send()?;
+    if !response.status().is_success() {
+        // 400 с кодом -1121 - символа нет на фьючерсах
+        return Err(format!("futures API error: {}", response.status()).into());
+    }
+    Ok(response.json()?)
+}
+
+/// Parses `[{time_field: ms, value_field: "decimal"}, ...]`.
+fn parse_points(value: &serde_json::Value, time_field: &str, value_field: &str) -> Vec<(i64, f64)> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let time = item.get(time_field)?.as_i64()?;
+                    let value = item.get(value_field)?.as_str()?.parse::<f64>().ok()?;
+                    Some((time, value))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Funding rate settlements from `start_time`, oldest first.
+pub fn fetch_funding(
+    client: &Client,
+    symbol: &str,
+    start_time: i64,
+) -> Result<Vec<(i64, f64)>, Box<dyn Error>> {
+    let mut points = Vec::new();
+    let mut from = start_time;
+    loop {
+        let url = format!(
+            "/fapi/v1/fundingRate?symbol={}&startTime={}&limit={}",
+            symbol, from, FUNDING_LIMIT
+        );
+        let page = parse_points(&get_json(client, &url)?, "fundingTime", "fundingRate");
+        let full = page.len() == FUNDING_LIMIT;
+        if let Some((last, _)) = page.last() {
+            from = last + 1;
+        }
+        points.extend(page);
+        if !full {
+            return Ok(points);
+        }
+    }
+}
+
+/// Open interest (in base asset) every `DERIVATIVES_OI_PERIOD` from `start_time`, oldest first.
+pub fn fetch_open_interest(
+    client: &Client,
+    symbol: &str,
+    start_time: i64,
+) -> Result<Vec<(i64, f64)>, Box<dyn Error>> {
+    let period_ms = match DERIVATIVES_OI_PERIOD {
+        "5m" => 300_000,
+        "15m" => 900_000,
+        "30m" => 1_800_000,
+        "2h" => 7_200_000,
+        "4h" => 14_400_000,
+        "1d" => DAY_MS,
+        _ => 3_600_000,
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut points = Vec::new();
+    let mut from = start_time;
+    while from < now {
+        let to = (from + period_ms * OI_LIMIT).min(now);
+        let url = format!(
+            "/futures/data/openInterestHist?symbol={}&period={}&startTime={}&endTime={}&limit={}",
+            symbol, DERIVATIVES_OI_PERIOD, from, to, OI_LIMIT
+        );
+        points.extend(parse_points(
+            &get_json(client, &url)?,
+            "timestamp",
+            "sumOpenInterest",
+        ));
+        from = to + 1;
+    }
+    Ok(points)
+}
+
+/// Fetches funding and open interest newer than the stored points (or the last
+/// `DERIVATIVES_HISTORY_DAYS`) and stores them. Points stored by another
+/// `DERIVATIVES_VERSION` are dropped and fetched again. Returns the number of new points.
+pub fn sync(db: &Database, symbol: &str) -> Result<(usize, usize), Box<dyn Error>> {
+    let client = Client::new();
+    let initial = chrono::Utc::now().timestamp_millis() - DERIVATIVES_HISTORY_DAYS * DAY_MS;
+    let start = |metric: &str| -> Result<i64, Box<dyn Error>> {
+        db.ensure_metric_version(metric, symbol, DERIVATIVES_VERSION)?;
+        Ok(db
+            .last_metric_time(metric, symbol)?
+            .map_or(initial, |t| t + 1))
+    };
+    let funding = fetch_funding(&client, symbol, start(FUNDING)?)?;
+    db.insert_metric_points(FUNDING, symbol, &funding)?;
+    let open_interest = fetch_open_interest(&client, symbol, start(OPEN_INTEREST)?)?;
+    db.insert_metric_points(OPEN_INTEREST, symbol, &open_interest)?;
+    Ok((funding.len(), open_interest.len()))
+}
+
+#[cfg(feature = "gui")]
+/// Value in effect at `time`: the last point not after it.
+fn value_at(points: &[(i64, f64)], time: i64) -> Option<f64> {
+    let index = points.partition_point(|(t, _)| *t <= time);
+    index.checked_sub(1).map(|i| points[i].1)
+}
+
+#[cfg(feature = "gui")]
+// (symbol, first bar time, last bar time, completed syncs)
+type PaneKey = (String, i64, i64, u32);
+
+/// Sub-chart under the price chart: funding rate as a histogram around zero and
+/// open interest as a line, sampled at each bar's open time.
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct DerivativesPane {
+    pub show: bool,
+    funding: Vec<(i64, f64)>,
+    open_interest: Vec<(i64, f64)>,
+    job: Option<JoinHandle<Result<(usize, usize), String>>>,
+    // (symbol, time) of the last sync start
+    synced: Option<(String, Instant)>,
+    syncs: u32,
+    error: Option<String>,
+    loaded_for: Option<PaneKey>,
+}
+
+#[cfg(feature = "gui")]
+impl DerivativesPane {
+    pub fn loading(&self) -> bool {
+        self.job.is_some()
+    }
+
+    /// Starts a background sync when the pane is shown for a new symbol or the sync
+    /// interval passed, and reloads the points of the shown bars from the DB.
+    /// Returns a status message when a sync finished.
+    pub fn refresh(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        data_window: &DataWindow,
+    ) -> Option<String> {
+        if !self.show {
+            return None;
+        }
+        let mut message = None;
+        if self.job.as_ref().is_some_and(|job| job.is_finished()) {
+            let result = self.job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("derivatives thread panicked".into()))
+            });
+            self.syncs += 1;
+            let synced = self.synced.as_ref().map_or(symbol, |(s, _)| s.as_str());
+            match result {
+                Some(Ok((funding, open_interest))) => {
+                    self.error = None;
+                    if funding + open_interest > 0 {
+                        message = Some(format!(
+                            "Loaded {} funding and {} open interest points of {}",
+                            funding, open_interest, synced
+                        ));
+                    }
+                }
+                Some(Err(e)) => {
+                    message = Some(format!("No futures data for {}: {}", synced, e));
+                    self.error = Some(e);
+                }
+                None => {}
+            }
+        }
+        let due = self.synced.as_ref().is_none_or(|(synced, at)| {
+            synced != symbol || at.elapsed() >= Duration::from_secs(DERIVATIVES_SYNC_SECS)
+        });
+        if self.job.is_none() && due {
+            if self
+                .synced
+                .as_ref()
+                .is_some_and(|(synced, _)| synced != symbol)
+            {
+                self.error = None;
+            }
+            let db = db.clone();
+            let thread_symbol = symbol.to_string();
+            self.job = Some(std::thread::spawn(move || {
+                sync(&db, &thread_symbol).map_err(|e| e.to_string())
+            }));
+            self.synced = Some((symbol.to_string(), Instant::now()));
+        }
+
+        let key = (
+            symbol.to_string(),
+            data_window.bars.first().map_or(0, |b| b.time),
+            data_window.bars.last().map_or(0, |b| b.time),
+            self.syncs,
+        );
+        if self.loaded_for.as_ref() != Some(&key) {
+            // с запасом на день назад - последняя выплата перед первым баром
+            let (from, to) = (key.1 - DAY_MS, i64::MAX);
+            let loaded = db
+                .get_metric_points(FUNDING, symbol, from, to)
+                .and_then(|funding| {
+                    Ok((
+                        funding,
+                        db.get_metric_points(OPEN_INTEREST, symbol, from, to)?,
+                    ))
+                });
+            match loaded {
+                Ok((funding, open_interest)) => {
+                    self.funding = funding;
+                    self.open_interest = open_interest;
+                }
+                Err(e) => tracing::warn!("Unable to load funding/open interest: {}", e),
+            }
+            self.loaded_for = Some(key);
+        }
+        message
+    }
+
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        data_window: &DataWindow,
+        font: &FontId,
+    ) {
+        let painter = ui.painter();
+        painter.line_segment(
+            [rect.left_top(), rect.right_top()],
+            Stroke::new(1.0, Color32::from_gray(60)),
+        );
+        let (start, end) = data_window.visible_range;
+        let end = end.min(data_window.bars.len() as i64);
+        let samples: Vec<(Option<f64>, Option<f64>)> = if start >= 0 && start < end {
+            data_window.bars[start as usize..end as usize]
+                .iter()
+                .map(|bar| {
+                    (
+                        value_at(&self.funding, bar.time),
+                        value_at(&self.open_interest, bar.time),
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let funding_color = Color32::from_rgb(120, 180, 240);
+        let oi_color = Color32::from_rgb(230, 190, 90);
+        let label = match (
+            samples.iter().rev().find_map(|s| s.0),
+            samples.iter().rev().find_map(|s| s.1),
+        ) {
+            (None, None) => match (&self.error, &self.job) {
+                (Some(_), _) => "No futures data for this symbol".to_string(),
+                (None, Some(_)) => "Loading funding and open interest...".to_string(),
+                (None, None) => "No funding or open interest for the shown bars".to_string(),
+            },
+            (funding, open_interest) => format!(
+                "funding {}   OI {}",
+                funding.map_or("-".into(), |f| format!("{:.4}%", f * 100.0)),
+                open_interest.map_or("-".into(), crate::crosshair::format_volume)
+            ),
+        };
+        painter.text(
+            rect.left_top() + egui::vec2(4.0, 2.0),
+            Align2::LEFT_TOP,
+            label,
+            font.clone(),
+            Color32::from_gray(180),
+        );
+        if samples.is_empty() {
+            return;
+        }
+
+        let visible_count = samples.len();
+        let max_funding = samples
+            .iter()
+            .filter_map(|s| s.0)
+            .fold(0.0f64, |max, f| max.max(f.abs()));
+        let (oi_min, oi_max) = samples
+            .iter()
+            .filter_map(|s| s.1)
+            .fold((f64::MAX, f64::MIN), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        let plot = rect.shrink2(egui::vec2(0.0, 4.0));
+        let zero_y = plot.center().y;
+        painter.line_segment(
+            [
+                egui::pos2(rect.left(), zero_y),
+                egui::pos2(rect.right(), zero_y),
+            ],
+            Stroke::new(1.0, Color32::from_gray(45)),
+        );
+
+        let mut oi_line = Vec::new();
+        for (visible_index, (funding, open_interest)) in samples.iter().enumerate() {
+            let (x_left, x_right) = drawing_util::calculate_bar_x_position(
+                visible_index,
+                visible_count,
+                rect,
+                data_window.pixel_offset,
+            );
+            if let Some(funding) = funding.filter(|_| max_funding > 0.0) {
+                let y = zero_y - (funding / max_funding) as f32 * plot.height() / 2.0;
+                let color = if funding >= 0.0 {
+                    funding_color
+                } else {
+                    Color32::from_rgb(220, 90, 90)
+                };
+                painter.rect_filled(
+                    egui::Rect::from_two_pos(egui::pos2(x_left, zero_y), egui::pos2(x_right, y)),
+                    0.0,
+                    color.gamma_multiply(0.6),
+                );
+            }
+            if let Some(open_interest) = open_interest {
+                let share = if oi_max > oi_min {
+                    (open_interest - oi_min) / (oi_max - oi_min)
+                } else {
+                    0.5
+                };
+                oi_line.push(egui::pos2(
+                    (x_left + x_right) / 2.0,
+                    plot.bottom() - share as f32 * plot.height(),
+                ));
+            }
+        }
+        painter.add(egui::Shape::line(oi_line, Stroke::new(1.5, oi_color)));
+    }
+}
@@ -0,0 +1,89 @@
+// downloads.rs - Download manager window listing the scheduler's sync jobs
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::interactivegui::format_age;
+use crate::scheduler::{JobState, SyncScheduler};
+use crate::timeframe::SyncProgress;
+use chrono::{Local, TimeZone};
+use eframe::egui;
+use std::time::Instant;
+
+fn format_range(range: Option<(i64, i64)>) -> String {
+    let format = |ts: i64| {
+        Local
+            .timestamp_millis_opt(ts)
+            .single()
+            .map(|dt| dt.format("%d %b %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    match range {
+        Some((from, to)) => format!("{} - {}", format(from), format(to)),
+        None => "-".to_string(),
+    }
+}
+
+/// "12/40 ~3m": blocks fetched, total and the estimated time left.
+pub fn progress_text(progress: &SyncProgress) -> String {
+    match progress.eta.filter(|_| progress.blocks_done > 0) {
+        Some(eta) => format!(
+            "{}/{} ~{}",
+            progress.blocks_done,
+            progress.blocks_total,
+            format_age(eta.as_secs())
+        ),
+        None => format!("{}/{}", progress.blocks_done, progress.blocks_total),
+    }
+}
+
+/// Shows active and queued sync jobs with progress, errors and a retry button.
+pub fn show(ctx: &egui::Context, open: &mut bool, scheduler: &SyncScheduler) {
+    let mut jobs = scheduler.jobs();
+    // running first, then in launch order
+    jobs.sort_by_key(|job| (job.state != JobState::Running, job.next_run));
+    let now = Instant::now();
+
+    egui::Window::new("Downloads")
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if jobs.is_empty() {
+                ui.label("No symbols scheduled");
+                return;
+            }
+            egui::Grid::new("download_jobs")
+                .striped(true)
+                .num_columns(4)
+                .show(ui, |ui| {
+                    for job in &jobs {
+                        ui.label(&job.symbol);
+                        ui.label(format_range(job.range));
+                        match &job.state {
+                            JobState::Running => {
+                                ui.add(
+                                    egui::ProgressBar::new(job.progress.fraction())
+                                        .desired_width(120.0)
+                                        .text(progress_text(&job.progress)),
+                                );
+                            }
+                            JobState::Queued => {
+                                ui.label("queued");
+                            }
+                            JobState::Idle => {
+                                let wait = job.next_run.saturating_duration_since(now).as_secs();
+                                ui.label(format!("next in {}s", wait));
+                            }
+                            JobState::Failed(error) => {
+                                ui.colored_label(egui::Color32::LIGHT_RED, "failed")
+                                    .on_hover_text(error);
+                            }
+                        }
+                        if matches!(job.state, JobState::Failed(_) | JobState::Idle)
+                            && ui.small_button("retry").clicked()
+                        {
+                            scheduler.retry(&job.symbol);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
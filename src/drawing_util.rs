@@ -1,4 +1,6 @@
 // drawing_util.rs
+use crate::datawindow::DataWindow;
+use crate::settings::MAGNET_DISTANCE;
 use eframe::egui::{Pos2, Rect};
 
 /// Рассчитывает X-координаты и ширину бара.
@@ -35,3 +37,71 @@ pub fn calculate_bar_x_position(
 
     (x_left, x_right)
 }
+
+/// Возвращает X-координату центра бара (центр слота с учетом панорамирования).
+pub fn calculate_bar_center_x(
+    visible_index: usize,
+    visible_count: usize,
+    chart_rect: Rect,
+    pixel_offset: f32,
+) -> f32 {
+    let (x_left, x_right) =
+        calculate_bar_x_position(visible_index, visible_count, chart_rect, pixel_offset);
+    (x_left + x_right) / 2.0
+}
+
+/// Находит абсолютный индекс бара (в `bars`) под X-координатой.
+///
+/// # Returns
+/// `None`, если координата вне видимого диапазона баров.
+pub fn bar_index_at_x(
+    x: f32,
+    visible_range: (i64, i64),
+    bars_len: usize,
+    chart_rect: Rect,
+    pixel_offset: f32,
+) -> Option<usize> {
+    let (start, end) = visible_range;
+    let end = end.min(bars_len as i64);
+    if start < 0 || start >= end {
+        return None;
+    }
+    let visible_count = (end - start) as f32;
+    let normalized_x = (x - pixel_offset - chart_rect.left()) / chart_rect.width();
+    if !(0.0..1.0).contains(&normalized_x) {
+        return None;
+    }
+    let index = (normalized_x * visible_count).floor() as i64 + start;
+    (index < end).then_some(index as usize)
+}
+
+/// Притягивает цену к ближайшему значению OHLC бара под курсором (режим магнита).
+///
+/// # Arguments
+/// * `pos` - Позиция курсора.
+/// * `chart_rect` - Область графика, по ширине которой расположены бары.
+/// * `scale_price` - Преобразование цены в Y-координату.
+///
+/// # Returns
+/// Цену open/high/low/close, если до нее не больше `MAGNET_DISTANCE` пикселей, иначе `None`.
+pub fn snap_to_ohlc(
+    pos: Pos2,
+    chart_rect: Rect,
+    data_window: &DataWindow,
+    scale_price: &impl Fn(f64) -> f32,
+) -> Option<f64> {
+    let index = bar_index_at_x(
+        pos.x,
+        data_window.visible_range,
+        data_window.bars.len(),
+        chart_rect,
+        data_window.pixel_offset,
+    )?;
+    let bar = &data_window.bars[index];
+    [bar.open, bar.high, bar.low, bar.close]
+        .into_iter()
+        .map(|price| (price, (scale_price(price) - pos.y).abs()))
+        .filter(|(_, distance)| *distance <= MAGNET_DISTANCE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(price, _)| price)
+}
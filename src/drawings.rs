@@ -0,0 +1,462 @@
+// drawings.rs - User drawing tools placed on the chart (anchored VWAP, anchored volume profile)
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::db::Database;
+use crate::drawing_util;
+use crate::render::Renderer;
+use crate::settings::VOLUME_PROFILE_BINS;
+use crate::volume_profile::VolumeProfile;
+use crate::vwap;
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
+
+const HANDLE_RADIUS: f32 = 4.0;
+const HANDLE_HIT_DISTANCE: f32 = 8.0;
+// Maximum number of undo steps kept
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawingTool {
+    AnchoredVwap,
+    VolumeProfile,
+}
+
+impl DrawingTool {
+    /// Number of anchors the user has to click to place the drawing.
+    fn anchor_count(self) -> usize {
+        match self {
+            DrawingTool::AnchoredVwap => 1,
+            DrawingTool::VolumeProfile => 2,
+        }
+    }
+}
+
+// (anchor_time, bars.len(), first bar time) identifying the bars a cache was built for
+type CacheKey = (i64, usize, i64);
+
+#[derive(Debug, Clone)]
+pub struct AnchoredVwap {
+    pub anchor_time: i64,
+    cache: Option<(CacheKey, Vec<Option<f64>>)>,
+}
+
+impl AnchoredVwap {
+    pub fn new(anchor_time: i64) -> Self {
+        Self {
+            anchor_time,
+            cache: None,
+        }
+    }
+
+    /// Returns the anchor bar index and VWAP values from it, recomputing only
+    /// when the anchor or the underlying bars changed.
+    fn values(&mut self, data_window: &DataWindow) -> Option<(usize, &[Option<f64>])> {
+        let bars = &data_window.bars;
+        let anchor_index = anchor_index(data_window, self.anchor_time)?;
+        let key = (
+            self.anchor_time,
+            bars.len(),
+            bars.first().map_or(0, |b| b.time),
+        );
+        if self.cache.as_ref().is_none_or(|(k, _)| *k != key) {
+            self.cache = Some((key, vwap::anchored_vwap(bars, anchor_index)));
+        }
+        self.cache
+            .as_ref()
+            .map(|(_, values)| (anchor_index, values.as_slice()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnchoredProfile {
+    pub start_time: i64,
+    pub end_time: i64,
+    // Range of 1m data the profile was built from
+    built_for: Option<(i64, i64)>,
+    pub profile: Option<VolumeProfile>,
+}
+
+impl AnchoredProfile {
+    pub fn new(start_time: i64, end_time: i64) -> Self {
+        Self {
+            start_time: start_time.min(end_time),
+            end_time: start_time.max(end_time),
+            built_for: None,
+            profile: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Drawing {
+    AnchoredVwap(AnchoredVwap),
+    VolumeProfile(AnchoredProfile),
+}
+
+impl Drawing {
+    fn new(tool: DrawingTool, anchors: &[i64]) -> Self {
+        match tool {
+            DrawingTool::AnchoredVwap => Drawing::AnchoredVwap(AnchoredVwap::new(anchors[0])),
+            DrawingTool::VolumeProfile => {
+                Drawing::VolumeProfile(AnchoredProfile::new(anchors[0], anchors[1]))
+            }
+        }
+    }
+
+    pub fn anchor_time(&self, anchor: usize) -> i64 {
+        match self {
+            Drawing::AnchoredVwap(v) => v.anchor_time,
+            Drawing::VolumeProfile(p) if anchor == 0 => p.start_time,
+            Drawing::VolumeProfile(p) => p.end_time,
+        }
+    }
+
+    fn set_anchor_time(&mut self, anchor: usize, time: i64) {
+        match self {
+            Drawing::AnchoredVwap(v) => v.anchor_time = time,
+            Drawing::VolumeProfile(p) if anchor == 0 => p.start_time = time,
+            Drawing::VolumeProfile(p) => p.end_time = time,
+        }
+    }
+}
+
+/// Chart position restored by undoing a major view jump.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewState {
+    pub symbol: String,
+    pub timeframe: i32,
+    pub visible_range: (i64, i64),
+}
+
+/// State saved before an undoable change.
+#[derive(Debug, Clone)]
+enum UndoEntry {
+    Drawings(Vec<Drawing>),
+    View(ViewState),
+}
+
+#[derive(Default)]
+pub struct Drawings {
+    pub items: Vec<Drawing>,
+    pub active_tool: Option<DrawingTool>,
+    /// Price anchors snap to the OHLC of the bar under the cursor.
+    pub magnet: bool,
+    // Anchors already clicked for the drawing being placed
+    pending_anchors: Vec<i64>,
+    // (item, anchor) being dragged
+    dragging: Option<(usize, usize)>,
+    // Screen positions of drawing handles from the last frame, used for hit-testing
+    handles: Vec<(usize, usize, Pos2)>,
+    undo_stack: Vec<UndoEntry>,
+    redo_stack: Vec<UndoEntry>,
+}
+
+/// Index of the first bar at or after `time`, if it lies inside the loaded bars.
+fn anchor_index(data_window: &DataWindow, time: i64) -> Option<usize> {
+    let index = data_window.bars.partition_point(|b| b.time < time);
+    (index < data_window.bars.len()).then_some(index)
+}
+
+impl Drawings {
+    fn push_undo(&mut self, entry: UndoEntry) {
+        self.undo_stack.push(entry);
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Saves the drawings before they are created, moved or removed.
+    fn checkpoint(&mut self) {
+        self.push_undo(UndoEntry::Drawings(self.items.clone()));
+    }
+
+    /// Saves the view before a major jump (timeframe or symbol switch).
+    pub fn record_view(&mut self, view: ViewState) {
+        self.push_undo(UndoEntry::View(view));
+    }
+
+    /// Reverts the last change. Drawing changes are applied here; for a view jump
+    /// the view to restore is returned. `current` is saved for redo.
+    pub fn undo(&mut self, current: ViewState) -> Option<ViewState> {
+        let entry = self.undo_stack.pop()?;
+        let (redo, view) = self.apply(entry, current);
+        self.redo_stack.push(redo);
+        view
+    }
+
+    /// Repeats the last undone change, see `undo`.
+    pub fn redo(&mut self, current: ViewState) -> Option<ViewState> {
+        let entry = self.redo_stack.pop()?;
+        let (undo, view) = self.apply(entry, current);
+        self.undo_stack.push(undo);
+        view
+    }
+
+    /// Restores `entry`, returning the entry that reverts it and the view to show.
+    fn apply(&mut self, entry: UndoEntry, current: ViewState) -> (UndoEntry, Option<ViewState>) {
+        self.dragging = None;
+        self.pending_anchors.clear();
+        self.handles.clear();
+        match entry {
+            UndoEntry::Drawings(items) => (
+                UndoEntry::Drawings(std::mem::replace(&mut self.items, items)),
+                None,
+            ),
+            UndoEntry::View(view) => (UndoEntry::View(current), Some(view)),
+        }
+    }
+
+    pub fn toggle_tool(&mut self, tool: DrawingTool) {
+        self.pending_anchors.clear();
+        self.active_tool = if self.active_tool == Some(tool) {
+            None
+        } else {
+            Some(tool)
+        };
+    }
+
+    fn handle_at(&self, pos: Pos2) -> Option<(usize, usize)> {
+        self.handles
+            .iter()
+            .find(|(_, _, handle)| handle.distance(pos) <= HANDLE_HIT_DISTANCE)
+            .map(|(i, anchor, _)| (*i, *anchor))
+    }
+
+    fn bar_time_at(&self, pos: Pos2, rect: Rect, data_window: &DataWindow) -> Option<i64> {
+        let index = drawing_util::bar_index_at_x(
+            pos.x,
+            data_window.visible_range,
+            data_window.bars.len(),
+            rect,
+            data_window.pixel_offset,
+        )?;
+        Some(data_window.bars[index].time)
+    }
+
+    /// Handles placing, moving and removing drawings.
+    ///
+    /// # Returns
+    /// `true` if the pointer interaction was consumed and must not pan the chart.
+    pub fn handle_input(
+        &mut self,
+        response: &egui::Response,
+        rect: Rect,
+        data_window: &DataWindow,
+    ) -> bool {
+        let pointer = response.interact_pointer_pos().or(response.hover_pos());
+
+        if response.ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.active_tool = None;
+            self.pending_anchors.clear();
+        }
+
+        if let (Some(tool), true, Some(pos)) = (self.active_tool, response.clicked(), pointer) {
+            if let Some(time) = self.bar_time_at(pos, rect, data_window) {
+                self.pending_anchors.push(time);
+                if self.pending_anchors.len() >= tool.anchor_count() {
+                    self.checkpoint();
+                    self.items.push(Drawing::new(tool, &self.pending_anchors));
+                    self.pending_anchors.clear();
+                    self.active_tool = None;
+                }
+            }
+            return true;
+        }
+
+        if response.secondary_clicked() {
+            if let Some((i, _)) = pointer.and_then(|pos| self.handle_at(pos)) {
+                self.checkpoint();
+                self.items.remove(i);
+                self.handles.clear();
+                return true;
+            }
+        }
+
+        if response.drag_started() {
+            self.dragging = pointer.and_then(|pos| self.handle_at(pos));
+            if self.dragging.is_some() {
+                self.checkpoint();
+            }
+        }
+
+        if let Some((i, anchor)) = self.dragging {
+            if response.dragged() {
+                if let Some(time) = pointer.and_then(|pos| self.bar_time_at(pos, rect, data_window))
+                {
+                    if let Some(drawing) = self.items.get_mut(i) {
+                        if drawing.anchor_time(anchor) != time {
+                            drawing.set_anchor_time(anchor, time);
+                            response.ctx.request_repaint();
+                        }
+                    }
+                }
+            } else {
+                self.dragging = None;
+            }
+            return true;
+        }
+
+        false
+    }
+
+    /// Traded volume at `price` from the most recently placed volume profile covering it.
+    pub fn volume_at_price(&self, price: f64) -> Option<f64> {
+        self.items.iter().rev().find_map(|drawing| match drawing {
+            Drawing::VolumeProfile(p) => p.profile.as_ref()?.volume_at(price),
+            _ => None,
+        })
+    }
+
+    /// Rebuilds volume profiles whose anchors changed, reading 1m data from the DB.
+    /// Skipped while an anchor is being dragged to avoid decoding blocks every frame.
+    pub fn refresh_profiles(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        data_window: &DataWindow,
+        timeframe_minutes: i32,
+    ) {
+        if self.dragging.is_some() {
+            return;
+        }
+        for drawing in &mut self.items {
+            let Drawing::VolumeProfile(p) = drawing else {
+                continue;
+            };
+            let (start, end) = (p.start_time.min(p.end_time), p.start_time.max(p.end_time));
+            // The end anchor bar is included up to its last minute
+            let range = (start, end + timeframe_minutes as i64 * 60_000 - 1);
+            if p.built_for == Some(range) {
+                continue;
+            }
+            p.built_for = Some(range);
+            p.profile = match data_window.load_minute_klines(db, symbol, range.0, range.1) {
+                Ok(klines) => VolumeProfile::from_klines(&klines, VOLUME_PROFILE_BINS),
+                Err(e) => {
+                    tracing::warn!("Unable to build volume profile: {}", e);
+                    None
+                }
+            };
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        renderer: &mut impl Renderer,
+        rect: Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+    ) {
+        let vwap_color = Color32::from_rgb(230, 160, 40);
+        let profile_color = Color32::from_rgba_unmultiplied(90, 140, 220, 70);
+        let poc_color = Color32::from_rgba_unmultiplied(90, 140, 220, 150);
+        self.handles.clear();
+
+        let (start, end) = data_window.visible_range;
+        let end = end.min(data_window.bars.len() as i64);
+        if start < 0 || start >= end {
+            return;
+        }
+        let (start, end) = (start as usize, end as usize);
+        let visible_count = end - start;
+        let bar_x = |bar_index: usize| {
+            drawing_util::calculate_bar_center_x(
+                bar_index - start,
+                visible_count,
+                rect,
+                data_window.pixel_offset,
+            )
+        };
+
+        for (i, drawing) in self.items.iter_mut().enumerate() {
+            match drawing {
+                Drawing::AnchoredVwap(avwap) => {
+                    let Some((anchor_index, values)) = avwap.values(data_window) else {
+                        continue;
+                    };
+                    let from = anchor_index.max(start);
+                    let points: Vec<Pos2> = (from..end)
+                        .filter_map(|bar_index| {
+                            let value = values.get(bar_index - anchor_index).copied().flatten()?;
+                            Some(egui::pos2(bar_x(bar_index), scale_price(value)))
+                        })
+                        .collect();
+                    if points.len() >= 2 {
+                        renderer.polyline(points.clone(), Stroke::new(1.5, vwap_color));
+                    }
+                    // Handle on the anchor bar for dragging/removal
+                    if anchor_index >= start {
+                        if let Some(&handle) = points.first() {
+                            renderer.circle_stroke(
+                                handle,
+                                HANDLE_RADIUS,
+                                Stroke::new(1.5, vwap_color),
+                            );
+                            self.handles.push((i, 0, handle));
+                        }
+                    }
+                }
+                Drawing::VolumeProfile(p) => {
+                    let (Some(first), Some(last)) = (
+                        anchor_index(data_window, p.start_time),
+                        anchor_index(data_window, p.end_time),
+                    ) else {
+                        continue;
+                    };
+                    let (first, last) = (first.min(last), first.max(last));
+                    if last < start || first >= end {
+                        continue;
+                    }
+                    let x_left = bar_x(first.max(start));
+                    let x_right = bar_x(last.min(end - 1)).max(x_left + 1.0);
+
+                    if let Some(profile) = &p.profile {
+                        let max_volume = profile.max_volume();
+                        let poc = profile.poc_index();
+                        if max_volume > 0.0 {
+                            for (bin, volume) in profile.bins.iter().enumerate() {
+                                let (low, high) = profile.bin_range(bin);
+                                let width = (volume / max_volume) as f32 * (x_right - x_left);
+                                let color = if Some(bin) == poc {
+                                    poc_color
+                                } else {
+                                    profile_color
+                                };
+                                renderer.rect_filled(
+                                    Rect::from_min_max(
+                                        egui::pos2(x_left, scale_price(high)),
+                                        egui::pos2(x_left + width, scale_price(low) - 1.0),
+                                    ),
+                                    0.0,
+                                    color,
+                                );
+                            }
+                        }
+                    }
+
+                    // Range borders with handles at the bottom of the chart area
+                    for (anchor, bar_index) in [(0, first), (1, last)] {
+                        if bar_index < start || bar_index >= end {
+                            continue;
+                        }
+                        let x = bar_x(bar_index);
+                        renderer.line_segment(
+                            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                            Stroke::new(0.5, poc_color),
+                        );
+                        let handle = egui::pos2(x, rect.bottom() - HANDLE_HIT_DISTANCE);
+                        renderer.circle_stroke(handle, HANDLE_RADIUS, Stroke::new(1.5, poc_color));
+                        // Anchors may have been swapped by dragging one past the other
+                        let stored_anchor = if p.start_time <= p.end_time {
+                            anchor
+                        } else {
+                            1 - anchor
+                        };
+                        self.handles.push((i, stored_anchor, handle));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+// events.rs - User event markers (halvings, listings, macro events) loaded from a JSON/CSV file
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::AxisTimezone;
+use crate::datawindow::DataWindow;
+use crate::drawing_util;
+use crate::settings::EVENTS_FILE;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use eframe::egui::{self, Color32, Rect, Stroke};
+use std::error::Error;
+use std::path::Path;
+
+const FLAG_HEIGHT: f32 = 10.0;
+const FLAG_WIDTH: f32 = 7.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartEvent {
+    pub time: i64,
+    pub label: String,
+}
+
+/// Milliseconds since epoch from a number (seconds or milliseconds) or a UTC date:
+/// `2024-04-20`, `2024-04-20 00:09`, `2024-04-20T00:09:27` or RFC 3339.
+pub fn parse_time(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<i64>() {
+        // до 1e11 - секунды, иначе миллисекунды
+        return Some(if number.abs() < 100_000_000_000 {
+            number * 1000
+        } else {
+            number
+        });
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.timestamp_millis());
+    }
+    for pattern in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, pattern) {
+            return Some(dt.and_utc().timestamp_millis());
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp_millis())
+}
+
+/// JSON array of `{"timestamp": ..., "label": "..."}` (`time` is accepted too).
+fn parse_json(text: &str) -> Result<Vec<ChartEvent>, Box<dyn Error>> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+    let items = value.as_array().ok_or("events JSON must be an array")?;
+    let mut events = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let time = item
+            .get("timestamp")
+            .or_else(|| item.get("time"))
+            .and_then(|t| match t {
+                serde_json::Value::Number(n) => n.as_i64().and_then(|n| parse_time(&n.to_string())),
+                serde_json::Value::String(s) => parse_time(s),
+                _ => None,
+            })
+            .ok_or_else(|| format!("event {}: missing or invalid timestamp", index + 1))?;
+        let label = item.get("label").and_then(|l| l.as_str()).unwrap_or("");
+        events.push(ChartEvent {
+            time,
+            label: label.to_string(),
+        });
+    }
+    Ok(events)
+}
+
+/// `timestamp,label` lines; a header line and `#` comments are skipped.
+fn parse_csv(text: &str) -> Result<Vec<ChartEvent>, Box<dyn Error>> {
+    let mut events = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (time, label) = line.split_once(',').unwrap_or((line, ""));
+        let Some(time) = parse_time(time.trim_matches('"')) else {
+            if number == 0 {
+                continue;
+            }
+            return Err(format!("line {}: invalid timestamp '{}'", number + 1, time).into());
+        };
+        events.push(ChartEvent {
+            time,
+            label: label.trim().trim_matches('"').to_string(),
+        });
+    }
+    Ok(events)
+}
+
+/// Reads events by file extension (`.json`, otherwise CSV), sorted by time.
+pub fn load_events(path: &Path) -> Result<Vec<ChartEvent>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let mut events = if is_json {
+        parse_json(&text)?
+    } else {
+        parse_csv(&text)?
+    };
+    events.sort_by_key(|e| e.time);
+    Ok(events)
+}
+
+pub struct EventMarkers {
+    pub show: bool,
+    pub path: String,
+    events: Vec<ChartEvent>,
+}
+
+impl Default for EventMarkers {
+    fn default() -> Self {
+        let mut markers = Self {
+            show: true,
+            path: EVENTS_FILE.to_string(),
+            events: Vec::new(),
+        };
+        // файл событий необязателен
+        if Path::new(EVENTS_FILE).exists() {
+            if let Err(e) = markers.reload() {
+                tracing::warn!("Unable to load events from {}: {}", EVENTS_FILE, e);
+            }
+        }
+        markers
+    }
+}
+
+impl EventMarkers {
+    /// Loads the events file at `path`; returns the number of events.
+    pub fn reload(&mut self) -> Result<usize, Box<dyn Error>> {
+        self.events = load_events(Path::new(&self.path))?;
+        Ok(self.events.len())
+    }
+
+    /// Flags standing on the time axis at the bar containing each event, with a
+    /// faint vertical line; hovering a flag shows the labels of that bar.
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        data_window: &DataWindow,
+        time_zone: AxisTimezone,
+    ) {
+        if !self.show || self.events.is_empty() {
+            return;
+        }
+        let bars = &data_window.bars;
+        let (start, end) = data_window.visible_range;
+        let end = end.min(bars.len() as i64);
+        if start < 0 || start >= end {
+            return;
+        }
+        let (start, end) = (start as usize, end as usize);
+        let painter = ui.painter();
+        let color = Color32::from_rgb(240, 200, 80);
+        let hover = ui.ctx().pointer_hover_pos();
+        let mut hovered = Vec::new();
+        for event in &self.events {
+            let Some(index) = bars
+                .partition_point(|b| b.time <= event.time)
+                .checked_sub(1)
+            else {
+                continue;
+            };
+            // события позже последнего бара к нему не привязываются
+            let past_end = index + 1 == bars.len()
+                && index > 0
+                && event.time >= 2 * bars[index].time - bars[index - 1].time;
+            if index < start || index >= end || past_end {
+                continue;
+            }
+            let x = drawing_util::calculate_bar_center_x(
+                index - start,
+                end - start,
+                rect,
+                data_window.pixel_offset,
+            );
+            let base = rect.bottom();
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, base)],
+                Stroke::new(1.0, color.gamma_multiply(0.15)),
+            );
+            painter.line_segment(
+                [egui::pos2(x, base), egui::pos2(x, base - FLAG_HEIGHT)],
+                Stroke::new(1.0, color),
+            );
+            painter.add(egui::Shape::convex_polygon(
+                vec![
+                    egui::pos2(x, base - FLAG_HEIGHT),
+                    egui::pos2(x + FLAG_WIDTH, base - FLAG_HEIGHT * 0.75),
+                    egui::pos2(x, base - FLAG_HEIGHT * 0.5),
+                ],
+                color,
+                Stroke::NONE,
+            ));
+            let flag = Rect::from_min_max(
+                egui::pos2(x - 2.0, base - FLAG_HEIGHT),
+                egui::pos2(x + FLAG_WIDTH, base),
+            );
+            if hover.is_some_and(|pos| flag.contains(pos)) {
+                hovered.push(format!(
+                    "{}  {}",
+                    time_zone.datetime(event.time).format("%Y-%m-%d %H:%M"),
+                    event.label
+                ));
+            }
+        }
+        if !hovered.is_empty() {
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                egui::Id::new("event_marker"),
+                |ui| ui.label(hovered.join("\n")),
+            );
+        }
+    }
+
+    /// Returns a status message after a reload.
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        ui.checkbox(&mut self.show, "Show event flags");
+        let mut message = None;
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(180.0))
+                .on_hover_text("JSON array of {timestamp, label} or CSV lines timestamp,label");
+            if ui.button("load").clicked() {
+                message = Some(match self.reload() {
+                    Ok(count) => format!("Loaded {} events from {}", count, self.path),
+                    Err(e) => format!("Unable to load events from {}: {}", self.path, e),
+                });
+            }
+        });
+        ui.label(format!("{} events loaded", self.events.len()));
+        message
+    }
+}
@@ -0,0 +1,146 @@
+// exchange.rs - `Exchange` trait over the kline sources (Binance, Bybit, Coinbase), the active one chosen by `EXCHANGE`
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::bybit::Bybit;
+use crate::coinbase::Coinbase;
+use crate::fetch::{self, KLine};
+use crate::settings::{BINANCE_FUTURES_STREAM_URL, BINANCE_STREAM_URL, EXCHANGE};
+use reqwest::blocking::Client;
+use std::error::Error;
+
+// префикс символов фьючерсов: свои ключи блоков, агрегатов и хвоста в БД
+const FUTURES_PREFIX: &str = "F:";
+
+/// Market of a chart symbol. Futures symbols carry the `F:` prefix ("F:BTCUSDT"), so
+/// everything stored under the symbol is kept apart from the spot pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Market {
+    #[default]
+    Spot,
+    /// USD-M perpetual futures.
+    Futures,
+}
+
+impl Market {
+    /// Market and exchange pair of a chart symbol.
+    pub fn split(symbol: &str) -> (Market, &str) {
+        match symbol.strip_prefix(FUTURES_PREFIX) {
+            Some(pair) => (Market::Futures, pair),
+            None => (Market::Spot, symbol),
+        }
+    }
+
+    /// Chart symbol of the pair of `symbol` on this market.
+    pub fn symbol(self, symbol: &str) -> String {
+        let (_, pair) = Self::split(symbol);
+        match self {
+            Market::Spot => pair.to_string(),
+            Market::Futures => format!("{}{}", FUTURES_PREFIX, pair),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Market::Spot => "spot",
+            Market::Futures => "futures",
+        }
+    }
+}
+
+/// Source of 1m klines and symbol names for the sync pipeline. Symbols are stored
+/// in the DB without the exchange name, so one DB should be filled from one exchange.
+pub trait Exchange: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Klines of `interval` ("1m", "1h", "1d", ...) in ascending order, at most
+    /// `limit`, with open times in `[start_time, end_time]`.
+    fn fetch_klines(
+        &self,
+        client: &Client,
+        symbol: &str,
+        interval: &str,
+        limit: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<KLine>, Box<dyn Error>>;
+    /// Exchange clock, ms.
+    fn server_time(&self, client: &Client) -> Result<i64, Box<dyn Error>>;
+    /// Names of the symbols currently trading, sorted.
+    fn symbols(&self, client: &Client) -> Result<Vec<String>, Box<dyn Error>>;
+    /// Websocket URL of the Binance-format `@kline_1m` stream of `symbol`
+    /// (`KlineStream`), `None` when the exchange has none.
+    fn kline_stream_url(&self, _symbol: &str) -> Option<String> {
+        None
+    }
+    /// Whether `Market::Futures` symbols can be fetched.
+    fn has_futures(&self) -> bool {
+        false
+    }
+}
+
+/// Binance spot through the failover client of `fetch.rs`, USD-M futures for
+/// `Market::Futures` symbols.
+pub struct Binance;
+
+impl Exchange for Binance {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn fetch_klines(
+        &self,
+        client: &Client,
+        symbol: &str,
+        interval: &str,
+        limit: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        match Market::split(symbol) {
+            (Market::Spot, pair) => {
+                fetch::fetch_klines(client, pair, interval, limit, start_time, end_time)
+            }
+            (Market::Futures, pair) => {
+                fetch::fetch_futures_klines(client, pair, interval, limit, start_time, end_time)
+            }
+        }
+    }
+
+    fn server_time(&self, client: &Client) -> Result<i64, Box<dyn Error>> {
+        fetch::fetch_server_time(client)
+    }
+
+    fn symbols(&self, client: &Client) -> Result<Vec<String>, Box<dyn Error>> {
+        fetch::fetch_trading_symbols(client)
+    }
+
+    fn kline_stream_url(&self, symbol: &str) -> Option<String> {
+        let (market, pair) = Market::split(symbol);
+        let base = match market {
+            Market::Spot => BINANCE_STREAM_URL,
+            Market::Futures => BINANCE_FUTURES_STREAM_URL,
+        };
+        Some(format!("{}?streams={}@kline_1m", base, pair.to_lowercase()))
+    }
+
+    fn has_futures(&self) -> bool {
+        true
+    }
+}
+
+/// Exchange by its `name()`.
+pub fn by_name(name: &str) -> Option<&'static dyn Exchange> {
+    match name.to_lowercase().as_str() {
+        "binance" => Some(&Binance),
+        "bybit" => Some(&Bybit),
+        "coinbase" => Some(&Coinbase),
+        _ => None,
+    }
+}
+
+/// The exchange of `EXCHANGE`, Binance when the name is unknown.
+pub fn active() -> &'static dyn Exchange {
+    by_name(EXCHANGE).unwrap_or_else(|| {
+        tracing::warn!("Unknown exchange {:?}, using Binance", EXCHANGE);
+        &Binance
+    })
+}
@@ -0,0 +1,418 @@
+// export.rs - Export of stored series to CSV / JSON / text / Parquet files, Parquet import of edited klines
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::compress;
+use crate::db::{AggrPeriod, Database};
+use crate::fetch::{convert_to_u64, volume_to_f64, KLine, PRICE_MULTIPLIER, VOLUME_MULTIPLIER};
+use crate::parquet::{ColumnData, ColumnKind, ParquetFile, ParquetWriter, ValueUnit};
+use crate::timeframe::{Bar, Timeframe};
+use chrono::{Local, TimeZone, Utc};
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Layout version of exported Parquet files (`n_ohlcv.schema_version` metadata). Raised
+/// when columns change meaning; files of a newer version are refused on import.
+pub const PARQUET_SCHEMA_VERSION: u32 = 1;
+const PARQUET_VERSION_KEY: &str = "n_ohlcv.schema_version";
+const PARQUET_SYMBOL_KEY: &str = "n_ohlcv.symbol";
+// строк в группе: ~4.8 МБ столбцов в памяти при записи
+const PARQUET_ROW_GROUP: usize = 100_000;
+const PARQUET_COLUMNS: [&str; 6] = ["open_time", "open", "high", "low", "close", "volume"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Text,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Text => "txt",
+        }
+    }
+}
+
+fn price_to_f64(price: u64) -> f64 {
+    price as f64 / 10f64.powi(PRICE_MULTIPLIER as i32)
+}
+
+fn iso_time(timestamp_ms: i64) -> String {
+    Utc.timestamp_millis_opt(timestamp_ms)
+        .single()
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+const CSV_HEADER: &str = "open_time,time,open,high,low,close,volume";
+
+fn write_csv_row(file: &mut impl Write, k: &KLine) -> std::io::Result<()> {
+    writeln!(
+        file,
+        "{},{},{},{},{},{},{}",
+        k.open_time,
+        iso_time(k.open_time),
+        price_to_f64(k.open),
+        price_to_f64(k.high),
+        price_to_f64(k.low),
+        price_to_f64(k.close),
+        volume_to_f64(k.volume)
+    )
+}
+
+/// File name part of a symbol: futures symbols ("F:BTCUSDT") have a colon.
+pub fn file_stem(symbol: &str) -> String {
+    symbol.replace(':', "-")
+}
+
+fn create_file(path: &Path) -> Result<std::io::BufWriter<fs::File>, Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(std::io::BufWriter::new(fs::File::create(path)?))
+}
+
+/// One-line human readable record, as printed after aggregation.
+pub fn format_record(kline: &KLine) -> String {
+    let datetime = Local
+        .timestamp_millis_opt(kline.open_time)
+        .single()
+        .map(|dt| dt.format("%H:%M %d.%m.%y").to_string())
+        .unwrap_or_default();
+    format!(
+        "{} | O:{:.2} H:{:.2} L:{:.2} C:{:.2} V:{:.3}",
+        datetime,
+        price_to_f64(kline.open),
+        price_to_f64(kline.high),
+        price_to_f64(kline.low),
+        price_to_f64(kline.close),
+        volume_to_f64(kline.volume)
+    )
+}
+
+/// Writes klines to `path`; prices and volume are converted from fixed-point.
+pub fn write_klines(
+    path: &Path,
+    klines: &[KLine],
+    format: ExportFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = create_file(path)?;
+    match format {
+        ExportFormat::Csv => {
+            writeln!(file, "{}", CSV_HEADER)?;
+            for k in klines {
+                write_csv_row(&mut file, k)?;
+            }
+        }
+        ExportFormat::Json => {
+            let records: Vec<serde_json::Value> = klines
+                .iter()
+                .map(|k| {
+                    serde_json::json!({
+                        "open_time": k.open_time,
+                        "time": iso_time(k.open_time),
+                        "open": price_to_f64(k.open),
+                        "high": price_to_f64(k.high),
+                        "low": price_to_f64(k.low),
+                        "close": price_to_f64(k.close),
+                        "volume": volume_to_f64(k.volume),
+                    })
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut file, &records)?;
+        }
+        ExportFormat::Text => {
+            for k in klines {
+                writeln!(file, "{}", format_record(k))?;
+            }
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Exports the `{symbol}_aggr` hourly records to `{dir}/{symbol}_aggr.{ext}`.
+pub fn export_aggr(
+    db: &Database,
+    symbol: &str,
+    dir: &str,
+    format: ExportFormat,
+) -> Result<(PathBuf, usize), Box<dyn Error>> {
+    let records = db.get_aggr_records(symbol, AggrPeriod::HOUR, i64::MIN, i64::MAX)?;
+    let path = Path::new(dir).join(format!("{}_aggr.{}", file_stem(symbol), format.extension()));
+    write_klines(&path, &records, format)?;
+    Ok((path, records.len()))
+}
+
+/// Writes chart bars to `{dir}/{symbol}_{label}.csv`, e.g. the visible range of the
+/// `DataWindow` with the timeframe label.
+pub fn export_bars_csv(
+    bars: &[Bar],
+    symbol: &str,
+    label: &str,
+    dir: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = Path::new(dir).join(format!("{}_{}.csv", file_stem(symbol), label));
+    let mut file = create_file(&path)?;
+    writeln!(file, "{}", CSV_HEADER)?;
+    for bar in bars {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{}",
+            bar.time,
+            iso_time(bar.time),
+            bar.open,
+            bar.high,
+            bar.low,
+            bar.close,
+            bar.volume
+        )?;
+    }
+    file.flush()?;
+    Ok(path)
+}
+
+/// Calls `f` with the klines of every stored block of `symbol` in time order.
+/// Unreadable blocks are skipped with a warning.
+fn for_each_block(
+    db: &Database,
+    symbol: &str,
+    mut f: impl FnMut(&[KLine]) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    for block_start in db.block_timestamps(symbol)? {
+        let klines = match db.get_block(symbol, block_start) {
+            Ok(Some(data)) => compress::decompress_klines(&data)?,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("Skipping block {}_{}: {}", symbol, block_start, e);
+                continue;
+            }
+        };
+        f(&klines)?;
+    }
+    Ok(())
+}
+
+/// Writes every stored 1m kline of `symbol` to `{dir}/{symbol}_1m.csv`, one block
+/// at a time. Unreadable blocks are skipped with a warning. Returns the row count.
+pub fn export_history_csv(
+    db: &Database,
+    symbol: &str,
+    dir: &str,
+) -> Result<(PathBuf, usize), Box<dyn Error>> {
+    let path = Path::new(dir).join(format!("{}_1m.csv", file_stem(symbol)));
+    let mut file = create_file(&path)?;
+    writeln!(file, "{}", CSV_HEADER)?;
+    let mut rows = 0;
+    for_each_block(db, symbol, |klines| {
+        for k in klines {
+            write_csv_row(&mut file, k)?;
+        }
+        rows += klines.len();
+        Ok(())
+    })?;
+    file.flush()?;
+    Ok((path, rows))
+}
+
+/// Path of the Parquet history file of `symbol` in `dir`, written by
+/// `export_history_parquet` and read back by `import_history_parquet`.
+pub fn parquet_path(symbol: &str, dir: &str) -> PathBuf {
+    Path::new(dir).join(format!("{}_1m.parquet", file_stem(symbol)))
+}
+
+/// Writes every stored 1m kline of `symbol` to `{dir}/{symbol}_1m.parquet` for
+/// pandas/polars: `open_time` as a UTC millisecond timestamp, prices and volume as
+/// exact DECIMAL(18, 8). The file metadata carries `PARQUET_SCHEMA_VERSION` and the
+/// symbol. Returns the row count.
+pub fn export_history_parquet(
+    db: &Database,
+    symbol: &str,
+    dir: &str,
+) -> Result<(PathBuf, usize), Box<dyn Error>> {
+    let path = parquet_path(symbol, dir);
+    let kinds = [
+        ColumnKind::TimestampMillis,
+        ColumnKind::Decimal {
+            scale: PRICE_MULTIPLIER,
+        },
+        ColumnKind::Decimal {
+            scale: PRICE_MULTIPLIER,
+        },
+        ColumnKind::Decimal {
+            scale: PRICE_MULTIPLIER,
+        },
+        ColumnKind::Decimal {
+            scale: PRICE_MULTIPLIER,
+        },
+        ColumnKind::Decimal {
+            scale: VOLUME_MULTIPLIER,
+        },
+    ];
+    let schema: Vec<(&str, ColumnKind)> = PARQUET_COLUMNS.into_iter().zip(kinds).collect();
+    let mut writer = ParquetWriter::new(
+        create_file(&path)?,
+        &schema,
+        &[
+            (PARQUET_VERSION_KEY, PARQUET_SCHEMA_VERSION.to_string()),
+            (PARQUET_SYMBOL_KEY, symbol.to_string()),
+        ],
+    )?;
+    let mut columns: [Vec<i64>; 6] = Default::default();
+    let mut rows = 0;
+    let flush = |writer: &mut ParquetWriter<_>, columns: &mut [Vec<i64>; 6]| {
+        let slices: Vec<&[i64]> = columns.iter().map(|c| c.as_slice()).collect();
+        writer.write_row_group(&slices)?;
+        columns.iter_mut().for_each(Vec::clear);
+        Ok::<_, Box<dyn Error>>(())
+    };
+    for_each_block(db, symbol, |klines| {
+        for k in klines {
+            let fixed = |v: u64| i64::try_from(v).map_err(|_| "value exceeds DECIMAL(18, 8)");
+            columns[0].push(k.open_time);
+            columns[1].push(fixed(k.open)?);
+            columns[2].push(fixed(k.high)?);
+            columns[3].push(fixed(k.low)?);
+            columns[4].push(fixed(k.close)?);
+            columns[5].push(fixed(k.volume)?);
+        }
+        rows += klines.len();
+        if columns[0].len() >= PARQUET_ROW_GROUP {
+            flush(&mut writer, &mut columns)?;
+        }
+        Ok(())
+    })?;
+    flush(&mut writer, &mut columns)?;
+    writer.finish()?;
+    Ok((path, rows))
+}
+
+/// Fixed-point values with `decimals` decimals of a price or volume column: exact
+/// for integer and decimal columns, rounded to `decimals` for floats.
+fn fixed_point(
+    data: ColumnData,
+    unit: ValueUnit,
+    decimals: u32,
+) -> Result<Vec<u64>, Box<dyn Error>> {
+    let negative = || -> Box<dyn Error> { "negative price or volume".into() };
+    match data {
+        ColumnData::Double(values) => values
+            .into_iter()
+            .map(|v| {
+                if v.is_finite() && v >= 0.0 {
+                    Ok(convert_to_u64(&format!("{:.8}", v), decimals))
+                } else {
+                    Err(negative())
+                }
+            })
+            .collect(),
+        ColumnData::Int(values) => {
+            let scale = match unit {
+                ValueUnit::Decimal(scale) => scale,
+                _ => 0,
+            };
+            values
+                .into_iter()
+                .map(|v| {
+                    let v = u64::try_from(v).map_err(|_| negative())?;
+                    let rescaled = if scale <= decimals {
+                        10u64
+                            .checked_pow(decimals - scale)
+                            .and_then(|m| v.checked_mul(m))
+                    } else {
+                        10u64.checked_pow(scale - decimals).map(|d| v / d)
+                    };
+                    rescaled.ok_or_else(|| "value out of range".into())
+                })
+                .collect()
+        }
+    }
+}
+
+/// Klines of a Parquet file with the `PARQUET_COLUMNS` columns, sorted by time, and the
+/// symbol from its metadata. Files without the n-ohlcv metadata (e.g. rewritten by
+/// pandas) are accepted: timestamps may be in ms/µs/ns, prices and volume decimal,
+/// integer or float columns.
+pub fn read_klines_parquet(path: &Path) -> Result<(Option<String>, Vec<KLine>), Box<dyn Error>> {
+    let file = ParquetFile::parse(fs::read(path)?)?;
+    if let Some(version) = file.metadata.get(PARQUET_VERSION_KEY) {
+        let version: u32 = version
+            .parse()
+            .map_err(|_| format!("invalid schema version {:?}", version))?;
+        if version > PARQUET_SCHEMA_VERSION {
+            return Err(format!(
+                "schema version {} is newer than the supported {}",
+                version, PARQUET_SCHEMA_VERSION
+            )
+            .into());
+        }
+    }
+    let columns = file.columns();
+    let unit = |name: &str| {
+        columns
+            .iter()
+            .find(|c| c.name == name)
+            .map_or(ValueUnit::Plain, |c| c.unit)
+    };
+    let open_time: Vec<i64> = match file.read_column("open_time")? {
+        ColumnData::Int(values) => match unit("open_time") {
+            ValueUnit::Timestamp { per_ms } => values.into_iter().map(|t| t / per_ms).collect(),
+            _ => values,
+        },
+        ColumnData::Double(values) => values.into_iter().map(|t| t as i64).collect(),
+    };
+    let mut prices = Vec::with_capacity(4);
+    for name in &PARQUET_COLUMNS[1..5] {
+        prices.push(fixed_point(
+            file.read_column(name)?,
+            unit(name),
+            PRICE_MULTIPLIER,
+        )?);
+    }
+    let volume = fixed_point(
+        file.read_column("volume")?,
+        unit("volume"),
+        VOLUME_MULTIPLIER,
+    )?;
+    if prices.iter().any(|p| p.len() != open_time.len()) || volume.len() != open_time.len() {
+        return Err("columns differ in length".into());
+    }
+    let mut klines = Vec::with_capacity(open_time.len());
+    for (i, time) in open_time.into_iter().enumerate() {
+        if time % 60_000 != 0 {
+            return Err(format!("open_time {} is not a whole minute", iso_time(time)).into());
+        }
+        klines.push(KLine {
+            open_time: time,
+            open: prices[0][i],
+            high: prices[1][i],
+            low: prices[2][i],
+            close: prices[3][i],
+            volume: volume[i],
+        });
+    }
+    klines.sort_by_key(|k| k.open_time);
+    Ok((file.metadata.get(PARQUET_SYMBOL_KEY).cloned(), klines))
+}
+
+/// Stores the klines of `{dir}/{symbol}_1m.parquet` (e.g. an exported history corrected
+/// in pandas) over the stored minutes of `symbol` with `Timeframe::import_klines`.
+/// Returns the rows read and the blocks written.
+pub fn import_history_parquet(
+    db: &Database,
+    symbol: &str,
+    dir: &str,
+) -> Result<(usize, usize), Box<dyn Error>> {
+    let (file_symbol, klines) = read_klines_parquet(&parquet_path(symbol, dir))?;
+    if let Some(file_symbol) = file_symbol.filter(|s| s != symbol) {
+        return Err(format!("the file holds {}, not {}", file_symbol, symbol).into());
+    }
+    let rows = klines.len();
+    let blocks = Timeframe::import_klines(db, symbol, klines)?;
+    Ok((rows, blocks))
+}
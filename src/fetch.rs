@@ -1,23 +1,127 @@
 // fetch.rs - Binance API client, KLine struct definition, price conversion
 // See CONVENTIONS.md for project structure and workflow
 
+use crate::settings::{API_FAILOVER_ERRORS, API_TIMEOUT_SECS, BINANCE_API_URLS, FUTURES_API_URL};
+use crate::symbol_info::SymbolInfo;
 use reqwest::blocking::Client;
 use serde;
 use serde_json;
 use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
+#[derive(
+    Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
+)]
 pub struct KLine {
     pub open_time: i64,
     pub open: u64,
     pub high: u64,
     pub low: u64,
     pub close: u64,
-    pub volume: f64,
+    pub volume: u64,
 }
 
-pub const PRICE_MULTIPLIER: u32 = 2;
+// 8 decimals keep sub-cent assets (e.g. SHIBUSDT) distinguishable
+pub const PRICE_MULTIPLIER: u32 = 8;
+// Binance reports volume with up to 8 decimals; 4 are kept so that weekly tiers of
+// meme coins (1e13..1e14 units a day) stay below u64::MAX / 10^4 ≈ 1.8e15 units
+pub const VOLUME_MULTIPLIER: u32 = 4;
 
+/// Converts a fixed-point volume back to base asset units.
+pub fn volume_to_f64(volume: u64) -> f64 {
+    volume as f64 / 10f64.powi(VOLUME_MULTIPLIER as i32)
+}
+
+/// Active API endpoint shared by all fetching threads.
+struct Failover {
+    active: usize,
+    errors: u32,
+    notices: Vec<String>,
+}
+
+static FAILOVER: Mutex<Failover> = Mutex::new(Failover {
+    active: 0,
+    errors: 0,
+    notices: Vec::new(),
+});
+
+fn active_base_url() -> &'static str {
+    let active = FAILOVER.lock().map(|f| f.active).unwrap_or(0);
+    BINANCE_API_URLS[active % BINANCE_API_URLS.len()]
+}
+
+/// Counts a failed request; after `API_FAILOVER_ERRORS` in a row switches to the
+/// next URL of `BINANCE_API_URLS`.
+fn report_failure(base_url: &str, error: &str) {
+    let Ok(mut failover) = FAILOVER.lock() else {
+        return;
+    };
+    // another thread may have switched already
+    if BINANCE_API_URLS[failover.active] != base_url {
+        return;
+    }
+    failover.errors += 1;
+    if failover.errors >= API_FAILOVER_ERRORS {
+        failover.active = (failover.active + 1) % BINANCE_API_URLS.len();
+        failover.errors = 0;
+        let notice = format!(
+            "API {} failed ({}), switched to {}",
+            base_url, error, BINANCE_API_URLS[failover.active]
+        );
+        tracing::warn!("{}", notice);
+        failover.notices.push(notice);
+    }
+}
+
+fn report_success() {
+    if let Ok(mut failover) = FAILOVER.lock() {
+        failover.errors = 0;
+    }
+}
+
+/// Endpoint switches since the last call, for display in the status line.
+pub fn take_failover_notices() -> Vec<String> {
+    FAILOVER
+        .lock()
+        .map(|mut f| std::mem::take(&mut f.notices))
+        .unwrap_or_default()
+}
+
+/// Requests `path_and_query` from the active endpoint. Timeouts, connection errors
+/// and 5xx responses are retried, failing over to the next endpoint when they repeat.
+fn get_with_failover(
+    client: &Client,
+    path_and_query: &str,
+) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+    let attempts = API_FAILOVER_ERRORS as usize * BINANCE_API_URLS.len();
+    let mut last_error = String::new();
+    for attempt in 0..attempts {
+        if attempt > 0 {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        let base_url = active_base_url();
+        let url = format!("{}{}", base_url, path_and_query);
+        match client
+            .get(&url)
+            .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+            .send()
+        {
+            Ok(response) if response.status().is_server_error() => {
+                last_error = format!("API error: {}", response.status());
+            }
+            Ok(response) => {
+                report_success();
+                return Ok(response);
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+        report_failure(base_url, &last_error);
+    }
+    Err(format!("all API endpoints failed, last error: {}", last_error).into())
+}
+
+/// Klines of `symbol` from the spot API (`/api/v3/klines`).
 pub fn fetch_klines(
     client: &Client,
     symbol: &str,
@@ -26,33 +130,79 @@ pub fn fetch_klines(
     start_time: Option<i64>,
     end_time: Option<i64>,
 ) -> Result<Vec<KLine>, Box<dyn Error>> {
-    let mut url = format!(
-        "https://api.binance.com/api/v3/klines?symbol={}&interval={}&limit={}",
-        symbol, interval, limit
+    let url = klines_query(
+        "/api/v3/klines",
+        symbol,
+        interval,
+        limit,
+        start_time,
+        end_time,
+    );
+    //println!("fetch url: {url}");
+    let response = get_with_failover(client, &url)?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    Ok(parse_klines(response.json()?))
+}
+
+/// Klines of `symbol` from the USD-M futures API (`/fapi/v1/klines`), same format as spot.
+pub fn fetch_futures_klines(
+    client: &Client,
+    symbol: &str,
+    interval: &str,
+    limit: i64,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<Vec<KLine>, Box<dyn Error>> {
+    let url = klines_query(
+        "/fapi/v1/klines",
+        symbol,
+        interval,
+        limit,
+        start_time,
+        end_time,
     );
+    let response = client
+        .get(format!("{}{}", FUTURES_API_URL, url))
+        .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+        .send()?;
+    if !response.status().is_success() {
+        return Err(format!("futures API error: {}", response.status()).into());
+    }
+    Ok(parse_klines(response.json()?))
+}
 
+fn klines_query(
+    path: &str,
+    symbol: &str,
+    interval: &str,
+    limit: i64,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> String {
+    let mut url = format!(
+        "{}?symbol={}&interval={}&limit={}",
+        path, symbol, interval, limit
+    );
     if let Some(start) = start_time {
         url.push_str(&format!("&startTime={}", start));
     }
     if let Some(end) = end_time {
         url.push_str(&format!("&endTime={}", end));
     }
-    //println!("fetch url: {url}");
-    let response = client.get(&url).send()?;
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
-    }
+    url
+}
 
-    let klines = response
-        .json::<Vec<Vec<serde_json::Value>>>()?
-        .into_iter()
+fn parse_klines(rows: Vec<Vec<serde_json::Value>>) -> Vec<KLine> {
+    rows.into_iter()
         .map(|k| {
             let open_time = k[0].as_i64().unwrap_or(0);
-            let open = convert_price_to_u64(k[1].as_str().unwrap_or("0"));
-            let high = convert_price_to_u64(k[2].as_str().unwrap_or("0"));
-            let low = convert_price_to_u64(k[3].as_str().unwrap_or("0"));
-            let close = convert_price_to_u64(k[4].as_str().unwrap_or("0"));
-            let volume = k[5].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+            let open = convert_to_u64(k[1].as_str().unwrap_or("0"), PRICE_MULTIPLIER);
+            let high = convert_to_u64(k[2].as_str().unwrap_or("0"), PRICE_MULTIPLIER);
+            let low = convert_to_u64(k[3].as_str().unwrap_or("0"), PRICE_MULTIPLIER);
+            let close = convert_to_u64(k[4].as_str().unwrap_or("0"), PRICE_MULTIPLIER);
+            let volume = convert_to_u64(k[5].as_str().unwrap_or("0"), VOLUME_MULTIPLIER);
             KLine {
                 open_time,
                 open,
@@ -62,24 +212,167 @@ pub fn fetch_klines(
                 volume,
             }
         })
+        .collect()
+}
+
+/// Binance server clock from `/api/v3/time`, ms.
+pub fn fetch_server_time(client: &Client) -> Result<i64, Box<dyn Error>> {
+    let response = get_with_failover(client, "/api/v3/time")?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    let value: serde_json::Value = response.json()?;
+    Ok(value
+        .get("serverTime")
+        .and_then(|t| t.as_i64())
+        .ok_or("no serverTime in response")?)
+}
+
+/// Last price and 24h change of a symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayTicker {
+    pub symbol: String,
+    pub last_price: f64,
+    pub change_percent: f64,
+}
+
+/// 24h statistics of `symbols` in one `/api/v3/ticker/24hr` request.
+pub fn fetch_day_tickers(
+    client: &Client,
+    symbols: &[String],
+) -> Result<Vec<DayTicker>, Box<dyn Error>> {
+    if symbols.is_empty() {
+        return Ok(Vec::new());
+    }
+    // ["BTCUSDT","ETHUSDT"] в кодировке URL
+    let list = symbols
+        .iter()
+        .map(|s| format!("%22{}%22", s))
+        .collect::<Vec<_>>()
+        .join(",");
+    let response = get_with_failover(
+        client,
+        &format!("/api/v3/ticker/24hr?symbols=%5B{}%5D", list),
+    )?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    let value: serde_json::Value = response.json()?;
+    Ok(value
+        .as_array()
+        .ok_or("unexpected 24h ticker response")?
+        .iter()
+        .filter_map(parse_day_ticker)
+        .collect())
+}
+
+/// 24h statistics of a USD-M futures pair (`/fapi/v1/ticker/24hr`); `symbol` of the
+/// result is the pair as returned by the exchange, without the `F:` prefix.
+pub fn fetch_futures_day_ticker(client: &Client, pair: &str) -> Result<DayTicker, Box<dyn Error>> {
+    let response = client
+        .get(format!(
+            "{}/fapi/v1/ticker/24hr?symbol={}",
+            FUTURES_API_URL, pair
+        ))
+        .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+        .send()?;
+    if !response.status().is_success() {
+        return Err(format!("futures API error: {}", response.status()).into());
+    }
+    let value: serde_json::Value = response.json()?;
+    Ok(parse_day_ticker(&value).ok_or("unexpected futures 24h ticker response")?)
+}
+
+fn parse_day_ticker(item: &serde_json::Value) -> Option<DayTicker> {
+    let number = |field: &str| {
+        item.get(field)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+    Some(DayTicker {
+        symbol: item.get("symbol")?.as_str()?.to_string(),
+        last_price: number("lastPrice"),
+        change_percent: number("priceChangePercent"),
+    })
+}
+
+/// Names of the symbols currently trading, from the full `/api/v3/exchangeInfo`.
+pub fn fetch_trading_symbols(client: &Client) -> Result<Vec<String>, Box<dyn Error>> {
+    let response = get_with_failover(client, "/api/v3/exchangeInfo")?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    let value: serde_json::Value = response.json()?;
+    let mut symbols: Vec<String> = value
+        .get("symbols")
+        .and_then(|s| s.as_array())
+        .ok_or("no symbols in exchange info")?
+        .iter()
+        .filter(|s| s.get("status").and_then(|v| v.as_str()) == Some("TRADING"))
+        .filter_map(|s| Some(s.get("symbol")?.as_str()?.to_string()))
         .collect();
+    symbols.sort();
+    Ok(symbols)
+}
 
-    Ok(klines)
+/// Tick size, lot size and assets of `symbol` from `/api/v3/exchangeInfo`.
+pub fn fetch_symbol_info(client: &Client, symbol: &str) -> Result<SymbolInfo, Box<dyn Error>> {
+    let response = get_with_failover(client, &format!("/api/v3/exchangeInfo?symbol={}", symbol))?;
+    if !response.status().is_success() {
+        return Err(format!("API error: {}", response.status()).into());
+    }
+    let value: serde_json::Value = response.json()?;
+    let info = value
+        .get("symbols")
+        .and_then(|s| s.as_array())
+        .and_then(|s| s.first())
+        .ok_or_else(|| format!("no exchange info for {}", symbol))?;
+    let text = |item: &serde_json::Value, field: &str| {
+        item.get(field)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let filters = info
+        .get("filters")
+        .and_then(|f| f.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let filter_value = |filter_type: &str, field: &str| {
+        filters
+            .iter()
+            .find(|f| f.get("filterType").and_then(|t| t.as_str()) == Some(filter_type))
+            .and_then(|f| f.get(field)?.as_str()?.parse::<f64>().ok())
+    };
+    Ok(SymbolInfo {
+        symbol: text(info, "symbol"),
+        base_asset: text(info, "baseAsset"),
+        quote_asset: text(info, "quoteAsset"),
+        tick_size: filter_value("PRICE_FILTER", "tickSize").unwrap_or(0.0),
+        step_size: filter_value("LOT_SIZE", "stepSize").unwrap_or(0.0),
+        min_qty: filter_value("LOT_SIZE", "minQty").unwrap_or(0.0),
+        min_notional: filter_value("NOTIONAL", "minNotional")
+            .or_else(|| filter_value("MIN_NOTIONAL", "minNotional")),
+        updated: chrono::Utc::now().timestamp_millis(),
+    })
 }
 
-fn convert_price_to_u64(price_str: &str) -> u64 {
+/// Parses a decimal string such as "42.17" into a fixed-point value with `decimals`.
+/// Values beyond `u64::MAX` saturate.
+pub fn convert_to_u64(value_str: &str, decimals: u32) -> u64 {
     // A multiplier to convert the decimal part to an integer.
-    // For PRICE_MULTIPLIER = 2, this is 100.
-    const MULT: u64 = 10u64.pow(PRICE_MULTIPLIER);
+    // For decimals = 2, this is 100.
+    let mult: u64 = 10u64.pow(decimals);
 
-    if let Some(dot_pos) = price_str.find('.') {
+    if let Some(dot_pos) = value_str.find('.') {
         // Integer part
-        let integer_part = price_str[..dot_pos].parse::<u64>().unwrap_or(0);
+        let integer_part = value_str[..dot_pos].parse::<u64>().unwrap_or(0);
 
         // Decimal part
-        let decimal_part_str = &price_str[dot_pos + 1..];
+        let decimal_part_str = &value_str[dot_pos + 1..];
         // Take only the required number of decimals
-        let num_decimals = decimal_part_str.len().min(PRICE_MULTIPLIER as usize);
+        let num_decimals = decimal_part_str.len().min(decimals as usize);
         let decimal_part = if num_decimals > 0 {
             decimal_part_str[..num_decimals].parse::<u64>().unwrap_or(0)
         } else {
@@ -87,16 +380,18 @@ fn convert_price_to_u64(price_str: &str) -> u64 {
         };
 
         // If the provided decimal part is shorter, pad with zeros mathematically.
-        // e.g., if price is "1.2" and PRICE_MULTIPLIER is 2,
+        // e.g., if value is "1.2" and decimals is 2,
         // decimal_part is 2, num_decimals is 1.
         // We need to make it 20. So, 2 * 10^(2-1) = 20.
-        let padding_power = (PRICE_MULTIPLIER as usize).saturating_sub(num_decimals);
+        let padding_power = (decimals as usize).saturating_sub(num_decimals);
         let adjusted_decimal = decimal_part * 10u64.pow(padding_power as u32);
 
-        integer_part * MULT + adjusted_decimal
+        integer_part
+            .saturating_mul(mult)
+            .saturating_add(adjusted_decimal)
     } else {
         // No decimal point, just integer.
-        // e.g., if price is "12" and PRICE_MULTIPLIER is 2, result is 1200.
-        price_str.parse::<u64>().unwrap_or(0) * MULT
+        // e.g., if value is "12" and decimals is 2, result is 1200.
+        value_str.parse::<u64>().unwrap_or(0).saturating_mul(mult)
     }
 }
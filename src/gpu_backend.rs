@@ -1,14 +1,95 @@
 // src/gpu_backend.rs
+use crate::config;
+use crate::settings::{WINDOW_FILE, WINDOW_MONITOR_ORIGIN};
 use eframe;
 use eframe::wgpu::Instance;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// Window placement remembered in `WINDOW_FILE` between runs. Positions are in the
+/// virtual desktop coordinates spanning all monitors, so the position also selects
+/// the monitor a fullscreen window opens on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub position: [f32; 2],
+    /// Inner size of the window when it is not fullscreen.
+    pub size: [f32; 2],
+    pub fullscreen: bool,
+}
+
+impl Default for WindowGeometry {
+    /// Size and mode of the config.
+    fn default() -> Self {
+        let config = config::get();
+        Self {
+            position: [0.0, 0.0], // В верхний левый угол
+            size: [config.window_width, config.window_height],
+            fullscreen: config.fullscreen, // maximized is PAIN. Keep calm and use fullscreen
+        }
+    }
+}
+
+impl WindowGeometry {
+    /// Geometry from `WINDOW_FILE`, or the default one; `WINDOW_MONITOR_ORIGIN` places it
+    /// on the target monitor instead of the remembered position.
+    pub fn load_default() -> Self {
+        let mut geometry = Self::default();
+        if Path::new(WINDOW_FILE).exists() {
+            match Self::load(Path::new(WINDOW_FILE)) {
+                Ok(loaded) => geometry = loaded,
+                Err(e) => {
+                    tracing::warn!("Unable to load window geometry from {}: {}", WINDOW_FILE, e)
+                }
+            }
+        }
+        if let Some((x, y)) = WINDOW_MONITOR_ORIGIN {
+            geometry.position = [x, y];
+        }
+        geometry
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Follows moves and resizes of the root window; the windowed size is kept while
+    /// it is fullscreen.
+    pub fn track(&mut self, ctx: &egui::Context) {
+        let (outer, inner, fullscreen) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.outer_rect,
+                viewport.inner_rect,
+                viewport.fullscreen,
+            )
+        });
+        if let Some(outer) = outer {
+            self.position = [outer.min.x, outer.min.y];
+        }
+        if let Some(fullscreen) = fullscreen {
+            self.fullscreen = fullscreen;
+        }
+        if let Some(inner) = inner.filter(|_| !self.fullscreen) {
+            self.size = [inner.width(), inner.height()];
+        }
+    }
+}
 
 pub fn native_options() -> eframe::NativeOptions {
+    let geometry = WindowGeometry::load_default();
     eframe::NativeOptions {
         // eframe = "0.31.1"
         viewport: egui::ViewportBuilder::default()
-            .with_fullscreen(true)
-            //.with_inner_size([1920.0, 1080.0])  // maximized is PAIN. Keep calm and use fullscreen
-            .with_position([0.0, 0.0]) // В верхний левый угол
+            .with_fullscreen(geometry.fullscreen)
+            .with_inner_size(geometry.size)
+            .with_position(geometry.position)
             .with_decorations(false), // Скрыть рамки
         renderer: eframe::Renderer::Wgpu,
         hardware_acceleration: eframe::HardwareAcceleration::Preferred,
@@ -26,5 +107,5 @@ pub async fn log_gpu_api() {
         .request_adapter(&eframe::wgpu::RequestAdapterOptions::default())
         .await
         .expect("Failed to find a suitable GPU adapter!");
-    println!("[GPU] Backend: {:?}", adapter.get_info().backend);
+    tracing::info!("[GPU] Backend: {:?}", adapter.get_info().backend);
 }
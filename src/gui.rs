@@ -1,40 +1,101 @@
 // gui.rs - Main GUI framework, chart layout, event handling
 // See CONVENTIONS.md for project structure and workflow
-use crate::axes_util;
+use crate::axes_util::{self, AxisMode, AxisTimezone};
+use crate::calendar::TradingCalendar;
+use crate::coverage::CoverageAction;
+use crate::crosshair::format_volume;
+use crate::drawings::DrawingTool;
+use crate::exchange::{self, Market};
+use crate::export::{self, ExportFormat};
+use crate::interactivegui::{format_age, start_stream, InteractiveGui};
 use crate::settings;
-use crate::{axes, hlcbars, interactivegui::InteractiveGui, volbars};
+use crate::ticker::TickerStream;
+use crate::timeframe_switch::TimeframeSwitch;
+use crate::{axes, downloads, hlcbars, indicator_pane, trade_tape, visible_profile, volbars};
 use eframe::{egui, Frame};
 use std::time::{Duration, Instant};
 
 impl eframe::App for InteractiveGui {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
         let frame_start_time = Instant::now();
+        self.poll_background_messages();
+        self.window_geometry.track(ctx);
+        self.reload_changed_files(ctx);
+        if let Some(tf) = self.timeframe_switch.handle_input(ctx) {
+            self.set_timeframe(tf);
+        }
+        // в текстовых полях Ctrl+Z остается за полем
+        let (undo, redo) = if ctx.wants_keyboard_input() {
+            (false, false)
+        } else {
+            ctx.input_mut(|i| {
+                // Ctrl+Shift+Z проверяется первым: Ctrl+Z совпадает и с нажатым shift
+                let redo = i.consume_shortcut(&egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::Z,
+                )) || i.consume_shortcut(&egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND,
+                    egui::Key::Y,
+                ));
+                let undo = i.consume_shortcut(&egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND,
+                    egui::Key::Z,
+                ));
+                (undo, redo)
+            })
+        };
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+        self.crosshair.handle_keys(ctx, &mut self.data_window);
+        self.refresh_bookmarks();
+        if let Some(time) = self.bookmarks.handle_keys(ctx, &self.data_window) {
+            self.jump_to_time(time);
+        }
+        // клавиатурное перекрестие заменяет мышиное
+        let crosshair_pos = self
+            .crosshair
+            .key_position(&self.data_window)
+            .or_else(|| ctx.pointer_hover_pos());
+        let compact = ctx.screen_rect().width() < settings::COMPACT_LAYOUT_WIDTH;
+        if self.watchlist.open && !compact {
+            self.show_watchlist(ctx);
+        }
+        // лента сделок занимает место справа до центральной панели
+        if let Some(ticker) = self.ticker.as_ref().filter(|_| self.show_tape && !compact) {
+            trade_tape::show(
+                ctx,
+                &ticker.trades(),
+                self.data_window.price_decimals(),
+                self.time_zone,
+            );
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.horizontal(|ui| {
-                    let measure_button_text = if self.measure_frame_time { "x" } else { "F" };
-                    if ui.button(measure_button_text).clicked() {
-                        self.measure_frame_time = !self.measure_frame_time;
-                    }
-                    if ui
-                        .button(if self.show_candles { "bars" } else { "candles" })
-                        .clicked()
-                    {
-                        self.show_candles = !self.show_candles;
-                    }
-                    for &tf in &[5, 15, 60, 240] {
-                        if ui.button(format!("{}", tf)).clicked() {
-                            self.timeframe = tf;
-                            self.update_data_window();
-                        }
+                if compact {
+                    // узкое окно: панель инструментов в меню, крупные элементы для касаний
+                    enlarge_touch_targets(ui);
+                    ui.menu_button("menu", |ui| {
+                        enlarge_touch_targets(ui);
+                        self.toolbar_items(ui);
+                    });
+                    let menu = ui.menu_button(self.timeframe_text(), |ui| {
+                        TimeframeSwitch::show_menu(ui, self.timeframe)
+                    });
+                    if let Some(tf) = menu.inner.flatten() {
+                        self.set_timeframe(tf);
                     }
                     if ui.button("+").clicked() {
-                        self.zoom(0.1); // Zoom in
+                        self.zoom(0.1);
                     }
                     if ui.button("-").clicked() {
-                        self.zoom(-0.1); // Zoom out
+                        self.zoom(-0.1);
                     }
-                });
+                } else {
+                    ui.horizontal(|ui| self.toolbar_items(ui));
+                }
                 ui.add_space(15.0);
                 // bar info
                 if self.measure_frame_time {
@@ -43,30 +104,117 @@ impl eframe::App for InteractiveGui {
                         ui.label(format!("{:.2} ms ", t_avg));
                     }
                 }
-                if let Some(pos) = ctx.pointer_hover_pos() {
-                    if let Some(bar_info) = self.crosshair.get_bar_info(pos, &self.data_window) {
-                        ui.horizontal(|ui| {
-                            ui.label(bar_info);
-                            ui.with_layout(
-                                egui::Layout::right_to_left(egui::Align::Center),
-                                |ui| {
-                                    ui.label(format!("{} {}m", self.symbol, self.timeframe));
-                                },
-                            );
-                        });
+                let bar_info = crosshair_pos.and_then(|pos| {
+                    let bar_info = self.crosshair.get_bar_info(
+                        pos,
+                        &self.data_window,
+                        self.time_zone,
+                        self.time_format,
+                    )?;
+                    // объем на цене курсора по активному профилю объема
+                    Some(
+                        match self
+                            .crosshair
+                            .price_at(pos, &self.data_window)
+                            .and_then(|price| self.drawings.volume_at_price(price))
+                        {
+                            Some(volume) => format!("{} | vp {}", bar_info, format_volume(volume)),
+                            None => bar_info,
+                        },
+                    )
+                });
+                ui.horizontal(|ui| {
+                    if let Some(bar_info) = bar_info {
+                        ui.label(bar_info);
                     }
-                }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let market = Market::split(&self.symbol).0;
+                        ui.label(format!(
+                            "{} {} {}m",
+                            self.pair(),
+                            market.label(),
+                            self.timeframe
+                        ));
+                        if let Some(progress) = &self.sync_progress {
+                            let bar = ui
+                                .add(
+                                    egui::ProgressBar::new(progress.fraction())
+                                        .desired_width(120.0)
+                                        .text(format!(
+                                            "sync {}",
+                                            downloads::progress_text(progress)
+                                        )),
+                                )
+                                .on_hover_text("Fetching the blocks missing since the last start");
+                            if let Some(error) = &progress.last_error {
+                                bar.on_hover_text(error);
+                            }
+                        }
+                        if let Some((age, stale)) = self.data_freshness() {
+                            let text = format!("updated {} ago", format_age(age));
+                            if stale {
+                                ui.colored_label(egui::Color32::LIGHT_RED, text)
+                                    .on_hover_text(
+                                        "No new data within the expected update interval",
+                                    );
+                            } else {
+                                ui.weak(text);
+                            }
+                        }
+                        let ticker = self.ticker.as_ref().filter(|_| !compact);
+                        if let Some(ticker) = ticker.and_then(|t| t.latest()) {
+                            let decimals = self.data_window.price_decimals();
+                            ui.label(format!(
+                                "bid {} ask {} spread {}",
+                                axes_util::format_price(ticker.bid, decimals),
+                                axes_util::format_price(ticker.ask, decimals),
+                                axes_util::format_price(ticker.spread(), decimals)
+                            ))
+                            .on_hover_text(format!(
+                                "bid size {}, ask size {}",
+                                format_volume(ticker.bid_qty),
+                                format_volume(ticker.ask_qty)
+                            ));
+                        }
+                    });
+                });
             });
 
             egui::Frame::canvas(ui.style()).show(ui, |ui| {
                 let response = ui.interact(
                     ui.available_rect_before_wrap(),
                     ui.id().with("chart_area"),
-                    egui::Sense::drag(),
+                    egui::Sense::click_and_drag(),
                 );
 
                 let mut rect = response.rect;
-                rect.set_height(rect.height() - settings::CHART_BOTTOM_MARGIN);
+                rect.set_height(
+                    rect.height() - settings::CHART_BOTTOM_MARGIN - self.theme.time_labels_extra(),
+                );
+                let chart_font = self.theme.chart_font();
+                // панель funding/OI под подписями оси времени, в компактном режиме скрыта
+                let derivatives_rect = (self.derivatives.show && !compact).then(|| {
+                    let time_labels_height = 16.0 + self.theme.time_labels_extra();
+                    let pane_height = rect.height() * settings::DERIVATIVES_PANE_RATIO;
+                    rect.set_height(rect.height() - pane_height);
+                    egui::Rect::from_min_max(
+                        egui::pos2(rect.left(), rect.bottom() + time_labels_height),
+                        egui::pos2(rect.right(), rect.bottom() + pane_height),
+                    )
+                });
+                // панели индикаторов между подписями времени и панелью funding/OI
+                self.indicators.update(&self.data_window.bars);
+                let pane_rects: Vec<egui::Rect> = (0..self.indicators.panes().count())
+                    .map(|_| {
+                        let time_labels_height = 16.0 + self.theme.time_labels_extra();
+                        let pane_height = rect.height() * self.data_window.pane_height_ratio;
+                        rect.set_height(rect.height() - pane_height);
+                        egui::Rect::from_min_max(
+                            egui::pos2(rect.left(), rect.bottom() + time_labels_height),
+                            egui::pos2(rect.right(), rect.bottom() + pane_height),
+                        )
+                    })
+                    .collect();
                 // let me actually draw chart
                 self.data_window.update_price_range_extrema();
                 let volume_height = rect.height() * self.data_window.volume_height_ratio;
@@ -75,25 +223,151 @@ impl eframe::App for InteractiveGui {
                     egui::pos2(rect.max.x, rect.max.y - volume_height),
                 );
                 let scale_price = axes_util::create_scale_price_fn(&self.data_window, price_rect);
+                let mut painter = ui.painter().clone();
                 // Crosshair handling
-                if let Some(pos) = ctx.pointer_hover_pos() {
+                self.crosshair.set_rect(rect);
+                if let Some(pos) = crosshair_pos {
                     if rect.contains(pos) {
-                        self.crosshair.draw(ui, rect, &self.data_window, pos);
+                        self.crosshair.draw(
+                            &mut painter,
+                            rect,
+                            &self.data_window,
+                            pos,
+                            &self.theme,
+                        );
                         self.crosshair.highlight_bar(
-                            ui,
+                            &mut painter,
                             rect,
                             &self.data_window,
                             pos,
                             &scale_price,
+                            &self.theme,
                         );
                     }
                 }
-                hlcbars::draw(ui, rect, &self.data_window, self.show_candles, &scale_price);
-                volbars::draw(ui, rect, &mut self.data_window);
-                axes::draw(ui, rect, &self.data_window, &scale_price);
+                self.highlights.evaluate(&self.data_window.bars);
+                self.highlights
+                    .draw(ui, rect, &self.data_window, &scale_price);
+                self.refresh_session_levels();
+                self.session_levels.draw(
+                    ui,
+                    price_rect,
+                    &scale_price,
+                    &chart_font,
+                    self.data_window.price_decimals(),
+                );
+                if self.show_volume_profile {
+                    visible_profile::draw(
+                        &mut painter,
+                        price_rect,
+                        &mut self.data_window,
+                        &scale_price,
+                        &self.theme,
+                    );
+                }
+                hlcbars::draw(
+                    &mut painter,
+                    rect,
+                    &self.data_window,
+                    self.show_candles,
+                    &self.indicators,
+                    &scale_price,
+                    &self.theme,
+                );
+                self.price_gaps.update(&self.data_window.bars);
+                self.price_gaps
+                    .draw(ui, rect, &self.data_window, &scale_price);
+                self.patterns.detect(&self.data_window.bars);
+                self.patterns
+                    .draw(ui, rect, &self.data_window, &scale_price);
+                self.refresh_liquidations();
+                self.liquidations
+                    .draw(ui, price_rect, &self.data_window, &scale_price);
+                volbars::draw(&mut painter, rect, &mut self.data_window, &self.theme);
+                // первая панель ближе всех к графику
+                for (slot, pane_rect) in self.indicators.panes().zip(pane_rects.iter().rev()) {
+                    indicator_pane::draw_pane(
+                        &mut painter,
+                        *pane_rect,
+                        &self.data_window,
+                        slot,
+                        crosshair_pos,
+                        &chart_font,
+                        &self.theme,
+                    );
+                }
+                self.refresh_seasonality();
+                self.seasonality.draw(
+                    ui,
+                    price_rect,
+                    &self.data_window,
+                    &scale_price,
+                    self.timeframe,
+                    &chart_font,
+                );
+                self.refresh_alerts();
+                self.alerts.draw(ui, price_rect, &scale_price, &chart_font);
+                self.refresh_drawings();
+                self.drawings
+                    .draw(&mut painter, rect, &self.data_window, &scale_price);
+                axes::draw(
+                    &mut painter,
+                    rect,
+                    &self.data_window,
+                    &scale_price,
+                    self.time_zone,
+                    &self.time_format,
+                    self.axis_mode,
+                    &self.theme,
+                );
+                self.events
+                    .draw(ui, rect, &self.data_window, self.time_zone);
+                self.bookmarks
+                    .draw(ui, price_rect, &self.data_window, &chart_font);
+                if let Some(pane_rect) = derivatives_rect {
+                    self.refresh_derivatives();
+                    self.derivatives
+                        .draw(ui, pane_rect, &self.data_window, &chart_font);
+                }
 
-                if response.dragged() && response.drag_delta().x != 0.0 {
-                    let delta_x = response.drag_delta().x;
+                let time_axis_rect = egui::Rect::from_min_max(
+                    egui::pos2(rect.left(), rect.bottom()),
+                    egui::pos2(
+                        rect.right(),
+                        rect.bottom() + 16.0 + self.theme.time_labels_extra(),
+                    ),
+                );
+                let zoom_input = self.zoom_selection.handle_input(
+                    &response,
+                    rect,
+                    time_axis_rect,
+                    &mut self.data_window,
+                );
+                self.zoom_selection.draw(&painter, rect);
+                let drawing_input = zoom_input
+                    || self.alerts.handle_input(
+                        &response,
+                        price_rect,
+                        &self.data_window,
+                        &scale_price,
+                        self.drawings.magnet,
+                    )
+                    || self
+                        .drawings
+                        .handle_input(&response, rect, &self.data_window);
+                let drag_x = if !drawing_input && response.dragged() {
+                    response.drag_delta().x
+                } else {
+                    0.0
+                };
+                // горизонтальное колесо (egui передает сюда и shift+колесо) панорамирует как перетаскивание
+                let wheel_x = if response.hovered() {
+                    ctx.input(|i| i.raw_scroll_delta.x)
+                } else {
+                    0.0
+                };
+                let delta_x = drag_x + wheel_x;
+                if delta_x != 0.0 {
                     let bars_len = self.data_window.bars.len() as i64;
                     let (start_idx, end_idx) = self.data_window.visible_range;
                     let visible_count = end_idx - start_idx;
@@ -101,6 +375,10 @@ impl eframe::App for InteractiveGui {
                     // Проверяем, находимся ли мы у правого края и тянем влево
                     let at_right_edge = end_idx >= bars_len;
                     let dragging_left = delta_x < 0.0;
+                    // Тянем вправо у левого края - догружаем историю
+                    if self.history_auto_load && start_idx == 0 && !dragging_left {
+                        self.load_history();
+                    }
 
                     if !(at_right_edge && dragging_left) {
                         // Обновляем смещение в пикселях
@@ -133,19 +411,448 @@ impl eframe::App for InteractiveGui {
                 }
             });
 
-            if self.status_messages_last_ts.map_or(false, |ts| {
+            if self.status_messages_last_ts.is_some_and(|ts| {
                 ts.elapsed() < Duration::from_secs(settings::STATUS_MESSAGE_HIDE_TIME)
             }) {
+                let mut retry = None;
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    for msg in &self.status_messages {
-                        ui.label(msg);
+                    for (index, msg) in self.status_messages.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(&msg.text);
+                            if msg.retry.is_some() && ui.small_button("retry").clicked() {
+                                retry = Some(index);
+                            }
+                        });
                     }
                 });
+                if let Some(action) = retry.and_then(|i| self.status_messages.remove(i).retry) {
+                    self.retry(action);
+                }
             }
         }); // Закрытие для egui::CentralPanel::default().show
+        if self.scanner.open {
+            self.show_scanner(ctx);
+        }
+        if self.session_clock.open {
+            self.session_clock.show(ctx);
+        }
+        if self.returns.open {
+            self.returns.show(ctx, &self.data_window);
+        }
+        if self.heatmap.open {
+            self.show_heatmap(ctx);
+        }
+        if self.correlation.open {
+            self.show_correlation(ctx);
+        }
+        if self.log_viewer.open {
+            self.log_viewer.show(ctx);
+        }
+        self.timeframe_switch.show_overlay(ctx);
+        if self.show_downloads {
+            downloads::show(ctx, &mut self.show_downloads, &self.scheduler);
+            // прогресс меняется в фоновом потоке
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        // возраст данных в заголовке; новые минуты live-обновления забираются в
+        // poll_background_messages
+        ctx.request_repaint_after(Duration::from_secs(1));
+        if self.ticker.is_some() || self.liquidations.enabled {
+            // котировки и ликвидации обновляются в фоновых потоках
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+        if self.history_loading() || self.derivatives.loading() || self.sync_progress.is_some() {
+            // результат догрузки истории забирается в poll_background_messages
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        if self.coverage.open {
+            match self.coverage.show(ctx) {
+                Some(CoverageAction::Refresh) => self.refresh_coverage(),
+                Some(CoverageAction::Delete(request)) => self.delete_data(request),
+                None => {}
+            }
+        }
+        if self.anomaly_report.open {
+            self.show_anomaly_report(ctx);
+        }
+        if self.bookmarks.open {
+            if let Some(time) = self.bookmarks.show(ctx, &self.data_window, self.time_zone) {
+                self.jump_to_time(time);
+            }
+        }
+        if self.alert_history.open {
+            if let Some(action) = self.alert_history.show(ctx) {
+                self.handle_alert_history(action);
+            }
+        }
         let frame_end_time = Instant::now();
         self.frame_info
             .record_frame_time(frame_end_time - frame_start_time);
         //ctx.request_repaint(); // Ensure continuous repainting
     } // Закрытие для impl eframe::App for TradingApp
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_window_geometry();
+        self.save_ui_state();
+        self.shutdown();
+    }
 } // Закрытие для impl TradingApp
+
+/// Larger buttons and row height for touch screens in the compact layout.
+fn enlarge_touch_targets(ui: &mut egui::Ui) {
+    let spacing = ui.spacing_mut();
+    spacing.interact_size.y = settings::COMPACT_TOUCH_SIZE;
+    spacing.button_padding = egui::vec2(10.0, 8.0);
+    spacing.item_spacing.y = 6.0;
+}
+
+impl InteractiveGui {
+    /// Toolbar buttons and menus: a row of the header, or the "menu" list in the compact layout.
+    fn toolbar_items(&mut self, ui: &mut egui::Ui) {
+        let picker = ui.menu_button(self.pair().to_string(), |ui| {
+            self.symbol_picker.show_menu(ui, &self.symbol)
+        });
+        if picker.response.clicked() {
+            self.refresh_symbol_picker();
+        }
+        let market = Market::split(&self.symbol).0;
+        if let Some(symbol) = picker.inner.flatten() {
+            // пары из списка биржи открываются на текущем рынке
+            if Market::split(&symbol).0 == Market::Spot {
+                self.set_symbol(&market.symbol(&symbol));
+            } else {
+                self.set_symbol(&symbol);
+            }
+        }
+        if exchange::active().has_futures() {
+            for option in [Market::Spot, Market::Futures] {
+                if ui
+                    .selectable_label(market == option, option.label())
+                    .on_hover_text("Market of the chart symbol; futures data is stored separately")
+                    .clicked()
+                {
+                    self.set_symbol(&option.symbol(&self.symbol));
+                }
+            }
+        }
+        let measure_button_text = if self.measure_frame_time { "x" } else { "F" };
+        if ui.button(measure_button_text).clicked() {
+            self.measure_frame_time = !self.measure_frame_time;
+        }
+        if ui
+            .button(if self.show_candles { "bars" } else { "candles" })
+            .clicked()
+        {
+            self.show_candles = !self.show_candles;
+        }
+        for &tf in &[5, 15, 60, 240] {
+            if ui.button(format!("{}", tf)).clicked() {
+                self.set_timeframe(tf);
+            }
+        }
+        let menu = ui.menu_button(self.timeframe_text(), |ui| {
+            TimeframeSwitch::show_menu(ui, self.timeframe)
+        });
+        if let Some(tf) = menu.inner.flatten() {
+            self.set_timeframe(tf);
+        }
+        let menu = ui.menu_button(self.data_window.bar_type.label(), |ui| {
+            TimeframeSwitch::show_bar_type_menu(
+                ui,
+                self.data_window.bar_type,
+                &mut self.bar_percent,
+            )
+        });
+        if let Some(bar_type) = menu.inner.flatten() {
+            self.set_bar_type(bar_type);
+        }
+        if ui
+            .selectable_label(
+                self.drawings.active_tool == Some(DrawingTool::AnchoredVwap),
+                "avwap",
+            )
+            .clicked()
+        {
+            self.drawings.toggle_tool(DrawingTool::AnchoredVwap);
+        }
+        if ui
+            .selectable_label(
+                self.drawings.active_tool == Some(DrawingTool::VolumeProfile),
+                "vprofile",
+            )
+            .clicked()
+        {
+            self.drawings.toggle_tool(DrawingTool::VolumeProfile);
+        }
+        if ui
+            .selectable_label(self.drawings.magnet, "magnet")
+            .on_hover_text("Snap price anchors to the bar's open/high/low/close")
+            .clicked()
+        {
+            self.drawings.magnet = !self.drawings.magnet;
+        }
+        egui::ComboBox::from_id_salt("axis_time_zone")
+            .selected_text(self.time_zone.name())
+            .show_ui(ui, |ui| {
+                for name in settings::AXIS_TIMEZONE_PRESETS {
+                    if let Some(tz) = AxisTimezone::parse(name) {
+                        ui.selectable_value(&mut self.time_zone, tz, name);
+                    }
+                }
+                let input = ui.add(
+                    egui::TextEdit::singleline(&mut self.time_zone_input).hint_text("Area/City"),
+                );
+                if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    match AxisTimezone::parse(&self.time_zone_input) {
+                        Some(tz) => self.time_zone = tz,
+                        None => {
+                            let input = self.time_zone_input.clone();
+                            self.message_add(format!("Unknown timezone: {}", input))
+                        }
+                    }
+                }
+            });
+        ui.menu_button("time fmt", |ui| {
+            ui.checkbox(&mut self.time_format.hour12, "12-hour clock");
+            ui.checkbox(&mut self.time_format.month_first, "Month before day");
+            ui.checkbox(&mut self.time_format.show_seconds, "Show seconds");
+            ui.separator();
+            ui.radio_value(&mut self.axis_mode, AxisMode::Time, "Wall-clock labels");
+            ui.radio_value(&mut self.axis_mode, AxisMode::BarIndex, "Bar labels")
+                .on_hover_text("Label every N-th bar, for data with gaps");
+            ui.separator();
+            let current = self.data_window.calendar.name;
+            egui::ComboBox::from_label("calendar")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for calendar in TradingCalendar::presets() {
+                        let name = calendar.name;
+                        if ui.selectable_label(current == name, name).clicked() {
+                            self.data_window.calendar = calendar;
+                            self.update_data_window();
+                        }
+                    }
+                });
+        });
+        if ui
+            .selectable_label(self.watchlist.open, "watchlist")
+            .clicked()
+        {
+            self.watchlist.open = !self.watchlist.open;
+        }
+        if ui.selectable_label(self.show_tape, "trades").clicked() {
+            self.show_tape = !self.show_tape;
+            // лента использует соединение котировок
+            if self.show_tape && self.ticker.is_none() {
+                self.ticker = Some(TickerStream::start(&self.symbol));
+            }
+        }
+        if ui
+            .selectable_label(self.show_volume_profile, "profile")
+            .clicked()
+        {
+            self.show_volume_profile = !self.show_volume_profile;
+        }
+        if ui
+            .selectable_label(self.show_downloads, "downloads")
+            .clicked()
+        {
+            self.show_downloads = !self.show_downloads;
+        }
+        if ui
+            .selectable_label(self.coverage.open, "coverage")
+            .clicked()
+        {
+            self.coverage.open = !self.coverage.open;
+            if self.coverage.open {
+                self.refresh_coverage();
+            }
+        }
+        if ui
+            .button("verify")
+            .on_hover_text(format!(
+                "Check the stored blocks of {} for missing minutes and corruption",
+                self.symbol
+            ))
+            .clicked()
+        {
+            self.verify_data();
+        }
+        if ui
+            .selectable_label(self.anomaly_report.open, "anomalies")
+            .clicked()
+        {
+            self.anomaly_report.open = !self.anomaly_report.open;
+            if self.anomaly_report.open {
+                self.refresh_anomaly_symbols();
+            }
+        }
+        if ui
+            .selectable_label(self.bookmarks.open, "bookmarks")
+            .clicked()
+        {
+            self.bookmarks.open = !self.bookmarks.open;
+        }
+        ui.menu_button("overlays", |ui| {
+            indicator_pane::show_overlay_menu(ui, &mut self.indicators)
+        });
+        ui.menu_button("indicators", |ui| {
+            indicator_pane::show_pane_menu(ui, &mut self.indicators);
+            ui.separator();
+            ui.add(
+                egui::Slider::new(&mut self.data_window.pane_height_ratio, 0.05..=0.4)
+                    .text("pane height"),
+            );
+        });
+        ui.menu_button("highlight", |ui| self.highlights.show_menu(ui));
+        ui.menu_button("patterns", |ui| self.patterns.show_menu(ui));
+        ui.menu_button("gaps", |ui| self.price_gaps.show_menu(ui));
+        ui.menu_button("levels", |ui| self.session_levels.show_menu(ui));
+        ui.menu_button("avg day", |ui| self.seasonality.show_menu(ui));
+        ui.menu_button("alerts", |ui| {
+            self.alerts.show_menu(ui);
+            ui.separator();
+            if ui.button("History").clicked() {
+                self.alert_history.open = true;
+                self.refresh_alert_history();
+                ui.close_menu();
+            }
+        });
+        ui.menu_button("theme", |ui| {
+            if let Some(message) = self.theme_editor.show_menu(ui, &mut self.theme) {
+                self.message_add(message);
+            }
+        });
+        ui.menu_button("liquidations", |ui| self.liquidations.show_menu(ui));
+        ui.menu_button("events", |ui| {
+            if let Some(message) = self.events.show_menu(ui) {
+                self.message_add(message);
+            }
+        });
+        let mut live = self.live.is_some();
+        if ui
+            .checkbox(&mut live, "live")
+            .on_hover_text("Stream 1m klines over a websocket: the last bar updates in real time")
+            .changed()
+        {
+            self.live = live.then(|| start_stream(ui.ctx(), &self.symbol)).flatten();
+        }
+        ui.checkbox(&mut self.derivatives.show, "funding/OI")
+            .on_hover_text("Funding rate and open interest of the futures symbol");
+        if ui.selectable_label(self.scanner.open, "scanner").clicked() {
+            self.scanner.open = !self.scanner.open;
+        }
+        if ui
+            .selectable_label(self.session_clock.open, "clock")
+            .clicked()
+        {
+            self.session_clock.open = !self.session_clock.open;
+        }
+        if ui.selectable_label(self.returns.open, "returns").clicked() {
+            self.returns.open = !self.returns.open;
+        }
+        if ui.selectable_label(self.heatmap.open, "heatmap").clicked() {
+            self.heatmap.open = !self.heatmap.open;
+        }
+        if ui
+            .selectable_label(self.correlation.open, "correlation")
+            .clicked()
+        {
+            self.correlation.open = !self.correlation.open;
+        }
+        if ui.selectable_label(self.log_viewer.open, "log").clicked() {
+            self.log_viewer.open = !self.log_viewer.open;
+        }
+        ui.menu_button("export", |ui| {
+            for (format, label) in [
+                (ExportFormat::Csv, "Hourly aggregates (CSV)"),
+                (ExportFormat::Json, "Hourly aggregates (JSON)"),
+                (ExportFormat::Text, "Hourly aggregates (text)"),
+            ] {
+                if ui.button(label).clicked() {
+                    self.export_aggr(format);
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            if ui
+                .button("Visible bars (CSV)")
+                .on_hover_text("Bars of the visible range at the current timeframe")
+                .clicked()
+            {
+                self.export_visible_csv();
+                ui.close_menu();
+            }
+            if ui
+                .button("Full 1m history (CSV)")
+                .on_hover_text("Every stored minute of the symbol")
+                .clicked()
+            {
+                self.export_history_csv();
+                ui.close_menu();
+            }
+            if ui
+                .button("Full 1m history (Parquet)")
+                .on_hover_text("Every stored minute, decimal prices, for pandas/polars")
+                .clicked()
+            {
+                self.export_history_parquet();
+                ui.close_menu();
+            }
+            if ui
+                .button("Import 1m history (Parquet)")
+                .on_hover_text(format!(
+                    "Stores the minutes of {} over the stored ones",
+                    export::parquet_path(&self.symbol, settings::EXPORT_DIR).display()
+                ))
+                .clicked()
+            {
+                self.import_history_parquet();
+                ui.close_menu();
+            }
+            ui.separator();
+            if ui
+                .button("Chart (SVG)")
+                .on_hover_text("Visible bars, volume, axes and drawings as vector shapes")
+                .clicked()
+            {
+                self.export_svg();
+                ui.close_menu();
+            }
+            if ui
+                .button("Chart report (PDF)")
+                .on_hover_text("Visible chart, range summary and active indicators")
+                .clicked()
+            {
+                self.export_report();
+                ui.close_menu();
+            }
+        });
+        ui.menu_button("backup", |ui| {
+            if ui.button("Upload new blocks").clicked() {
+                self.run_backup(false);
+                ui.close_menu();
+            }
+            if ui.button("Download missing blocks").clicked() {
+                self.run_backup(true);
+                ui.close_menu();
+            }
+        });
+        if ui
+            .add_enabled(!self.history_loading(), egui::Button::new("history"))
+            .on_hover_text(format!(
+                "Load {} more days of history",
+                settings::HISTORY_CHUNK_DAYS
+            ))
+            .clicked()
+        {
+            self.load_history();
+        }
+        if ui.button("+").clicked() {
+            self.zoom(0.1); // Zoom in
+        }
+        if ui.button("-").clicked() {
+            self.zoom(-0.1); // Zoom out
+        }
+    }
+}
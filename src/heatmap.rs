@@ -0,0 +1,251 @@
+// heatmap.rs - Average return and volume by hour of day and weekday from the stored 1m data
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::AxisTimezone;
+use crate::crosshair::format_volume;
+use crate::db::Database;
+use crate::fetch::{volume_to_f64, KLine};
+use crate::settings::HEATMAP_DAYS;
+use chrono::{Datelike, Timelike};
+use eframe::egui::{self, Align2, Color32, FontId, Rect, Stroke};
+use std::error::Error;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const CELL: egui::Vec2 = egui::vec2(22.0, 18.0);
+const LABEL_WIDTH: f32 = 32.0;
+
+/// Averages of one weekday/hour cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HourCell {
+    /// Mean return of the hour, %.
+    pub mean_return: f64,
+    pub mean_volume: f64,
+    /// Number of hours averaged.
+    pub samples: usize,
+}
+
+/// Cells indexed `[weekday from Monday][hour]` in the local time of `time_zone`.
+pub type HourGrid = [[HourCell; 24]; 7];
+
+/// Groups 1m klines into local hours; the return of an hour is its last close
+/// against its first open, so missing minutes do not distort it.
+pub fn hour_grid(klines: &[KLine], time_zone: AxisTimezone) -> HourGrid {
+    let mut sums = [[(0.0f64, 0.0f64, 0usize); 24]; 7];
+    let mut add = |hour: &Option<(usize, usize, u64, u64, f64)>| {
+        if let Some((weekday, h, open, close, volume)) = *hour {
+            if open > 0 {
+                let cell = &mut sums[weekday][h];
+                cell.0 += (close as f64 / open as f64 - 1.0) * 100.0;
+                cell.1 += volume;
+                cell.2 += 1;
+            }
+        }
+    };
+    // (weekday, hour, open, close, volume) of the hour being collected
+    let mut current: Option<(usize, usize, u64, u64, f64)> = None;
+    let mut current_start = i64::MIN;
+    for kline in klines {
+        let local = time_zone.datetime(kline.open_time);
+        // начало часа в UTC, чтобы часы с одинаковым номером в разные дни не сливались
+        let hour_start =
+            kline.open_time - (local.minute() as i64 * 60 + local.second() as i64) * 1000;
+        if hour_start != current_start {
+            add(&current);
+            current_start = hour_start;
+            current = Some((
+                local.weekday().num_days_from_monday() as usize,
+                local.hour() as usize,
+                kline.open,
+                kline.close,
+                0.0,
+            ));
+        }
+        if let Some(hour) = current.as_mut() {
+            hour.3 = kline.close;
+            hour.4 += volume_to_f64(kline.volume);
+        }
+    }
+    add(&current);
+
+    let mut grid = [[HourCell::default(); 24]; 7];
+    for (weekday, hours) in sums.iter().enumerate() {
+        for (hour, (returns, volume, samples)) in hours.iter().enumerate() {
+            if *samples > 0 {
+                grid[weekday][hour] = HourCell {
+                    mean_return: returns / *samples as f64,
+                    mean_volume: volume / *samples as f64,
+                    samples: *samples,
+                };
+            }
+        }
+    }
+    grid
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HeatmapValue {
+    Return,
+    Volume,
+}
+
+pub struct TimingHeatmap {
+    pub open: bool,
+    pub days: i64,
+    value: HeatmapValue,
+    // (symbol, timezone name, days) of the computed grid
+    computed_for: Option<(String, String, i64)>,
+    grid: Option<HourGrid>,
+    error: Option<String>,
+}
+
+impl Default for TimingHeatmap {
+    fn default() -> Self {
+        Self {
+            open: false,
+            days: HEATMAP_DAYS,
+            value: HeatmapValue::Return,
+            computed_for: None,
+            grid: None,
+            error: None,
+        }
+    }
+}
+
+impl TimingHeatmap {
+    /// Reads the last `days` of 1m data of `symbol` and rebuilds the grid.
+    pub fn compute(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        time_zone: AxisTimezone,
+    ) -> Result<(), Box<dyn Error>> {
+        let last = db.get_last_timestamp(symbol)?;
+        let from = last - self.days * 86_400_000;
+        let klines = db.get_range_data(symbol, from, last)?;
+        self.grid = Some(hour_grid(&klines, time_zone));
+        Ok(())
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        db: &Database,
+        symbol: &str,
+        time_zone: AxisTimezone,
+    ) {
+        // пересчет при открытии, смене символа или часового пояса
+        let wanted = (symbol.to_string(), time_zone.name(), self.days);
+        if self.computed_for.as_ref() != Some(&wanted) {
+            self.error = self
+                .compute(db, symbol, time_zone)
+                .err()
+                .map(|e| e.to_string());
+            self.computed_for = Some(wanted);
+        }
+        let mut open = self.open;
+        egui::Window::new(format!("Hour / weekday heatmap - {}", symbol))
+            .id(egui::Id::new("timing_heatmap"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.value, HeatmapValue::Return, "avg return");
+                    ui.selectable_value(&mut self.value, HeatmapValue::Volume, "avg volume");
+                    ui.label("days");
+                    ui.add(egui::DragValue::new(&mut self.days).range(7..=3650));
+                    if ui.button("recompute").clicked() {
+                        self.computed_for = None;
+                    }
+                });
+                if let Some(e) = &self.error {
+                    ui.colored_label(Color32::LIGHT_RED, format!("Unable to read data: {}", e));
+                }
+                if let Some(grid) = &self.grid {
+                    draw_grid(ui, grid, self.value);
+                    ui.label(format!("Hours in {} time", time_zone.name()));
+                }
+            });
+        self.open = open;
+    }
+}
+
+fn draw_grid(ui: &mut egui::Ui, grid: &HourGrid, value: HeatmapValue) {
+    let size = egui::vec2(LABEL_WIDTH + CELL.x * 24.0, CELL.y * 8.0);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let text_color = ui.visuals().text_color();
+    let font = FontId::proportional(10.0);
+    let cell_value = |cell: &HourCell| match value {
+        HeatmapValue::Return => cell.mean_return,
+        HeatmapValue::Volume => cell.mean_volume,
+    };
+    let scale = grid
+        .iter()
+        .flatten()
+        .filter(|cell| cell.samples > 0)
+        .map(|cell| cell_value(cell).abs())
+        .fold(0.0, f64::max)
+        .max(1e-12);
+
+    for hour in 0..24 {
+        painter.text(
+            egui::pos2(
+                rect.left() + LABEL_WIDTH + (hour as f32 + 0.5) * CELL.x,
+                rect.top(),
+            ),
+            Align2::CENTER_TOP,
+            hour.to_string(),
+            font.clone(),
+            text_color,
+        );
+    }
+    let mut hovered = None;
+    for (weekday, hours) in grid.iter().enumerate() {
+        let top = rect.top() + CELL.y * (weekday + 1) as f32;
+        painter.text(
+            egui::pos2(rect.left(), top + CELL.y / 2.0),
+            Align2::LEFT_CENTER,
+            WEEKDAYS[weekday],
+            font.clone(),
+            text_color,
+        );
+        for (hour, cell) in hours.iter().enumerate() {
+            let cell_rect = Rect::from_min_size(
+                egui::pos2(rect.left() + LABEL_WIDTH + hour as f32 * CELL.x, top),
+                CELL,
+            );
+            let intensity = (cell_value(cell).abs() / scale) as f32;
+            let color = match value {
+                _ if cell.samples == 0 => Color32::TRANSPARENT,
+                HeatmapValue::Return if cell.mean_return < 0.0 => {
+                    Color32::from_rgb(200, 60, 60).gamma_multiply(intensity)
+                }
+                HeatmapValue::Return => Color32::from_rgb(60, 180, 60).gamma_multiply(intensity),
+                HeatmapValue::Volume => Color32::from_rgb(80, 140, 240).gamma_multiply(intensity),
+            };
+            painter.rect_filled(cell_rect.shrink(0.5), 0.0, color);
+            if response
+                .hover_pos()
+                .is_some_and(|pos| cell_rect.contains(pos))
+            {
+                painter.rect_stroke(
+                    cell_rect,
+                    0.0,
+                    Stroke::new(1.0, text_color),
+                    egui::StrokeKind::Inside,
+                );
+                hovered = Some((weekday, hour, *cell));
+            }
+        }
+    }
+    if let Some((weekday, hour, cell)) = hovered {
+        response.on_hover_text(format!(
+            "{} {:02}:00\navg return {:+.3}%\navg volume {}\n{} hours",
+            WEEKDAYS[weekday],
+            hour,
+            cell.mean_return,
+            format_volume(cell.mean_volume),
+            cell.samples
+        ));
+    }
+}
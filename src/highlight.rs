@@ -0,0 +1,244 @@
+// highlight.rs - Conditional bar highlighting (visual scanner): user rules tinting or marking bars
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::drawing_util;
+use crate::settings::{
+    HIGHLIGHT_RANGE_PERCENT, HIGHLIGHT_VOLUME_LOOKBACK, HIGHLIGHT_VOLUME_MULTIPLE,
+};
+use crate::timeframe::Bar;
+use eframe::egui::{self, Color32, Rect};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarCondition {
+    /// Volume above `multiple` × average volume of the previous `lookback` bars.
+    VolumeSpike { multiple: f64, lookback: usize },
+    /// High-low range above `percent` of the open.
+    RangeAbove { percent: f64 },
+}
+
+impl BarCondition {
+    fn matches(&self, bars: &[Bar], index: usize) -> bool {
+        let bar = &bars[index];
+        match *self {
+            BarCondition::VolumeSpike { multiple, lookback } => {
+                if lookback == 0 || index < lookback {
+                    return false;
+                }
+                let average = bars[index - lookback..index]
+                    .iter()
+                    .map(|b| b.volume)
+                    .sum::<f64>()
+                    / lookback as f64;
+                average > 0.0 && bar.volume > multiple * average
+            }
+            BarCondition::RangeAbove { percent } => {
+                bar.open > 0.0 && (bar.high - bar.low) / bar.open * 100.0 > percent
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightStyle {
+    /// Translucent column behind the bar.
+    Tint,
+    /// Triangle above the bar high.
+    Marker,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighlightRule {
+    pub enabled: bool,
+    pub condition: BarCondition,
+    pub style: HighlightStyle,
+    pub color: Color32,
+}
+
+impl HighlightRule {
+    /// Volume spike rule with the defaults from settings.rs.
+    pub fn volume_spike(enabled: bool) -> Self {
+        Self {
+            enabled,
+            condition: BarCondition::VolumeSpike {
+                multiple: HIGHLIGHT_VOLUME_MULTIPLE,
+                lookback: HIGHLIGHT_VOLUME_LOOKBACK,
+            },
+            style: HighlightStyle::Tint,
+            color: Color32::from_rgba_unmultiplied(230, 200, 60, 40),
+        }
+    }
+
+    /// Wide range rule with the defaults from settings.rs.
+    pub fn range_above(enabled: bool) -> Self {
+        Self {
+            enabled,
+            condition: BarCondition::RangeAbove {
+                percent: HIGHLIGHT_RANGE_PERCENT,
+            },
+            style: HighlightStyle::Marker,
+            color: Color32::from_rgb(200, 120, 230),
+        }
+    }
+}
+
+// (bars.len(), first bar time, last bar time, last bar close/volume bits)
+type BarsKey = (usize, i64, i64, u64, u64);
+
+/// Rules and, per bar, the first matching rule; re-evaluated only when the bars
+/// or the rules change.
+pub struct BarHighlights {
+    pub rules: Vec<HighlightRule>,
+    matches: Vec<Option<usize>>,
+    evaluated_for: Option<(BarsKey, Vec<HighlightRule>)>,
+}
+
+impl Default for BarHighlights {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                HighlightRule::volume_spike(false),
+                HighlightRule::range_above(false),
+            ],
+            matches: Vec::new(),
+            evaluated_for: None,
+        }
+    }
+}
+
+fn bars_key(bars: &[Bar]) -> BarsKey {
+    let last = bars.last();
+    (
+        bars.len(),
+        bars.first().map_or(0, |b| b.time),
+        last.map_or(0, |b| b.time),
+        last.map_or(0, |b| b.close.to_bits()),
+        last.map_or(0, |b| b.volume.to_bits()),
+    )
+}
+
+impl BarHighlights {
+    /// Evaluates enabled rules over `bars` if the bars or the rules changed.
+    pub fn evaluate(&mut self, bars: &[Bar]) {
+        let key = bars_key(bars);
+        if self
+            .evaluated_for
+            .as_ref()
+            .is_some_and(|(k, rules)| *k == key && *rules == self.rules)
+        {
+            return;
+        }
+        self.matches = (0..bars.len())
+            .map(|index| {
+                self.rules
+                    .iter()
+                    .position(|rule| rule.enabled && rule.condition.matches(bars, index))
+            })
+            .collect();
+        self.evaluated_for = Some((key, self.rules.clone()));
+    }
+
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+    ) {
+        let painter = ui.painter();
+        let (start, end) = data_window.visible_range;
+        let end = end.min(data_window.bars.len() as i64);
+        if start < 0 || start >= end || self.matches.len() != data_window.bars.len() {
+            return;
+        }
+        let visible_count = (end - start) as usize;
+        for index in start as usize..end as usize {
+            let Some(rule) = self.matches[index].map(|i| &self.rules[i]) else {
+                continue;
+            };
+            let (x_left, x_right) = drawing_util::calculate_bar_x_position(
+                index - start as usize,
+                visible_count,
+                rect,
+                data_window.pixel_offset,
+            );
+            match rule.style {
+                HighlightStyle::Tint => {
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            egui::pos2(x_left - 1.0, rect.top()),
+                            egui::pos2(x_right + 1.0, rect.bottom()),
+                        ),
+                        0.0,
+                        rule.color,
+                    );
+                }
+                HighlightStyle::Marker => {
+                    let x_center = (x_left + x_right) / 2.0;
+                    let y = scale_price(data_window.bars[index].high) - 4.0;
+                    let half = ((x_right - x_left) / 2.0).max(3.0);
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![
+                            egui::pos2(x_center - half, y - half * 1.5),
+                            egui::pos2(x_center + half, y - half * 1.5),
+                            egui::pos2(x_center, y),
+                        ],
+                        rule.color,
+                        egui::Stroke::NONE,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Rule editor for the toolbar menu.
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        for (i, rule) in self.rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut rule.enabled, "");
+                match &mut rule.condition {
+                    BarCondition::VolumeSpike { multiple, lookback } => {
+                        ui.label("volume >");
+                        ui.add(
+                            egui::DragValue::new(multiple)
+                                .speed(0.1)
+                                .range(0.1..=100.0)
+                                .suffix("×"),
+                        );
+                        ui.label("avg of");
+                        ui.add(egui::DragValue::new(lookback).range(1..=500));
+                        ui.label("bars");
+                    }
+                    BarCondition::RangeAbove { percent } => {
+                        ui.label("range >");
+                        ui.add(
+                            egui::DragValue::new(percent)
+                                .speed(0.05)
+                                .range(0.01..=100.0)
+                                .suffix("%"),
+                        );
+                    }
+                }
+                ui.selectable_value(&mut rule.style, HighlightStyle::Tint, "tint");
+                ui.selectable_value(&mut rule.style, HighlightStyle::Marker, "marker");
+                ui.color_edit_button_srgba(&mut rule.color);
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.rules.remove(i);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("+ volume").clicked() {
+                self.rules.push(HighlightRule::volume_spike(true));
+            }
+            if ui.button("+ range").clicked() {
+                self.rules.push(HighlightRule::range_above(true));
+            }
+        });
+    }
+}
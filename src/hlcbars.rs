@@ -1,24 +1,29 @@
 use crate::datawindow::DataWindow;
 use crate::drawing_util;
+use crate::indicator::Indicators;
+use crate::indicator_pane;
+use crate::render::Renderer;
+use crate::theme::Theme;
 
 pub fn draw(
-    ui: &mut egui::Ui,
+    renderer: &mut impl Renderer,
     rect: egui::Rect,
     data_window: &DataWindow,
     show_candles: bool,
+    indicators: &Indicators,
     scale_price: &impl Fn(f64) -> f32,
+    theme: &Theme,
 ) {
-    let painter = ui.painter();
-    let pixels_per_point = ui.ctx().pixels_per_point();
+    let pixels_per_point = renderer.pixels_per_point();
     let pixel_offset = data_window.pixel_offset.floor();
 
     // Функция для выравнивания 1px линий
     let align_px =
         |x: f32| (x * pixels_per_point).floor() / pixels_per_point + 0.5 / pixels_per_point;
 
-    let up_color = egui::Color32::from_rgb(0, 180, 0);
-    let down_color = egui::Color32::from_rgb(180, 0, 0);
-    let gray = egui::Color32::from_rgb(180, 180, 180);
+    let up_color = theme.candle_up;
+    let down_color = theme.candle_down;
+    let gray = theme.bar_line;
 
     let (start, end) = data_window.visible_range;
     if start >= end || end as usize > data_window.bars.len() {
@@ -57,9 +62,9 @@ pub fn draw(
 
         if show_candles {
             let x_center = align_px((x_left + x_right) / 2.0);
-            painter.line_segment(
+            renderer.line_segment(
                 [egui::pos2(x_center, high_y), egui::pos2(x_center, low_y)],
-                (1.0, color),
+                egui::Stroke::new(1.0, color),
             );
 
             // Для прямоугольников используем то же выравнивание
@@ -68,7 +73,7 @@ pub fn draw(
             let rect_min_y = align_px(open_y.min(close_y));
             let rect_max_y = align_px(open_y.max(close_y));
 
-            painter.rect_filled(
+            renderer.rect_filled(
                 egui::Rect::from_min_max(
                     egui::pos2(rect_min_x, rect_min_y),
                     egui::pos2(rect_max_x, rect_max_y),
@@ -78,18 +83,22 @@ pub fn draw(
             );
         } else {
             let x_center = align_px((x_left + x_right) / 2.0);
-            painter.line_segment(
+            renderer.line_segment(
                 [egui::pos2(x_center, high_y), egui::pos2(x_center, low_y)],
-                (1.0, gray),
+                egui::Stroke::new(1.0, gray),
             );
 
             let bar_width = x_right - x_left;
             let tick_width = align_px(bar_width * 0.6);
             let tick_end = align_px(x_center + tick_width);
-            painter.line_segment(
+            renderer.line_segment(
                 [egui::pos2(x_center, close_y), egui::pos2(tick_end, close_y)],
-                (1.0, gray),
+                egui::Stroke::new(1.0, gray),
             );
         }
     }
+    // скользящие средние и прочие индикаторы поверх баров
+    for slot in indicators.overlays() {
+        indicator_pane::draw_overlay(renderer, rect, data_window, slot, scale_price);
+    }
 }
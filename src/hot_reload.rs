@@ -0,0 +1,49 @@
+// hot_reload.rs - Reloads the theme, event and config files when they change on disk
+// See CONVENTIONS.md for project structure and workflow
+
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Modification time of a file, polled at most every `interval`.
+pub struct FileWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    checked: Option<Instant>,
+    interval: Duration,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(Path::new(path))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+impl FileWatcher {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            path: String::new(),
+            modified: None,
+            checked: None,
+            interval,
+        }
+    }
+
+    /// True once after `path` was written since the previous check. Switching to another
+    /// path only records its current state; a missing file is not a change.
+    pub fn changed(&mut self, path: &str) -> bool {
+        if path != self.path {
+            self.path = path.to_string();
+            self.modified = modified(path);
+            self.checked = Some(Instant::now());
+            return false;
+        }
+        if self.checked.is_some_and(|t| t.elapsed() < self.interval) {
+            return false;
+        }
+        self.checked = Some(Instant::now());
+        let current = modified(path);
+        let changed = current.is_some() && current != self.modified;
+        self.modified = current;
+        changed
+    }
+}
@@ -0,0 +1,283 @@
+// indicator.rs - Indicator trait (values per bar plus render hints), built-in RSI/MACD/moving averages/session VWAP, registry with cached values
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::rsi::WilderRSI;
+use crate::settings::VWAP_SESSION_START_HOUR;
+use crate::timeframe::Bar;
+use crate::vwap;
+
+/// Where an indicator is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placement {
+    /// Over the bars, in price units.
+    Overlay,
+    /// Own pane under the volume bars. `range` fixes the scale (otherwise it fits the
+    /// visible values), `guides` are horizontal reference lines.
+    Pane {
+        range: Option<(f64, f64)>,
+        guides: Vec<f64>,
+    },
+}
+
+pub trait Indicator: Send {
+    fn name(&self) -> String;
+    /// One value per bar, NaN where it is not defined (e.g. during the warm-up period).
+    fn compute(&self, bars: &[Bar]) -> Vec<f64>;
+    fn placement(&self) -> Placement;
+    /// Default line color, RGB.
+    fn color(&self) -> [u8; 3];
+    /// Editable integer parameters (name, value), e.g. periods.
+    fn params_mut(&mut self) -> Vec<(&'static str, &mut usize)> {
+        Vec::new()
+    }
+}
+
+/// Exponential moving average seeded with the simple average of the first `period`
+/// values; NaN before that.
+pub fn ema(values: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; values.len()];
+    if period == 0 || values.len() < period {
+        return result;
+    }
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut current = values[..period].iter().sum::<f64>() / period as f64;
+    result[period - 1] = current;
+    for (i, value) in values.iter().enumerate().skip(period) {
+        current += alpha * (value - current);
+        result[i] = current;
+    }
+    result
+}
+
+/// Simple moving average, NaN before `period` values.
+pub fn sma(values: &[f64], period: usize) -> Vec<f64> {
+    let mut result = vec![f64::NAN; values.len()];
+    if period == 0 {
+        return result;
+    }
+    let mut sum = 0.0;
+    for (i, value) in values.iter().enumerate() {
+        sum += value;
+        if i >= period {
+            sum -= values[i - period];
+        }
+        if i + 1 >= period {
+            result[i] = sum / period as f64;
+        }
+    }
+    result
+}
+
+fn closes(bars: &[Bar]) -> Vec<f64> {
+    bars.iter().map(|b| b.close).collect()
+}
+
+/// Wilder RSI of the bar closes, 0-100 pane with 30/70 guides.
+pub struct Rsi {
+    pub period: usize,
+}
+
+impl Indicator for Rsi {
+    fn name(&self) -> String {
+        format!("RSI {}", self.period)
+    }
+
+    fn compute(&self, bars: &[Bar]) -> Vec<f64> {
+        let mut rsi = WilderRSI::new(self.period);
+        bars.iter()
+            .map(|bar| rsi.add_price(bar.time, bar.close).unwrap_or(f64::NAN))
+            .collect()
+    }
+
+    fn placement(&self) -> Placement {
+        Placement::Pane {
+            range: Some((0.0, 100.0)),
+            guides: vec![30.0, 50.0, 70.0],
+        }
+    }
+
+    fn color(&self) -> [u8; 3] {
+        [180, 130, 230]
+    }
+
+    fn params_mut(&mut self) -> Vec<(&'static str, &mut usize)> {
+        vec![("period", &mut self.period)]
+    }
+}
+
+/// MACD line: EMA(`fast`) - EMA(`slow`) of the closes, pane around zero.
+pub struct Macd {
+    pub fast: usize,
+    pub slow: usize,
+}
+
+impl Indicator for Macd {
+    fn name(&self) -> String {
+        format!("MACD {}/{}", self.fast, self.slow)
+    }
+
+    fn compute(&self, bars: &[Bar]) -> Vec<f64> {
+        let closes = closes(bars);
+        ema(&closes, self.fast)
+            .iter()
+            .zip(ema(&closes, self.slow))
+            .map(|(fast, slow)| fast - slow)
+            .collect()
+    }
+
+    fn placement(&self) -> Placement {
+        Placement::Pane {
+            range: None,
+            guides: vec![0.0],
+        }
+    }
+
+    fn color(&self) -> [u8; 3] {
+        [90, 170, 230]
+    }
+
+    fn params_mut(&mut self) -> Vec<(&'static str, &mut usize)> {
+        vec![("fast", &mut self.fast), ("slow", &mut self.slow)]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaKind {
+    Sma,
+    Ema,
+}
+
+/// Moving average of the closes over the bars.
+pub struct MovingAverage {
+    pub kind: MaKind,
+    pub period: usize,
+    pub color: [u8; 3],
+}
+
+impl Indicator for MovingAverage {
+    fn name(&self) -> String {
+        match self.kind {
+            MaKind::Sma => format!("SMA {}", self.period),
+            MaKind::Ema => format!("EMA {}", self.period),
+        }
+    }
+
+    fn compute(&self, bars: &[Bar]) -> Vec<f64> {
+        let closes = closes(bars);
+        match self.kind {
+            MaKind::Sma => sma(&closes, self.period),
+            MaKind::Ema => ema(&closes, self.period),
+        }
+    }
+
+    fn placement(&self) -> Placement {
+        Placement::Overlay
+    }
+
+    fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    fn params_mut(&mut self) -> Vec<(&'static str, &mut usize)> {
+        vec![("period", &mut self.period)]
+    }
+}
+
+/// VWAP over the bars, restarting every `session_hours` from `VWAP_SESSION_START_HOUR` UTC.
+pub struct SessionVwap {
+    pub session_hours: usize,
+}
+
+impl Indicator for SessionVwap {
+    fn name(&self) -> String {
+        if self.session_hours == 24 {
+            "VWAP session".to_string()
+        } else {
+            format!("VWAP {}h", self.session_hours)
+        }
+    }
+
+    fn compute(&self, bars: &[Bar]) -> Vec<f64> {
+        vwap::session_vwap(bars, self.session_hours, VWAP_SESSION_START_HOUR)
+    }
+
+    fn placement(&self) -> Placement {
+        Placement::Overlay
+    }
+
+    fn color(&self) -> [u8; 3] {
+        [240, 150, 60]
+    }
+
+    fn params_mut(&mut self) -> Vec<(&'static str, &mut usize)> {
+        vec![("hours", &mut self.session_hours)]
+    }
+}
+
+/// Registered indicator and its values for the current bars.
+pub struct IndicatorSlot {
+    pub indicator: Box<dyn Indicator>,
+    pub enabled: bool,
+    /// Line color, RGB; starts as the indicator's default.
+    pub color: [u8; 3],
+    values: Vec<f64>,
+    // (bars, first time, last time, last close) of the computed values
+    computed_for: Option<(usize, i64, i64, u64)>,
+}
+
+impl IndicatorSlot {
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Forces a recompute on the next update, after the parameters were edited.
+    pub fn invalidate(&mut self) {
+        self.computed_for = None;
+    }
+}
+
+#[derive(Default)]
+pub struct Indicators {
+    pub slots: Vec<IndicatorSlot>,
+}
+
+impl Indicators {
+    pub fn register(&mut self, indicator: Box<dyn Indicator>, enabled: bool) {
+        self.slots.push(IndicatorSlot {
+            color: indicator.color(),
+            indicator,
+            enabled,
+            values: Vec::new(),
+            computed_for: None,
+        });
+    }
+
+    /// Recomputes the enabled indicators when the bars changed since the last call.
+    pub fn update(&mut self, bars: &[Bar]) {
+        let key = bars
+            .first()
+            .zip(bars.last())
+            .map(|(first, last)| (bars.len(), first.time, last.time, last.close.to_bits()));
+        for slot in self.slots.iter_mut().filter(|s| s.enabled) {
+            if slot.computed_for != key {
+                slot.values = slot.indicator.compute(bars);
+                slot.computed_for = key;
+            }
+        }
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &IndicatorSlot> {
+        self.slots.iter().filter(|s| s.enabled)
+    }
+
+    /// Enabled indicators drawn in their own pane.
+    pub fn panes(&self) -> impl Iterator<Item = &IndicatorSlot> {
+        self.enabled()
+            .filter(|s| matches!(s.indicator.placement(), Placement::Pane { .. }))
+    }
+
+    pub fn overlays(&self) -> impl Iterator<Item = &IndicatorSlot> {
+        self.enabled()
+            .filter(|s| s.indicator.placement() == Placement::Overlay)
+    }
+}
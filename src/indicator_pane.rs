@@ -0,0 +1,202 @@
+// indicator_pane.rs - Drawing of registered indicators (own pane under the volume bars or overlay on the price chart), toolbar menus
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::drawing_util;
+use crate::indicator::{IndicatorSlot, Indicators, MaKind, MovingAverage, Placement};
+use crate::render::Renderer;
+use crate::theme::Theme;
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Stroke};
+
+fn color(slot: &IndicatorSlot) -> Color32 {
+    let [r, g, b] = slot.color;
+    Color32::from_rgb(r, g, b)
+}
+
+/// Polyline through the defined values of the visible bars, broken where the value is NaN.
+fn draw_series(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &DataWindow,
+    values: &[f64],
+    stroke: Stroke,
+    scale: impl Fn(f64) -> f32,
+) {
+    let (start, end) = data_window.visible_range;
+    let end = end.min(values.len() as i64);
+    if start < 0 || start >= end {
+        return;
+    }
+    let visible = &values[start as usize..end as usize];
+    let mut points = Vec::new();
+    for (i, value) in visible.iter().enumerate() {
+        if value.is_finite() {
+            let x = drawing_util::calculate_bar_center_x(
+                i,
+                visible.len(),
+                rect,
+                data_window.pixel_offset,
+            );
+            points.push(egui::pos2(x, scale(*value)));
+        } else if points.len() > 1 {
+            renderer.polyline(std::mem::take(&mut points), stroke);
+        } else {
+            points.clear();
+        }
+    }
+    if points.len() > 1 {
+        renderer.polyline(points, stroke);
+    }
+}
+
+/// Pane indicator in `rect` with the same horizontal layout as the chart. Without a
+/// fixed range the scale fits the visible values; with `hover` over the pane's x range
+/// the value of the bar under it is shown next to the name.
+pub fn draw_pane(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &DataWindow,
+    slot: &IndicatorSlot,
+    hover: Option<Pos2>,
+    font: &FontId,
+    theme: &Theme,
+) {
+    let Placement::Pane { range, guides } = slot.indicator.placement() else {
+        return;
+    };
+    let values = slot.values();
+    let (start, end) = data_window.visible_range;
+    let visible = values
+        .get(start.max(0) as usize..end.clamp(0, values.len() as i64) as usize)
+        .unwrap_or_default();
+    let (min, max) = range.unwrap_or_else(|| {
+        visible
+            .iter()
+            .chain(&guides)
+            .filter(|v| v.is_finite())
+            .fold((f64::MAX, f64::MIN), |(lo, hi), v| (lo.min(*v), hi.max(*v)))
+    });
+    let span = if max > min { max - min } else { 1.0 };
+    let scale = |value: f64| rect.bottom() - ((value - min) / span) as f32 * rect.height();
+
+    renderer.line_segment(
+        [rect.left_top(), rect.right_top()],
+        Stroke::new(1.0, theme.grid),
+    );
+    for level in guides {
+        let y = scale(level);
+        renderer.line_segment(
+            [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+            Stroke::new(1.0, theme.grid),
+        );
+        renderer.text(
+            egui::pos2(rect.right() - 2.0, y),
+            Align2::RIGHT_CENTER,
+            &format!("{}", level),
+            font,
+            theme.grid,
+        );
+    }
+    let line_color = color(slot);
+    draw_series(
+        renderer,
+        rect,
+        data_window,
+        values,
+        Stroke::new(1.2, line_color),
+        scale,
+    );
+
+    let hovered = hover
+        .filter(|pos| pos.x >= rect.left() && pos.x <= rect.right())
+        .and_then(|pos| {
+            drawing_util::bar_index_at_x(
+                pos.x,
+                data_window.visible_range,
+                data_window.bars.len(),
+                rect,
+                data_window.pixel_offset,
+            )
+        })
+        .and_then(|index| values.get(index).copied());
+    let name = slot.indicator.name();
+    let label = match hovered
+        .or_else(|| visible.last().copied())
+        .filter(|v| v.is_finite())
+    {
+        Some(value) => format!("{} {:.2}", name, value),
+        None => name,
+    };
+    renderer.text(
+        egui::pos2(rect.left() + 4.0, rect.top() + 2.0),
+        Align2::LEFT_TOP,
+        &label,
+        font,
+        line_color,
+    );
+}
+
+/// Overlay indicator over the bars of `rect`, in price units.
+pub fn draw_overlay(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &DataWindow,
+    slot: &IndicatorSlot,
+    scale_price: &impl Fn(f64) -> f32,
+) {
+    draw_series(
+        renderer,
+        rect,
+        data_window,
+        slot.values(),
+        Stroke::new(1.2, color(slot)),
+        scale_price,
+    );
+}
+
+/// Toggle and parameter fields of a slot; recomputed on the next frame after an edit.
+fn slot_row(ui: &mut egui::Ui, slot: &mut IndicatorSlot) {
+    ui.checkbox(&mut slot.enabled, slot.indicator.name());
+    let mut changed = false;
+    for (name, value) in slot.indicator.params_mut() {
+        ui.label(name);
+        changed |= ui.add(egui::DragValue::new(value).range(1..=500)).changed();
+    }
+    if changed {
+        slot.invalidate();
+    }
+}
+
+/// Pane indicators with their parameters.
+pub fn show_pane_menu(ui: &mut egui::Ui, indicators: &mut Indicators) {
+    for slot in &mut indicators.slots {
+        if slot.indicator.placement() != Placement::Overlay {
+            ui.horizontal(|ui| slot_row(ui, slot));
+        }
+    }
+}
+
+/// Moving averages and other overlays: toggle, parameters and line color; new SMA/EMA lines.
+pub fn show_overlay_menu(ui: &mut egui::Ui, indicators: &mut Indicators) {
+    for slot in &mut indicators.slots {
+        if slot.indicator.placement() == Placement::Overlay {
+            ui.horizontal(|ui| {
+                slot_row(ui, slot);
+                ui.color_edit_button_srgb(&mut slot.color);
+            });
+        }
+    }
+    ui.separator();
+    ui.horizontal(|ui| {
+        for (kind, label) in [(MaKind::Sma, "+ SMA"), (MaKind::Ema, "+ EMA")] {
+            if ui.button(label).clicked() {
+                let line = MovingAverage {
+                    kind,
+                    period: 20,
+                    color: [200, 200, 200],
+                };
+                indicators.register(Box::new(line), true);
+            }
+        }
+    });
+}
@@ -1,62 +1,265 @@
+use crate::alert_history::{AlertHistory, HistoryAction};
+use crate::alerts::PriceAlerts;
+use crate::anomaly_report::{AnomalyReport, RefetchRequest};
+use crate::app_error::{self, AppError, RetryAction, StatusMessage};
+use crate::axes_util::{AxisMode, AxisTimezone};
+use crate::backup::S3Backup;
+use crate::bookmarks::Bookmarks;
+use crate::calendar::TradingCalendar;
+use crate::config;
+use crate::correlation::CorrelationMatrix;
+use crate::coverage::{CoverageMap, DeleteRequest};
 use crate::crosshair;
 use crate::datawindow::DataWindow;
-use crate::db::Database;
-use crate::gpu_backend;
+use crate::db::{Database, UiState};
+use crate::derivatives::DerivativesPane;
+use crate::drawings::{Drawing, Drawings, ViewState};
+use crate::events::EventMarkers;
+use crate::exchange::Market;
+use crate::export::{self, ExportFormat};
+use crate::fetch::{self, KLine};
+use crate::gpu_backend::{self, WindowGeometry};
+use crate::heatmap::TimingHeatmap;
+use crate::highlight::BarHighlights;
+use crate::hot_reload::FileWatcher;
+use crate::indicator::{Indicators, MaKind, Macd, MovingAverage, Rsi, SessionVwap};
+use crate::liquidations::LiquidationMarkers;
+use crate::logging::LogViewer;
+use crate::patterns::PatternMarkers;
 use crate::performance::FrameInfo;
+use crate::price_gaps::PriceGaps;
+use crate::report::{self, ChartStyle};
+use crate::returns::ReturnsHistogram;
+use crate::scanner::Scanner;
+use crate::scheduler::SyncScheduler;
+use crate::seasonality::SeasonalityOverlay;
+use crate::session_clock::SessionClock;
+use crate::session_levels::SessionLevels;
 use crate::settings::*;
-use chrono::{Duration, Utc};
+use crate::stream::KlineStream;
+use crate::symbol_info::{self, SymbolInfo};
+use crate::symbol_picker::SymbolPicker;
+use crate::theme::{Theme, ThemeEditor};
+use crate::ticker::TickerStream;
+use crate::timeframe::{BarType, SyncProgress, Timeframe};
+use crate::timeframe_switch::{timeframe_label, TimeframeSwitch};
+use crate::watchlist::Watchlist;
+use crate::zoom_select::ZoomSelection;
+use chrono::{Duration, Local, TimeZone, Utc};
+use eframe::egui;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
 use std::time::Instant;
 
 pub struct InteractiveGui {
     db: Database,
     pub data_window: DataWindow,
     pub timeframe: i32,
-    pub status_messages: Vec<String>,
+    pub status_messages: Vec<StatusMessage>,
     pub status_messages_last_ts: Option<Instant>,
     pub symbol: String,
     pub show_candles: bool,
     pub measure_frame_time: bool,
     pub crosshair: crosshair::Crosshair,
+    pub drawings: Drawings,
+    pub zoom_selection: ZoomSelection,
+    pub time_zone: AxisTimezone,
+    pub time_zone_input: String,
+    pub time_format: TimeLabelFormat,
+    pub axis_mode: AxisMode,
     pub frame_info: FrameInfo,
+    pub scheduler: SyncScheduler,
+    pub show_downloads: bool,
+    pub coverage: CoverageMap,
+    pub highlights: BarHighlights,
+    pub scanner: Scanner,
+    pub patterns: PatternMarkers,
+    pub price_gaps: PriceGaps,
+    pub session_levels: SessionLevels,
+    pub derivatives: DerivativesPane,
+    pub liquidations: LiquidationMarkers,
+    pub alerts: PriceAlerts,
+    pub alert_history: AlertHistory,
+    pub bookmarks: Bookmarks,
+    /// Registered indicators, drawn as overlays or in panes under the volume bars.
+    pub indicators: Indicators,
+    pub anomaly_report: AnomalyReport,
+    pub theme: Theme,
+    pub theme_editor: ThemeEditor,
+    pub events: EventMarkers,
+    pub session_clock: SessionClock,
+    pub returns: ReturnsHistogram,
+    pub heatmap: TimingHeatmap,
+    pub seasonality: SeasonalityOverlay,
+    pub correlation: CorrelationMatrix,
+    pub log_viewer: LogViewer,
+    pub timeframe_switch: TimeframeSwitch,
+    pub ticker: Option<TickerStream>,
+    pub show_tape: bool,
+    pub watchlist: Watchlist,
+    /// Renko box / range bar size in percent of the price, for the bar type menu.
+    pub bar_percent: f64,
+    /// Volume profile of the visible bars at the right edge of the price chart.
+    pub show_volume_profile: bool,
+    pub live: Option<KlineStream>,
+    pub window_geometry: WindowGeometry,
+    // theme, event and config files changed on disk are applied live
+    theme_watcher: FileWatcher,
+    events_watcher: FileWatcher,
+    config_watcher: FileWatcher,
+    // recent_data changed since it was last saved to the DB
+    recent_unsaved: bool,
+    recent_saved: Instant,
+    // status messages from background tasks
+    message_tx: mpsc::Sender<String>,
+    message_rx: mpsc::Receiver<String>,
+    // days of history shown, grows with on-demand loading
+    pub history_days: i64,
+    history_job: Option<JoinHandle<Result<usize, String>>>,
+    // (symbol, blocks re-fetched) from the anomaly report
+    refetch_job: Option<JoinHandle<Result<(String, usize), String>>>,
+    import_job: Option<JoinHandle<Result<ImportedHistory, String>>>,
+    // auto-loading on back-pan stops at the start of history or after an error
+    pub history_auto_load: bool,
+    /// Tick/lot size and assets of the chart symbol, once loaded.
+    pub symbol_info: Option<SymbolInfo>,
+    symbol_info_job: Option<JoinHandle<Result<SymbolInfo, String>>>,
+    pub symbol_picker: SymbolPicker,
+    symbol_list_job: Option<JoinHandle<Result<Vec<String>, String>>>,
+    sync_job: Option<JoinHandle<Result<SyncedTail, String>>>,
+    sync_progress_rx: Option<mpsc::Receiver<SyncProgress>>,
+    /// Progress of the running sync of the chart symbol.
+    pub sync_progress: Option<SyncProgress>,
+}
+
+// (symbol, unfinished block tail) of a finished fetch of missing blocks
+type SyncedTail = (String, Vec<KLine>);
+// (symbol, klines read, blocks written) of a Parquet import
+type ImportedHistory = (String, usize, usize);
+
+/// Kline stream of `symbol` that repaints the window on every update, `None` when the
+/// exchange has no stream.
+pub fn start_stream(ctx: &egui::Context, symbol: &str) -> Option<KlineStream> {
+    let ctx = ctx.clone();
+    KlineStream::start(symbol, std::sync::Arc::new(move || ctx.request_repaint()))
+}
+
+/// "43s", "12m", "5h", "3d".
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
+fn format_time(ts: i64) -> String {
+    Local
+        .timestamp_millis_opt(ts)
+        .single()
+        .map(|dt| dt.format("%d %b %Y %H:%M").to_string())
+        .unwrap_or_default()
 }
 
 impl InteractiveGui {
-    pub fn new(cc: &eframe::CreationContext<'_>, symbol: &str, timeframe: i32) -> Self {
-        println!("Creating InteractiveGui object");
+    /// Chart of `symbol` at `timeframe`, or of the last session's symbol and timeframe
+    /// when `restore_chart` (with `RESTORE_UI_STATE`, which also restores the view).
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        symbol: &str,
+        timeframe: i32,
+        restore_chart: bool,
+    ) -> Self {
+        tracing::info!("Creating InteractiveGui object");
+        let db = Database::new(&config::get().db_path).expect("Error initializing DB");
+        let ui_state = if RESTORE_UI_STATE {
+            db.get_ui_state().unwrap_or_else(|e| {
+                tracing::warn!("Unable to load the UI state: {}", e);
+                None
+            })
+        } else {
+            None
+        };
+        let (symbol, timeframe) = match ui_state.as_ref().filter(|_| restore_chart) {
+            Some(state) => (state.symbol.as_str(), state.timeframe),
+            None => (symbol, timeframe),
+        };
 
         let future = gpu_backend::log_gpu_api();
         pollster::block_on(future);
         /*if let Some(_render_state) = &cc.wgpu_render_state {
         // just to know where it's at
         }*/
-        // dark theme
-        let mut style = (*cc.egui_ctx.style()).clone();
-        style.visuals.dark_mode = true;
-        cc.egui_ctx.set_style(style);
+        // dark theme unless the theme file says otherwise
+        let theme = Theme::load_default();
+        let mut watchlist = Watchlist::default();
+        watchlist.open = SHOW_WATCHLIST;
+        let mut indicators = Indicators::default();
+        indicators.register(Box::new(Rsi { period: RSI_PERIOD }), SHOW_RSI_PANE);
+        indicators.register(Box::new(Macd { fast: 12, slow: 26 }), false);
+        for (period, color) in MOVING_AVERAGES {
+            let ema = MovingAverage {
+                kind: MaKind::Ema,
+                period,
+                color,
+            };
+            indicators.register(Box::new(ema), SHOW_MOVING_AVERAGES);
+        }
+        let vwap = SessionVwap {
+            session_hours: VWAP_SESSION_HOURS,
+        };
+        indicators.register(Box::new(vwap), SHOW_SESSION_VWAP);
+        theme.apply(&cc.egui_ctx);
 
         let mut data_window = DataWindow {
             bars: Vec::new(),
             visible_range: (0, 0),
             price: (0.0, 0.0),
             recent_data: Vec::new(),
+            forming: None,
             timeframe_remainder: Vec::new(),
             volume_height_ratio: 0.2,
+            pane_height_ratio: INDICATOR_PANE_RATIO,
             pixel_offset: 0.0,
             min_indexes: None,
             max_indexes: None,
             cached_visible_range: None,
             cached_max_volume: None,
+            cached_volume_profile: None,
+            dirty: false,
+            calendar: TradingCalendar::for_symbol(symbol),
+            tick_decimals: None,
+            bar_type: BarType::Time,
+            bar_size: 0.0,
+            coarsened_timeframe: None,
         };
         let now = chrono::Utc::now().timestamp_millis();
-        let start_time = now - chrono::Duration::days(INITIAL_LOAD_DAYS).num_milliseconds();
-        let db = Database::new("ohlcv_db").expect("Error initializing DB");
-        // loading initial data window
+        let start_time =
+            now - chrono::Duration::days(config::get().initial_load_days).num_milliseconds();
+        let (message_tx, message_rx) = mpsc::channel();
+        let scheduler = SyncScheduler::start(db.clone(), &SYNC_SYMBOLS);
+        if let Err(e) = Timeframe::restore_recent(symbol, &db, &mut data_window) {
+            app_error::report(AppError::RecentData {
+                symbol: symbol.to_string(),
+                message: e.to_string(),
+            });
+        }
+        if let Some(state) = &ui_state {
+            data_window.bar_type = state.bar_type;
+            data_window.volume_height_ratio = state.volume_height_ratio;
+            data_window.pane_height_ratio = state.pane_height_ratio;
+        }
+        // сразу показываем сохраненные данные, недостающие блоки догружает start_sync
         if let Err(e) =
-            DataWindow::get_data_window(&db, symbol, start_time, now, timeframe, &mut data_window)
+            DataWindow::load_bars(&db, symbol, start_time, now, timeframe, &mut data_window)
         {
-            eprintln!("Unable to get data window: {}", e);
+            app_error::report(AppError::DataWindow {
+                symbol: symbol.to_string(),
+                message: e.to_string(),
+            });
         }
-        Self {
+        let mut gui = Self {
             db,
             data_window,
             timeframe,
@@ -66,17 +269,781 @@ impl InteractiveGui {
             show_candles: true,
             measure_frame_time: false,
             crosshair: crosshair::Crosshair::default(),
+            drawings: Drawings::default(),
+            zoom_selection: ZoomSelection::default(),
+            time_zone: AxisTimezone::default(),
+            time_zone_input: String::new(),
+            time_format: TimeLabelFormat::default(),
+            axis_mode: AxisMode::default(),
             frame_info: FrameInfo::default(),
+            scheduler,
+            show_downloads: false,
+            coverage: CoverageMap::default(),
+            highlights: BarHighlights::default(),
+            scanner: Scanner::default(),
+            patterns: PatternMarkers::default(),
+            price_gaps: PriceGaps::default(),
+            session_levels: SessionLevels::default(),
+            derivatives: DerivativesPane::default(),
+            liquidations: LiquidationMarkers::default(),
+            alerts: PriceAlerts::default(),
+            alert_history: AlertHistory::default(),
+            bookmarks: Bookmarks::default(),
+            indicators,
+            anomaly_report: AnomalyReport::default(),
+            theme,
+            theme_editor: ThemeEditor::default(),
+            events: EventMarkers::default(),
+            session_clock: SessionClock::default(),
+            returns: ReturnsHistogram::default(),
+            heatmap: TimingHeatmap::default(),
+            seasonality: SeasonalityOverlay::default(),
+            correlation: CorrelationMatrix::default(),
+            log_viewer: LogViewer::default(),
+            timeframe_switch: TimeframeSwitch::default(),
+            ticker: (SHOW_BOOK_TICKER || SHOW_TRADE_TAPE).then(|| TickerStream::start(symbol)),
+            show_tape: SHOW_TRADE_TAPE,
+            watchlist,
+            bar_percent: PRICE_BAR_PERCENT,
+            show_volume_profile: SHOW_VISIBLE_PROFILE,
+            live: LIVE_UPDATE
+                .then(|| start_stream(&cc.egui_ctx, symbol))
+                .flatten(),
+            window_geometry: WindowGeometry::load_default(),
+            theme_watcher: FileWatcher::new(std::time::Duration::from_secs(
+                HOT_RELOAD_INTERVAL_SECS,
+            )),
+            events_watcher: FileWatcher::new(std::time::Duration::from_secs(
+                HOT_RELOAD_INTERVAL_SECS,
+            )),
+            config_watcher: FileWatcher::new(std::time::Duration::from_secs(
+                HOT_RELOAD_INTERVAL_SECS,
+            )),
+            recent_unsaved: false,
+            recent_saved: Instant::now(),
+            message_tx,
+            message_rx,
+            history_days: config::get().initial_load_days,
+            history_job: None,
+            refetch_job: None,
+            import_job: None,
+            history_auto_load: HISTORY_AUTO_LOAD,
+            symbol_info: None,
+            symbol_info_job: None,
+            symbol_picker: SymbolPicker::default(),
+            symbol_list_job: None,
+            sync_job: None,
+            sync_progress_rx: None,
+            sync_progress: None,
+        };
+        if let Some(state) = ui_state {
+            gui.show_candles = state.show_candles;
+            // диапазон относится к барам того же символа и таймфрейма
+            if let Some((first, last)) = state
+                .visible_times
+                .filter(|_| state.symbol == gui.symbol && state.timeframe == gui.timeframe)
+            {
+                gui.data_window
+                    .set_visible_times(first, last, state.at_last_bar);
+            }
         }
+        gui.load_symbol_info();
+        gui.start_sync();
+        gui
+    }
+    pub fn message_add(&mut self, new_message: String) {
+        self.push_status(StatusMessage {
+            text: new_message,
+            retry: None,
+        });
+    }
+
+    /// Shows an error in the status list, with a retry button when it has an action.
+    pub fn error_add(&mut self, error: AppError) {
+        self.push_status(StatusMessage {
+            text: error.to_string(),
+            retry: error.retry(),
+        });
     }
-    fn message_add(&mut self, new_message: String) {
-        self.status_messages.push(new_message);
+
+    fn push_status(&mut self, message: StatusMessage) {
+        self.status_messages.push(message);
         self.status_messages_last_ts = Some(Instant::now());
         if self.status_messages.len() > STATUS_MESSAGE_MAX_COUNT {
             self.status_messages.remove(0);
         }
     }
 
+    /// Moves messages sent by background tasks to the status list.
+    pub fn poll_background_messages(&mut self) {
+        while let Ok(message) = self.message_rx.try_recv() {
+            self.message_add(message);
+        }
+        for notice in fetch::take_failover_notices() {
+            self.message_add(notice);
+        }
+        for error in app_error::take_reported() {
+            self.error_add(error);
+        }
+        let chunks = self
+            .live
+            .as_ref()
+            .map(|live| live.take_chunks())
+            .unwrap_or_default();
+        for chunk in chunks {
+            let stored = chunk.map_err(|e| e.into()).and_then(|klines| {
+                Timeframe::process_data_chunk(&self.symbol, klines, &self.db, &mut self.data_window)
+            });
+            match stored {
+                Ok(()) => {
+                    self.data_window.dirty = true;
+                    self.recent_unsaved = true;
+                }
+                // повторяется на следующем цикле обновления, в консоль
+                Err(e) => tracing::warn!("Live update of {} failed: {}", self.symbol, e),
+            }
+        }
+        let forming = self.live.as_ref().and_then(|live| live.forming());
+        if forming != self.data_window.forming {
+            self.data_window.forming = forming;
+            self.data_window.dirty = true;
+        }
+        if self.data_window.dirty {
+            self.append_new_bars();
+        }
+        if self.recent_unsaved && self.recent_saved.elapsed().as_secs() >= RECENT_SAVE_INTERVAL {
+            self.save_recent();
+        }
+        if self
+            .symbol_info_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let result = self.symbol_info_job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("symbol info thread panicked".into()))
+            });
+            match result {
+                // символ мог смениться, пока шел запрос
+                Some(Ok(info)) if info.symbol == self.pair() => {
+                    self.data_window.tick_decimals = Some(info.price_decimals());
+                    self.alerts.set_symbol_info(Some(info.clone()));
+                    self.symbol_info = Some(info);
+                }
+                Some(Err(e)) => tracing::warn!("Unable to load symbol metadata: {}", e),
+                _ => {}
+            }
+        }
+        if self
+            .symbol_list_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let result = self.symbol_list_job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("symbol list thread panicked".into()))
+            });
+            self.symbol_picker.loading = false;
+            match result {
+                Some(Ok(symbols)) => self.symbol_picker.symbols = symbols,
+                Some(Err(e)) => self.message_add(format!("Unable to load the symbol list: {}", e)),
+                None => {}
+            }
+        }
+        if self
+            .refetch_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let result = self.refetch_job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("re-fetch thread panicked".into()))
+            });
+            self.anomaly_report.refetching = false;
+            match result {
+                Some(Ok((symbol, blocks))) => {
+                    self.message_add(format!("Re-fetched {} blocks of {}", blocks, symbol));
+                    if symbol == self.symbol {
+                        self.update_data_window();
+                    }
+                    if self.anomaly_report.scanned() == Some(symbol.as_str()) {
+                        self.scan_anomalies();
+                    }
+                }
+                Some(Err(e)) => self.message_add(format!("Re-fetch failed: {}", e)),
+                None => {}
+            }
+        }
+        if self
+            .import_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let result = self.import_job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("import thread panicked".into()))
+            });
+            match result {
+                Some(Ok((symbol, rows, blocks))) => {
+                    self.message_add(format!(
+                        "Imported {} klines of {} ({} blocks written)",
+                        rows, symbol, blocks
+                    ));
+                    if symbol == self.symbol {
+                        self.update_data_window();
+                    }
+                }
+                Some(Err(e)) => self.message_add(format!("Import failed: {}", e)),
+                None => {}
+            }
+        }
+        if let Some(progress) = self
+            .sync_progress_rx
+            .as_ref()
+            .and_then(|rx| rx.try_iter().last())
+        {
+            self.sync_progress = Some(progress);
+        }
+        if self.sync_job.as_ref().is_some_and(|job| job.is_finished()) {
+            let result = self.sync_job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("sync thread panicked".into()))
+            });
+            self.sync_progress_rx = None;
+            self.sync_progress = None;
+            match result {
+                // символ мог смениться, пока шла загрузка
+                Some(Ok((symbol, tail))) if symbol == self.symbol => {
+                    if let Err(e) = Timeframe::process_data_chunk(
+                        &symbol,
+                        tail,
+                        &self.db,
+                        &mut self.data_window,
+                    ) {
+                        tracing::warn!("Unable to merge the synced tail of {}: {}", symbol, e);
+                    }
+                    self.recent_unsaved = true;
+                    self.update_data_window();
+                }
+                Some(Err(e)) => self.error_add(AppError::Sync {
+                    symbol: self.symbol.clone(),
+                    message: e,
+                }),
+                _ => {}
+            }
+        }
+        if self
+            .history_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            let result = self.history_job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("history thread panicked".into()))
+            });
+            match result {
+                Some(Ok(0)) => {
+                    self.history_auto_load = false;
+                    self.message_add(format!("No older {} data", self.symbol))
+                }
+                Some(Ok(blocks)) => {
+                    self.message_add(format!("Loaded {} older blocks of {}", blocks, self.symbol));
+                    self.history_days += HISTORY_CHUNK_DAYS;
+                    // keep the bars on screen in place after older ones are prepended
+                    let (start, end) = self.data_window.visible_range;
+                    let old_len = self.data_window.bars.len() as i64;
+                    self.reload_chart();
+                    let added = self.data_window.bars.len() as i64 - old_len;
+                    if added > 0 {
+                        self.data_window.visible_range = (start + added, end + added);
+                    }
+                }
+                Some(Err(e)) => {
+                    self.history_auto_load = false;
+                    self.message_add(format!("History loading failed: {}", e))
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Applies the theme file of the theme menu, the events file and the live keys of
+    /// the config file when they were edited outside the application.
+    pub fn reload_changed_files(&mut self, ctx: &egui::Context) {
+        if HOT_RELOAD_INTERVAL_SECS == 0 {
+            return;
+        }
+        if self.theme_watcher.changed(&self.theme_editor.path) {
+            match Theme::load(std::path::Path::new(&self.theme_editor.path)) {
+                Ok(theme) => {
+                    self.theme = theme;
+                    self.theme.apply(ctx);
+                    self.message_add(format!("Theme reloaded from {}", self.theme_editor.path));
+                }
+                Err(e) => self.message_add(format!(
+                    "Unable to reload theme from {}: {}",
+                    self.theme_editor.path, e
+                )),
+            }
+        }
+        if self.events_watcher.changed(&self.events.path) {
+            let message = match self.events.reload() {
+                Ok(count) => format!("Reloaded {} events from {}", count, self.events.path),
+                Err(e) => format!("Unable to reload events from {}: {}", self.events.path, e),
+            };
+            self.message_add(message);
+        }
+        if let Some(path) = config::path() {
+            let path = path.to_string_lossy();
+            if self.config_watcher.changed(&path) {
+                let message = match config::reload() {
+                    Ok(reloaded) if reloaded.needs_restart => Some(format!(
+                        "Config reloaded from {}; symbol, timeframe, DB path and window apply after a restart",
+                        path
+                    )),
+                    Ok(reloaded) => reloaded
+                        .changed
+                        .then(|| format!("Config reloaded from {}", path)),
+                    Err(e) => Some(format!("Unable to reload config from {}: {}", path, e)),
+                };
+                if let Some(message) = message {
+                    self.message_add(message);
+                }
+            }
+        }
+        // проверка файлов продолжается и без ввода пользователя
+        ctx.request_repaint_after(std::time::Duration::from_secs(HOT_RELOAD_INTERVAL_SECS));
+    }
+
+    /// Appends the bars of newly stored data; a chart scrolled to the right edge
+    /// follows the new bars.
+    pub fn append_new_bars(&mut self) {
+        let (start, end) = self.data_window.visible_range;
+        let at_right_edge = end >= self.data_window.bars.len() as i64;
+        match self
+            .data_window
+            .append_new_bars(&self.db, &self.symbol, self.timeframe)
+        {
+            Ok(added) if added > 0 && at_right_edge => {
+                let added = added as i64;
+                self.data_window.visible_range = (start + added, end + added);
+            }
+            Ok(_) => {}
+            Err(e) => self.error_add(AppError::LiveUpdate {
+                symbol: self.symbol.clone(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    /// Age of the newest minute in seconds and whether it is older than the expected
+    /// update interval (live updates, else the scheduler interval of the symbol).
+    pub fn data_freshness(&self) -> Option<(u64, bool)> {
+        let latest = self.data_window.latest_data_time(self.timeframe)?;
+        let age = ((Utc::now().timestamp_millis() - latest).max(0) / 1000) as u64;
+        let expected = if self.live.is_some() {
+            Some(UPDATE_INTERVAL)
+        } else {
+            SYNC_SYMBOLS
+                .iter()
+                .find(|(symbol, _)| *symbol == self.symbol)
+                .map(|(_, interval)| *interval)
+        };
+        let stale = expected.is_some_and(|interval| age > interval + FRESHNESS_GRACE_SECS);
+        Some((age, stale))
+    }
+
+    /// Runs the retry action of an error from the status list.
+    pub fn retry(&mut self, action: RetryAction) {
+        let result = match &action {
+            RetryAction::Aggregate(symbol) => self
+                .db
+                .aggregate_ohlcv_data(symbol)
+                .map(|()| Some(format!("Aggregated {}", symbol))),
+            RetryAction::RefetchBlock {
+                symbol,
+                block_start,
+            } => Timeframe::refetch_block(&self.db, symbol, *block_start, &mut self.data_window)
+                .map(|()| Some(format!("Re-fetched block {}_{}", symbol, block_start))),
+            RetryAction::ReloadChart => {
+                self.sync_if_idle();
+                Ok(None)
+            }
+            RetryAction::SyncChart => {
+                self.start_sync();
+                Ok(None)
+            }
+        };
+        match result {
+            Ok(message) => {
+                if let Some(message) = message {
+                    self.message_add(message);
+                }
+                self.update_data_window();
+            }
+            Err(e) => self.message_add(format!("Retry failed: {}", e)),
+        }
+    }
+
+    /// Fetches the blocks missing since the last stored one (or `history_days` for a
+    /// new symbol) in a background thread; the bars are rebuilt when it finishes. A
+    /// sync of the previous symbol keeps running and its result is dropped.
+    pub fn start_sync(&mut self) {
+        let db = self.db.clone();
+        let symbol = self.symbol.clone();
+        let end_time = Utc::now().timestamp_millis();
+        let start_time = end_time - Duration::days(self.history_days).num_milliseconds();
+        let (tx, rx) = mpsc::channel();
+        self.sync_progress = Some(SyncProgress::default());
+        self.sync_progress_rx = Some(rx);
+        self.sync_job = Some(std::thread::spawn(move || {
+            // хвост незавершенного блока возвращается в DataWindow графика
+            let mut window = DataWindow::default();
+            Timeframe::sync_data(
+                SYNC_REQUEST_PAUSE,
+                &db,
+                &symbol,
+                start_time,
+                end_time,
+                &mut window,
+                &mut |progress| {
+                    let _ = tx.send(progress.clone());
+                },
+            )
+            .map(|()| (symbol, window.recent_data))
+            .map_err(|e| e.to_string())
+        }));
+    }
+
+    /// Starts `start_sync` unless a sync of the chart symbol is already running.
+    pub fn sync_if_idle(&mut self) {
+        if self.sync_job.is_none() {
+            self.start_sync();
+        }
+    }
+
+    /// Rebuilds the bars from the stored data at once and fetches the blocks missing
+    /// since the last sync in the background.
+    pub fn reload_chart(&mut self) {
+        self.update_data_window();
+        self.sync_if_idle();
+    }
+
+    pub fn history_loading(&self) -> bool {
+        self.history_job.is_some()
+    }
+
+    /// Fetches `HISTORY_CHUNK_DAYS` before the oldest stored block in a background
+    /// thread; the chart is extended when it finishes.
+    pub fn load_history(&mut self) {
+        if self.history_job.is_some() {
+            return;
+        }
+        let db = self.db.clone();
+        let symbol = self.symbol.clone();
+        self.message_add(format!(
+            "Loading {} days of older {} data",
+            HISTORY_CHUNK_DAYS, symbol
+        ));
+        self.history_job = Some(std::thread::spawn(move || {
+            Timeframe::backfill_history(
+                SYNC_REQUEST_PAUSE,
+                &db,
+                &symbol,
+                HISTORY_CHUNK_DAYS,
+                &mut |_| {},
+            )
+            .map_err(|e| e.to_string())
+        }));
+    }
+
+    /// Loads the stored metadata of the chart symbol (of the spot pair for futures),
+    /// fetching it in a background thread when it is missing or stale.
+    pub fn load_symbol_info(&mut self) {
+        self.symbol_info = None;
+        self.data_window.tick_decimals = None;
+        self.alerts.set_symbol_info(None);
+        let db = self.db.clone();
+        let symbol = self.pair().to_string();
+        self.symbol_info_job = Some(std::thread::spawn(move || {
+            symbol_info::load_or_fetch(&db, &reqwest::blocking::Client::new(), &symbol)
+                .map_err(|e| e.to_string())
+        }));
+    }
+
+    /// Lists the stored symbols in the picker and loads the exchange symbols in a
+    /// background thread, once.
+    pub fn refresh_symbol_picker(&mut self) {
+        self.symbol_picker.stored = self.db.symbols().unwrap_or_default();
+        if !self.symbol_picker.symbols.is_empty() || self.symbol_list_job.is_some() {
+            return;
+        }
+        let db = self.db.clone();
+        self.symbol_picker.loading = true;
+        self.symbol_list_job = Some(std::thread::spawn(move || {
+            symbol_info::load_or_fetch_symbols(&db, &reqwest::blocking::Client::new())
+                .map_err(|e| e.to_string())
+        }));
+    }
+
+    /// Uploads (or with `pull` downloads) blocks of all known symbols in a background thread.
+    pub fn run_backup(&mut self, pull: bool) {
+        let Some(backup) = S3Backup::from_settings() else {
+            self.message_add(format!(
+                "Backup is not configured (BACKUP_ENDPOINT, {}, {})",
+                BACKUP_ACCESS_KEY_ENV, BACKUP_SECRET_KEY_ENV
+            ));
+            return;
+        };
+        let db = self.db.clone();
+        let tx = self.message_tx.clone();
+        std::thread::spawn(move || {
+            let mut symbols = db.symbols().unwrap_or_default();
+            for (symbol, _) in SYNC_SYMBOLS {
+                if !symbols.iter().any(|s| s == symbol) {
+                    symbols.push(symbol.to_string());
+                }
+            }
+            for symbol in symbols {
+                let message = if pull {
+                    backup
+                        .pull(&db, &symbol)
+                        .map(|n| format!("Restored {} blocks of {}", n, symbol))
+                } else {
+                    backup
+                        .push(&db, &symbol)
+                        .map(|n| format!("Backed up {} blocks of {}", n, symbol))
+                };
+                let _ = tx.send(
+                    message.unwrap_or_else(|e| format!("Backup of {} failed: {}", symbol, e)),
+                );
+            }
+        });
+    }
+
+    /// Checks the stored blocks of the current symbol (`Database::verify`) in a background
+    /// thread; the first issues and the summary go to the status messages.
+    pub fn verify_data(&mut self) {
+        let db = self.db.clone();
+        let symbol = self.symbol.clone();
+        let tx = self.message_tx.clone();
+        self.message_add(format!("Verifying the stored blocks of {}", symbol));
+        std::thread::spawn(move || {
+            let report = match db.verify(&symbol) {
+                Ok(report) => report,
+                Err(e) => {
+                    let _ = tx.send(format!("Verification of {} failed: {}", symbol, e));
+                    return;
+                }
+            };
+            // остальные места списка статуса - для сводки и счетчика
+            let shown = STATUS_MESSAGE_MAX_COUNT.saturating_sub(2);
+            for issue in report.issues.iter().take(shown) {
+                let _ = tx.send(format!("{}: {}", symbol, issue.describe(format_time)));
+            }
+            if report.issues.len() > shown {
+                let _ = tx.send(format!(
+                    "{} more issues, all are listed by `n-ohlcv verify {}`",
+                    report.issues.len() - shown,
+                    symbol
+                ));
+            }
+            let _ = tx.send(report.summary(&symbol));
+        });
+    }
+
+    /// Writes the hourly aggregated series of the current symbol to `EXPORT_DIR`.
+    pub fn export_aggr(&mut self, format: ExportFormat) {
+        match export::export_aggr(&self.db, &self.symbol, EXPORT_DIR, format) {
+            Ok((path, count)) => {
+                self.message_add(format!("Exported {} records to {}", count, path.display()))
+            }
+            Err(e) => self.message_add(format!("Export failed: {}", e)),
+        }
+    }
+
+    /// Writes the visible bars of the chart to a CSV file in `EXPORT_DIR`.
+    pub fn export_visible_csv(&mut self) {
+        let (start, end) = self.data_window.visible_range;
+        let end = end.clamp(0, self.data_window.bars.len() as i64) as usize;
+        let bars = &self.data_window.bars[(start.max(0) as usize).min(end)..end];
+        let label = match self.data_window.bar_type {
+            BarType::Time => timeframe_label(
+                self.data_window
+                    .coarsened_timeframe
+                    .unwrap_or(self.timeframe),
+            ),
+            bar_type => bar_type.label().to_string(),
+        };
+        match export::export_bars_csv(bars, &self.symbol, &label, EXPORT_DIR) {
+            Ok(path) => self.message_add(format!(
+                "Exported {} bars to {}",
+                bars.len(),
+                path.display()
+            )),
+            Err(e) => self.message_add(format!("Export failed: {}", e)),
+        }
+    }
+
+    /// Writes the whole stored 1m history of the current symbol to a CSV file in
+    /// `EXPORT_DIR`, in a background thread.
+    pub fn export_history_csv(&mut self) {
+        let db = self.db.clone();
+        let symbol = self.symbol.clone();
+        let tx = self.message_tx.clone();
+        self.message_add(format!("Exporting the 1m history of {}", symbol));
+        std::thread::spawn(move || {
+            let message = match export::export_history_csv(&db, &symbol, EXPORT_DIR) {
+                Ok((path, rows)) => format!("Exported {} klines to {}", rows, path.display()),
+                Err(e) => format!("Export of {} failed: {}", symbol, e),
+            };
+            let _ = tx.send(message);
+        });
+    }
+
+    /// Writes the whole stored 1m history of the current symbol to a Parquet file in
+    /// `EXPORT_DIR`, in a background thread.
+    pub fn export_history_parquet(&mut self) {
+        let db = self.db.clone();
+        let symbol = self.symbol.clone();
+        let tx = self.message_tx.clone();
+        self.message_add(format!("Exporting the 1m history of {}", symbol));
+        std::thread::spawn(move || {
+            let message = match export::export_history_parquet(&db, &symbol, EXPORT_DIR) {
+                Ok((path, rows)) => format!("Exported {} klines to {}", rows, path.display()),
+                Err(e) => format!("Export of {} failed: {}", symbol, e),
+            };
+            let _ = tx.send(message);
+        });
+    }
+
+    /// Stores the klines of the current symbol's Parquet file in `EXPORT_DIR` (as
+    /// written by `export_history_parquet`, possibly edited) in a background thread.
+    pub fn import_history_parquet(&mut self) {
+        if self.import_job.is_some() {
+            return;
+        }
+        let db = self.db.clone();
+        let symbol = self.symbol.clone();
+        self.message_add(format!(
+            "Importing {}",
+            export::parquet_path(&symbol, EXPORT_DIR).display()
+        ));
+        self.import_job = Some(std::thread::spawn(move || {
+            let (rows, blocks) = export::import_history_parquet(&db, &symbol, EXPORT_DIR)
+                .map_err(|e| format!("{}: {}", symbol, e))?;
+            Ok((symbol, rows, blocks))
+        }));
+    }
+
+    /// Overlays, panes and drawings currently shown on the chart, for the report.
+    pub fn active_indicators(&self) -> Vec<String> {
+        let mut active: Vec<String> = self
+            .patterns
+            .enabled
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(pattern, _)| pattern.name().to_string())
+            .collect();
+        for (on, name) in [
+            (
+                self.highlights.rules.iter().any(|r| r.enabled),
+                "Bar highlights",
+            ),
+            (self.price_gaps.enabled, "Price gaps"),
+            (self.session_levels.show_daily, "Daily high/low"),
+            (self.session_levels.show_weekly, "Weekly high/low"),
+            (
+                self.session_levels.show_prev_close,
+                "Previous session close",
+            ),
+            (self.liquidations.enabled, "Liquidations"),
+            (self.seasonality.show, "Average day path"),
+            (self.events.show, "Events"),
+            (self.show_volume_profile, "Visible volume profile"),
+        ] {
+            if on {
+                active.push(name.to_string());
+            }
+        }
+        active.extend(self.indicators.enabled().map(|s| s.indicator.name()));
+        let vwaps = self
+            .drawings
+            .items
+            .iter()
+            .filter(|d| matches!(d, Drawing::AnchoredVwap(_)))
+            .count();
+        let profiles = self.drawings.items.len() - vwaps;
+        if vwaps > 0 {
+            active.push(format!("Anchored VWAP x{}", vwaps));
+        }
+        if profiles > 0 {
+            active.push(format!("Volume profile x{}", profiles));
+        }
+        active
+    }
+
+    /// Writes the visible chart with its drawings as SVG to `EXPORT_DIR`.
+    pub fn export_svg(&mut self) {
+        let style = ChartStyle {
+            show_candles: self.show_candles,
+            time_zone: self.time_zone,
+            time_format: self.time_format,
+            axis_mode: self.axis_mode,
+            theme: &self.theme,
+            indicators: &self.indicators,
+        };
+        let svg = report::chart_svg(&mut self.data_window, &mut self.drawings, &style);
+        let path = std::path::Path::new(EXPORT_DIR).join(format!(
+            "{}_{}_chart.svg",
+            export::file_stem(&self.symbol),
+            timeframe_label(self.timeframe)
+        ));
+        let written = std::fs::create_dir_all(EXPORT_DIR).and_then(|()| std::fs::write(&path, svg));
+        match written {
+            Ok(()) => self.message_add(format!("Chart saved to {}", path.display())),
+            Err(e) => self.message_add(format!("SVG export failed: {}", e)),
+        }
+    }
+
+    /// Writes the visible chart with its summary and active indicators to a PDF in `EXPORT_DIR`.
+    pub fn export_report(&mut self) {
+        let style = ChartStyle {
+            show_candles: self.show_candles,
+            time_zone: self.time_zone,
+            time_format: self.time_format,
+            axis_mode: self.axis_mode,
+            theme: &self.theme,
+            indicators: &self.indicators,
+        };
+        let image = report::chart_image(&mut self.data_window, &mut self.drawings, &style);
+        let mut rows = report::visible_summary(&self.data_window, self.time_zone, self.time_format);
+        let indicators = self.active_indicators();
+        rows.push((
+            "Indicators".to_string(),
+            if indicators.is_empty() {
+                "none".to_string()
+            } else {
+                indicators.join(", ")
+            },
+        ));
+        rows.push((
+            "Generated".to_string(),
+            Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+        ));
+        let timeframe = timeframe_label(self.timeframe);
+        let title = format!("{} {} report", self.symbol, timeframe);
+        let path = std::path::Path::new(EXPORT_DIR).join(format!(
+            "{}_{}_report.pdf",
+            export::file_stem(&self.symbol),
+            timeframe
+        ));
+        match report::write_pdf(&path, &title, &image, &rows) {
+            Ok(()) => self.message_add(format!("Report saved to {}", path.display())),
+            Err(e) => self.message_add(format!("Report export failed: {}", e)),
+        }
+    }
+
     pub fn zoom(&mut self, amount: f64) {
         let (mut start_idx, mut end_idx) = self.data_window.visible_range;
         let len = self.data_window.bars.len() as i64;
@@ -85,7 +1052,7 @@ impl InteractiveGui {
         }
 
         let range = end_idx - start_idx;
-        let zoom = (range as f64 * ZOOM_SENSITIVITY).max(1.0) as i64; // Минимум 1 бар
+        let zoom = (range as f64 * config::get().zoom_sensitivity).max(1.0) as i64; // Минимум 1 бар
 
         if amount > 0.0 {
             // Zoom in
@@ -104,11 +1071,355 @@ impl InteractiveGui {
         self.data_window.visible_range = (start_idx, end_idx);
     }
 
+    /// Rebuilds drawing data that depends on the DB (anchored volume profiles).
+    pub fn refresh_drawings(&mut self) {
+        self.drawings
+            .refresh_profiles(&self.db, &self.symbol, &self.data_window, self.timeframe);
+    }
+
+    /// Recomputes day/week reference levels when new data arrived.
+    pub fn refresh_session_levels(&mut self) {
+        if let Err(e) = self
+            .session_levels
+            .refresh(&self.db, &self.symbol, &self.data_window)
+        {
+            tracing::warn!("Unable to compute session levels: {}", e);
+        }
+    }
+
+    pub fn refresh_seasonality(&mut self) {
+        if let Err(e) =
+            self.seasonality
+                .refresh(&self.db, &self.symbol, &self.data_window, self.time_zone)
+        {
+            tracing::warn!("Unable to compute the average day path: {}", e);
+        }
+    }
+
+    /// Exchange pair of the chart symbol, without the market prefix.
+    pub fn pair(&self) -> &str {
+        Market::split(&self.symbol).1
+    }
+
+    pub fn refresh_derivatives(&mut self) {
+        let pair = Market::split(&self.symbol).1;
+        if let Some(message) = self.derivatives.refresh(&self.db, pair, &self.data_window) {
+            self.message_add(message);
+        }
+    }
+
+    pub fn refresh_liquidations(&mut self) {
+        let pair = Market::split(&self.symbol).1;
+        self.liquidations.refresh(&self.db, pair, &self.data_window);
+    }
+
+    pub fn refresh_alerts(&mut self) {
+        match self
+            .alerts
+            .refresh(&self.db, &self.symbol, &self.data_window)
+        {
+            Ok(messages) => {
+                if !messages.is_empty() && self.alert_history.open {
+                    self.refresh_alert_history();
+                }
+                for message in messages {
+                    self.message_add(message);
+                }
+            }
+            Err(e) => tracing::warn!("Unable to update alerts: {}", e),
+        }
+    }
+
+    pub fn show_anomaly_report(&mut self, ctx: &egui::Context) {
+        let (scan, refetch) = self.anomaly_report.show(ctx);
+        if scan {
+            self.scan_anomalies();
+        }
+        if let Some(request) = refetch {
+            self.refetch_ranges(request);
+        }
+    }
+
+    pub fn refresh_anomaly_symbols(&mut self) {
+        if let Err(e) = self.anomaly_report.refresh_symbols(&self.db, &self.symbol) {
+            self.message_add(format!("Unable to list symbols: {}", e));
+        }
+    }
+
+    fn scan_anomalies(&mut self) {
+        if let Err(e) = self.anomaly_report.scan(&self.db) {
+            self.message_add(format!("Anomaly scan failed: {}", e));
+        }
+    }
+
+    /// Downloads the ranges flagged by the anomaly report again in a background thread.
+    fn refetch_ranges(&mut self, request: RefetchRequest) {
+        if self.refetch_job.is_some() {
+            return;
+        }
+        let db = self.db.clone();
+        self.anomaly_report.refetching = true;
+        self.refetch_job = Some(std::thread::spawn(move || {
+            let mut blocks = 0;
+            for (stale, from, to) in request.ranges {
+                blocks += Timeframe::refetch_range(
+                    SYNC_REQUEST_PAUSE,
+                    &db,
+                    &request.symbol,
+                    &[stale],
+                    from,
+                    to,
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            Ok((request.symbol, blocks))
+        }));
+    }
+
+    pub fn refresh_bookmarks(&mut self) {
+        if let Err(e) = self.bookmarks.refresh(&self.db, &self.symbol) {
+            tracing::warn!("Unable to update bookmarks: {}", e);
+        }
+    }
+
+    /// Centers the chart on `time`; undoable with Ctrl+Z.
+    pub fn jump_to_time(&mut self, time: i64) {
+        let view = self.view_state();
+        if self.data_window.center_on_time(time) {
+            self.drawings.record_view(view);
+        } else {
+            self.message_add("Bookmark is before the loaded history".to_string());
+        }
+    }
+
+    pub fn refresh_alert_history(&mut self) {
+        if let Err(e) = self.alert_history.refresh(&self.db) {
+            self.message_add(format!("Unable to read alert history: {}", e));
+        }
+    }
+
+    pub fn handle_alert_history(&mut self, action: HistoryAction) {
+        let result = match &action {
+            HistoryAction::Rearm(event) => self.alerts.rearm(&self.db, event),
+            HistoryAction::Clear => self.db.clear_alert_history(),
+        };
+        match result {
+            Ok(()) => {
+                if let HistoryAction::Rearm(event) = action {
+                    self.message_add(format!("Alert re-armed: {} {}", event.symbol, event.level));
+                }
+                self.refresh_alert_history();
+            }
+            Err(e) => self.message_add(format!("Alert history: {}", e)),
+        }
+    }
+
+    pub fn refresh_coverage(&mut self) {
+        if let Err(e) = self.coverage.refresh(&self.db) {
+            self.message_add(format!("Unable to read coverage: {}", e));
+        }
+    }
+
+    /// Persists the unfinished block tail, so a crash loses at most
+    /// `RECENT_SAVE_INTERVAL` seconds of it.
+    fn save_recent(&mut self) {
+        self.recent_saved = Instant::now();
+        match self
+            .db
+            .save_recent(&self.symbol, &self.data_window.recent_data)
+        {
+            Ok(()) => self.recent_unsaved = false,
+            Err(e) => self.error_add(AppError::RecentData {
+                symbol: self.symbol.clone(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    /// Persists the unfinished block tail and flushes the DB; called on exit.
+    pub fn shutdown(&mut self) {
+        self.save_recent();
+        if let Err(e) = self.db.flush() {
+            tracing::error!("Unable to flush DB: {}", e);
+        }
+    }
+
+    /// Remembers the chart view for the next start (`RESTORE_UI_STATE`); called on exit.
+    pub fn save_ui_state(&self) {
+        let (_, end) = self.data_window.visible_range;
+        let state = UiState {
+            symbol: self.symbol.clone(),
+            timeframe: self.timeframe,
+            visible_times: self.data_window.visible_times(),
+            at_last_bar: end >= self.data_window.bars.len() as i64,
+            show_candles: self.show_candles,
+            bar_type: self.data_window.bar_type,
+            volume_height_ratio: self.data_window.volume_height_ratio,
+            pane_height_ratio: self.data_window.pane_height_ratio,
+        };
+        if let Err(e) = self.db.set_ui_state(&state) {
+            tracing::warn!("Unable to save the UI state: {}", e);
+        }
+    }
+
+    /// Remembers the window placement for the next start; called on exit.
+    pub fn save_window_geometry(&self) {
+        if let Err(e) = self.window_geometry.save(std::path::Path::new(WINDOW_FILE)) {
+            tracing::warn!("Unable to save window geometry to {}: {}", WINDOW_FILE, e);
+        }
+    }
+
+    /// Executes a deletion confirmed in the coverage window.
+    pub fn delete_data(&mut self, request: DeleteRequest) {
+        let result = match &request {
+            DeleteRequest::Symbol(symbol) => self
+                .db
+                .delete_symbol(symbol)
+                .map(|blocks| format!("Deleted {}: {} blocks", symbol, blocks)),
+            DeleteRequest::Range { symbol, from, to } => self
+                .db
+                .delete_range(symbol, *from, *to)
+                .map(|klines| format!("Deleted {} klines of {}", klines, symbol)),
+        };
+        match result {
+            Ok(message) => {
+                // хвост в памяти иначе сохранился бы снова
+                match &request {
+                    DeleteRequest::Symbol(symbol) if *symbol == self.symbol => {
+                        self.data_window.recent_data.clear();
+                    }
+                    DeleteRequest::Range { symbol, from, to } if *symbol == self.symbol => {
+                        self.data_window
+                            .recent_data
+                            .retain(|k| k.open_time < *from || k.open_time >= *to);
+                    }
+                    _ => {}
+                }
+                self.message_add(message)
+            }
+            Err(e) => self.message_add(format!("Unable to delete data: {}", e)),
+        }
+        self.refresh_coverage();
+    }
+
+    pub fn show_heatmap(&mut self, ctx: &egui::Context) {
+        self.heatmap
+            .show(ctx, &self.db, &self.symbol, self.time_zone);
+    }
+
+    /// Draws the watchlist panel and opens the chart of a clicked symbol.
+    pub fn show_watchlist(&mut self, ctx: &egui::Context) {
+        self.watchlist.refresh(&self.db);
+        if let Some(symbol) = self.watchlist.show(ctx, &self.db, &self.symbol) {
+            self.set_symbol(&symbol);
+        }
+    }
+
+    /// Draws the correlation window and opens the chart of a clicked symbol.
+    pub fn show_correlation(&mut self, ctx: &egui::Context) {
+        if let Some(symbol) = self.correlation.show(ctx, &self.db) {
+            self.set_symbol(&symbol);
+        }
+    }
+
+    /// Draws the scanner window and opens the chart of a clicked hit.
+    pub fn show_scanner(&mut self, ctx: &egui::Context) {
+        if let Some(symbol) = self.scanner.show(ctx, &self.db) {
+            self.set_symbol(&symbol);
+        }
+    }
+
+    fn view_state(&self) -> ViewState {
+        ViewState {
+            symbol: self.symbol.clone(),
+            timeframe: self.timeframe,
+            visible_range: self.data_window.visible_range,
+        }
+    }
+
+    /// Switches the timeframe (reloading the bars); undoable with Ctrl+Z.
+    pub fn set_timeframe(&mut self, timeframe: i32) {
+        if timeframe != self.timeframe {
+            self.drawings.record_view(self.view_state());
+        }
+        self.timeframe = timeframe;
+        self.reload_chart();
+    }
+
+    /// Switches between time, Renko and range bars (reloading the bars).
+    pub fn set_bar_type(&mut self, bar_type: BarType) {
+        self.data_window.bar_type = bar_type;
+        self.update_data_window();
+    }
+
+    /// Switches the chart to another symbol, keeping the timeframe. A symbol without
+    /// stored data is synced from the exchange while the bars are loaded.
+    pub fn set_symbol(&mut self, symbol: &str) {
+        if symbol == self.symbol {
+            return;
+        }
+        self.drawings.record_view(self.view_state());
+        self.load_symbol(symbol);
+    }
+
+    fn restore_view(&mut self, view: ViewState) {
+        if view.symbol != self.symbol {
+            self.load_symbol(&view.symbol);
+        }
+        if view.timeframe != self.timeframe {
+            self.timeframe = view.timeframe;
+            self.reload_chart();
+        }
+        let len = self.data_window.bars.len() as i64;
+        let (start, end) = view.visible_range;
+        let count = (end - start).clamp(2.min(len), len.max(1));
+        let start = start.clamp(0, (len - count).max(0));
+        self.data_window.visible_range = (start, (start + count).min(len));
+        self.data_window.pixel_offset = 0.0;
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(view) = self.drawings.undo(self.view_state()) {
+            self.restore_view(view);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(view) = self.drawings.redo(self.view_state()) {
+            self.restore_view(view);
+        }
+    }
+
+    fn load_symbol(&mut self, symbol: &str) {
+        self.shutdown();
+        self.symbol = symbol.to_string();
+        if self.ticker.is_some() {
+            self.ticker = Some(TickerStream::start(symbol));
+        }
+        self.live = self.live.as_ref().and_then(|live| live.restart(symbol));
+        self.data_window.forming = None;
+        if let Err(e) = Timeframe::restore_recent(symbol, &self.db, &mut self.data_window) {
+            self.error_add(AppError::RecentData {
+                symbol: symbol.to_string(),
+                message: e.to_string(),
+            });
+        }
+        self.data_window.timeframe_remainder.clear();
+        self.data_window.calendar = TradingCalendar::for_symbol(symbol);
+        self.history_days = config::get().initial_load_days;
+        self.history_auto_load = HISTORY_AUTO_LOAD;
+        self.load_symbol_info();
+        self.update_data_window();
+        self.start_sync();
+    }
+
+    /// Rebuilds the bars of the chart from the stored data only; `reload_chart` also
+    /// fetches what is missing.
     pub fn update_data_window(&mut self) {
         let now = Utc::now().timestamp_millis();
-        let start_time = now - Duration::days(INITIAL_LOAD_DAYS).num_milliseconds();
+        let start_time = now - Duration::days(self.history_days).num_milliseconds();
         use DataWindow;
-        if let Err(e) = DataWindow::get_data_window(
+        if let Err(e) = DataWindow::load_bars(
             &self.db,
             &self.symbol,
             start_time,
@@ -116,12 +1427,34 @@ impl InteractiveGui {
             self.timeframe,
             &mut self.data_window,
         ) {
-            self.message_add(format!("Ошибка обновления данных: {}", e));
+            self.error_add(AppError::DataWindow {
+                symbol: self.symbol.clone(),
+                message: e.to_string(),
+            });
         } else {
             self.message_add(format!(
                 "Обновлено отображение: {} баров",
                 self.data_window.bars.len()
             ));
+            if let Some(minutes) = self.data_window.coarsened_timeframe {
+                self.message_add(format!(
+                    "Диапазон слишком длинный для {}: бары {} из агрегатов",
+                    timeframe_label(self.timeframe),
+                    timeframe_label(minutes)
+                ));
+            }
+        }
+    }
+
+    /// Timeframe label, with the period of the bars when they were coarsened.
+    pub fn timeframe_text(&self) -> String {
+        match self.data_window.coarsened_timeframe {
+            Some(minutes) => format!(
+                "{} → {}",
+                timeframe_label(self.timeframe),
+                timeframe_label(minutes)
+            ),
+            None => timeframe_label(self.timeframe),
         }
     }
 }
@@ -1,19 +1,151 @@
 // lib.rs
 
-pub mod axes;
-pub mod axes_util;
+//! OHLCV collector and chart.
+//!
+//! Without the default `gui` feature the crate is a GUI-free library of the data
+//! layer: fetching 1m klines from Binance ([`fetch`]), block storage with the
+//! 1h/4h/1d/1w aggregation tiers ([`db`]), sync and timeframe conversion
+//! ([`timeframe`], [`datawindow`]), compression ([`compress`]) and indicators
+//! ([`indicator`], [`rsi`], [`vwap`], [`volume_profile`]).
+//!
+//! ```toml
+//! n-ohlcv = { path = "../n-ohlcv", default-features = false }
+//! ```
+//!
+//! ```no_run
+//! use n_ohlcv::datawindow::DataWindow;
+//! use n_ohlcv::db::{AggrPeriod, Database};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new("ohlcv_db")?;
+//! let now = chrono::Utc::now().timestamp_millis();
+//! let mut data_window = DataWindow::default();
+//! // syncs the missing blocks, then converts the last day to 15m bars
+//! DataWindow::get_data_window(&db, "BTCUSDT", now - 86_400_000, now, 15, &mut data_window)?;
+//! let hourly = db.get_aggr_records("BTCUSDT", AggrPeriod::HOUR, now - 86_400_000, now)?;
+//! println!("{} bars, {} hourly records", data_window.bars.len(), hourly.len());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The egui application (`gui` feature, `n-ohlcv` binary) is built on top of it.
+//!
+//! Only native targets are supported: the data layer relies on sled files, blocking
+//! reqwest, tungstenite sockets and background threads, none of which exist in the
+//! browser. The work a wasm32 build needs is listed under "Platforms" in CONVENTIONS.md.
+
+#[cfg(target_arch = "wasm32")]
+compile_error!(
+    "n-ohlcv does not build for wasm32: sled, blocking reqwest, tungstenite and threads are native-only"
+);
+
+// data layer
+pub mod anomalies;
+pub mod app_error;
+pub mod backup;
+pub mod bybit;
+pub mod calendar;
+pub mod cli;
+pub mod coinbase;
 pub mod compress;
-pub mod crosshair;
+pub mod config;
+pub mod crypto;
 pub mod datawindow;
 pub mod db;
-pub mod drawing_util;
+pub mod derivatives;
+pub mod exchange;
+pub mod export;
 pub mod fetch;
-pub mod gpu_backend;
-pub mod gui;
-pub mod hlcbars;
-pub mod interactivegui;
+pub mod indicator;
+pub mod liquidations;
+pub mod live_update;
+pub mod logging;
+pub mod parquet;
 pub mod performance;
 pub mod rsi;
+pub mod scheduler;
 pub mod settings;
+pub mod stream;
+pub mod symbol_info;
+pub mod ticker;
 pub mod timeframe;
+pub mod volume_profile;
+pub mod vwap;
+
+// egui application
+#[cfg(feature = "gui")]
+pub mod alert_history;
+#[cfg(feature = "gui")]
+pub mod alerts;
+#[cfg(feature = "gui")]
+pub mod anomaly_report;
+#[cfg(feature = "gui")]
+pub mod axes;
+#[cfg(feature = "gui")]
+pub mod axes_util;
+#[cfg(feature = "gui")]
+pub mod bookmarks;
+#[cfg(feature = "gui")]
+pub mod correlation;
+#[cfg(feature = "gui")]
+pub mod coverage;
+#[cfg(feature = "gui")]
+pub mod crosshair;
+#[cfg(feature = "gui")]
+pub mod downloads;
+#[cfg(feature = "gui")]
+pub mod drawing_util;
+#[cfg(feature = "gui")]
+pub mod drawings;
+#[cfg(feature = "gui")]
+pub mod events;
+#[cfg(feature = "gui")]
+pub mod gpu_backend;
+#[cfg(feature = "gui")]
+pub mod gui;
+#[cfg(feature = "gui")]
+pub mod heatmap;
+#[cfg(feature = "gui")]
+pub mod highlight;
+#[cfg(feature = "gui")]
+pub mod hlcbars;
+#[cfg(feature = "gui")]
+pub mod hot_reload;
+#[cfg(feature = "gui")]
+pub mod indicator_pane;
+#[cfg(feature = "gui")]
+pub mod interactivegui;
+#[cfg(feature = "gui")]
+pub mod patterns;
+#[cfg(feature = "gui")]
+pub mod price_gaps;
+#[cfg(feature = "gui")]
+pub mod render;
+#[cfg(feature = "gui")]
+pub mod report;
+#[cfg(feature = "gui")]
+pub mod returns;
+#[cfg(feature = "gui")]
+pub mod scanner;
+#[cfg(feature = "gui")]
+pub mod seasonality;
+#[cfg(feature = "gui")]
+pub mod session_clock;
+#[cfg(feature = "gui")]
+pub mod session_levels;
+#[cfg(feature = "gui")]
+pub mod symbol_picker;
+#[cfg(feature = "gui")]
+pub mod theme;
+#[cfg(feature = "gui")]
+pub mod timeframe_switch;
+#[cfg(feature = "gui")]
+pub mod trade_tape;
+#[cfg(feature = "gui")]
+pub mod visible_profile;
+#[cfg(feature = "gui")]
 pub mod volbars;
+#[cfg(feature = "gui")]
+pub mod watchlist;
+#[cfg(feature = "gui")]
+pub mod zoom_select;
@@ -0,0 +1,300 @@
+// liquidations.rs - Forced liquidations of USD-M futures: websocket stream, storage and chart markers (gui feature)
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::db::Database;
+use crate::settings::FUTURES_WS_URL;
+use crate::ticker::{connect_stream, is_timeout};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+// маркеры на графике - только с feature gui
+#[cfg(feature = "gui")]
+use crate::{datawindow::DataWindow, drawing_util, settings::LIQUIDATION_MIN_NOTIONAL};
+#[cfg(feature = "gui")]
+use eframe::egui::{self, Color32, Rect, Stroke};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+#[cfg(feature = "gui")]
+const MARKER_MIN_RADIUS: f32 = 2.0;
+#[cfg(feature = "gui")]
+const MARKER_MAX_RADIUS: f32 = 9.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Liquidation {
+    pub time: i64,
+    /// A long position was closed (forced sell order).
+    pub long: bool,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+impl Liquidation {
+    pub fn notional(&self) -> f64 {
+        self.price * self.quantity
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(text).ok()?;
+        let order = value.get("o")?;
+        let field = |name: &str| order.get(name)?.as_str()?.parse::<f64>().ok();
+        let price = field("ap").filter(|p| *p > 0.0).or_else(|| field("p"))?;
+        let quantity = field("z").filter(|q| *q > 0.0).or_else(|| field("q"))?;
+        Some(Self {
+            time: order.get("T")?.as_i64()?,
+            long: order.get("S")?.as_str()? == "SELL",
+            price,
+            quantity,
+        })
+    }
+}
+
+/// Background reader of `{symbol}@forceOrder` storing every event in the DB;
+/// stopped when dropped.
+pub struct LiquidationStream {
+    pub symbol: String,
+    received: Arc<AtomicU32>,
+    stop: Arc<AtomicBool>,
+}
+
+impl LiquidationStream {
+    pub fn start(db: &Database, symbol: &str) -> Self {
+        let received = Arc::new(AtomicU32::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let url = format!("{}/{}@forceOrder", FUTURES_WS_URL, symbol.to_lowercase());
+        let db = db.clone();
+        let thread_symbol = symbol.to_string();
+        let thread_received = Arc::clone(&received);
+        let thread_stop = Arc::clone(&stop);
+        let spawned = thread::Builder::new()
+            .name(format!("liquidations-{}", symbol))
+            .spawn(move || Self::run(&url, &db, &thread_symbol, &thread_received, &thread_stop));
+        if let Err(e) = spawned {
+            tracing::error!("Unable to start liquidation stream: {}", e);
+        }
+        Self {
+            symbol: symbol.to_string(),
+            received,
+            stop,
+        }
+    }
+
+    /// Events stored since the stream started.
+    pub fn received(&self) -> u32 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    fn run(url: &str, db: &Database, symbol: &str, received: &AtomicU32, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            let mut socket = match connect_stream(url) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    tracing::warn!("Liquidation stream connection failed: {}", e);
+                    thread::sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            };
+            while !stop.load(Ordering::Relaxed) {
+                match socket.read() {
+                    Ok(tungstenite::Message::Text(text)) => {
+                        let Some(event) = Liquidation::parse(&text) else {
+                            continue;
+                        };
+                        let seq = received.load(Ordering::Relaxed);
+                        match db.insert_liquidation(
+                            symbol,
+                            event.time,
+                            seq,
+                            event.long,
+                            event.price,
+                            event.quantity,
+                        ) {
+                            Ok(()) => {
+                                received.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(e) => tracing::warn!("Unable to store liquidation: {}", e),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) if is_timeout(&e) => {}
+                    Err(e) => {
+                        tracing::warn!("Liquidation stream error: {}", e);
+                        break;
+                    }
+                }
+            }
+            let _ = socket.close(None);
+        }
+    }
+}
+
+impl Drop for LiquidationStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "gui")]
+// (symbol, first bar time, events received by the stream)
+type EventsKey = (String, i64, u32);
+
+/// Liquidation markers sized by notional; the stream runs while they are enabled.
+#[cfg(feature = "gui")]
+pub struct LiquidationMarkers {
+    pub enabled: bool,
+    pub min_notional: f64,
+    stream: Option<LiquidationStream>,
+    events: Vec<Liquidation>,
+    loaded_for: Option<EventsKey>,
+}
+
+#[cfg(feature = "gui")]
+impl Default for LiquidationMarkers {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_notional: LIQUIDATION_MIN_NOTIONAL,
+            stream: None,
+            events: Vec::new(),
+            loaded_for: None,
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl LiquidationMarkers {
+    /// Starts or stops the stream to follow `enabled` and the active symbol and
+    /// reloads the stored events of the shown bars when new ones arrived.
+    pub fn refresh(&mut self, db: &Database, symbol: &str, data_window: &DataWindow) {
+        if !self.enabled {
+            self.stream = None;
+            return;
+        }
+        if self.stream.as_ref().is_none_or(|s| s.symbol != symbol) {
+            self.stream = Some(LiquidationStream::start(db, symbol));
+        }
+        let key = (
+            symbol.to_string(),
+            data_window.bars.first().map_or(0, |b| b.time),
+            self.stream.as_ref().map_or(0, |s| s.received()),
+        );
+        if self.loaded_for.as_ref() == Some(&key) {
+            return;
+        }
+        match db.get_liquidations(symbol, key.1, i64::MAX) {
+            Ok(events) => {
+                self.events = events
+                    .into_iter()
+                    .map(|(time, long, price, quantity)| Liquidation {
+                        time,
+                        long,
+                        price,
+                        quantity,
+                    })
+                    .collect();
+            }
+            Err(e) => tracing::warn!("Unable to load liquidations: {}", e),
+        }
+        self.loaded_for = Some(key);
+    }
+
+    /// Circles at the liquidation price on the bar containing the event, radius
+    /// by the square root of the notional relative to the largest shown one.
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let bars = &data_window.bars;
+        let (start, end) = data_window.visible_range;
+        let end = end.min(bars.len() as i64);
+        if start < 0 || start >= end {
+            return;
+        }
+        let (start, end) = (start as usize, end as usize);
+        let shown: Vec<(usize, &Liquidation)> = self
+            .events
+            .iter()
+            .filter(|e| e.notional() >= self.min_notional)
+            .filter_map(|event| {
+                let index = bars
+                    .partition_point(|b| b.time <= event.time)
+                    .checked_sub(1)?;
+                (index >= start && index < end).then_some((index, event))
+            })
+            .collect();
+        let max_notional = shown
+            .iter()
+            .fold(0.0f64, |max, (_, e)| max.max(e.notional()));
+        if max_notional <= 0.0 {
+            return;
+        }
+
+        let painter = ui.painter();
+        let hover = ui.ctx().pointer_hover_pos();
+        let mut hovered = Vec::new();
+        for (index, event) in shown {
+            let x = drawing_util::calculate_bar_center_x(
+                index - start,
+                end - start,
+                rect,
+                data_window.pixel_offset,
+            );
+            let center = egui::pos2(x, scale_price(event.price));
+            let share = (event.notional() / max_notional).sqrt() as f32;
+            let radius = MARKER_MIN_RADIUS + share * (MARKER_MAX_RADIUS - MARKER_MIN_RADIUS);
+            let color = if event.long {
+                Color32::from_rgb(230, 80, 80)
+            } else {
+                Color32::from_rgb(80, 200, 120)
+            };
+            painter.circle(
+                center,
+                radius,
+                color.gamma_multiply(0.35),
+                Stroke::new(1.0, color),
+            );
+            if hover.is_some_and(|pos| pos.distance(center) <= radius.max(4.0)) {
+                hovered.push(format!(
+                    "{} liquidated {} @ {} ({})",
+                    if event.long { "Long" } else { "Short" },
+                    crate::crosshair::format_volume(event.quantity),
+                    crate::axes_util::format_price(event.price, data_window.price_decimals()),
+                    crate::crosshair::format_volume(event.notional()),
+                ));
+            }
+        }
+        if !hovered.is_empty() {
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                egui::Id::new("liquidation_marker"),
+                |ui| ui.label(hovered.join("\n")),
+            );
+        }
+    }
+
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Show liquidations (futures)");
+        ui.horizontal(|ui| {
+            ui.label("Min notional");
+            ui.add(
+                egui::DragValue::new(&mut self.min_notional)
+                    .speed(1000.0)
+                    .range(0.0..=f64::MAX),
+            );
+        });
+        if let Some(stream) = &self.stream {
+            ui.label(format!(
+                "{} events received from {}",
+                stream.received(),
+                stream.symbol
+            ));
+        }
+    }
+}
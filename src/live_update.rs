@@ -0,0 +1,50 @@
+// live_update.rs - Background fetching of the latest minutes of the chart symbol for auto-refresh
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::fetch::KLine;
+use crate::timeframe::Timeframe;
+use reqwest::blocking::Client;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// Runs `Timeframe::update_loop` for one symbol; the fetched chunks are taken on the
+/// GUI thread, which stores them and appends the new bars.
+pub struct LiveUpdate {
+    pub symbol: String,
+    rx: mpsc::Receiver<Result<Vec<KLine>, String>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl LiveUpdate {
+    pub fn start(symbol: &str) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread_symbol = symbol.to_string();
+        let spawned = thread::Builder::new()
+            .name(format!("live-{}", symbol))
+            .spawn(move || {
+                Timeframe::update_loop(&Client::new(), &thread_symbol, &tx, &thread_stop)
+            });
+        if let Err(e) = spawned {
+            tracing::error!("Unable to start live update: {}", e);
+        }
+        Self {
+            symbol: symbol.to_string(),
+            rx,
+            stop,
+        }
+    }
+
+    /// Chunks fetched since the last call.
+    pub fn take_chunks(&self) -> Vec<Result<Vec<KLine>, String>> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for LiveUpdate {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,374 @@
+// logging.rs - tracing subscriber keeping recent log lines for the in-app log viewer (gui feature), console and file output
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::settings::{LOG_CONSOLE_LEVEL, LOG_FILE, LOG_LEVEL, LOG_MAX_LINES};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+// окно журнала - только с feature gui
+#[cfg(feature = "gui")]
+use eframe::egui::{self, Color32};
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub level: Level,
+    pub target: String,
+    /// Names of the entered spans with their fields, outermost first.
+    pub spans: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:5} {}: ",
+            self.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.level,
+            self.target
+        )?;
+        if !self.spans.is_empty() {
+            write!(f, "{}: ", self.spans)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+static LINES: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+/// Snapshot of the kept log lines, oldest first.
+pub fn lines() -> Vec<LogLine> {
+    LINES
+        .lock()
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn parse_level(name: &str) -> Option<Level> {
+    name.parse().ok()
+}
+
+/// Collects `message` and the other fields of an event or span as `key=value`.
+#[derive(Default)]
+struct FieldText {
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldText {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            if !self.fields.is_empty() {
+                self.fields.push(' ');
+            }
+            let _ = write!(self.fields, "{}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: String,
+    refs: usize,
+}
+
+thread_local! {
+    // вложенные спаны текущего потока
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+struct AppLogger {
+    level: Level,
+    console_level: Level,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    file: Option<Mutex<File>>,
+}
+
+impl AppLogger {
+    fn span_context(&self) -> String {
+        let Ok(spans) = self.spans.lock() else {
+            return String::new();
+        };
+        ENTERED.with(|entered| {
+            entered
+                .borrow()
+                .iter()
+                .filter_map(|id| spans.get(id))
+                .map(|span| {
+                    if span.fields.is_empty() {
+                        span.name.to_string()
+                    } else {
+                        format!("{}{{{}}}", span.name, span.fields)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(":")
+        })
+    }
+}
+
+impl Subscriber for AppLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        // Level: TRACE > DEBUG > INFO > WARN > ERROR
+        *metadata.level() <= self.level
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut text = FieldText::default();
+        attrs.record(&mut text);
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.insert(
+                id,
+                SpanData {
+                    name: attrs.metadata().name(),
+                    fields: text.fields,
+                    refs: 1,
+                },
+            );
+        }
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut text = FieldText::default();
+        values.record(&mut text);
+        if let Some(data) = self
+            .spans
+            .lock()
+            .ok()
+            .as_mut()
+            .and_then(|s| s.get_mut(&span.into_u64()))
+        {
+            if !data.fields.is_empty() && !text.fields.is_empty() {
+                data.fields.push(' ');
+            }
+            data.fields.push_str(&text.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut text = FieldText::default();
+        event.record(&mut text);
+        let mut message = text.message;
+        if !text.fields.is_empty() {
+            if !message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&text.fields);
+        }
+        let metadata = event.metadata();
+        let line = LogLine {
+            time: chrono::Local::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            spans: self.span_context(),
+            message,
+        };
+        if line.level <= self.console_level {
+            if line.level <= Level::WARN {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+            if let Some(file) = &self.file {
+                if let Ok(mut file) = file.lock() {
+                    let _ = writeln!(file, "{}", line);
+                }
+            }
+        }
+        if let Ok(mut lines) = LINES.lock() {
+            lines.push_back(line);
+            while lines.len() > LOG_MAX_LINES {
+                lines.pop_front();
+            }
+        }
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        let id = span.into_u64();
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(index) = entered.iter().rposition(|e| *e == id) {
+                entered.remove(index);
+            }
+        });
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        if let Some(data) = self
+            .spans
+            .lock()
+            .ok()
+            .as_mut()
+            .and_then(|s| s.get_mut(&id.into_u64()))
+        {
+            data.refs += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let Ok(mut spans) = self.spans.lock() else {
+            return false;
+        };
+        let key = id.into_u64();
+        let closed = spans.get_mut(&key).is_some_and(|data| {
+            data.refs -= 1;
+            data.refs == 0
+        });
+        if closed {
+            spans.remove(&key);
+        }
+        closed
+    }
+}
+
+/// Installs the global subscriber: lines up to `LOG_LEVEL` are kept for the viewer,
+/// lines up to `LOG_CONSOLE_LEVEL` also go to the console and, when set, `LOG_FILE`.
+pub fn init() {
+    let file = (!LOG_FILE.is_empty())
+        .then(|| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(LOG_FILE)
+                .map_err(|e| eprintln!("Unable to open log file {}: {}", LOG_FILE, e))
+                .ok()
+        })
+        .flatten()
+        .map(Mutex::new);
+    let logger = AppLogger {
+        level: parse_level(LOG_LEVEL).unwrap_or(Level::DEBUG),
+        console_level: parse_level(LOG_CONSOLE_LEVEL).unwrap_or(Level::INFO),
+        next_id: AtomicU64::new(1),
+        spans: Mutex::new(HashMap::new()),
+        file,
+    };
+    if tracing::subscriber::set_global_default(logger).is_err() {
+        eprintln!("A tracing subscriber is already installed");
+    }
+}
+
+#[cfg(feature = "gui")]
+const LEVELS: [Level; 5] = [
+    Level::ERROR,
+    Level::WARN,
+    Level::INFO,
+    Level::DEBUG,
+    Level::TRACE,
+];
+
+#[cfg(feature = "gui")]
+fn level_color(level: Level) -> Color32 {
+    match level {
+        Level::ERROR => Color32::LIGHT_RED,
+        Level::WARN => Color32::from_rgb(240, 200, 80),
+        Level::INFO => Color32::LIGHT_GRAY,
+        _ => Color32::GRAY,
+    }
+}
+
+/// Log window with level and text filters.
+#[cfg(feature = "gui")]
+pub struct LogViewer {
+    pub open: bool,
+    pub level: Level,
+    pub filter: String,
+}
+
+#[cfg(feature = "gui")]
+impl Default for LogViewer {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: parse_level(LOG_CONSOLE_LEVEL).unwrap_or(Level::INFO),
+            filter: String::new(),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl LogViewer {
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Log")
+            .open(&mut open)
+            .default_size([720.0, 320.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("log_level")
+                        .selected_text(self.level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in LEVELS {
+                                ui.selectable_value(&mut self.level, level, level.as_str());
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.filter)
+                            .hint_text("filter")
+                            .desired_width(200.0),
+                    );
+                    if !LOG_FILE.is_empty() {
+                        ui.weak(format!("also written to {}", LOG_FILE));
+                    }
+                });
+                ui.separator();
+                let filter = self.filter.to_lowercase();
+                let lines: Vec<LogLine> = lines()
+                    .into_iter()
+                    .filter(|line| line.level <= self.level)
+                    .filter(|line| {
+                        filter.is_empty() || line.to_string().to_lowercase().contains(&filter)
+                    })
+                    .collect();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show_rows(
+                        ui,
+                        ui.text_style_height(&egui::TextStyle::Monospace),
+                        lines.len(),
+                        |ui, rows| {
+                            for line in &lines[rows] {
+                                ui.label(
+                                    egui::RichText::new(line.to_string())
+                                        .monospace()
+                                        .color(level_color(line.level)),
+                                );
+                            }
+                        },
+                    );
+            });
+        self.open = open;
+        if self.open {
+            // новые строки пишутся из других потоков
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    }
+}
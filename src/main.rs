@@ -1,34 +1,68 @@
-// main.rs - Application entry point, initializes eframe with InteractiveGui
+// main.rs - Application entry point: config and command line (cli.rs), headless commands or eframe with InteractiveGui
 // See CONVENTIONS.md for project structure and workflow
 
-use crate::interactivegui::InteractiveGui;
+use n_ohlcv::cli::Cli;
+use n_ohlcv::config;
+use n_ohlcv::logging;
+#[cfg(feature = "gui")]
+use n_ohlcv::{gpu_backend, interactivegui::InteractiveGui};
+use std::path::Path;
+use std::process::ExitCode;
 
-mod axes;
-mod axes_util;
-mod compress;
-mod crosshair;
-mod datawindow;
-mod db;
-mod drawing_util;
-mod fetch;
-mod gpu_backend;
-mod gui;
-mod hlcbars;
-mod interactivegui;
-mod performance;
-mod rsi;
-mod settings;
-mod timeframe;
-mod volbars;
+fn main() -> ExitCode {
+    logging::init();
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        // --help, --version и ошибки разбора печатает clap
+        Err(e) => e.exit(),
+    };
+    let config = cli.config();
+    // явно заданные символ/таймфрейм важнее сохраненных с прошлого сеанса
+    let restore_chart = !cli
+        .overrides
+        .iter()
+        .any(|(key, _)| *key == "symbol" || *key == "timeframe");
+    config::init(
+        config.clone(),
+        Path::new(&cli.config_path),
+        cli.overrides.clone(),
+    );
+    // Команда в аргументах - работа без окна (cron, сервер)
+    if let Some(command) = &cli.command {
+        return match command.run(&config) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    run_gui(config, restore_chart)
+}
 
-fn main() -> eframe::Result<()> {
+#[cfg(feature = "gui")]
+fn run_gui(config: config::Config, restore_chart: bool) -> ExitCode {
     // Запускаем приложение eframe
-    println!("Running eframe::run_native");
+    tracing::info!("Running eframe::run_native");
     eframe::run_native(
         "n-ohlc",
         gpu_backend::native_options(),
-        Box::new(|cc| Ok(Box::new(InteractiveGui::new(cc, "BTCUSDT", 15)))),
+        Box::new(move |cc| {
+            Ok(Box::new(InteractiveGui::new(
+                cc,
+                &config.symbol,
+                config.timeframe,
+                restore_chart,
+            )))
+        }),
     )
     .unwrap();
-    Ok(())
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(feature = "gui"))]
+fn run_gui(_config: config::Config, _restore_chart: bool) -> ExitCode {
+    eprintln!("n-ohlcv was built without the gui feature: give a command, see n-ohlcv --help");
+    ExitCode::from(2)
 }
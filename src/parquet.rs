@@ -0,0 +1,1255 @@
+// parquet.rs - Minimal Apache Parquet reader/writer for flat tables of numeric columns (kline export/import)
+// See CONVENTIONS.md for project structure and workflow
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::Write;
+
+const MAGIC: &[u8; 4] = b"PAR1";
+
+// Thrift compact protocol: типы полей
+const T_TRUE: u8 = 1;
+const T_FALSE: u8 = 2;
+const T_BYTE: u8 = 3;
+const T_I16: u8 = 4;
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_DOUBLE: u8 = 7;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_SET: u8 = 10;
+const T_MAP: u8 = 11;
+const T_STRUCT: u8 = 12;
+
+// parquet.thrift: физические типы, повторяемость, converted types, кодировки, кодеки, страницы
+const TYPE_INT32: i32 = 1;
+const TYPE_INT64: i32 = 2;
+const TYPE_DOUBLE: i32 = 5;
+const REPETITION_REQUIRED: i32 = 0;
+const REPETITION_OPTIONAL: i32 = 1;
+const CONVERTED_DECIMAL: i32 = 5;
+const CONVERTED_TIMESTAMP_MILLIS: i32 = 9;
+const CONVERTED_TIMESTAMP_MICROS: i32 = 10;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_PLAIN_DICTIONARY: i32 = 2;
+const ENCODING_RLE: i32 = 3;
+const ENCODING_RLE_DICTIONARY: i32 = 8;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const CODEC_SNAPPY: i32 = 1;
+const PAGE_DATA: i32 = 0;
+const PAGE_DICTIONARY: i32 = 2;
+const PAGE_DATA_V2: i32 = 3;
+
+/// Logical type of an INT64 column written by `ParquetWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// UTC timestamp in milliseconds.
+    TimestampMillis,
+    /// Fixed-point number with `scale` decimals (DECIMAL(18, scale)).
+    Decimal { scale: u32 },
+}
+
+/// Column of a file: name and how its values are to be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub unit: ValueUnit,
+}
+
+/// Meaning of the stored numbers of a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueUnit {
+    /// Plain integers or floats.
+    Plain,
+    /// Integers with this many decimals.
+    Decimal(u32),
+    /// Timestamp integers, ticks per millisecond (1, 1000, 1_000_000).
+    Timestamp { per_ms: i64 },
+}
+
+/// Decoded values of a column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnData {
+    Int(Vec<i64>),
+    Double(Vec<f64>),
+}
+
+impl ColumnData {
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::Int(values) => values.len(),
+            ColumnData::Double(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Thrift compact protocol
+
+#[derive(Debug, Clone, PartialEq)]
+enum Thrift {
+    Bool(bool),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(BTreeMap<i16, Thrift>),
+}
+
+impl Thrift {
+    fn string(s: &str) -> Self {
+        Thrift::Binary(s.as_bytes().to_vec())
+    }
+
+    fn structure<const N: usize>(fields: [(i16, Thrift); N]) -> Self {
+        Thrift::Struct(fields.into_iter().collect())
+    }
+
+    fn type_id(&self) -> u8 {
+        match self {
+            Thrift::Bool(true) => T_TRUE,
+            Thrift::Bool(false) => T_FALSE,
+            Thrift::I32(_) => T_I32,
+            Thrift::I64(_) => T_I64,
+            Thrift::Double(_) => T_DOUBLE,
+            Thrift::Binary(_) => T_BINARY,
+            Thrift::List(_) => T_LIST,
+            Thrift::Struct(_) => T_STRUCT,
+        }
+    }
+
+    fn field(&self, id: i16) -> Option<&Thrift> {
+        match self {
+            Thrift::Struct(fields) => fields.get(&id),
+            _ => None,
+        }
+    }
+
+    fn int(&self, id: i16) -> Option<i64> {
+        match self.field(id)? {
+            Thrift::I32(v) => Some(*v as i64),
+            Thrift::I64(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn text(&self, id: i16) -> Option<String> {
+        match self.field(id)? {
+            Thrift::Binary(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            _ => None,
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Thrift] {
+        match self.field(id) {
+            Some(Thrift::List(items)) => items,
+            _ => &[],
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            // значение bool поля хранится в его заголовке, здесь - элемент списка
+            Thrift::Bool(v) => out.push(if *v { T_TRUE } else { T_FALSE }),
+            Thrift::I32(v) => write_varint(out, zigzag(*v as i64)),
+            Thrift::I64(v) => write_varint(out, zigzag(*v)),
+            Thrift::Double(v) => out.extend_from_slice(&v.to_le_bytes()),
+            Thrift::Binary(bytes) => {
+                write_varint(out, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+            Thrift::List(items) => {
+                let elem = items.first().map_or(T_STRUCT, |i| match i.type_id() {
+                    T_FALSE => T_TRUE,
+                    t => t,
+                });
+                if items.len() < 15 {
+                    out.push(((items.len() as u8) << 4) | elem);
+                } else {
+                    out.push(0xF0 | elem);
+                    write_varint(out, items.len() as u64);
+                }
+                for item in items {
+                    item.encode(out);
+                }
+            }
+            Thrift::Struct(fields) => {
+                let mut last = 0i16;
+                for (id, value) in fields {
+                    let delta = id - last;
+                    if (1..=15).contains(&delta) {
+                        out.push(((delta as u8) << 4) | value.type_id());
+                    } else {
+                        out.push(value.type_id());
+                        write_varint(out, zigzag(*id as i64));
+                    }
+                    if !matches!(value, Thrift::Bool(_)) {
+                        value.encode(out);
+                    }
+                    last = *id;
+                }
+                out.push(0);
+            }
+        }
+    }
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// Reader over a byte slice, shared by the Thrift and page decoders.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("unexpected end of Parquet data")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, Box<dyn Error>> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= ((b & 0x7F) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint too long".into())
+    }
+
+    fn zigzag(&mut self) -> Result<i64, Box<dyn Error>> {
+        let v = self.varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn thrift_value(&mut self, type_id: u8, depth: usize) -> Result<Thrift, Box<dyn Error>> {
+        if depth > 32 {
+            return Err("Thrift structure nested too deeply".into());
+        }
+        Ok(match type_id {
+            T_TRUE => Thrift::Bool(true),
+            T_FALSE => Thrift::Bool(false),
+            T_BYTE => Thrift::I32(self.byte()? as i8 as i32),
+            T_I16 | T_I32 => Thrift::I32(self.zigzag()? as i32),
+            T_I64 => Thrift::I64(self.zigzag()?),
+            T_DOUBLE => Thrift::Double(f64::from_le_bytes(self.take(8)?.try_into()?)),
+            T_BINARY => {
+                let len = self.varint()? as usize;
+                Thrift::Binary(self.take(len)?.to_vec())
+            }
+            T_LIST | T_SET => {
+                let header = self.byte()?;
+                let size = match header >> 4 {
+                    15 => self.varint()? as usize,
+                    n => n as usize,
+                };
+                let elem = header & 0x0F;
+                let mut items = Vec::with_capacity(size.min(1024));
+                for _ in 0..size {
+                    // bool в списке - отдельный байт
+                    items.push(if elem == T_TRUE || elem == T_FALSE {
+                        Thrift::Bool(self.byte()? == T_TRUE)
+                    } else {
+                        self.thrift_value(elem, depth + 1)?
+                    });
+                }
+                Thrift::List(items)
+            }
+            T_MAP => {
+                // в метаданных Parquet карт нет: читаются и отбрасываются
+                let size = self.varint()? as usize;
+                if size > 0 {
+                    let types = self.byte()?;
+                    for _ in 0..size {
+                        self.thrift_value(types >> 4, depth + 1)?;
+                        self.thrift_value(types & 0x0F, depth + 1)?;
+                    }
+                }
+                Thrift::List(Vec::new())
+            }
+            T_STRUCT => {
+                let mut fields = BTreeMap::new();
+                let mut last = 0i16;
+                loop {
+                    let header = self.byte()?;
+                    if header == 0 {
+                        break;
+                    }
+                    let field_type = header & 0x0F;
+                    let id = match header >> 4 {
+                        0 => self.zigzag()? as i16,
+                        delta => last + delta as i16,
+                    };
+                    fields.insert(id, self.thrift_value(field_type, depth + 1)?);
+                    last = id;
+                }
+                Thrift::Struct(fields)
+            }
+            other => return Err(format!("unknown Thrift type {}", other).into()),
+        })
+    }
+
+    fn thrift_struct(&mut self) -> Result<Thrift, Box<dyn Error>> {
+        self.thrift_value(T_STRUCT, 0)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Writer
+
+/// Writes a flat Parquet file of INT64 columns: one uncompressed PLAIN data page per
+/// column chunk, one row group per `write_row_group` call, string key/value metadata
+/// in the footer.
+pub struct ParquetWriter<W: Write> {
+    out: W,
+    offset: u64,
+    columns: Vec<(String, ColumnKind)>,
+    metadata: Vec<(String, String)>,
+    row_groups: Vec<Thrift>,
+    num_rows: i64,
+}
+
+impl<W: Write> ParquetWriter<W> {
+    pub fn new(
+        mut out: W,
+        columns: &[(&str, ColumnKind)],
+        metadata: &[(&str, String)],
+    ) -> Result<Self, Box<dyn Error>> {
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out,
+            offset: MAGIC.len() as u64,
+            columns: columns
+                .iter()
+                .map(|(name, kind)| (name.to_string(), *kind))
+                .collect(),
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+            row_groups: Vec::new(),
+            num_rows: 0,
+        })
+    }
+
+    /// Writes one row group; `columns` are in schema order and of equal length.
+    pub fn write_row_group(&mut self, columns: &[&[i64]]) -> Result<(), Box<dyn Error>> {
+        if columns.len() != self.columns.len() {
+            return Err(format!(
+                "{} columns given, the schema has {}",
+                columns.len(),
+                self.columns.len()
+            )
+            .into());
+        }
+        let rows = columns.first().map_or(0, |c| c.len());
+        if rows == 0 {
+            return Ok(());
+        }
+        if columns.iter().any(|c| c.len() != rows) {
+            return Err("columns of a row group differ in length".into());
+        }
+        let mut chunks = Vec::with_capacity(columns.len());
+        let mut group_size = 0i64;
+        for ((name, _), values) in self.columns.iter().zip(columns) {
+            let mut page = Vec::with_capacity(values.len() * 8);
+            for v in values.iter() {
+                page.extend_from_slice(&v.to_le_bytes());
+            }
+            let header = Thrift::structure([
+                (1, Thrift::I32(PAGE_DATA)),
+                (2, Thrift::I32(page.len() as i32)),
+                (3, Thrift::I32(page.len() as i32)),
+                (
+                    5,
+                    Thrift::structure([
+                        (1, Thrift::I32(rows as i32)),
+                        (2, Thrift::I32(ENCODING_PLAIN)),
+                        (3, Thrift::I32(ENCODING_RLE)),
+                        (4, Thrift::I32(ENCODING_RLE)),
+                    ]),
+                ),
+            ]);
+            let mut bytes = Vec::new();
+            header.encode(&mut bytes);
+            bytes.extend_from_slice(&page);
+            let chunk_offset = self.offset as i64;
+            self.out.write_all(&bytes)?;
+            self.offset += bytes.len() as u64;
+            group_size += bytes.len() as i64;
+            chunks.push(Thrift::structure([
+                (2, Thrift::I64(chunk_offset)),
+                (
+                    3,
+                    Thrift::structure([
+                        (1, Thrift::I32(TYPE_INT64)),
+                        (
+                            2,
+                            Thrift::List(vec![
+                                Thrift::I32(ENCODING_PLAIN),
+                                Thrift::I32(ENCODING_RLE),
+                            ]),
+                        ),
+                        (3, Thrift::List(vec![Thrift::string(name)])),
+                        (4, Thrift::I32(CODEC_UNCOMPRESSED)),
+                        (5, Thrift::I64(rows as i64)),
+                        (6, Thrift::I64(bytes.len() as i64)),
+                        (7, Thrift::I64(bytes.len() as i64)),
+                        (9, Thrift::I64(chunk_offset)),
+                    ]),
+                ),
+            ]));
+        }
+        self.row_groups.push(Thrift::structure([
+            (1, Thrift::List(chunks)),
+            (2, Thrift::I64(group_size)),
+            (3, Thrift::I64(rows as i64)),
+        ]));
+        self.num_rows += rows as i64;
+        Ok(())
+    }
+
+    /// Writes the footer and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, Box<dyn Error>> {
+        let mut schema = vec![Thrift::structure([
+            (4, Thrift::string("schema")),
+            (5, Thrift::I32(self.columns.len() as i32)),
+        ])];
+        for (name, kind) in &self.columns {
+            let mut fields = BTreeMap::from([
+                (1, Thrift::I32(TYPE_INT64)),
+                (3, Thrift::I32(REPETITION_REQUIRED)),
+                (4, Thrift::string(name)),
+            ]);
+            match kind {
+                ColumnKind::TimestampMillis => {
+                    fields.insert(6, Thrift::I32(CONVERTED_TIMESTAMP_MILLIS));
+                    let millis = Thrift::structure([(1, Thrift::structure([]))]);
+                    let timestamp = Thrift::structure([(1, Thrift::Bool(true)), (2, millis)]);
+                    fields.insert(10, Thrift::structure([(8, timestamp)]));
+                }
+                ColumnKind::Decimal { scale } => {
+                    fields.insert(6, Thrift::I32(CONVERTED_DECIMAL));
+                    fields.insert(7, Thrift::I32(*scale as i32));
+                    fields.insert(8, Thrift::I32(18));
+                    let decimal =
+                        Thrift::structure([(1, Thrift::I32(*scale as i32)), (2, Thrift::I32(18))]);
+                    fields.insert(10, Thrift::structure([(5, decimal)]));
+                }
+            }
+            schema.push(Thrift::Struct(fields));
+        }
+        let key_values = self
+            .metadata
+            .iter()
+            .map(|(key, value)| {
+                Thrift::structure([(1, Thrift::string(key)), (2, Thrift::string(value))])
+            })
+            .collect();
+        let footer = Thrift::structure([
+            (1, Thrift::I32(1)),
+            (2, Thrift::List(schema)),
+            (3, Thrift::I64(self.num_rows)),
+            (4, Thrift::List(std::mem::take(&mut self.row_groups))),
+            (5, Thrift::List(key_values)),
+            (
+                6,
+                Thrift::string(concat!("n-ohlcv version ", env!("CARGO_PKG_VERSION"))),
+            ),
+        ]);
+        let mut bytes = Vec::new();
+        footer.encode(&mut bytes);
+        self.out.write_all(&bytes)?;
+        self.out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.out.write_all(MAGIC)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Reader
+
+/// Schema column as stored in the footer.
+struct SchemaColumn {
+    column: Column,
+    physical: i32,
+    optional: bool,
+}
+
+/// Parquet file held in memory. Reads flat files of INT32 / INT64 / DOUBLE columns
+/// written with PLAIN or dictionary encoding, uncompressed or Snappy, data pages v1
+/// or v2 - the pyarrow/pandas defaults with `compression="snappy"` or `None`.
+/// Null values are rejected.
+pub struct ParquetFile {
+    data: Vec<u8>,
+    columns: Vec<SchemaColumn>,
+    row_groups: Vec<Thrift>,
+    pub metadata: HashMap<String, String>,
+    pub num_rows: usize,
+}
+
+impl ParquetFile {
+    pub fn parse(data: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        let len = data.len();
+        if len < 12 || &data[..4] != MAGIC || &data[len - 4..] != MAGIC {
+            return Err("not a Parquet file".into());
+        }
+        let footer_len = u32::from_le_bytes(data[len - 8..len - 4].try_into()?) as usize;
+        let footer_start = (len - 8)
+            .checked_sub(footer_len)
+            .filter(|start| *start >= 4)
+            .ok_or("corrupted Parquet footer")?;
+        let footer = Cursor::new(&data[footer_start..len - 8]).thrift_struct()?;
+
+        let schema = footer.list(2);
+        let root = schema.first().ok_or("empty Parquet schema")?;
+        if root.int(5).unwrap_or(0) as usize != schema.len() - 1 {
+            return Err("nested Parquet schemas are not supported".into());
+        }
+        let mut columns = Vec::new();
+        for element in &schema[1..] {
+            let name = element.text(4).ok_or("unnamed Parquet column")?;
+            if element.int(5).unwrap_or(0) > 0 {
+                return Err(format!("column {} is nested", name).into());
+            }
+            let physical = element.int(1).unwrap_or(-1) as i32;
+            let logical = element.field(10);
+            let timestamp_unit = logical
+                .and_then(|l| l.field(8))
+                .and_then(|t| t.field(2))
+                .and_then(|unit| match unit {
+                    Thrift::Struct(fields) => fields.keys().next().copied(),
+                    _ => None,
+                });
+            let unit = match (element.int(6).map(|c| c as i32), timestamp_unit) {
+                (_, Some(1)) | (Some(CONVERTED_TIMESTAMP_MILLIS), _) => {
+                    ValueUnit::Timestamp { per_ms: 1 }
+                }
+                (_, Some(2)) | (Some(CONVERTED_TIMESTAMP_MICROS), _) => {
+                    ValueUnit::Timestamp { per_ms: 1000 }
+                }
+                (_, Some(3)) => ValueUnit::Timestamp { per_ms: 1_000_000 },
+                (Some(CONVERTED_DECIMAL), _) => {
+                    ValueUnit::Decimal(element.int(7).unwrap_or(0).max(0) as u32)
+                }
+                _ => match logical.and_then(|l| l.field(5)) {
+                    Some(decimal) => ValueUnit::Decimal(decimal.int(1).unwrap_or(0).max(0) as u32),
+                    None => ValueUnit::Plain,
+                },
+            };
+            columns.push(SchemaColumn {
+                column: Column { name, unit },
+                physical,
+                optional: element.int(3) == Some(REPETITION_OPTIONAL as i64),
+            });
+        }
+        let metadata = footer
+            .list(5)
+            .iter()
+            .filter_map(|kv| Some((kv.text(1)?, kv.text(2).unwrap_or_default())))
+            .collect();
+        Ok(Self {
+            num_rows: footer.int(3).unwrap_or(0).max(0) as usize,
+            row_groups: footer.list(4).to_vec(),
+            columns,
+            metadata,
+            data,
+        })
+    }
+
+    pub fn columns(&self) -> Vec<Column> {
+        self.columns.iter().map(|c| c.column.clone()).collect()
+    }
+
+    /// All values of the column `name`, row groups concatenated.
+    pub fn read_column(&self, name: &str) -> Result<ColumnData, Box<dyn Error>> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| c.column.name == name)
+            .ok_or_else(|| format!("no column {} in the Parquet file", name))?;
+        let schema = &self.columns[index];
+        let mut values = match schema.physical {
+            TYPE_INT32 | TYPE_INT64 => ColumnData::Int(Vec::with_capacity(self.num_rows)),
+            TYPE_DOUBLE => ColumnData::Double(Vec::with_capacity(self.num_rows)),
+            other => return Err(format!("column {} has unsupported type {}", name, other).into()),
+        };
+        for group in &self.row_groups {
+            let chunk = group.list(1).get(index).ok_or("missing column chunk")?;
+            let meta = chunk.field(3).ok_or("column chunk without metadata")?;
+            self.read_chunk(schema, meta, &mut values)?;
+        }
+        Ok(values)
+    }
+
+    fn read_chunk(
+        &self,
+        schema: &SchemaColumn,
+        meta: &Thrift,
+        values: &mut ColumnData,
+    ) -> Result<(), Box<dyn Error>> {
+        let name = &schema.column.name;
+        let codec = meta.int(4).unwrap_or(0) as i32;
+        if codec != CODEC_UNCOMPRESSED && codec != CODEC_SNAPPY {
+            return Err(format!(
+                "column {} uses compression codec {}; write the file with compression \"snappy\" or none",
+                name, codec
+            )
+            .into());
+        }
+        let start = meta
+            .int(11)
+            .filter(|o| *o > 0)
+            .or(meta.int(9))
+            .ok_or("column chunk without offset")? as usize;
+        let size = meta.int(7).ok_or("column chunk without size")? as usize;
+        let end = start
+            .checked_add(size)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("column chunk outside the file")?;
+        let expected = meta.int(5).unwrap_or(0) as usize;
+
+        let mut cursor = Cursor::new(&self.data[start..end]);
+        let mut dictionary: Option<ColumnData> = None;
+        let mut read = 0;
+        while read < expected {
+            let header = cursor.thrift_struct()?;
+            let page_type = header.int(1).unwrap_or(-1) as i32;
+            let uncompressed_len = header.int(2).unwrap_or(0) as usize;
+            let page = cursor.take(header.int(3).unwrap_or(0) as usize)?;
+            match page_type {
+                PAGE_DICTIONARY => {
+                    let page = decompress(codec, page, uncompressed_len)?;
+                    let count = header.field(7).and_then(|d| d.int(1)).unwrap_or(0) as usize;
+                    dictionary = Some(plain_values(schema.physical, &page, count)?);
+                }
+                PAGE_DATA => {
+                    let data_header = header.field(5).ok_or("data page without header")?;
+                    let count = data_header.int(1).unwrap_or(0) as usize;
+                    let encoding = data_header.int(2).unwrap_or(0) as i32;
+                    let page = decompress(codec, page, uncompressed_len)?;
+                    let mut body = Cursor::new(&page);
+                    if schema.optional {
+                        let levels_len = u32::from_le_bytes(body.take(4)?.try_into()?) as usize;
+                        check_no_nulls(name, body.take(levels_len)?, count)?;
+                    }
+                    let decoded = page_values(
+                        schema.physical,
+                        encoding,
+                        &page[body.pos..],
+                        count,
+                        dictionary.as_ref(),
+                    )?;
+                    append(values, decoded);
+                    read += count;
+                }
+                PAGE_DATA_V2 => {
+                    let v2 = header.field(8).ok_or("data page v2 without header")?;
+                    let count = v2.int(1).unwrap_or(0) as usize;
+                    if v2.int(2).unwrap_or(0) > 0 {
+                        return Err(format!("column {} has null values", name).into());
+                    }
+                    let encoding = v2.int(4).unwrap_or(0) as i32;
+                    let levels_len =
+                        (v2.int(5).unwrap_or(0) + v2.int(6).unwrap_or(0)).max(0) as usize;
+                    let compressed = !matches!(v2.field(7), Some(Thrift::Bool(false)));
+                    let levels = page.get(..levels_len).ok_or("corrupted data page")?;
+                    let body = &page[levels.len()..];
+                    let body = if compressed {
+                        decompress(codec, body, uncompressed_len.saturating_sub(levels_len))?
+                    } else {
+                        body.to_vec()
+                    };
+                    let decoded =
+                        page_values(schema.physical, encoding, &body, count, dictionary.as_ref())?;
+                    append(values, decoded);
+                    read += count;
+                }
+                // индексные и прочие страницы пропускаются
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn append(values: &mut ColumnData, decoded: ColumnData) {
+    match (values, decoded) {
+        (ColumnData::Int(values), ColumnData::Int(decoded)) => values.extend(decoded),
+        (ColumnData::Double(values), ColumnData::Double(decoded)) => values.extend(decoded),
+        _ => {}
+    }
+}
+
+fn decompress(codec: i32, page: &[u8], uncompressed_len: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    match codec {
+        CODEC_SNAPPY => snappy_decompress(page, uncompressed_len),
+        _ => Ok(page.to_vec()),
+    }
+}
+
+fn plain_values(physical: i32, data: &[u8], count: usize) -> Result<ColumnData, Box<dyn Error>> {
+    let width = if physical == TYPE_INT32 { 4 } else { 8 };
+    let bytes = data
+        .get(..count * width)
+        .ok_or("data page shorter than its values")?;
+    Ok(match physical {
+        TYPE_INT32 => ColumnData::Int(
+            bytes
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as i64)
+                .collect(),
+        ),
+        TYPE_DOUBLE => ColumnData::Double(
+            bytes
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap_or_default()))
+                .collect(),
+        ),
+        _ => ColumnData::Int(
+            bytes
+                .chunks_exact(8)
+                .map(|b| i64::from_le_bytes(b.try_into().unwrap_or_default()))
+                .collect(),
+        ),
+    })
+}
+
+fn page_values(
+    physical: i32,
+    encoding: i32,
+    data: &[u8],
+    count: usize,
+    dictionary: Option<&ColumnData>,
+) -> Result<ColumnData, Box<dyn Error>> {
+    match encoding {
+        ENCODING_PLAIN => plain_values(physical, data, count),
+        ENCODING_PLAIN_DICTIONARY | ENCODING_RLE_DICTIONARY => {
+            let dictionary = dictionary.ok_or("dictionary page missing")?;
+            let (&bit_width, indices) = data.split_first().ok_or("empty data page")?;
+            let indices = decode_hybrid(indices, bit_width, count)?;
+            let lookup = |i: &u32| -> Result<usize, Box<dyn Error>> {
+                let i = *i as usize;
+                if i < dictionary.len() {
+                    Ok(i)
+                } else {
+                    Err("dictionary index out of range".into())
+                }
+            };
+            Ok(match dictionary {
+                ColumnData::Int(dict) => ColumnData::Int(
+                    indices
+                        .iter()
+                        .map(|i| lookup(i).map(|i| dict[i]))
+                        .collect::<Result<_, _>>()?,
+                ),
+                ColumnData::Double(dict) => ColumnData::Double(
+                    indices
+                        .iter()
+                        .map(|i| lookup(i).map(|i| dict[i]))
+                        .collect::<Result<_, _>>()?,
+                ),
+            })
+        }
+        other => Err(format!("unsupported Parquet encoding {}", other).into()),
+    }
+}
+
+/// Definition levels (bit width 1) of an optional column must all be 1.
+fn check_no_nulls(name: &str, levels: &[u8], count: usize) -> Result<(), Box<dyn Error>> {
+    if decode_hybrid(levels, 1, count)?.contains(&0) {
+        return Err(format!("column {} has null values", name).into());
+    }
+    Ok(())
+}
+
+/// RLE / bit-packing hybrid run decoder of dictionary indices and levels.
+fn decode_hybrid(data: &[u8], bit_width: u8, count: usize) -> Result<Vec<u32>, Box<dyn Error>> {
+    if bit_width > 32 {
+        return Err("invalid bit width".into());
+    }
+    let mut cursor = Cursor::new(data);
+    let mut values = Vec::with_capacity(count);
+    while values.len() < count {
+        let header = cursor.varint()?;
+        if header & 1 == 0 {
+            let run = (header >> 1) as usize;
+            let mut value = 0u32;
+            for (i, b) in cursor
+                .take(bit_width.div_ceil(8) as usize)?
+                .iter()
+                .enumerate()
+            {
+                value |= (*b as u32) << (8 * i);
+            }
+            values.extend(std::iter::repeat_n(value, run.min(count - values.len())));
+        } else {
+            let groups = (header >> 1) as usize;
+            let bytes = cursor.take(groups * bit_width as usize)?;
+            let mask = if bit_width == 32 {
+                u32::MAX
+            } else {
+                (1u32 << bit_width) - 1
+            };
+            for n in 0..groups * 8 {
+                if values.len() == count {
+                    break;
+                }
+                let mut value = 0u64;
+                let bit = n * bit_width as usize;
+                for (i, b) in bytes[bit / 8..].iter().take(5).enumerate() {
+                    value |= (*b as u64) << (8 * i);
+                }
+                values.push((value >> (bit % 8)) as u32 & mask);
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Raw Snappy block (no framing), as used for Parquet pages.
+fn snappy_decompress(data: &[u8], expected_len: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut cursor = Cursor::new(data);
+    let len = cursor.varint()? as usize;
+    if len != expected_len {
+        return Err("Snappy page length mismatch".into());
+    }
+    let mut out: Vec<u8> = Vec::with_capacity(len);
+    while cursor.pos < data.len() {
+        let tag = cursor.byte()?;
+        let (copy_len, offset) = match tag & 3 {
+            0 => {
+                let mut literal_len = (tag >> 2) as usize;
+                if literal_len >= 60 {
+                    let extra = literal_len - 59;
+                    literal_len = 0;
+                    for (i, b) in cursor.take(extra)?.iter().enumerate() {
+                        literal_len |= (*b as usize) << (8 * i);
+                    }
+                }
+                out.extend_from_slice(cursor.take(literal_len + 1)?);
+                continue;
+            }
+            1 => (
+                ((tag >> 2) & 7) as usize + 4,
+                (((tag >> 5) as usize) << 8) | cursor.byte()? as usize,
+            ),
+            2 => {
+                let b = cursor.take(2)?;
+                (
+                    (tag >> 2) as usize + 1,
+                    u16::from_le_bytes([b[0], b[1]]) as usize,
+                )
+            }
+            _ => {
+                let b = cursor.take(4)?;
+                (
+                    (tag >> 2) as usize + 1,
+                    u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize,
+                )
+            }
+        };
+        if offset == 0 || offset > out.len() || out.len() + copy_len > len {
+            return Err("corrupted Snappy data".into());
+        }
+        // копии могут перекрываться с собой
+        let from = out.len() - offset;
+        for i in 0..copy_len {
+            out.push(out[from + i]);
+        }
+    }
+    if out.len() != len {
+        return Err("corrupted Snappy data".into());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use parquet::basic::{Compression, LogicalType, TimeUnit};
+    use parquet::column::reader::get_typed_column_reader;
+    use parquet::data_type::{DoubleType, Int32Type, Int64Type};
+    use parquet::file::metadata::KeyValue;
+    use parquet::file::properties::{WriterProperties, WriterVersion};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    const SCHEMA: [(&str, ColumnKind); 2] = [
+        ("open_time", ColumnKind::TimestampMillis),
+        ("close", ColumnKind::Decimal { scale: 8 }),
+    ];
+
+    fn write(groups: &[(&[i64], &[i64])]) -> Vec<u8> {
+        let metadata = [
+            ("symbol", "BTCUSDT".to_string()),
+            ("interval", "1m".to_string()),
+        ];
+        let mut writer = ParquetWriter::new(Vec::new(), &SCHEMA, &metadata).unwrap();
+        for (times, closes) in groups {
+            writer.write_row_group(&[times, closes]).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn int_column(file: &ParquetFile, name: &str) -> Vec<i64> {
+        match file.read_column(name).unwrap() {
+            ColumnData::Int(values) => values,
+            other => panic!("column {} is not integer: {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn round_trip_across_row_groups() {
+        let data = write(&[
+            (&[0, 60_000, 120_000], &[1, 2, 3]),
+            (&[], &[]),
+            (&[-60_000, i64::MAX], &[i64::MIN, 0]),
+        ]);
+        let file = ParquetFile::parse(data).unwrap();
+        assert_eq!(file.num_rows, 5);
+        assert_eq!(file.row_groups.len(), 2);
+        assert_eq!(file.metadata["symbol"], "BTCUSDT");
+        assert_eq!(file.metadata["interval"], "1m");
+        assert_eq!(
+            file.columns(),
+            vec![
+                Column {
+                    name: "open_time".into(),
+                    unit: ValueUnit::Timestamp { per_ms: 1 },
+                },
+                Column {
+                    name: "close".into(),
+                    unit: ValueUnit::Decimal(8),
+                },
+            ]
+        );
+        assert_eq!(
+            int_column(&file, "open_time"),
+            [0, 60_000, 120_000, -60_000, i64::MAX]
+        );
+        assert_eq!(int_column(&file, "close"), [1, 2, 3, i64::MIN, 0]);
+        assert!(file.read_column("volume").is_err());
+    }
+
+    #[test]
+    fn empty_file_round_trip() {
+        let file = ParquetFile::parse(write(&[])).unwrap();
+        assert_eq!(file.num_rows, 0);
+        assert!(file.row_groups.is_empty());
+        assert!(int_column(&file, "open_time").is_empty());
+    }
+
+    #[test]
+    fn row_groups_are_checked() {
+        let mut writer = ParquetWriter::new(Vec::new(), &SCHEMA, &[]).unwrap();
+        assert!(writer.write_row_group(&[&[1]]).is_err());
+        assert!(writer.write_row_group(&[&[1, 2], &[1]]).is_err());
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        let data = write(&[(&[0], &[1])]);
+        assert!(ParquetFile::parse(data[..data.len() - 1].to_vec()).is_err());
+        assert!(ParquetFile::parse(b"PAR1PAR1".to_vec()).is_err());
+        let mut truncated = data.clone();
+        let footer_len = truncated.len() as u32;
+        let at = truncated.len() - 8;
+        truncated[at..at + 4].copy_from_slice(&footer_len.to_le_bytes());
+        assert!(ParquetFile::parse(truncated).is_err());
+    }
+
+    #[test]
+    fn thrift_round_trip() {
+        let value = Thrift::structure([
+            (1, Thrift::I32(-7)),
+            (2, Thrift::Bool(true)),
+            (3, Thrift::Bool(false)),
+            // разрыв идентификаторов больше 15 пишется длинным заголовком
+            (40, Thrift::I64(i64::MIN)),
+            (41, Thrift::Double(-0.5)),
+            (42, Thrift::string("name")),
+            (43, Thrift::List((0..20).map(Thrift::I32).collect())),
+            (
+                44,
+                Thrift::List(vec![Thrift::Bool(true), Thrift::Bool(false)]),
+            ),
+            (45, Thrift::structure([(1, Thrift::structure([]))])),
+        ]);
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        let mut cursor = Cursor::new(&bytes);
+        assert_eq!(cursor.thrift_struct().unwrap(), value);
+        assert_eq!(cursor.pos, bytes.len());
+        assert!(Cursor::new(&bytes[..bytes.len() - 1])
+            .thrift_struct()
+            .is_err());
+    }
+
+    #[test]
+    fn hybrid_runs() {
+        // RLE: 5 повторов значения 3 шириной 2 бита
+        assert_eq!(decode_hybrid(&[5 << 1, 3], 2, 5).unwrap(), [3; 5]);
+        // bit-packing: 0..8 шириной 3 бита (пример из спецификации)
+        assert_eq!(
+            decode_hybrid(&[3, 0x88, 0xC6, 0xFA], 3, 8).unwrap(),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        // RLE-прогон, затем группа bit-packing, дополненная до 8 значений
+        assert_eq!(
+            decode_hybrid(&[2 << 1, 1, 3, 0b0000_0101], 1, 4).unwrap(),
+            [1, 1, 1, 0]
+        );
+        assert_eq!(
+            decode_hybrid(&[4 << 1, 0x34, 0x12], 16, 4).unwrap(),
+            [0x1234; 4]
+        );
+        assert!(decode_hybrid(&[3, 0x88], 3, 8).is_err());
+        assert!(decode_hybrid(&[], 1, 1).is_err());
+        assert!(decode_hybrid(&[2, 0], 33, 1).is_err());
+    }
+
+    #[test]
+    fn null_levels_are_rejected() {
+        assert!(check_no_nulls("close", &[3 << 1, 1], 3).is_ok());
+        assert!(check_no_nulls("close", &[3, 0b0000_0101], 3).is_err());
+    }
+
+    #[test]
+    fn snappy_literals_and_copies() {
+        // литерал "abc", затем перекрывающаяся копия 9 байт со смещением 3
+        let data = [12, 2 << 2, b'a', b'b', b'c', ((9 - 4) << 2) | 1, 3];
+        assert_eq!(snappy_decompress(&data, 12).unwrap(), b"abcabcabcabc");
+        // копия с двухбайтовым смещением
+        let data = [6, 2 << 2, b'x', b'y', b'z', ((3 - 1) << 2) | 2, 3, 0];
+        assert_eq!(snappy_decompress(&data, 6).unwrap(), b"xyzxyz");
+        assert!(snappy_decompress(&data, 7).is_err());
+        assert!(snappy_decompress(&[4, 3 << 2, b'a', b'b'], 4).is_err());
+        // копия с нулевым смещением
+        assert!(snappy_decompress(&[5, 0, b'a', 1, 0], 5).is_err());
+    }
+
+    /// Values of an INT64 column of every row group, read by the parquet crate.
+    fn crate_int64_column(reader: &SerializedFileReader<Bytes>, column: usize) -> Vec<i64> {
+        let mut values = Vec::new();
+        for i in 0..reader.num_row_groups() {
+            let group = reader.get_row_group(i).unwrap();
+            let mut column =
+                get_typed_column_reader::<Int64Type>(group.get_column_reader(column).unwrap());
+            let rows = group.metadata().num_rows() as usize;
+            let mut chunk = Vec::new();
+            let (records, _, _) = column.read_records(rows, None, None, &mut chunk).unwrap();
+            assert_eq!(records, rows);
+            values.extend(chunk);
+        }
+        values
+    }
+
+    #[test]
+    fn parquet_crate_reads_written_files() {
+        let data = write(&[
+            (&[0, 60_000, 120_000], &[1, 2, 3]),
+            (&[], &[]),
+            (&[-60_000, i64::MAX], &[i64::MIN, 0]),
+        ]);
+        let reader = SerializedFileReader::new(Bytes::from(data)).unwrap();
+        let meta = reader.metadata().file_metadata();
+        assert_eq!(meta.num_rows(), 5);
+        assert_eq!(reader.num_row_groups(), 2);
+        let key_values = meta.key_value_metadata().unwrap();
+        assert!(key_values.contains(&KeyValue::new("symbol".into(), "BTCUSDT".to_string())));
+        let schema = meta.schema_descr();
+        assert_eq!(
+            schema.column(0).logical_type_ref(),
+            Some(&LogicalType::timestamp(true, TimeUnit::MILLIS))
+        );
+        assert_eq!(
+            schema.column(1).logical_type_ref(),
+            Some(&LogicalType::decimal(8, 18))
+        );
+        assert_eq!(
+            crate_int64_column(&reader, 0),
+            [0, 60_000, 120_000, -60_000, i64::MAX]
+        );
+        assert_eq!(crate_int64_column(&reader, 1), [1, 2, 3, i64::MIN, 0]);
+
+        let empty = SerializedFileReader::new(Bytes::from(write(&[]))).unwrap();
+        assert_eq!(empty.metadata().file_metadata().num_rows(), 0);
+        assert_eq!(empty.num_row_groups(), 0);
+    }
+
+    const CRATE_SCHEMA: &str = "
+        message schema {
+            REQUIRED INT64 open_time (TIMESTAMP(MILLIS, true));
+            OPTIONAL INT64 close (DECIMAL(18, 2));
+            REQUIRED DOUBLE volume;
+            REQUIRED INT32 trades;
+        }";
+
+    /// File written by the parquet crate: row groups of up to 4 rows, pages of up to
+    /// 2 rows; `null_at` leaves that row of `close` empty.
+    fn crate_file(
+        version: WriterVersion,
+        compression: Compression,
+        null_at: Option<usize>,
+    ) -> Vec<u8> {
+        let properties = WriterProperties::builder()
+            .set_writer_version(version)
+            .set_compression(compression)
+            .set_dictionary_enabled(true)
+            .set_write_batch_size(2)
+            .set_data_page_row_count_limit(2)
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                "symbol".into(),
+                "ETHUSDT".to_string(),
+            )]))
+            .build();
+        let schema = Arc::new(parse_message_type(CRATE_SCHEMA).unwrap());
+        let mut out = Vec::new();
+        let mut writer = SerializedFileWriter::new(&mut out, schema, Arc::new(properties)).unwrap();
+        let rows: Vec<usize> = (0..10).collect();
+        for group in rows.chunks(4) {
+            let mut row_group = writer.next_row_group().unwrap();
+
+            let mut column = row_group.next_column().unwrap().unwrap();
+            let times: Vec<i64> = group.iter().map(|&i| i as i64 * 60_000).collect();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&times, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            let mut column = row_group.next_column().unwrap().unwrap();
+            let levels: Vec<i16> = group
+                .iter()
+                .map(|&i| if Some(i) == null_at { 0 } else { 1 })
+                .collect();
+            let closes: Vec<i64> = group
+                .iter()
+                .filter(|&&i| Some(i) != null_at)
+                .map(|&i| 100 + (i % 3) as i64)
+                .collect();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&closes, Some(&levels), None)
+                .unwrap();
+            column.close().unwrap();
+
+            let mut column = row_group.next_column().unwrap().unwrap();
+            let volumes: Vec<f64> = group.iter().map(|&i| i as f64 * 0.5).collect();
+            column
+                .typed::<DoubleType>()
+                .write_batch(&volumes, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            let mut column = row_group.next_column().unwrap().unwrap();
+            let trades: Vec<i32> = group.iter().map(|&i| -(i as i32)).collect();
+            column
+                .typed::<Int32Type>()
+                .write_batch(&trades, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            row_group.close().unwrap();
+        }
+        writer.close().unwrap();
+        out
+    }
+
+    fn compression_codec(compression: Compression) -> i64 {
+        match compression {
+            Compression::SNAPPY => CODEC_SNAPPY as i64,
+            _ => CODEC_UNCOMPRESSED as i64,
+        }
+    }
+
+    #[test]
+    fn reads_parquet_crate_files() {
+        for version in [WriterVersion::PARQUET_1_0, WriterVersion::PARQUET_2_0] {
+            for compression in [Compression::UNCOMPRESSED, Compression::SNAPPY] {
+                let file = ParquetFile::parse(crate_file(version, compression, None)).unwrap();
+                assert_eq!(file.num_rows, 10);
+                assert_eq!(file.row_groups.len(), 3);
+                assert_eq!(file.metadata["symbol"], "ETHUSDT");
+                // страницы словаря действительно есть, сжатие - заданное
+                let chunk = file.row_groups[0].list(1)[1].field(3).unwrap();
+                assert!(chunk.int(11).is_some());
+                assert_eq!(chunk.int(4), Some(compression_codec(compression)));
+                let units: Vec<ValueUnit> = file.columns().into_iter().map(|c| c.unit).collect();
+                assert_eq!(
+                    units,
+                    [
+                        ValueUnit::Timestamp { per_ms: 1 },
+                        ValueUnit::Decimal(2),
+                        ValueUnit::Plain,
+                        ValueUnit::Plain,
+                    ]
+                );
+                assert_eq!(
+                    int_column(&file, "open_time"),
+                    (0..10).map(|i| i * 60_000).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    int_column(&file, "close"),
+                    (0..10).map(|i| 100 + i % 3).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    file.read_column("volume").unwrap(),
+                    ColumnData::Double((0..10).map(|i| i as f64 * 0.5).collect())
+                );
+                assert_eq!(
+                    int_column(&file, "trades"),
+                    (0..10).map(|i| -i).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parquet_crate_nulls_are_rejected() {
+        for version in [WriterVersion::PARQUET_1_0, WriterVersion::PARQUET_2_0] {
+            let file =
+                ParquetFile::parse(crate_file(version, Compression::SNAPPY, Some(5))).unwrap();
+            let err = file.read_column("close").unwrap_err();
+            assert_eq!(err.to_string(), "column close has null values");
+            assert_eq!(int_column(&file, "open_time").len(), 10);
+        }
+    }
+}
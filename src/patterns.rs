@@ -0,0 +1,189 @@
+// patterns.rs - Candlestick pattern detection (engulfing, doji, hammer, inside bar) and markers
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::drawing_util;
+use crate::timeframe::Bar;
+use eframe::egui::{self, Color32, Rect};
+
+const MARKER_HIT_DISTANCE: f32 = 6.0;
+// doji: body at most this share of the range
+const DOJI_BODY_RATIO: f64 = 0.1;
+// hammer: lower shadow at least this many bodies, upper shadow at most one body
+const HAMMER_SHADOW_RATIO: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandlePattern {
+    BullishEngulfing,
+    BearishEngulfing,
+    Doji,
+    Hammer,
+    InsideBar,
+}
+
+impl CandlePattern {
+    pub const ALL: [CandlePattern; 5] = [
+        CandlePattern::BullishEngulfing,
+        CandlePattern::BearishEngulfing,
+        CandlePattern::Doji,
+        CandlePattern::Hammer,
+        CandlePattern::InsideBar,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CandlePattern::BullishEngulfing => "Bullish engulfing",
+            CandlePattern::BearishEngulfing => "Bearish engulfing",
+            CandlePattern::Doji => "Doji",
+            CandlePattern::Hammer => "Hammer",
+            CandlePattern::InsideBar => "Inside bar",
+        }
+    }
+
+    fn color(self) -> Color32 {
+        match self {
+            CandlePattern::BullishEngulfing | CandlePattern::Hammer => {
+                Color32::from_rgb(90, 200, 120)
+            }
+            CandlePattern::BearishEngulfing => Color32::from_rgb(220, 90, 90),
+            CandlePattern::Doji | CandlePattern::InsideBar => Color32::from_gray(170),
+        }
+    }
+
+    /// Whether the pattern completes on `bars[index]`.
+    fn matches(self, bars: &[Bar], index: usize) -> bool {
+        let bar = &bars[index];
+        let body = (bar.close - bar.open).abs();
+        let range = bar.high - bar.low;
+        let previous = index.checked_sub(1).map(|i| &bars[i]);
+        match self {
+            CandlePattern::BullishEngulfing => previous.is_some_and(|p| {
+                p.close < p.open
+                    && bar.close > bar.open
+                    && bar.open <= p.close
+                    && bar.close >= p.open
+            }),
+            CandlePattern::BearishEngulfing => previous.is_some_and(|p| {
+                p.close > p.open
+                    && bar.close < bar.open
+                    && bar.open >= p.close
+                    && bar.close <= p.open
+            }),
+            CandlePattern::Doji => range > 0.0 && body <= range * DOJI_BODY_RATIO,
+            CandlePattern::Hammer => {
+                let lower_shadow = bar.open.min(bar.close) - bar.low;
+                let upper_shadow = bar.high - bar.open.max(bar.close);
+                body > 0.0 && lower_shadow >= body * HAMMER_SHADOW_RATIO && upper_shadow <= body
+            }
+            CandlePattern::InsideBar => {
+                previous.is_some_and(|p| bar.high < p.high && bar.low > p.low)
+            }
+        }
+    }
+}
+
+// (bars.len(), first bar time, last bar time, last bar close bits)
+type BarsKey = (usize, i64, i64, u64);
+
+/// Detected patterns per bar, recomputed when the bars or the enabled set change.
+pub struct PatternMarkers {
+    pub enabled: Vec<(CandlePattern, bool)>,
+    found: Vec<(usize, CandlePattern)>,
+    detected_for: Option<(BarsKey, Vec<(CandlePattern, bool)>)>,
+}
+
+impl Default for PatternMarkers {
+    fn default() -> Self {
+        Self {
+            enabled: CandlePattern::ALL.iter().map(|p| (*p, false)).collect(),
+            found: Vec::new(),
+            detected_for: None,
+        }
+    }
+}
+
+impl PatternMarkers {
+    pub fn detect(&mut self, bars: &[Bar]) {
+        let last = bars.last();
+        let key = (
+            bars.len(),
+            bars.first().map_or(0, |b| b.time),
+            last.map_or(0, |b| b.time),
+            last.map_or(0, |b| b.close.to_bits()),
+        );
+        if self
+            .detected_for
+            .as_ref()
+            .is_some_and(|(k, enabled)| *k == key && *enabled == self.enabled)
+        {
+            return;
+        }
+        self.found.clear();
+        for index in 0..bars.len() {
+            for (pattern, on) in &self.enabled {
+                if *on && pattern.matches(bars, index) {
+                    self.found.push((index, *pattern));
+                }
+            }
+        }
+        self.detected_for = Some((key, self.enabled.clone()));
+    }
+
+    /// Small dots under the bar low, several patterns of one bar stacked downwards;
+    /// hovering a dot shows the pattern names.
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+    ) {
+        let painter = ui.painter();
+        let (start, end) = data_window.visible_range;
+        let end = end.min(data_window.bars.len() as i64);
+        if start < 0 || start >= end {
+            return;
+        }
+        let (start, end) = (start as usize, end as usize);
+        let visible_count = end - start;
+        let hover = ui.ctx().pointer_hover_pos();
+        let mut hovered: Vec<&str> = Vec::new();
+        let mut stacked = (usize::MAX, 0);
+        for &(index, pattern) in &self.found {
+            if index < start || index >= end {
+                continue;
+            }
+            stacked = if stacked.0 == index {
+                (index, stacked.1 + 1)
+            } else {
+                (index, 0)
+            };
+            let x = drawing_util::calculate_bar_center_x(
+                index - start,
+                visible_count,
+                rect,
+                data_window.pixel_offset,
+            );
+            let y = scale_price(data_window.bars[index].low) + 6.0 + stacked.1 as f32 * 5.0;
+            let center = egui::pos2(x, y);
+            painter.circle_filled(center, 2.0, pattern.color());
+            if hover.is_some_and(|pos| pos.distance(center) <= MARKER_HIT_DISTANCE) {
+                hovered.push(pattern.name());
+            }
+        }
+        if !hovered.is_empty() {
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                egui::Id::new("pattern_marker"),
+                |ui| ui.label(hovered.join("\n")),
+            );
+        }
+    }
+
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        for (pattern, on) in &mut self.enabled {
+            ui.checkbox(on, pattern.name());
+        }
+    }
+}
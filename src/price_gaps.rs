@@ -0,0 +1,157 @@
+// price_gaps.rs - Price gaps between consecutive bars (close -> next open) with gap-fill lines
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::drawing_util;
+use crate::settings::PRICE_GAP_PERCENT;
+use crate::timeframe::Bar;
+use eframe::egui::{self, Color32, Stroke};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceGap {
+    /// Bar opening away from the previous close.
+    pub index: usize,
+    pub prev_close: f64,
+    pub open: f64,
+    /// First later bar whose range reaches `prev_close`.
+    pub filled_at: Option<usize>,
+}
+
+pub fn find_gaps(bars: &[Bar], threshold_percent: f64) -> Vec<PriceGap> {
+    let mut gaps = Vec::new();
+    for index in 1..bars.len() {
+        let prev_close = bars[index - 1].close;
+        let open = bars[index].open;
+        if prev_close <= 0.0 || ((open - prev_close) / prev_close * 100.0).abs() < threshold_percent
+        {
+            continue;
+        }
+        let filled_at =
+            (index..bars.len()).find(|&i| bars[i].low <= prev_close && bars[i].high >= prev_close);
+        gaps.push(PriceGap {
+            index,
+            prev_close,
+            open,
+            filled_at,
+        });
+    }
+    gaps
+}
+
+// (bars.len(), first bar time, last bar time, last bar low/high bits)
+type BarsKey = (usize, i64, i64, u64, u64);
+
+pub struct PriceGaps {
+    pub enabled: bool,
+    pub threshold_percent: f64,
+    pub fill_lines: bool,
+    gaps: Vec<PriceGap>,
+    found_for: Option<(BarsKey, f64)>,
+}
+
+impl Default for PriceGaps {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_percent: PRICE_GAP_PERCENT,
+            fill_lines: true,
+            gaps: Vec::new(),
+            found_for: None,
+        }
+    }
+}
+
+impl PriceGaps {
+    pub fn update(&mut self, bars: &[Bar]) {
+        if !self.enabled {
+            return;
+        }
+        let last = bars.last();
+        let key = (
+            bars.len(),
+            bars.first().map_or(0, |b| b.time),
+            last.map_or(0, |b| b.time),
+            last.map_or(0, |b| b.low.to_bits()),
+            last.map_or(0, |b| b.high.to_bits()),
+        );
+        if self.found_for == Some((key, self.threshold_percent)) {
+            return;
+        }
+        self.gaps = find_gaps(bars, self.threshold_percent);
+        self.found_for = Some((key, self.threshold_percent));
+    }
+
+    /// A bracket on the gapping bar between the previous close and the open; with
+    /// `fill_lines` a dashed line at the previous close up to the filling bar.
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let painter = ui.painter();
+        let color = Color32::from_rgb(230, 140, 60);
+        let (start, end) = data_window.visible_range;
+        let end = end.min(data_window.bars.len() as i64);
+        if start < 0 || start >= end {
+            return;
+        }
+        let (start, end) = (start as usize, end as usize);
+        let visible_count = end - start;
+        let bar_x = |index: usize| {
+            drawing_util::calculate_bar_x_position(
+                index - start,
+                visible_count,
+                rect,
+                data_window.pixel_offset,
+            )
+        };
+
+        for gap in &self.gaps {
+            let last = gap.filled_at.unwrap_or(data_window.bars.len() - 1);
+            if last < start || gap.index >= end {
+                continue;
+            }
+            let close_y = scale_price(gap.prev_close);
+            if gap.index >= start {
+                let (x_left, _) = bar_x(gap.index);
+                let x = x_left - 2.0;
+                painter.line_segment(
+                    [egui::pos2(x, close_y), egui::pos2(x, scale_price(gap.open))],
+                    Stroke::new(2.0, color),
+                );
+            }
+            if self.fill_lines {
+                let from_x = bar_x(gap.index.max(start)).0;
+                let to_x = if last < end {
+                    bar_x(last).1
+                } else {
+                    rect.right()
+                };
+                painter.extend(egui::Shape::dashed_line(
+                    &[egui::pos2(from_x, close_y), egui::pos2(to_x, close_y)],
+                    Stroke::new(1.0, color.gamma_multiply(0.7)),
+                    4.0,
+                    3.0,
+                ));
+            }
+        }
+    }
+
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Show price gaps");
+        ui.horizontal(|ui| {
+            ui.label("Threshold, %");
+            ui.add(
+                egui::DragValue::new(&mut self.threshold_percent)
+                    .speed(0.01)
+                    .range(0.01..=50.0),
+            );
+        });
+        ui.checkbox(&mut self.fill_lines, "Gap-fill lines");
+    }
+}
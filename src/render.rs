@@ -0,0 +1,379 @@
+// render.rs - Renderer trait for chart drawing: egui painter on screen, software rasterizer for images, SVG writer
+// See CONVENTIONS.md for project structure and workflow
+
+use eframe::egui::{self, Align2, Color32, ColorImage, FontId, Pos2, Rect, Stroke, Vec2};
+use eframe::epaint::text::{FontDefinitions, Fonts};
+use eframe::epaint::{ClippedShape, Primitive, Shape, TessellationOptions, Tessellator};
+
+// максимальный размер атласа шрифтов для рендера в изображение
+const MAX_TEXTURE_SIDE: usize = 2048;
+
+/// Drawing primitives used by `hlcbars`, `volbars`, `axes`, `crosshair` and `drawings`.
+/// Coordinates are in points, as with `egui::Painter`.
+pub trait Renderer {
+    fn pixels_per_point(&self) -> f32;
+    /// Default color of labels.
+    fn text_color(&self) -> Color32;
+    fn line_segment(&mut self, points: [Pos2; 2], stroke: Stroke);
+    /// Open line through `points`.
+    fn polyline(&mut self, points: Vec<Pos2>, stroke: Stroke);
+    fn circle_stroke(&mut self, center: Pos2, radius: f32, stroke: Stroke);
+    fn rect_filled(&mut self, rect: Rect, corner_radius: f32, color: Color32);
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: &FontId, color: Color32);
+    fn text_size(&self, text: &str, font: &FontId) -> Vec2;
+}
+
+impl Renderer for egui::Painter {
+    fn pixels_per_point(&self) -> f32 {
+        self.ctx().pixels_per_point()
+    }
+
+    fn text_color(&self) -> Color32 {
+        self.ctx().style().visuals.text_color()
+    }
+
+    fn line_segment(&mut self, points: [Pos2; 2], stroke: Stroke) {
+        egui::Painter::line_segment(self, points, stroke);
+    }
+
+    fn polyline(&mut self, points: Vec<Pos2>, stroke: Stroke) {
+        self.add(Shape::line(points, stroke));
+    }
+
+    fn circle_stroke(&mut self, center: Pos2, radius: f32, stroke: Stroke) {
+        egui::Painter::circle_stroke(self, center, radius, stroke);
+    }
+
+    fn rect_filled(&mut self, rect: Rect, corner_radius: f32, color: Color32) {
+        egui::Painter::rect_filled(self, rect, corner_radius, color);
+    }
+
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: &FontId, color: Color32) {
+        egui::Painter::text(self, pos, anchor, text, font.clone(), color);
+    }
+
+    fn text_size(&self, text: &str, font: &FontId) -> Vec2 {
+        self.layout_no_wrap(text.to_string(), font.clone(), Color32::WHITE)
+            .size()
+    }
+}
+
+/// Collects shapes without an egui context and rasterizes them into an image,
+/// for exporting charts and for tests.
+pub struct ImageRenderer {
+    size: [usize; 2],
+    pixels_per_point: f32,
+    background: Color32,
+    text_color: Color32,
+    fonts: Fonts,
+    shapes: Vec<Shape>,
+}
+
+impl ImageRenderer {
+    /// `size` in pixels.
+    pub fn new(
+        size: [usize; 2],
+        pixels_per_point: f32,
+        background: Color32,
+        text_color: Color32,
+    ) -> Self {
+        Self {
+            size,
+            pixels_per_point,
+            background,
+            text_color,
+            fonts: Fonts::new(
+                pixels_per_point,
+                MAX_TEXTURE_SIDE,
+                FontDefinitions::default(),
+            ),
+            shapes: Vec::new(),
+        }
+    }
+
+    /// Whole image in points.
+    pub fn rect(&self) -> Rect {
+        Rect::from_min_size(
+            Pos2::ZERO,
+            egui::vec2(self.size[0] as f32, self.size[1] as f32) / self.pixels_per_point,
+        )
+    }
+
+    /// Tessellates the collected shapes and fills the triangles into the image.
+    pub fn finish(self) -> ColorImage {
+        let [width, height] = self.size;
+        let mut image = ColorImage::new(self.size, self.background);
+        let clip_rect = self.rect();
+        let shapes = self
+            .shapes
+            .into_iter()
+            .map(|shape| ClippedShape { clip_rect, shape })
+            .collect();
+        let prepared_discs = self.fonts.texture_atlas().lock().prepared_discs();
+        let primitives = Tessellator::new(
+            self.pixels_per_point,
+            TessellationOptions::default(),
+            self.fonts.font_image_size(),
+            prepared_discs,
+        )
+        .tessellate_shapes(shapes);
+        // атлас шрифтов после раскладки всех надписей; белый пиксель для остальных фигур
+        let font_image = self.fonts.image();
+        let texels: Vec<Color32> = font_image.srgba_pixels(None).collect();
+        let [tex_width, tex_height] = font_image.size;
+        let sample = |u: f32, v: f32| {
+            let x = ((u * tex_width as f32) as usize).min(tex_width - 1);
+            let y = ((v * tex_height as f32) as usize).min(tex_height - 1);
+            texels[y * tex_width + x]
+        };
+
+        for primitive in primitives {
+            let Primitive::Mesh(mesh) = primitive.primitive else {
+                continue;
+            };
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+                let [pa, pb, pc] = [a, b, c].map(|v| v.pos * self.pixels_per_point);
+                let area = edge(pa, pb, pc);
+                if area.abs() < f32::EPSILON {
+                    continue;
+                }
+                let min_x = pa.x.min(pb.x).min(pc.x).floor().max(0.0) as usize;
+                let min_y = pa.y.min(pb.y).min(pc.y).floor().max(0.0) as usize;
+                let max_x = (pa.x.max(pb.x).max(pc.x).ceil() as usize).min(width);
+                let max_y = (pa.y.max(pb.y).max(pc.y).ceil() as usize).min(height);
+                for y in min_y..max_y {
+                    for x in min_x..max_x {
+                        let p = egui::pos2(x as f32 + 0.5, y as f32 + 0.5);
+                        let wa = edge(pb, pc, p) / area;
+                        let wb = edge(pc, pa, p) / area;
+                        let wc = edge(pa, pb, p) / area;
+                        if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                            continue;
+                        }
+                        let uv = a.uv.to_vec2() * wa + b.uv.to_vec2() * wb + c.uv.to_vec2() * wc;
+                        let texel = sample(uv.x, uv.y);
+                        let channel = |i: usize| {
+                            let vertex = a.color[i] as f32 * wa
+                                + b.color[i] as f32 * wb
+                                + c.color[i] as f32 * wc;
+                            vertex * texel[i] as f32 / 255.0
+                        };
+                        // цвета вершин и атласа с premultiplied alpha
+                        let src = [channel(0), channel(1), channel(2), channel(3)];
+                        let dst = &mut image.pixels[y * width + x];
+                        let keep = 1.0 - src[3] / 255.0;
+                        *dst = Color32::from_rgba_premultiplied(
+                            (src[0] + dst.r() as f32 * keep).round() as u8,
+                            (src[1] + dst.g() as f32 * keep).round() as u8,
+                            (src[2] + dst.b() as f32 * keep).round() as u8,
+                            (src[3] + dst.a() as f32 * keep).round() as u8,
+                        );
+                    }
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+impl Renderer for ImageRenderer {
+    fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+
+    fn text_color(&self) -> Color32 {
+        self.text_color
+    }
+
+    fn line_segment(&mut self, points: [Pos2; 2], stroke: Stroke) {
+        self.shapes.push(Shape::line_segment(points, stroke));
+    }
+
+    fn polyline(&mut self, points: Vec<Pos2>, stroke: Stroke) {
+        self.shapes.push(Shape::line(points, stroke));
+    }
+
+    fn circle_stroke(&mut self, center: Pos2, radius: f32, stroke: Stroke) {
+        self.shapes
+            .push(Shape::circle_stroke(center, radius, stroke));
+    }
+
+    fn rect_filled(&mut self, rect: Rect, corner_radius: f32, color: Color32) {
+        self.shapes
+            .push(Shape::rect_filled(rect, corner_radius, color));
+    }
+
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: &FontId, color: Color32) {
+        self.shapes.push(Shape::text(
+            &self.fonts,
+            pos,
+            anchor,
+            text,
+            font.clone(),
+            color,
+        ));
+    }
+
+    fn text_size(&self, text: &str, font: &FontId) -> Vec2 {
+        self.fonts
+            .layout_no_wrap(text.to_string(), font.clone(), Color32::WHITE)
+            .size()
+    }
+}
+
+/// Writes the shapes as SVG elements; text is measured with the egui default fonts,
+/// so the layout matches the screen and the image backend.
+pub struct SvgRenderer {
+    size: Vec2,
+    background: Color32,
+    text_color: Color32,
+    fonts: Fonts,
+    elements: String,
+}
+
+/// `fill="#rrggbb" fill-opacity=".."` style attribute pair.
+fn svg_paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut paint = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, r, g, b);
+    if a < 255 {
+        paint.push_str(&format!(
+            " {}-opacity=\"{:.3}\"",
+            attribute,
+            a as f32 / 255.0
+        ));
+    }
+    paint
+}
+
+fn svg_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl SvgRenderer {
+    /// `size` in points, which are also the SVG user units.
+    pub fn new(size: Vec2, background: Color32, text_color: Color32) -> Self {
+        Self {
+            size,
+            background,
+            text_color,
+            fonts: Fonts::new(1.0, MAX_TEXTURE_SIDE, FontDefinitions::default()),
+            elements: String::new(),
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::from_min_size(Pos2::ZERO, self.size)
+    }
+
+    /// Complete SVG document.
+    pub fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n<rect width=\"100%\" height=\"100%\" {}/>\n{}</svg>\n",
+            svg_paint("fill", self.background),
+            self.elements,
+            w = self.size.x,
+            h = self.size.y,
+        )
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn pixels_per_point(&self) -> f32 {
+        1.0
+    }
+
+    fn text_color(&self) -> Color32 {
+        self.text_color
+    }
+
+    fn line_segment(&mut self, points: [Pos2; 2], stroke: Stroke) {
+        self.polyline(points.to_vec(), stroke);
+    }
+
+    fn polyline(&mut self, points: Vec<Pos2>, stroke: Stroke) {
+        if stroke.is_empty() || points.len() < 2 {
+            return;
+        }
+        let points: Vec<String> = points
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+            .collect();
+        self.elements.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\"/>\n",
+            points.join(" "),
+            svg_paint("stroke", stroke.color),
+            stroke.width
+        ));
+    }
+
+    fn circle_stroke(&mut self, center: Pos2, radius: f32, stroke: Stroke) {
+        if stroke.is_empty() {
+            return;
+        }
+        self.elements.push_str(&format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{}\" fill=\"none\" {} stroke-width=\"{}\"/>\n",
+            center.x,
+            center.y,
+            radius,
+            svg_paint("stroke", stroke.color),
+            stroke.width
+        ));
+    }
+
+    fn rect_filled(&mut self, rect: Rect, corner_radius: f32, color: Color32) {
+        if color == Color32::TRANSPARENT {
+            return;
+        }
+        self.elements.push_str(&format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"{}\" {}/>\n",
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            corner_radius,
+            svg_paint("fill", color)
+        ));
+    }
+
+    fn text(&mut self, pos: Pos2, anchor: Align2, text: &str, font: &FontId, color: Color32) {
+        let text_anchor = match anchor.x() {
+            egui::Align::Min => "start",
+            egui::Align::Center => "middle",
+            egui::Align::Max => "end",
+        };
+        let baseline = match anchor.y() {
+            egui::Align::Min => "hanging",
+            egui::Align::Center => "central",
+            egui::Align::Max => "text-after-edge",
+        };
+        let family = match font.family {
+            egui::FontFamily::Monospace => "monospace",
+            _ => "sans-serif",
+        };
+        self.elements.push_str(&format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"{}\" font-size=\"{}\" text-anchor=\"{}\" dominant-baseline=\"{}\" {}>{}</text>\n",
+            pos.x,
+            pos.y,
+            family,
+            font.size,
+            text_anchor,
+            baseline,
+            svg_paint("fill", color),
+            svg_escape(text)
+        ));
+    }
+
+    fn text_size(&self, text: &str, font: &FontId) -> Vec2 {
+        self.fonts
+            .layout_no_wrap(text.to_string(), font.clone(), Color32::WHITE)
+            .size()
+    }
+}
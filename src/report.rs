@@ -0,0 +1,345 @@
+// report.rs - Chart exports without the screen: PDF report (ImageRenderer chart plus a stats table), SVG
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::{self, format_price, AxisMode, AxisTimezone};
+use crate::crosshair::format_volume;
+use crate::datawindow::DataWindow;
+use crate::drawings::Drawings;
+use crate::indicator::Indicators;
+use crate::render::{ImageRenderer, Renderer, SvgRenderer};
+use crate::settings::{TimeLabelFormat, CHART_BOTTOM_MARGIN, REPORT_IMAGE_SIZE, SVG_EXPORT_SIZE};
+use crate::theme::Theme;
+use crate::{axes, hlcbars, volbars};
+use eframe::egui::{self, ColorImage, Rect};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// A4 landscape в пунктах PDF
+const PAGE_SIZE: (f32, f32) = (842.0, 595.0);
+const PAGE_MARGIN: f32 = 20.0;
+const TABLE_FONT_SIZE: f32 = 9.0;
+const TABLE_LINE_HEIGHT: f32 = 11.0;
+// символов значения в строке таблицы до переноса
+const TABLE_VALUE_WIDTH: usize = 60;
+
+/// Display settings of the chart, as on screen.
+pub struct ChartStyle<'a> {
+    pub show_candles: bool,
+    pub time_zone: AxisTimezone,
+    pub time_format: TimeLabelFormat,
+    pub axis_mode: AxisMode,
+    pub theme: &'a Theme,
+    pub indicators: &'a Indicators,
+}
+
+/// Bars, volume, drawings and axes of the visible range into `rect`; time labels go below it.
+pub fn draw_chart(
+    renderer: &mut impl Renderer,
+    rect: Rect,
+    data_window: &mut DataWindow,
+    drawings: &mut Drawings,
+    style: &ChartStyle,
+) {
+    data_window.update_price_range_extrema();
+    let volume_height = rect.height() * data_window.volume_height_ratio;
+    let price_rect =
+        Rect::from_min_max(rect.min, egui::pos2(rect.max.x, rect.max.y - volume_height));
+    let scale_price = axes_util::create_scale_price_fn(data_window, price_rect);
+    hlcbars::draw(
+        renderer,
+        rect,
+        data_window,
+        style.show_candles,
+        style.indicators,
+        &scale_price,
+        style.theme,
+    );
+    volbars::draw(renderer, rect, data_window, style.theme);
+    drawings.draw(renderer, rect, data_window, &scale_price);
+    axes::draw(
+        renderer,
+        rect,
+        data_window,
+        &scale_price,
+        style.time_zone,
+        &style.time_format,
+        style.axis_mode,
+        style.theme,
+    );
+}
+
+fn visuals(theme: &Theme) -> egui::Visuals {
+    if theme.dark_mode {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    }
+}
+
+/// Chart area of an export of `size` points, leaving room for the time labels.
+fn chart_rect(size: egui::Vec2, theme: &Theme) -> Rect {
+    let mut rect = Rect::from_min_size(egui::Pos2::ZERO, size);
+    rect.set_height(rect.height() - 16.0 - CHART_BOTTOM_MARGIN - theme.time_labels_extra());
+    rect
+}
+
+/// Visible range of the chart as an image of `REPORT_IMAGE_SIZE` pixels.
+pub fn chart_image(
+    data_window: &mut DataWindow,
+    drawings: &mut Drawings,
+    style: &ChartStyle,
+) -> ColorImage {
+    let visuals = visuals(style.theme);
+    let mut renderer = ImageRenderer::new(
+        REPORT_IMAGE_SIZE,
+        2.0,
+        visuals.extreme_bg_color,
+        visuals.text_color(),
+    );
+    let rect = chart_rect(renderer.rect().size(), style.theme);
+    draw_chart(&mut renderer, rect, data_window, drawings, style);
+    renderer.finish()
+}
+
+/// Visible range of the chart as an SVG document of `SVG_EXPORT_SIZE` points.
+pub fn chart_svg(
+    data_window: &mut DataWindow,
+    drawings: &mut Drawings,
+    style: &ChartStyle,
+) -> String {
+    let visuals = visuals(style.theme);
+    let size = egui::vec2(SVG_EXPORT_SIZE[0], SVG_EXPORT_SIZE[1]);
+    let mut renderer = SvgRenderer::new(size, visuals.extreme_bg_color, visuals.text_color());
+    draw_chart(
+        &mut renderer,
+        chart_rect(size, style.theme),
+        data_window,
+        drawings,
+        style,
+    );
+    renderer.finish()
+}
+
+/// Summary of the visible bars: range, OHLC, change and volume.
+pub fn visible_summary(
+    data_window: &DataWindow,
+    time_zone: AxisTimezone,
+    time_format: TimeLabelFormat,
+) -> Vec<(String, String)> {
+    let (start, end) = data_window.visible_range;
+    let Some(bars) = data_window
+        .bars
+        .get(start.max(0) as usize..end.max(0) as usize)
+        .filter(|bars| !bars.is_empty())
+    else {
+        return vec![("Bars".to_string(), "0".to_string())];
+    };
+    let (first, last) = (&bars[0], &bars[bars.len() - 1]);
+    let high = bars.iter().map(|b| b.high).fold(f64::MIN, f64::max);
+    let low = bars.iter().map(|b| b.low).fold(f64::MAX, f64::min);
+    let volume: f64 = bars.iter().map(|b| b.volume).sum();
+    let change = last.close - first.open;
+    let decimals = data_window.price_decimals();
+    let price = |value: f64| format_price(value, decimals);
+    let percent = |value: f64, base: f64| {
+        if base != 0.0 {
+            value / base * 100.0
+        } else {
+            0.0
+        }
+    };
+    let time = |t: i64| {
+        time_zone
+            .datetime(t)
+            .format(time_format.time_pattern(false))
+            .to_string()
+    };
+    vec![
+        ("From".to_string(), time(first.time)),
+        ("To".to_string(), time(last.time)),
+        ("Bars".to_string(), bars.len().to_string()),
+        ("Open".to_string(), price(first.open)),
+        ("High".to_string(), price(high)),
+        ("Low".to_string(), price(low)),
+        ("Close".to_string(), price(last.close)),
+        (
+            "Change".to_string(),
+            format!(
+                "{:+.*} ({:+.2}%)",
+                decimals,
+                change,
+                percent(change, first.open)
+            ),
+        ),
+        (
+            "High-low range".to_string(),
+            format!("{} ({:.2}%)", price(high - low), percent(high - low, low)),
+        ),
+        ("Volume".to_string(), format_volume(volume)),
+        (
+            "Average bar volume".to_string(),
+            format_volume(volume / bars.len() as f64),
+        ),
+    ]
+}
+
+/// Characters outside WinAnsi become '?', PDF string delimiters are escaped.
+fn pdf_text(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => bytes.extend([b'\\', c as u8]),
+            ' '..='~' => bytes.push(c as u8),
+            '\u{a0}'..='\u{ff}' => bytes.push(c as u32 as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
+
+/// Splits a comma separated value into lines of about `TABLE_VALUE_WIDTH` characters.
+fn wrap_value(value: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for part in value.split(", ") {
+        match lines.last_mut() {
+            Some(line) if line.len() + part.len() + 2 <= TABLE_VALUE_WIDTH => {
+                line.push_str(", ");
+                line.push_str(part);
+            }
+            _ => lines.push(part.to_string()),
+        }
+    }
+    lines
+}
+
+/// Writes a one-page PDF: `title`, the chart image and the `rows` table in two columns.
+pub fn write_pdf(
+    path: &Path,
+    title: &str,
+    image: &ColorImage,
+    rows: &[(String, String)],
+) -> Result<(), Box<dyn Error>> {
+    let (page_width, page_height) = PAGE_SIZE;
+    let [width, height] = image.size;
+
+    let mut content = Vec::new();
+    content.extend(format!("BT /F2 14 Tf {} {} Td (", PAGE_MARGIN, page_height - 30.0).bytes());
+    content.extend(pdf_text(title));
+    content.extend(b") Tj ET\n");
+    let image_width = page_width - 2.0 * PAGE_MARGIN;
+    let image_height = image_width * height as f32 / width as f32;
+    let image_top = page_height - 40.0;
+    content.extend(
+        format!(
+            "q {} 0 0 {} {} {} cm /Im1 Do Q\n",
+            image_width,
+            image_height,
+            PAGE_MARGIN,
+            image_top - image_height
+        )
+        .bytes(),
+    );
+
+    let lines: Vec<(&str, String)> = rows
+        .iter()
+        .flat_map(|(key, value)| {
+            wrap_value(value)
+                .into_iter()
+                .enumerate()
+                .map(move |(i, line)| (if i == 0 { key.as_str() } else { "" }, line))
+        })
+        .collect();
+    let per_column = lines.len().div_ceil(2);
+    let column_width = image_width / 2.0;
+    let table_top = image_top - image_height - 20.0;
+    for (i, (key, value)) in lines.iter().enumerate() {
+        let x = PAGE_MARGIN + (i / per_column.max(1)) as f32 * column_width;
+        let y = table_top - (i % per_column.max(1)) as f32 * TABLE_LINE_HEIGHT;
+        content.extend(format!("BT /F2 {} Tf {} {} Td (", TABLE_FONT_SIZE, x, y).bytes());
+        content.extend(pdf_text(key));
+        content.extend(
+            format!(
+                ") Tj ET\nBT /F1 {} Tf {} {} Td (",
+                TABLE_FONT_SIZE,
+                x + 110.0,
+                y
+            )
+            .bytes(),
+        );
+        content.extend(pdf_text(value));
+        content.extend(b") Tj ET\n");
+    }
+
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for pixel in &image.pixels {
+        rgb.extend([pixel.r(), pixel.g(), pixel.b()]);
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&rgb)?;
+    let pixels = encoder.finish()?;
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 5 0 R /F2 6 0 R >> /XObject << /Im1 7 0 R >> >> /Contents 4 0 R >>",
+            page_width, page_height
+        )
+        .into_bytes(),
+    ];
+    let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+    stream.extend(&content);
+    stream.extend(b"\nendstream");
+    objects.push(stream);
+    for font in ["Helvetica", "Helvetica-Bold"] {
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font
+            )
+            .into_bytes(),
+        );
+    }
+    let mut stream = format!(
+        "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+        width,
+        height,
+        pixels.len()
+    )
+    .into_bytes();
+    stream.extend(&pixels);
+    stream.extend(b"\nendstream");
+    objects.push(stream);
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .bytes(),
+    );
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, pdf)?;
+    Ok(())
+}
@@ -0,0 +1,238 @@
+// returns.rs - Histogram of bar-to-bar returns of the visible range or the whole loaded history
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::settings::{RETURNS_HISTOGRAM_BINS, RETURNS_HISTOGRAM_SIGMAS};
+use crate::timeframe::Bar;
+use eframe::egui::{self, Align2, Color32, FontId, Rect, Stroke};
+
+const PLOT_SIZE: egui::Vec2 = egui::vec2(420.0, 220.0);
+
+/// Distribution of close-to-close returns in percent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReturnStats {
+    pub count: usize,
+    pub mean: f64,
+    pub sigma: f64,
+    /// Left edge of the first bin and the bin width, %.
+    pub from: f64,
+    pub width: f64,
+    pub bins: Vec<usize>,
+    /// Returns outside ±`RETURNS_HISTOGRAM_SIGMAS` σ, added to the edge bins.
+    pub clipped: usize,
+}
+
+/// Close-to-close returns of consecutive bars, %.
+pub fn bar_returns(bars: &[Bar]) -> Vec<f64> {
+    bars.windows(2)
+        .filter(|pair| pair[0].close > 0.0)
+        .map(|pair| (pair[1].close - pair[0].close) / pair[0].close * 100.0)
+        .collect()
+}
+
+/// Histogram over mean ± `RETURNS_HISTOGRAM_SIGMAS` σ, so single outliers do not
+/// squeeze the bulk of the distribution into a couple of bins.
+pub fn histogram(returns: &[f64], bin_count: usize) -> Option<ReturnStats> {
+    if returns.len() < 2 || bin_count == 0 {
+        return None;
+    }
+    let count = returns.len();
+    let mean = returns.iter().sum::<f64>() / count as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+    let sigma = variance.sqrt();
+    let (min, max) = returns
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), r| (lo.min(*r), hi.max(*r)));
+    let from = min.max(mean - RETURNS_HISTOGRAM_SIGMAS * sigma);
+    let to = max.min(mean + RETURNS_HISTOGRAM_SIGMAS * sigma);
+    // все доходности одинаковые - один столбец
+    let width = ((to - from) / bin_count as f64).max(1e-9);
+    let mut bins = vec![0; bin_count];
+    let mut clipped = 0;
+    for r in returns {
+        if *r < from || *r > to {
+            clipped += 1;
+        }
+        let bin = ((r - from) / width)
+            .floor()
+            .clamp(0.0, (bin_count - 1) as f64);
+        bins[bin as usize] += 1;
+    }
+    Some(ReturnStats {
+        count,
+        mean,
+        sigma,
+        from,
+        width,
+        bins,
+        clipped,
+    })
+}
+
+// (whole history, bins, visible range, bar count, last bar time, last close bits)
+type StatsKey = (bool, usize, (i64, i64), usize, i64, u64);
+
+pub struct ReturnsHistogram {
+    pub open: bool,
+    pub whole_history: bool,
+    pub bins: usize,
+    stats: Option<ReturnStats>,
+    computed_for: Option<StatsKey>,
+}
+
+impl Default for ReturnsHistogram {
+    fn default() -> Self {
+        Self {
+            open: false,
+            whole_history: false,
+            bins: RETURNS_HISTOGRAM_BINS,
+            stats: None,
+            computed_for: None,
+        }
+    }
+}
+
+impl ReturnsHistogram {
+    fn update(&mut self, data_window: &DataWindow) {
+        let bars = &data_window.bars;
+        let last = bars.last();
+        let key = (
+            self.whole_history,
+            self.bins,
+            data_window.visible_range,
+            bars.len(),
+            last.map_or(0, |b| b.time),
+            last.map_or(0, |b| b.close.to_bits()),
+        );
+        if self.computed_for == Some(key) {
+            return;
+        }
+        let slice = if self.whole_history {
+            &bars[..]
+        } else {
+            let (start, end) = data_window.visible_range;
+            let end = (end.max(0) as usize).min(bars.len());
+            let start = (start.max(0) as usize).min(end);
+            &bars[start..end]
+        };
+        self.stats = histogram(&bar_returns(slice), self.bins);
+        self.computed_for = Some(key);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, data_window: &DataWindow) {
+        self.update(data_window);
+        let mut open = self.open;
+        egui::Window::new("Returns distribution")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.whole_history, false, "visible range");
+                    ui.selectable_value(&mut self.whole_history, true, "whole history");
+                    ui.add(egui::Slider::new(&mut self.bins, 10..=200).text("bins"));
+                });
+                match &self.stats {
+                    Some(stats) => draw_histogram(ui, stats),
+                    None => {
+                        ui.label("Not enough bars");
+                    }
+                }
+            });
+        self.open = open;
+    }
+}
+
+fn draw_histogram(ui: &mut egui::Ui, stats: &ReturnStats) {
+    let (rect, response) = ui.allocate_exact_size(PLOT_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+    let text_color = visuals.text_color();
+    let font = FontId::proportional(10.0);
+    let plot = Rect::from_min_max(
+        rect.min + egui::vec2(4.0, 4.0),
+        rect.max - egui::vec2(4.0, 16.0),
+    );
+
+    let max_count = stats.bins.iter().copied().max().unwrap_or(0).max(1);
+    let span = stats.width * stats.bins.len() as f64;
+    let x_of = |value: f64| plot.left() + ((value - stats.from) / span) as f32 * plot.width();
+    let bar_width = plot.width() / stats.bins.len() as f32;
+    let mut hovered = None;
+    for (index, count) in stats.bins.iter().enumerate() {
+        let left = plot.left() + index as f32 * bar_width;
+        let height = *count as f32 / max_count as f32 * plot.height();
+        let bar = Rect::from_min_max(
+            egui::pos2(left, plot.bottom() - height),
+            egui::pos2(left + bar_width, plot.bottom()),
+        );
+        let low = stats.from + index as f64 * stats.width;
+        let color = if low + stats.width / 2.0 < 0.0 {
+            Color32::from_rgb(180, 80, 80)
+        } else {
+            Color32::from_rgb(80, 160, 80)
+        };
+        painter.rect_filled(bar.shrink2(egui::vec2(0.5, 0.0)), 0.0, color);
+        let column = Rect::from_x_y_ranges(bar.x_range(), plot.y_range());
+        if response.hover_pos().is_some_and(|pos| column.contains(pos)) {
+            hovered = Some((low, *count));
+        }
+    }
+
+    // среднее и ±1σ
+    let marker = |value: f64, label: &str, color: Color32| {
+        let x = x_of(value);
+        if x < plot.left() || x > plot.right() {
+            return;
+        }
+        painter.line_segment(
+            [egui::pos2(x, plot.top()), egui::pos2(x, plot.bottom())],
+            Stroke::new(1.0, color),
+        );
+        painter.text(
+            egui::pos2(x + 2.0, plot.top()),
+            Align2::LEFT_TOP,
+            label,
+            font.clone(),
+            color,
+        );
+    };
+    let sigma_color = Color32::from_rgb(120, 160, 240);
+    marker(stats.mean, "μ", Color32::from_rgb(240, 200, 80));
+    marker(stats.mean - stats.sigma, "-σ", sigma_color);
+    marker(stats.mean + stats.sigma, "+σ", sigma_color);
+
+    painter.text(
+        egui::pos2(plot.left(), rect.bottom() - 2.0),
+        Align2::LEFT_BOTTOM,
+        format!("{:+.2}%", stats.from),
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        egui::pos2(plot.right(), rect.bottom() - 2.0),
+        Align2::RIGHT_BOTTOM,
+        format!("{:+.2}%", stats.from + span),
+        font,
+        text_color,
+    );
+
+    ui.label(format!(
+        "{} returns   mean {:+.3}%   σ {:.3}%",
+        stats.count, stats.mean, stats.sigma
+    ));
+    if stats.clipped > 0 {
+        ui.label(format!(
+            "{} beyond ±{}σ counted in the edge bins",
+            stats.clipped, RETURNS_HISTOGRAM_SIGMAS
+        ));
+    }
+    if let Some((low, count)) = hovered {
+        response.on_hover_text(format!(
+            "{:+.3}% .. {:+.3}%: {} bars",
+            low,
+            low + stats.width,
+            count
+        ));
+    }
+}
@@ -57,17 +57,17 @@ impl WilderRSI {
     pub fn add_price(&mut self, timestamp: i64, close: f64) -> Option<f64> {
         // --- Валидация ввода ---
         if close.is_nan() || close.is_infinite() {
-            println!("Предупреждение: Некорректная цена закрытия ({}). Пропуск.", close);
+            tracing::warn!("Предупреждение: Некорректная цена закрытия ({}). Пропуск.", close);
             return self.get_rsi(); // Вернуть последнее извеTimestampстное значение
         }
 
         // --- Логика обработки временных меток ---
-        let is_update = self.last_timestamp.map_or(false, |last| timestamp == last);
-        let is_new_bar = self.last_timestamp.is_none() || self.last_timestamp.map_or(false, |last| timestamp > last);
-        let is_old_data = self.last_timestamp.map_or(false, |last| timestamp < last);
+        let is_update = self.last_timestamp == Some(timestamp);
+        let is_new_bar = self.last_timestamp.is_none() || self.last_timestamp.is_some_and(|last| timestamp > last);
+        let is_old_data = self.last_timestamp.is_some_and(|last| timestamp < last);
 
         if is_old_data {
-            println!("Предупреждение: Получены данные не по порядку (Timestamp {} < Последний {}). Пропуск.",
+            tracing::warn!("Предупреждение: Получены данные не по порядку (Timestamp {} < Последний {}). Пропуск.",
                 timestamp,
                 self.last_timestamp.unwrap()
             );
@@ -167,7 +167,7 @@ impl WilderRSI {
             self.price_before_last = self.last_close;
 
             // Добавляем новую цену, эмулируя maxlen=period+1
-            if self.prices.len() >= self.period + 1 {
+            if self.prices.len() > self.period {
                 self.prices.pop_front();
             }
             self.prices.push_back(close);
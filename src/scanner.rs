@@ -0,0 +1,193 @@
+// scanner.rs - Multi-symbol market scanner over the hourly aggregates of all stored symbols
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::db::{AggrPeriod, Database};
+use crate::fetch::{volume_to_f64, KLine, PRICE_MULTIPLIER};
+use crate::rsi::WilderRSI;
+use crate::settings::{
+    SCANNER_CHANGE_PERCENT, SCANNER_LOOKBACK_HOURS, SCANNER_RSI_HIGH, SCANNER_RSI_LOW,
+    SCANNER_VOLUME_MULTIPLE,
+};
+use eframe::egui::{self, Color32};
+
+const RSI_PERIOD: usize = 14;
+// hours averaged for the volume spike condition
+const VOLUME_LOOKBACK: usize = 20;
+
+/// One condition met by a symbol on its latest hourly record.
+#[derive(Debug, Clone)]
+pub struct ScanHit {
+    pub symbol: String,
+    pub condition: String,
+    pub value: f64,
+}
+
+pub struct Scanner {
+    pub open: bool,
+    pub rsi_low: f64,
+    pub rsi_high: f64,
+    pub change_percent: f64,
+    pub volume_multiple: f64,
+    hits: Vec<ScanHit>,
+    scanned: Option<usize>,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self {
+            open: false,
+            rsi_low: SCANNER_RSI_LOW,
+            rsi_high: SCANNER_RSI_HIGH,
+            change_percent: SCANNER_CHANGE_PERCENT,
+            volume_multiple: SCANNER_VOLUME_MULTIPLE,
+            hits: Vec::new(),
+            scanned: None,
+        }
+    }
+}
+
+fn price(value: u64) -> f64 {
+    value as f64 / 10f64.powi(PRICE_MULTIPLIER as i32)
+}
+
+impl Scanner {
+    /// Evaluates the conditions for every symbol in the DB.
+    pub fn run(&mut self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        let symbols = db.symbols()?;
+        let mut hits = Vec::new();
+        for symbol in &symbols {
+            let (_, last) = db.get_aggr_info(symbol, AggrPeriod::HOUR)?;
+            if last == 0 {
+                continue;
+            }
+            let from = last - (SCANNER_LOOKBACK_HOURS - 1) * AggrPeriod::HOUR.duration_ms();
+            let records = db.get_aggr_records(symbol, AggrPeriod::HOUR, from, last)?;
+            hits.extend(self.scan_symbol(symbol, &records));
+        }
+        self.hits = hits;
+        self.scanned = Some(symbols.len());
+        Ok(())
+    }
+
+    fn scan_symbol(&self, symbol: &str, records: &[KLine]) -> Vec<ScanHit> {
+        let mut hits = Vec::new();
+        let Some(last) = records.last() else {
+            return hits;
+        };
+        let hit = |condition: &str, value: f64| ScanHit {
+            symbol: symbol.to_string(),
+            condition: condition.to_string(),
+            value,
+        };
+
+        let mut rsi = WilderRSI::new(RSI_PERIOD);
+        let rsi_value = records
+            .iter()
+            .filter_map(|k| rsi.add_price(k.open_time, price(k.close)))
+            .last();
+        if let Some(value) = rsi_value {
+            if value <= self.rsi_low {
+                hits.push(hit("RSI oversold", value));
+            } else if value >= self.rsi_high {
+                hits.push(hit("RSI overbought", value));
+            }
+        }
+
+        // изменение за 24 часа
+        if records.len() > 24 {
+            let base = price(records[records.len() - 25].close);
+            if base > 0.0 {
+                let change = (price(last.close) - base) / base * 100.0;
+                if change.abs() >= self.change_percent {
+                    hits.push(hit("24h change, %", change));
+                }
+            }
+        }
+
+        if records.len() > VOLUME_LOOKBACK {
+            let previous = &records[records.len() - 1 - VOLUME_LOOKBACK..records.len() - 1];
+            let average = previous
+                .iter()
+                .map(|k| volume_to_f64(k.volume))
+                .sum::<f64>()
+                / VOLUME_LOOKBACK as f64;
+            let volume = volume_to_f64(last.volume);
+            if average > 0.0 && volume >= self.volume_multiple * average {
+                hits.push(hit("volume spike, ×", volume / average));
+            }
+        }
+        hits
+    }
+
+    /// Draws the scanner window; returns the symbol clicked to open its chart.
+    pub fn show(&mut self, ctx: &egui::Context, db: &Database) -> Option<String> {
+        let mut selected = None;
+        let mut open = self.open;
+        egui::Window::new("Market scanner")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("scanner_conditions")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("RSI(14) below / above");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.rsi_low).range(1.0..=50.0));
+                            ui.add(egui::DragValue::new(&mut self.rsi_high).range(50.0..=99.0));
+                        });
+                        ui.end_row();
+                        ui.label("24h change over, %");
+                        ui.add(
+                            egui::DragValue::new(&mut self.change_percent)
+                                .speed(0.1)
+                                .range(0.1..=100.0),
+                        );
+                        ui.end_row();
+                        ui.label("Volume over avg, ×");
+                        ui.add(
+                            egui::DragValue::new(&mut self.volume_multiple)
+                                .speed(0.1)
+                                .range(1.0..=100.0),
+                        );
+                        ui.end_row();
+                    });
+                if ui.button("scan").clicked() {
+                    if let Err(e) = self.run(db) {
+                        tracing::warn!("Scan failed: {}", e);
+                    }
+                }
+                ui.separator();
+                match self.scanned {
+                    None => {
+                        ui.label("Conditions are checked on the last hourly record");
+                    }
+                    Some(_) if self.hits.is_empty() => {
+                        ui.label("No matches");
+                    }
+                    Some(count) => {
+                        ui.label(format!("{} matches in {} symbols", self.hits.len(), count));
+                        egui::Grid::new("scanner_hits")
+                            .striped(true)
+                            .num_columns(3)
+                            .show(ui, |ui| {
+                                for hit in &self.hits {
+                                    if ui.link(&hit.symbol).clicked() {
+                                        selected = Some(hit.symbol.clone());
+                                    }
+                                    ui.label(&hit.condition);
+                                    let color = if hit.value < 0.0 {
+                                        Color32::LIGHT_RED
+                                    } else {
+                                        Color32::LIGHT_GREEN
+                                    };
+                                    ui.colored_label(color, format!("{:.2}", hit.value));
+                                    ui.end_row();
+                                }
+                            });
+                    }
+                }
+            });
+        self.open = open;
+        selected
+    }
+}
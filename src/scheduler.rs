@@ -0,0 +1,180 @@
+// scheduler.rs - Background sync scheduler keeping a list of symbols up to date
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::backup::S3Backup;
+use crate::config;
+use crate::datawindow::DataWindow;
+use crate::db::Database;
+use crate::settings::{SYNC_REQUEST_PAUSE, SYNC_STAGGER_SECS};
+use crate::timeframe::{SyncProgress, Timeframe};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Idle,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncJob {
+    pub symbol: String,
+    pub interval: Duration,
+    pub next_run: Instant,
+    pub last_success: Option<Instant>,
+    pub state: JobState,
+    /// Time range of the current (or last) run, ms.
+    pub range: Option<(i64, i64)>,
+    /// Blocks fetched in the current (or last) run.
+    pub progress: SyncProgress,
+}
+
+/// Runs `Timeframe::sync_data` for every scheduled symbol on its own interval.
+/// The first run of each symbol is a backfill of `initial_load_days` (config); later runs
+/// only fetch blocks after `last_{symbol}`. Runs are serialized on one thread and
+/// separated by `SYNC_STAGGER_SECS` to stay under the exchange rate limits.
+pub struct SyncScheduler {
+    jobs: Arc<Mutex<Vec<SyncJob>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SyncScheduler {
+    /// Starts the scheduler thread for `symbols` given as `(symbol, interval in seconds)`.
+    pub fn start(db: Database, symbols: &[(&str, u64)]) -> Self {
+        let now = Instant::now();
+        let jobs: Vec<SyncJob> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, (symbol, interval))| SyncJob {
+                symbol: symbol.to_string(),
+                interval: Duration::from_secs(*interval),
+                next_run: now + Duration::from_secs(i as u64 * SYNC_STAGGER_SECS),
+                last_success: None,
+                state: JobState::Queued,
+                range: None,
+                progress: SyncProgress::default(),
+            })
+            .collect();
+        let jobs = Arc::new(Mutex::new(jobs));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_jobs = Arc::clone(&jobs);
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::Builder::new()
+            .name("sync-scheduler".to_string())
+            .spawn(move || Self::run(db, thread_jobs, thread_stop))
+            .map_err(|e| tracing::error!("Unable to start sync scheduler: {}", e))
+            .ok();
+
+        Self { jobs, stop, handle }
+    }
+
+    /// Snapshot of the scheduled jobs for display.
+    pub fn jobs(&self) -> Vec<SyncJob> {
+        self.jobs
+            .lock()
+            .map(|jobs| jobs.clone())
+            .unwrap_or_default()
+    }
+
+    /// Moves a job to the front of the queue, e.g. after a failed run.
+    pub fn retry(&self, symbol: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if let Some(job) = jobs.iter_mut().find(|job| job.symbol == symbol) {
+                if job.state != JobState::Running {
+                    job.next_run = Instant::now();
+                    job.state = JobState::Queued;
+                }
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn run(db: Database, jobs: Arc<Mutex<Vec<SyncJob>>>, stop: Arc<AtomicBool>) {
+        // unfinished block data of each symbol between runs
+        let mut windows: HashMap<String, DataWindow> = HashMap::new();
+        let backup = S3Backup::from_settings();
+        while !stop.load(Ordering::Relaxed) {
+            let due = jobs.lock().ok().and_then(|mut jobs| {
+                let now = Instant::now();
+                let job = jobs
+                    .iter_mut()
+                    .filter(|job| job.next_run <= now)
+                    .min_by_key(|job| job.next_run)?;
+                job.state = JobState::Running;
+                job.range = None;
+                job.progress = SyncProgress::default();
+                Some(job.symbol.clone())
+            });
+            let Some(symbol) = due else {
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            };
+
+            let end_time = chrono::Utc::now().timestamp_millis();
+            let start_time = end_time
+                - chrono::Duration::days(config::get().initial_load_days).num_milliseconds();
+            let result = Timeframe::sync_data(
+                SYNC_REQUEST_PAUSE,
+                &db,
+                &symbol,
+                start_time,
+                end_time,
+                windows.entry(symbol.clone()).or_default(),
+                &mut |progress| {
+                    if let Ok(mut jobs) = jobs.lock() {
+                        if let Some(job) = jobs.iter_mut().find(|job| job.symbol == symbol) {
+                            let from = job.range.map_or(progress.current_time, |(from, _)| from);
+                            job.range = Some((from, end_time));
+                            job.progress = progress.clone();
+                        }
+                    }
+                },
+            );
+            if let (Ok(()), Some(backup)) = (&result, &backup) {
+                match backup.push(&db, &symbol) {
+                    Ok(0) => {}
+                    Ok(uploaded) => tracing::info!("Backed up {} blocks of {}", uploaded, symbol),
+                    Err(e) => tracing::warn!("Backup of {} failed: {}", symbol, e),
+                }
+            }
+
+            if let Ok(mut jobs) = jobs.lock() {
+                if let Some(job) = jobs.iter_mut().find(|job| job.symbol == symbol) {
+                    let now = Instant::now();
+                    job.next_run = now + job.interval;
+                    job.state = match result {
+                        Ok(()) => {
+                            job.last_success = Some(now);
+                            JobState::Idle
+                        }
+                        Err(e) => {
+                            tracing::warn!("Scheduled sync of {} failed: {}", symbol, e);
+                            JobState::Failed(e.to_string())
+                        }
+                    };
+                }
+            }
+            thread::sleep(Duration::from_secs(SYNC_STAGGER_SECS));
+        }
+    }
+}
+
+impl Drop for SyncScheduler {
+    fn drop(&mut self) {
+        // no join here: a running sync can take minutes
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
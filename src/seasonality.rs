@@ -0,0 +1,163 @@
+// seasonality.rs - Average intraday path of the last N days, overlaid on today's bars from today's open
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::AxisTimezone;
+use crate::datawindow::DataWindow;
+use crate::db::Database;
+use crate::drawing_util;
+use crate::fetch::KLine;
+use crate::session_levels::session_start;
+use crate::settings::SEASONALITY_DAYS;
+use eframe::egui::{self, Align2, Color32, FontId, Stroke};
+use std::error::Error;
+
+const DAY_MS: i64 = 86_400_000;
+const DAY_MINUTES: usize = 1440;
+
+/// Mean close of every minute of the day relative to the day's first open, %;
+/// `None` for minutes no day had data for.
+pub fn average_day_path(klines: &[KLine], time_zone: AxisTimezone) -> Vec<Option<f64>> {
+    let mut sums = vec![(0.0, 0usize); DAY_MINUTES];
+    let mut day = None;
+    for kline in klines {
+        let start = session_start(time_zone, 0, kline.open_time);
+        if day.is_none_or(|(day_start, _)| day_start != start) {
+            day = Some((start, kline.open));
+        }
+        let Some((day_start, anchor)) = day.filter(|(_, anchor)| *anchor > 0) else {
+            continue;
+        };
+        // дни перехода на летнее время длиннее 24 часов
+        let minute = (((kline.open_time - day_start) / 60_000) as usize).min(DAY_MINUTES - 1);
+        sums[minute].0 += (kline.close as f64 / anchor as f64 - 1.0) * 100.0;
+        sums[minute].1 += 1;
+    }
+    sums.into_iter()
+        .map(|(sum, count)| (count > 0).then(|| sum / count as f64))
+        .collect()
+}
+
+// (symbol, today's start, days, timezone)
+type PathKey = (String, i64, i64, AxisTimezone);
+
+pub struct SeasonalityOverlay {
+    pub show: bool,
+    pub days: i64,
+    path: Vec<Option<f64>>,
+    computed_for: Option<PathKey>,
+}
+
+impl Default for SeasonalityOverlay {
+    fn default() -> Self {
+        Self {
+            show: false,
+            days: SEASONALITY_DAYS,
+            path: Vec::new(),
+            computed_for: None,
+        }
+    }
+}
+
+impl SeasonalityOverlay {
+    /// Recomputes the average path from the 1m data of the `days` days before today,
+    /// once per day, symbol or timezone.
+    pub fn refresh(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        data_window: &DataWindow,
+        time_zone: AxisTimezone,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.show {
+            return Ok(());
+        }
+        let today = session_start(time_zone, 0, chrono::Utc::now().timestamp_millis());
+        let key = (symbol.to_string(), today, self.days, time_zone);
+        if self.computed_for.as_ref() == Some(&key) {
+            return Ok(());
+        }
+        self.computed_for = Some(key);
+        let klines =
+            data_window.load_minute_klines(db, symbol, today - self.days * DAY_MS, today - 1)?;
+        self.path = average_day_path(&klines, time_zone);
+        Ok(())
+    }
+
+    /// Path from the open of today's first bar through the rest of the day, including
+    /// the empty slots right of the last bar. Only for intraday timeframes.
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        data_window: &DataWindow,
+        scale_price: &impl Fn(f64) -> f32,
+        timeframe_minutes: i32,
+        font: &FontId,
+    ) {
+        let Some((_, today, ..)) = self.computed_for else {
+            return;
+        };
+        if !self.show || self.path.is_empty() || timeframe_minutes >= DAY_MINUTES as i32 {
+            return;
+        }
+        let bars = &data_window.bars;
+        let first = bars.partition_point(|b| b.time < today);
+        let (Some(anchor), Some(last)) = (bars.get(first).map(|b| b.open), bars.last()) else {
+            return;
+        };
+        let (start, end) = data_window.visible_range;
+        if start < 0 || start >= end {
+            return;
+        }
+        let (start, end) = (start as usize, end as usize);
+        let bar_ms = timeframe_minutes as i64 * 60_000;
+        let mut points = Vec::new();
+        for index in first.max(start)..end {
+            // правее последнего бара - будущие бары того же таймфрейма
+            let time = bars.get(index).map_or_else(
+                || last.time + (index + 1 - bars.len()) as i64 * bar_ms,
+                |b| b.time,
+            );
+            if time >= today + DAY_MS {
+                break;
+            }
+            let minute = ((time - today + bar_ms) / 60_000 - 1).clamp(0, DAY_MINUTES as i64 - 1);
+            let Some(change) = self.path[minute as usize] else {
+                continue;
+            };
+            let x = drawing_util::calculate_bar_center_x(
+                index - start,
+                end - start,
+                rect,
+                data_window.pixel_offset,
+            );
+            points.push(egui::pos2(x, scale_price(anchor * (1.0 + change / 100.0))));
+        }
+        if points.len() < 2 {
+            return;
+        }
+        let color = Color32::from_rgb(200, 140, 255);
+        let painter = ui.painter_at(rect);
+        let label_pos = points[points.len() - 1] + egui::vec2(4.0, 0.0);
+        painter.add(egui::Shape::line(
+            points,
+            Stroke::new(1.5, color.gamma_multiply(0.8)),
+        ));
+        painter.text(
+            label_pos,
+            Align2::LEFT_CENTER,
+            format!("avg {}d", self.days),
+            font.clone(),
+            color,
+        );
+    }
+
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show, "Average day path");
+        ui.horizontal(|ui| {
+            ui.label("Days");
+            ui.add(egui::DragValue::new(&mut self.days).range(1..=365));
+        });
+        ui.label("Normalized to today's open, intraday timeframes only");
+    }
+}
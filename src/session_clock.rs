@@ -0,0 +1,185 @@
+// session_clock.rs - Clock window with the current time in several timezones and open/closed market sessions
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::AxisTimezone;
+use crate::settings::SESSION_CLOCKS;
+use chrono::{Datelike, Timelike, Weekday};
+use eframe::egui::{self, Color32};
+
+const DAY_MINUTES: u32 = 1440;
+
+/// A timezone of the clock, optionally with a weekday trading session in local time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockZone {
+    pub name: String,
+    pub timezone: AxisTimezone,
+    pub open_minutes: u32,
+    pub close_minutes: u32,
+}
+
+fn is_weekday(day: Weekday) -> bool {
+    !matches!(day, Weekday::Sat | Weekday::Sun)
+}
+
+impl ClockZone {
+    pub fn has_session(&self) -> bool {
+        self.open_minutes != self.close_minutes
+    }
+
+    /// Minutes of the local day inside the session; sessions may cross midnight.
+    fn in_session(&self, minute: u32) -> bool {
+        if self.open_minutes < self.close_minutes {
+            minute >= self.open_minutes && minute < self.close_minutes
+        } else {
+            minute >= self.open_minutes || minute < self.close_minutes
+        }
+    }
+
+    /// `(open, minutes until the next open or close)` at `now_ms`; `None` without a
+    /// session. Sessions run Monday to Friday by their opening day.
+    pub fn session_state(&self, now_ms: i64) -> Option<(bool, u32)> {
+        if !self.has_session() {
+            return None;
+        }
+        let local = self.timezone.datetime(now_ms);
+        let minute = local.hour() * 60 + local.minute();
+        let weekday = local.weekday();
+        // ночная сессия после полуночи принадлежит вчерашнему дню
+        let session_day = if self.open_minutes > self.close_minutes && minute < self.close_minutes {
+            weekday.pred()
+        } else {
+            weekday
+        };
+        if is_weekday(session_day) && self.in_session(minute) {
+            let left = (self.close_minutes + DAY_MINUTES - minute) % DAY_MINUTES;
+            return Some((true, left));
+        }
+        let mut day = weekday;
+        for days_ahead in 0..8 {
+            if is_weekday(day) && (days_ahead > 0 || minute < self.open_minutes) {
+                return Some((false, days_ahead * DAY_MINUTES + self.open_minutes - minute));
+            }
+            day = day.succ();
+        }
+        None
+    }
+}
+
+fn format_duration(minutes: u32) -> String {
+    match (
+        minutes / DAY_MINUTES,
+        minutes % DAY_MINUTES / 60,
+        minutes % 60,
+    ) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {:02}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+pub struct SessionClock {
+    pub open: bool,
+    pub zones: Vec<ClockZone>,
+    new_zone: String,
+}
+
+impl Default for SessionClock {
+    fn default() -> Self {
+        let zones = SESSION_CLOCKS
+            .iter()
+            .filter_map(|(name, timezone, open_minutes, close_minutes)| {
+                let zone = AxisTimezone::parse(timezone);
+                if zone.is_none() {
+                    tracing::warn!("Unknown clock timezone: {}", timezone);
+                }
+                Some(ClockZone {
+                    name: name.to_string(),
+                    timezone: zone?,
+                    open_minutes: *open_minutes,
+                    close_minutes: *close_minutes,
+                })
+            })
+            .collect();
+        Self {
+            open: false,
+            zones,
+            new_zone: String::new(),
+        }
+    }
+}
+
+impl SessionClock {
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut remove = None;
+        egui::Window::new("Session clock")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(ctx.screen_rect().right_top() + egui::vec2(-260.0, 60.0))
+            .show(ctx, |ui| {
+                egui::Grid::new("session_clock")
+                    .num_columns(4)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        for (index, zone) in self.zones.iter().enumerate() {
+                            ui.label(&zone.name);
+                            ui.monospace(
+                                zone.timezone.datetime(now).format("%H:%M:%S").to_string(),
+                            );
+                            match zone.session_state(now) {
+                                Some((true, left)) => {
+                                    ui.colored_label(Color32::LIGHT_GREEN, "open")
+                                        .on_hover_text(format!(
+                                            "closes in {}",
+                                            format_duration(left)
+                                        ));
+                                }
+                                Some((false, left)) => {
+                                    ui.colored_label(Color32::GRAY, "closed").on_hover_text(
+                                        format!("opens in {}", format_duration(left)),
+                                    );
+                                }
+                                None => {
+                                    ui.label("");
+                                }
+                            }
+                            if ui.small_button("×").clicked() {
+                                remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.new_zone)
+                            .hint_text("Europe/Berlin")
+                            .desired_width(140.0),
+                    );
+                    let timezone = AxisTimezone::parse(&self.new_zone);
+                    if ui
+                        .add_enabled(timezone.is_some(), egui::Button::new("add"))
+                        .clicked()
+                    {
+                        if let Some(timezone) = timezone {
+                            self.zones.push(ClockZone {
+                                name: timezone.name(),
+                                timezone,
+                                open_minutes: 0,
+                                close_minutes: 0,
+                            });
+                            self.new_zone.clear();
+                        }
+                    }
+                });
+            });
+        if let Some(index) = remove {
+            self.zones.remove(index);
+        }
+        self.open = open;
+        if self.open {
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    }
+}
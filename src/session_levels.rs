@@ -0,0 +1,207 @@
+// session_levels.rs - Reference price levels (day/week high/low, previous session close) drawn across the chart
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::AxisTimezone;
+use crate::datawindow::DataWindow;
+use crate::db::{AggrPeriod, Database};
+use crate::fetch::{KLine, PRICE_MULTIPLIER};
+use crate::settings::{AXIS_TIMEZONE_PRESETS, SESSION_START_MINUTES, SESSION_TIMEZONE};
+use eframe::egui::{self, Align2, Color32, FontId, Stroke};
+use std::error::Error;
+
+const DAY: AggrPeriod = AggrPeriod { minutes: 1440 };
+const WEEK: AggrPeriod = AggrPeriod { minutes: 10080 };
+const DAY_MS: i64 = 86_400_000;
+
+fn high_low<'a>(klines: impl Iterator<Item = &'a KLine>) -> Option<(f64, f64)> {
+    let divider = 10f64.powi(PRICE_MULTIPLIER as i32);
+    klines
+        .fold(None, |acc: Option<(u64, u64)>, k| match acc {
+            Some((high, low)) => Some((high.max(k.high), low.min(k.low))),
+            None => Some((k.high, k.low)),
+        })
+        .map(|(high, low)| (high as f64 / divider, low as f64 / divider))
+}
+
+/// Start of the session containing `timestamp_ms` for sessions starting daily at
+/// `start_minutes` after midnight in `timezone`.
+pub fn session_start(timezone: AxisTimezone, start_minutes: u32, timestamp_ms: i64) -> i64 {
+    let offset = timezone.offset_ms(timestamp_ms);
+    let local = timestamp_ms + offset;
+    let mut start = local - local.rem_euclid(DAY_MS) + start_minutes as i64 * 60_000;
+    if start > local {
+        start -= DAY_MS;
+    }
+    start - offset
+}
+
+// (symbol, last bar time, last bar close bits, session timezone, session start)
+type LevelsKey = (String, i64, u64, AxisTimezone, u32);
+
+pub struct SessionLevels {
+    pub show_daily: bool,
+    pub show_weekly: bool,
+    pub show_prev_close: bool,
+    /// Session definition for the previous close line.
+    pub session_timezone: AxisTimezone,
+    pub session_start_minutes: u32,
+    /// Running (high, low) of the current UTC day and week.
+    day: Option<(f64, f64)>,
+    week: Option<(f64, f64)>,
+    prev_close: Option<f64>,
+    computed_for: Option<LevelsKey>,
+}
+
+impl Default for SessionLevels {
+    fn default() -> Self {
+        Self {
+            show_daily: false,
+            show_weekly: false,
+            show_prev_close: false,
+            session_timezone: AxisTimezone::parse(SESSION_TIMEZONE).unwrap_or(AxisTimezone::Utc),
+            session_start_minutes: SESSION_START_MINUTES,
+            day: None,
+            week: None,
+            prev_close: None,
+            computed_for: None,
+        }
+    }
+}
+
+impl SessionLevels {
+    /// Recomputes the levels from the hourly and daily tiers, completed with 1m data
+    /// after the last hourly record, when the bars changed.
+    pub fn refresh(
+        &mut self,
+        db: &Database,
+        symbol: &str,
+        data_window: &DataWindow,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.show_daily && !self.show_weekly && !self.show_prev_close {
+            return Ok(());
+        }
+        let last = data_window.bars.last();
+        let key = (
+            symbol.to_string(),
+            last.map_or(0, |b| b.time),
+            last.map_or(0, |b| b.close.to_bits()),
+            self.session_timezone,
+            self.session_start_minutes,
+        );
+        if self.computed_for.as_ref() == Some(&key) {
+            return Ok(());
+        }
+        self.computed_for = Some(key);
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let day_start = DAY.bucket_start(now);
+        let week_start = WEEK.bucket_start(now);
+        let hours = db.get_aggr_records(symbol, AggrPeriod::HOUR, day_start, now)?;
+        let tail_start = hours
+            .last()
+            .map_or(day_start, |k| k.open_time + AggrPeriod::HOUR.duration_ms());
+        let minutes = data_window.load_minute_klines(db, symbol, tail_start, now)?;
+        let days = db.get_aggr_records(symbol, DAY, week_start, day_start - 1)?;
+
+        self.day = high_low(hours.iter().chain(&minutes));
+        self.week = high_low(days.iter().chain(&hours).chain(&minutes));
+
+        // последняя минута перед началом текущей сессии (с запасом на выходные)
+        let start = session_start(self.session_timezone, self.session_start_minutes, now);
+        self.prev_close = data_window
+            .load_minute_klines(db, symbol, start - 3 * DAY_MS, start - 1)?
+            .last()
+            .map(|k| k.close as f64 / 10f64.powi(PRICE_MULTIPLIER as i32));
+        Ok(())
+    }
+
+    pub fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        scale_price: &impl Fn(f64) -> f32,
+        font: &FontId,
+        decimals: usize,
+    ) {
+        let painter = ui.painter();
+        let mut lines = Vec::new();
+        if self.show_daily {
+            if let Some((high, low)) = self.day {
+                lines.push((high, "D high", Color32::from_rgb(120, 180, 240)));
+                lines.push((low, "D low", Color32::from_rgb(120, 180, 240)));
+            }
+        }
+        if self.show_weekly {
+            if let Some((high, low)) = self.week {
+                lines.push((high, "W high", Color32::from_rgb(200, 150, 240)));
+                lines.push((low, "W low", Color32::from_rgb(200, 150, 240)));
+            }
+        }
+        if self.show_prev_close {
+            if let Some(close) = self.prev_close {
+                let y = scale_price(close);
+                if y >= rect.top() && y <= rect.bottom() {
+                    let color = Color32::from_rgb(220, 220, 140);
+                    painter.extend(egui::Shape::dashed_line(
+                        &[egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                        Stroke::new(1.0, color.gamma_multiply(0.7)),
+                        6.0,
+                        4.0,
+                    ));
+                    painter.text(
+                        egui::pos2(rect.right() - 4.0, y - 2.0),
+                        Align2::RIGHT_BOTTOM,
+                        format!(
+                            "prev close {}",
+                            crate::axes_util::format_price(close, decimals)
+                        ),
+                        font.clone(),
+                        color,
+                    );
+                }
+            }
+        }
+        for (price, label, color) in lines {
+            let y = scale_price(price);
+            if y < rect.top() || y > rect.bottom() {
+                continue;
+            }
+            painter.line_segment(
+                [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                Stroke::new(1.0, color.gamma_multiply(0.6)),
+            );
+            painter.text(
+                egui::pos2(rect.right() - 4.0, y - 2.0),
+                Align2::RIGHT_BOTTOM,
+                label,
+                font.clone(),
+                color,
+            );
+        }
+    }
+
+    pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show_daily, "Day high/low (UTC)");
+        ui.checkbox(&mut self.show_weekly, "Week high/low (UTC)");
+        ui.separator();
+        ui.checkbox(&mut self.show_prev_close, "Previous session close");
+        ui.horizontal(|ui| {
+            ui.label("Session starts");
+            let mut hour = self.session_start_minutes / 60;
+            let mut minute = self.session_start_minutes % 60;
+            ui.add(egui::DragValue::new(&mut hour).range(0..=23));
+            ui.label(":");
+            ui.add(egui::DragValue::new(&mut minute).range(0..=59));
+            self.session_start_minutes = hour * 60 + minute;
+            egui::ComboBox::from_id_salt("session_timezone")
+                .selected_text(self.session_timezone.name())
+                .show_ui(ui, |ui| {
+                    for name in AXIS_TIMEZONE_PRESETS {
+                        if let Some(tz) = AxisTimezone::parse(name) {
+                            ui.selectable_value(&mut self.session_timezone, tz, name);
+                        }
+                    }
+                });
+        });
+    }
+}
@@ -1,17 +1,225 @@
 // settings.rs - Project constants, versions, and configuration
 // See CONVENTIONS.md for project structure and workflow
 
+// Значения по умолчанию для CONFIG_FILE: в коде читаются через config::get()
+pub const CONFIG_FILE: &str = "n-ohlcv.toml"; // Файл настроек, создается с умолчаниями при первом запуске (в командной строке - --config)
+pub const DEFAULT_SYMBOL: &str = "BTCUSDT"; // Символ графика при запуске
+pub const DEFAULT_TIMEFRAME: i32 = 15; // Таймфрейм графика при запуске (минут)
+pub const WINDOW_SIZE: [f32; 2] = [1280.0, 800.0]; // Размер окна, пока WINDOW_FILE не запомнил свой
+pub const WINDOW_FULLSCREEN: bool = true; // Полноэкранный режим, пока WINDOW_FILE не запомнил свой
 pub const ZOOM_SENSITIVITY: f64 = 0.05;
 pub const DRAG_SENSITIVITY: f64 = 1.0;
 pub const CHART_MARGIN: f32 = 0.0;
 pub const CHART_BOTTOM_MARGIN: f32 = 5.0;
-pub const PRICE_FRACTION_THRESHOLD: f64 = 0.01; // 1% порог для отображения дробной части
 pub const BAR_SPACING: f32 = 1.0; // расстояние между барами
-pub const INITIAL_LOAD_DAYS: i64 = 15; // Количество дней для начальной загрузки данных
+pub const INITIAL_LOAD_DAYS: i64 = 15; // Количество дней для начальной загрузки данных (по умолчанию, см. CONFIG_FILE)
+pub const MAX_CHART_BARS: usize = 20_000; // Если диапазон дает больше баров, они строятся из старшего тира агрегации, а не из блоков 1m
 pub const AVERAGE_FRAME_HISTORY_SIZE: usize = 60; // Количество кадров на значение (avg)
 pub const STATUS_MESSAGE_MAX_COUNT: usize = 8; // Максимальное количество сообщений в списке статуса
 pub const STATUS_MESSAGE_HIDE_TIME: u64 = 5;
+pub const AXIS_TIMEZONE_PRESETS: [&str; 6] = [
+    "Local",
+    "UTC",
+    "America/New_York",
+    "Europe/London",
+    "Asia/Tokyo",
+    "Asia/Hong_Kong",
+]; // Часовые пояса в выпадающем списке оси времени
+pub const DB_PATH: &str = "ohlcv_db"; // Папка базы sled (по умолчанию, см. CONFIG_FILE; в командной строке - --db)
+pub const BLOCK_CODEC: &str = "lzma"; // Кодек новых блоков: "lzma", "zstd" (нужна feature zstd) или "none" (по умолчанию, см. CONFIG_FILE)
+pub const BLOCK_SIZE: usize = 1000; // Минут в одном блоке БД (при изменении блоки перестраиваются)
+pub const DB_CACHE_CAPACITY: u64 = 4 * 1024 * 1024; // Кэш sled в байтах (для долгоработающего коллектора стоит увеличить)
+pub const DB_USE_COMPRESSION: bool = false; // zstd сжатие внутри sled (нужна feature sled-compression, для существующей БД не меняется)
+pub const DB_FLUSH_INTERVAL_MS: Option<u64> = Some(1000); // Период сброса sled на диск, None - только при выходе
+pub const ENCRYPTION_PASSPHRASE_ENV: &str = "N_OHLCV_PASSPHRASE"; // Переменная окружения с паролем шифрования БД (пусто - без шифрования)
+
+// Резервная копия в S3-совместимое хранилище (пустой endpoint - выключено)
+pub const BACKUP_ENDPOINT: &str = ""; // Например "https://s3.eu-central-1.amazonaws.com" или адрес MinIO
+pub const BACKUP_BUCKET: &str = "n-ohlcv";
+pub const BACKUP_REGION: &str = "us-east-1";
+pub const BACKUP_ACCESS_KEY_ENV: &str = "N_OHLCV_S3_ACCESS_KEY"; // Переменные окружения с ключами доступа
+pub const BACKUP_SECRET_KEY_ENV: &str = "N_OHLCV_S3_SECRET_KEY";
+pub const EXCHANGE: &str = "binance"; // Источник свечей: "binance", "bybit" или "coinbase" (символы в БД без имени биржи - одна БД на одну биржу)
+pub const BYBIT_API_URL: &str = "https://api.bybit.com"; // API Bybit v5 (спот)
+pub const COINBASE_API_URL: &str = "https://api.exchange.coinbase.com"; // Публичное API Coinbase Exchange, символы вида BTC-USD
+pub const BINANCE_API_URLS: [&str; 4] = [
+    "https://api.binance.com",
+    "https://api1.binance.com",
+    "https://api2.binance.com",
+    "https://api3.binance.com",
+]; // Адреса API, первый - основной, остальные резервные
+pub const BINANCE_STREAM_URL: &str = "wss://stream.binance.com:9443/stream"; // Адрес комбинированных потоков (несколько потоков в одном соединении)
+pub const BINANCE_FUTURES_STREAM_URL: &str = "wss://fstream.binance.com/stream"; // Комбинированные потоки фьючерсов USD-M (символы F:...)
+pub const SHOW_BOOK_TICKER: bool = true; // Лучшие bid/ask активного символа в заголовке
+pub const SHOW_TRADE_TAPE: bool = false; // Лента последних сделок справа от графика
+pub const TRADE_TAPE_SIZE: usize = 100; // Сколько последних сделок хранится для ленты
+pub const SHOW_WATCHLIST: bool = false; // Список наблюдения слева от графика при запуске
+pub const WATCHLIST_POLL_SECS: u64 = 30; // Интервал запроса цен и изменения за 24ч для списка наблюдения
+pub const LIVE_UPDATE: bool = true; // Поток 1m свечей активного символа по websocket: минуты сохраняются, последний бар обновляется в реальном времени
+pub const FRESHNESS_GRACE_SECS: u64 = 120; // Запас сверх интервала обновления, после которого данные считаются устаревшими
+pub const RECENT_SAVE_INTERVAL: u64 = 30; // Сохранение незавершенного хвоста блока (recent_data) в БД не чаще раза в N сек
+pub const UPDATE_INTERVAL: u64 = 60; // Интервал запроса последних минут (сек), не больше 5-минутного окна запроса
+pub const API_TIMEOUT_SECS: u64 = 10; // Таймаут одного запроса к API
+pub const SYMBOL_INFO_MAX_AGE_DAYS: i64 = 7; // Метаданные символа (шаг цены и лота) перезапрашиваются, если старше
+pub const ANOMALY_SPIKE_RATIO: f64 = 0.2; // Проверка данных: тень или разрыв open/close больше этой доли цены считается выбросом
+pub const ANOMALY_ZERO_VOLUME_MINUTES: usize = 30; // Проверка данных: столько минут подряд без объема считается аномалией
+pub const ALERT_HISTORY_LIMIT: usize = 500; // Сколько последних сработавших алертов хранится в истории
+pub const API_FAILOVER_ERRORS: u32 = 3; // Ошибок подряд до переключения на следующий адрес API
+pub const FUTURES_API_URL: &str = "https://fapi.binance.com"; // API фьючерсов USD-M (ставка финансирования, открытый интерес)
+pub const DERIVATIVES_HISTORY_DAYS: i64 = 30; // Дней истории funding/OI при первой загрузке (OI Binance хранит 30 дней)
+pub const DERIVATIVES_OI_PERIOD: &str = "1h"; // Период точек открытого интереса
+pub const DERIVATIVES_SYNC_SECS: u64 = 300; // Интервал обновления funding/OI при открытой панели
+pub const DERIVATIVES_PANE_RATIO: f32 = 0.2; // Доля высоты графика под панель funding/OI
+pub const DERIVATIVES_VERSION: i64 = 1792080000; // Версия точек funding/OI (Unix timestamp 15 Oct 2026 16:00 UTC), при смене сохраненные точки загружаются заново
+pub const RSI_PERIOD: usize = 14; // Период RSI баров графика
+pub const SHOW_RSI_PANE: bool = false; // Панель RSI под объемами при запуске
+pub const INDICATOR_PANE_RATIO: f32 = 0.15; // Доля высоты графика под каждую панель индикатора
+pub const MOVING_AVERAGES: [(usize, [u8; 3]); 3] = [
+    (20, [230, 200, 80]),
+    (50, [90, 200, 200]),
+    (200, [230, 120, 170]),
+]; // EMA на графике: период, цвет линии
+pub const SHOW_MOVING_AVERAGES: bool = false; // Линии EMA включены при запуске
+pub const VWAP_SESSION_HOURS: usize = 24; // Длина сессии VWAP, часы (24 - сброс каждый день)
+pub const VWAP_SESSION_START_HOUR: i64 = 0; // Начало сессии VWAP, час UTC
+pub const SHOW_SESSION_VWAP: bool = false; // Сессионный VWAP включен при запуске
+pub const PRICE_BAR_PERCENT: f64 = 0.5; // Размер кирпича Renko / диапазона range-баров по умолчанию, % цены
+pub const SHOW_VISIBLE_PROFILE: bool = false; // Профиль объема видимых баров у правого края графика
+pub const VISIBLE_PROFILE_BINS: usize = 60; // Число ценовых уровней профиля видимых баров
+pub const VISIBLE_PROFILE_WIDTH: f32 = 0.2; // Доля ширины графика под профиль видимых баров
+pub const FUTURES_WS_URL: &str = "wss://fstream.binance.com/ws"; // Адрес потоков websocket фьючерсов
+pub const LIQUIDATION_MIN_NOTIONAL: f64 = 10_000.0; // Ликвидации меньшего объема (в валюте котировки) не показываются
+pub const EXPORT_DIR: &str = "export"; // Папка для экспортируемых файлов
+pub const SVG_EXPORT_SIZE: [f32; 2] = [1200.0, 600.0]; // Размер SVG графика в пунктах (единицах SVG)
+pub const REPORT_IMAGE_SIZE: [usize; 2] = [1600, 760]; // Размер изображения графика в PDF отчете, пиксели
+pub const RESTORE_UI_STATE: bool = true; // Восстанавливать символ, таймфрейм, видимый диапазон и вид графика прошлого сеанса (--symbol/--timeframe имеют приоритет)
+pub const WINDOW_FILE: &str = "window.json"; // Положение и размер окна, сохраняются при выходе и восстанавливаются при запуске
+pub const WINDOW_MONITOR_ORIGIN: Option<(f32, f32)> = None; // Левый верхний угол монитора для окна, например (1920.0, 0.0) - второй справа; None - запомненное положение
+pub const THEME_FILE: &str = "theme.json"; // Файл темы (цвета графика), загружается при запуске, если есть
+pub const COMPACT_LAYOUT_WIDTH: f32 = 900.0; // Окно уже этого (в пунктах): панель инструментов в меню, без панели funding/OI и bid/ask
+pub const COMPACT_TOUCH_SIZE: f32 = 36.0; // Высота кнопок в компактном режиме, пункты
+pub const CHART_FONT_SIZE: f32 = 10.0; // Размер шрифта подписей на графике по умолчанию
+pub const CHART_FONT_SIZE_RANGE: (f32, f32) = (8.0, 24.0); // Допустимый размер шрифта графика
+pub const UI_SCALE_RANGE: (f32, f32) = (0.5, 3.0); // Допустимый масштаб интерфейса (множитель pixels_per_point)
+pub const EVENTS_FILE: &str = "events.json"; // Файл событий для флажков на оси времени (JSON или CSV: время, подпись)
+pub const HOT_RELOAD_INTERVAL_SECS: u64 = 2; // Как часто проверять изменения файлов темы, событий и настроек на диске, 0 - не следить
+pub const SYNC_SYMBOLS: [(&str, u64); 2] = [("BTCUSDT", 300), ("ETHUSDT", 600)]; // Символы фоновой синхронизации и интервал обновления (сек)
+pub const SYNC_STAGGER_SECS: u64 = 10; // Пауза между запусками синхронизации разных символов
+pub const SYNC_REQUEST_PAUSE: u64 = 3; // Пауза между запросами к API внутри одной синхронизации (сек)
+pub const HISTORY_CHUNK_DAYS: i64 = 15; // Сколько дней истории догружать за один запрос глубже INITIAL_LOAD_DAYS
+pub const HISTORY_AUTO_LOAD: bool = true; // Догружать историю при прокрутке графика за левый край
+pub const SYNC_BATCH_BLOCKS: usize = 50; // Сколько блоков записывать одной транзакцией при синхронизации
+pub const REFETCH_CORRUPTED_BLOCKS: bool = true; // Перезагружать блоки с неверной контрольной суммой
+pub const MAGNET_DISTANCE: f32 = 12.0; // Режим магнита: расстояние в пикселях, с которого якорь притягивается к OHLC бара
+pub const VOLUME_PROFILE_BINS: usize = 48; // Количество ценовых уровней в профиле объема
+pub const HIGHLIGHT_VOLUME_MULTIPLE: f64 = 3.0; // Подсветка: объем больше среднего во столько раз
+pub const HIGHLIGHT_VOLUME_LOOKBACK: usize = 20; // Подсветка: баров для среднего объема
+pub const HIGHLIGHT_RANGE_PERCENT: f64 = 2.0; // Подсветка: диапазон бара больше % от открытия
+pub const SESSION_TIMEZONE: &str = "UTC"; // Часовой пояс сессии для линии закрытия предыдущей сессии
+pub const SESSION_START_MINUTES: u32 = 0; // Начало сессии в минутах от полуночи (например 570 = 9:30)
+pub const TIMEFRAME_HOTKEYS: [i32; 9] = [1, 3, 5, 15, 30, 60, 240, 1440, 10080]; // Таймфреймы (минуты) на клавишах 1-9
+pub const TIMEFRAME_HOTKEY_DELAY_MS: u64 = 700; // Ожидание второй цифры перед переключением по клавише 1-9
+pub const SESSION_CLOCKS: [(&str, &str, u32, u32); 4] = [
+    ("UTC", "UTC", 0, 0),
+    ("London", "Europe/London", 480, 990),
+    ("New York", "America/New_York", 570, 960),
+    ("Tokyo", "Asia/Tokyo", 540, 900),
+]; // Часы сессий: название, часовой пояс, открытие и закрытие в минутах местного времени (равны - без сессии)
+pub const SYMBOL_CALENDARS: [(&str, &str); 2] = [("SPY", "NYSE"), ("ES", "CME Globex")]; // Торговый календарь символа (24/7, NYSE, CME Globex); остальные - 24/7
+pub const US_MARKET_HOLIDAYS: [&str; 21] = [
+    "2025-01-01",
+    "2025-01-09",
+    "2025-01-20",
+    "2025-02-17",
+    "2025-04-18",
+    "2025-05-26",
+    "2025-06-19",
+    "2025-07-04",
+    "2025-09-01",
+    "2025-11-27",
+    "2025-12-25",
+    "2026-01-01",
+    "2026-01-19",
+    "2026-02-16",
+    "2026-04-03",
+    "2026-05-25",
+    "2026-06-19",
+    "2026-07-03",
+    "2026-09-07",
+    "2026-11-26",
+    "2026-12-25",
+]; // Праздники NYSE/CME, обновлять ежегодно
+pub const PRICE_GAP_PERCENT: f64 = 0.5; // Разрыв между закрытием и следующим открытием, % для отметки на графике
+pub const SCANNER_LOOKBACK_HOURS: i64 = 200; // Сканер: часовых записей на символ для расчета условий
+pub const SCANNER_RSI_LOW: f64 = 30.0; // Сканер: RSI перепроданности
+pub const SCANNER_RSI_HIGH: f64 = 70.0; // Сканер: RSI перекупленности
+pub const SCANNER_CHANGE_PERCENT: f64 = 5.0; // Сканер: изменение за 24 часа, %
+pub const SCANNER_VOLUME_MULTIPLE: f64 = 3.0; // Сканер: объем последнего часа больше среднего во столько раз
+pub const RETURNS_HISTOGRAM_BINS: usize = 50; // Число столбцов гистограммы доходностей
+pub const SEASONALITY_DAYS: i64 = 20; // Средний внутридневной путь: число предыдущих дней
+pub const CORRELATION_HOURS: i64 = 720; // Матрица корреляций: окно часовых доходностей, часов
+pub const HEATMAP_DAYS: i64 = 90; // Тепловая карта час/день недели: сколько последних дней 1m данных учитывать
+pub const RETURNS_HISTOGRAM_SIGMAS: f64 = 4.0; // Гистограмма доходностей: диапазон ±N сигм, дальше - в крайние столбцы
+pub const LOG_LEVEL: &str = "debug"; // Уровень записей, сохраняемых для окна логов (error, warn, info, debug, trace)
+pub const LOG_CONSOLE_LEVEL: &str = "info"; // Уровень записей, выводимых в консоль и файл
+pub const LOG_FILE: &str = "n-ohlcv.log"; // Файл лога для долгой работы сборщика, пустая строка - не писать
+pub const LOG_MAX_LINES: usize = 5000; // Окно логов: сколько последних записей хранить в памяти
 
 // Версия агрегации OHLCV данных - дата создания функции (до минуты)
-// Обновлено: 25 Aug 2025 14:36
-pub const AGGREGATION_VERSION: i64 = 1724587016; // Unix timestamp для 25 Aug 2025 14:36:56 UTC
+// Обновлено: 15 Oct 2026 14:00 (уровни 4h/1d/1w, выравнивание по UTC)
+pub const AGGREGATION_VERSION: i64 = 1792072800; // Unix timestamp для 15 Oct 2026 14:00:00 UTC
+                                                 // Периоды уровней агрегации в минутах; 60 должен быть первым - из него строятся остальные
+pub const AGGREGATION_PERIODS: [i64; 4] = [60, 240, 1440, 10080];
+
+// Формат подписей времени по умолчанию
+pub const TIME_LABEL_12_HOUR: bool = false;
+pub const TIME_LABEL_MONTH_FIRST: bool = false;
+pub const TIME_LABEL_SHOW_SECONDS: bool = true;
+
+/// User-adjustable format of time axis tick labels and the crosshair time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLabelFormat {
+    pub hour12: bool,
+    pub month_first: bool,
+    pub show_seconds: bool,
+}
+
+impl Default for TimeLabelFormat {
+    fn default() -> Self {
+        Self {
+            hour12: TIME_LABEL_12_HOUR,
+            month_first: TIME_LABEL_MONTH_FIRST,
+            show_seconds: TIME_LABEL_SHOW_SECONDS,
+        }
+    }
+}
+
+impl TimeLabelFormat {
+    /// strftime pattern for a time of day.
+    pub fn time_pattern(&self, with_seconds: bool) -> &'static str {
+        match (self.hour12, with_seconds && self.show_seconds) {
+            (false, false) => "%H:%M",
+            (false, true) => "%H:%M:%S",
+            (true, false) => "%I:%M %p",
+            (true, true) => "%I:%M:%S %p",
+        }
+    }
+
+    /// strftime pattern for a day with abbreviated month name.
+    pub fn day_month_pattern(&self) -> &'static str {
+        if self.month_first {
+            "%b %d"
+        } else {
+            "%d %b"
+        }
+    }
+
+    /// strftime pattern for a numeric day and month.
+    pub fn numeric_day_month_pattern(&self) -> &'static str {
+        if self.month_first {
+            "%-m/%-d"
+        } else {
+            "%-d.%-m"
+        }
+    }
+}
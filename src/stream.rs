@@ -0,0 +1,170 @@
+// stream.rs - Live 1m klines of the chart symbol from the Binance `@kline_1m` websocket: closed minutes for storage, the forming minute for the last bar
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::exchange;
+use crate::fetch::{convert_to_u64, KLine, PRICE_MULTIPLIER, VOLUME_MULTIPLIER};
+use crate::ticker::{connect_stream, is_timeout};
+use reqwest::blocking::Client;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// минуты, догружаемые через REST после каждого (пере)подключения
+const BACKFILL_MINUTES: i64 = 5;
+
+/// Called by the reader thread after each update, e.g. to request a repaint.
+pub type Notify = Arc<dyn Fn() + Send + Sync>;
+
+/// Kline of a `kline` event and whether its minute is closed.
+fn parse_kline(value: &serde_json::Value) -> Option<(KLine, bool)> {
+    let k = value.get("k")?;
+    let price = |name: &str| Some(convert_to_u64(k.get(name)?.as_str()?, PRICE_MULTIPLIER));
+    let kline = KLine {
+        open_time: k.get("t")?.as_i64()?,
+        open: price("o")?,
+        high: price("h")?,
+        low: price("l")?,
+        close: price("c")?,
+        volume: convert_to_u64(k.get("v")?.as_str()?, VOLUME_MULTIPLIER),
+    };
+    Some((kline, k.get("x")?.as_bool()?))
+}
+
+/// Background reader of `{symbol}@kline_1m`, reconnecting after errors. Closed minutes
+/// are sent as chunks (like `LiveUpdate`) and stored on the GUI thread with
+/// `Timeframe::process_data_chunk`; minutes missed while disconnected are fetched
+/// through REST on every connect. The forming minute is only kept in memory.
+/// Stopped when dropped.
+pub struct KlineStream {
+    pub symbol: String,
+    rx: mpsc::Receiver<Result<Vec<KLine>, String>>,
+    forming: Arc<Mutex<Option<KLine>>>,
+    notify: Notify,
+    stop: Arc<AtomicBool>,
+}
+
+impl KlineStream {
+    /// `None` when the active exchange has no kline stream.
+    pub fn start(symbol: &str, notify: Notify) -> Option<Self> {
+        let url = exchange::active().kline_stream_url(symbol)?;
+        let (tx, rx) = mpsc::channel();
+        let forming = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_symbol = symbol.to_string();
+        let thread_forming = Arc::clone(&forming);
+        let thread_notify = Arc::clone(&notify);
+        let thread_stop = Arc::clone(&stop);
+        let spawned = thread::Builder::new()
+            .name(format!("kline-{}", symbol))
+            .spawn(move || {
+                Self::run(
+                    &url,
+                    &thread_symbol,
+                    &tx,
+                    &thread_forming,
+                    &*thread_notify,
+                    &thread_stop,
+                )
+            });
+        if let Err(e) = spawned {
+            tracing::error!("Unable to start kline stream: {}", e);
+        }
+        Some(Self {
+            symbol: symbol.to_string(),
+            rx,
+            forming,
+            notify,
+            stop,
+        })
+    }
+
+    /// New stream of another symbol with the same notify callback.
+    pub fn restart(&self, symbol: &str) -> Option<Self> {
+        Self::start(symbol, Arc::clone(&self.notify))
+    }
+
+    /// Chunks of closed minutes received since the last call.
+    pub fn take_chunks(&self) -> Vec<Result<Vec<KLine>, String>> {
+        self.rx.try_iter().collect()
+    }
+
+    /// The minute still open on the exchange, as of the last update.
+    pub fn forming(&self) -> Option<KLine> {
+        self.forming.lock().ok().and_then(|forming| forming.clone())
+    }
+
+    fn backfill(client: &Client, symbol: &str) -> Result<Vec<KLine>, String> {
+        let now = chrono::Utc::now().timestamp_millis();
+        exchange::active()
+            .fetch_klines(
+                client,
+                symbol,
+                "1m",
+                BACKFILL_MINUTES * 60,
+                Some(now - BACKFILL_MINUTES * 60_000),
+                Some(now - 60_000),
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    fn run(
+        url: &str,
+        symbol: &str,
+        tx: &mpsc::Sender<Result<Vec<KLine>, String>>,
+        forming: &Mutex<Option<KLine>>,
+        notify: &(dyn Fn() + Send + Sync),
+        stop: &AtomicBool,
+    ) {
+        let client = Client::new();
+        while !stop.load(Ordering::Relaxed) {
+            let mut socket = match connect_stream(url) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    tracing::warn!("Kline stream connection failed: {}", e);
+                    thread::sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            };
+            // подписка уже идет, поэтому догруженные минуты не теряются между ними
+            if tx.send(Self::backfill(&client, symbol)).is_err() {
+                return;
+            }
+            notify();
+            while !stop.load(Ordering::Relaxed) {
+                match socket.read() {
+                    Ok(tungstenite::Message::Text(text)) => {
+                        let Some((kline, closed)) =
+                            serde_json::from_str::<serde_json::Value>(&text)
+                                .ok()
+                                .and_then(|value| parse_kline(value.get("data")?))
+                        else {
+                            continue;
+                        };
+                        if let Ok(mut forming) = forming.lock() {
+                            *forming = (!closed).then(|| kline.clone());
+                        }
+                        if closed && tx.send(Ok(vec![kline])).is_err() {
+                            return;
+                        }
+                        notify();
+                    }
+                    Ok(_) => {}
+                    Err(e) if is_timeout(&e) => {}
+                    Err(e) => {
+                        tracing::warn!("Kline stream error: {}", e);
+                        break;
+                    }
+                }
+            }
+            let _ = socket.close(None);
+        }
+    }
+}
+
+impl Drop for KlineStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
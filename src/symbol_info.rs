@@ -0,0 +1,167 @@
+// symbol_info.rs - Per-symbol exchange metadata (tick size, lot size, base/quote assets) and the trading symbol list, stored in the DB
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::db::Database;
+use crate::exchange;
+use crate::fetch;
+use crate::settings::SYMBOL_INFO_MAX_AGE_DAYS;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Trading rules of a symbol from the exchange `exchangeInfo` filters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    /// Minimal price step (PRICE_FILTER).
+    pub tick_size: f64,
+    /// Minimal quantity step (LOT_SIZE).
+    pub step_size: f64,
+    pub min_qty: f64,
+    /// Minimal order value in the quote asset, if the exchange has one.
+    pub min_notional: Option<f64>,
+    /// Time of the request, ms.
+    pub updated: i64,
+}
+
+/// Decimals of a step such as 0.001 (3) or 1.0 (0).
+fn step_decimals(step: f64) -> usize {
+    if step <= 0.0 {
+        return 8;
+    }
+    let mut decimals = 0;
+    while decimals < 8 {
+        let scaled = step * 10f64.powi(decimals as i32);
+        if (scaled - scaled.round()).abs() < 1e-9 {
+            break;
+        }
+        decimals += 1;
+    }
+    decimals
+}
+
+/// Decimals for a price when the tick size is unknown: about five significant
+/// digits, at least two and at most eight decimals.
+pub fn guess_price_decimals(price: f64) -> usize {
+    let abs = price.abs();
+    if !abs.is_normal() || abs >= 1000.0 {
+        return 2;
+    }
+    (4.0 - abs.log10().floor()).clamp(2.0, 8.0) as usize
+}
+
+impl SymbolInfo {
+    pub fn price_decimals(&self) -> usize {
+        step_decimals(self.tick_size)
+    }
+
+    pub fn quantity_decimals(&self) -> usize {
+        step_decimals(self.step_size)
+    }
+
+    /// Nearest valid price.
+    pub fn round_price(&self, price: f64) -> f64 {
+        if self.tick_size <= 0.0 {
+            return price;
+        }
+        let rounded = (price / self.tick_size).round() * self.tick_size;
+        // убирает хвосты вида 0.30000000000000004
+        format!("{:.*}", self.price_decimals(), rounded)
+            .parse()
+            .unwrap_or(rounded)
+    }
+
+    /// Largest valid quantity not above `quantity`.
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        if self.step_size <= 0.0 {
+            return quantity;
+        }
+        let rounded = (quantity / self.step_size + 1e-9).floor() * self.step_size;
+        format!("{:.*}", self.quantity_decimals(), rounded)
+            .parse()
+            .unwrap_or(rounded)
+    }
+
+    pub fn format_price(&self, price: f64) -> String {
+        format!("{:.*}", self.price_decimals(), price)
+    }
+
+    pub fn format_quantity(&self, quantity: f64) -> String {
+        format!("{:.*}", self.quantity_decimals(), quantity)
+    }
+
+    fn is_stale(&self, now: i64) -> bool {
+        now - self.updated > SYMBOL_INFO_MAX_AGE_DAYS * DAY_MS
+    }
+}
+
+/// Stored metadata of `symbol`, fetched and stored when missing or older than
+/// `SYMBOL_INFO_MAX_AGE_DAYS`. A failed refresh falls back to the stored copy.
+pub fn load_or_fetch(
+    db: &Database,
+    client: &Client,
+    symbol: &str,
+) -> Result<SymbolInfo, Box<dyn Error>> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let stored = db.get_symbol_info(symbol)?;
+    if let Some(info) = stored.as_ref().filter(|info| !info.is_stale(now)) {
+        return Ok(info.clone());
+    }
+    match fetch::fetch_symbol_info(client, symbol) {
+        Ok(info) => {
+            db.set_symbol_info(&info)?;
+            Ok(info)
+        }
+        Err(e) => match stored {
+            Some(info) => {
+                tracing::warn!("Unable to refresh {} metadata: {}", symbol, e);
+                Ok(info)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Symbols trading on the exchange, stored under `symbol_list`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SymbolList {
+    pub symbols: Vec<String>,
+    /// Time of the request, ms.
+    pub updated: i64,
+}
+
+/// Trading symbols from the DB while younger than `SYMBOL_INFO_MAX_AGE_DAYS`, else
+/// fetched again; the stored list is kept when the request fails.
+pub fn load_or_fetch_symbols(
+    db: &Database,
+    client: &Client,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let stored = db.get_symbol_list()?;
+    if let Some(list) = stored
+        .as_ref()
+        .filter(|list| now - list.updated <= SYMBOL_INFO_MAX_AGE_DAYS * DAY_MS)
+    {
+        return Ok(list.symbols.clone());
+    }
+    match exchange::active().symbols(client) {
+        Ok(symbols) => {
+            db.set_symbol_list(&SymbolList {
+                symbols: symbols.clone(),
+                updated: now,
+            })?;
+            Ok(symbols)
+        }
+        Err(e) => match stored {
+            Some(list) => {
+                tracing::warn!("Unable to refresh the symbol list: {}", e);
+                Ok(list.symbols)
+            }
+            None => Err(e),
+        },
+    }
+}
@@ -0,0 +1,78 @@
+// symbol_picker.rs - Toolbar symbol picker: exchange symbol list filtered as you type, stored symbols first
+// See CONVENTIONS.md for project structure and workflow
+
+use eframe::egui::{self, RichText};
+
+// строк в списке, остальные скрыты до уточнения фильтра
+const MAX_ROWS: usize = 50;
+
+#[derive(Default)]
+pub struct SymbolPicker {
+    query: String,
+    /// Symbols trading on the exchange, empty until loaded.
+    pub symbols: Vec<String>,
+    /// Symbols with data in the DB.
+    pub stored: Vec<String>,
+    /// The exchange list is being loaded.
+    pub loading: bool,
+}
+
+impl SymbolPicker {
+    /// Menu body: the filter field and the matching symbols. Returns the picked
+    /// symbol; Enter picks the first match, or the typed name when nothing matches.
+    pub fn show_menu(&mut self, ui: &mut egui::Ui, current: &str) -> Option<String> {
+        let mut picked = None;
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.query)
+                .hint_text("search")
+                .desired_width(160.0),
+        );
+        if !response.has_focus() && self.query.is_empty() {
+            response.request_focus();
+        }
+        let query = self.query.trim().to_uppercase();
+        let matches: Vec<&String> = self
+            .stored
+            .iter()
+            .chain(self.symbols.iter().filter(|s| !self.stored.contains(s)))
+            .filter(|s| s.contains(&query))
+            .collect();
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            picked = match matches.first() {
+                Some(symbol) => Some(symbol.to_string()),
+                None if !query.is_empty() => Some(query.clone()),
+                None => None,
+            };
+        }
+        if self.loading {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("loading exchange symbols");
+            });
+        }
+        egui::ScrollArea::vertical()
+            .max_height(320.0)
+            .show(ui, |ui| {
+                for symbol in matches.iter().take(MAX_ROWS) {
+                    let mut text = RichText::new(symbol.as_str());
+                    if self.stored.contains(symbol) {
+                        text = text.strong();
+                    }
+                    if ui
+                        .selectable_label(symbol.as_str() == current, text)
+                        .clicked()
+                    {
+                        picked = Some(symbol.to_string());
+                    }
+                }
+                if matches.len() > MAX_ROWS {
+                    ui.weak(format!("{} more", matches.len() - MAX_ROWS));
+                }
+            });
+        if picked.is_some() {
+            self.query.clear();
+            ui.close_menu();
+        }
+        picked
+    }
+}
@@ -0,0 +1,229 @@
+// theme.rs - Chart colors, dark/light mode, UI scale and font size, exported to and imported from JSON theme files
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::settings::{CHART_FONT_SIZE, CHART_FONT_SIZE_RANGE, THEME_FILE, UI_SCALE_RANGE};
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// Colors as `#rrggbb` or `#rrggbbaa` strings in theme files.
+mod hex_color {
+    use eframe::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let text = if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse(&text).ok_or_else(|| serde::de::Error::custom(format!("invalid color '{}'", text)))
+    }
+
+    pub fn parse(text: &str) -> Option<Color32> {
+        let hex = text.trim().strip_prefix('#')?;
+        if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+        Some(Color32::from_rgba_unmultiplied(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            alpha,
+        ))
+    }
+}
+
+/// Appearance of the chart. Missing entries of an imported file keep their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub dark_mode: bool,
+    #[serde(with = "hex_color")]
+    pub candle_up: Color32,
+    #[serde(with = "hex_color")]
+    pub candle_down: Color32,
+    /// Lines of the HLC bar style.
+    #[serde(with = "hex_color")]
+    pub bar_line: Color32,
+    #[serde(with = "hex_color")]
+    pub volume_up: Color32,
+    #[serde(with = "hex_color")]
+    pub volume_down: Color32,
+    #[serde(with = "hex_color")]
+    pub grid: Color32,
+    #[serde(with = "hex_color")]
+    pub price_label_background: Color32,
+    #[serde(with = "hex_color")]
+    pub crosshair: Color32,
+    /// Background of the bar under the cursor.
+    #[serde(with = "hex_color")]
+    pub bar_highlight: Color32,
+    /// Zoom on top of the native pixels_per_point of the display.
+    pub ui_scale: f32,
+    /// Size of axis and overlay labels on the chart.
+    pub font_size: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            dark_mode: true,
+            candle_up: Color32::from_rgb(0, 180, 0),
+            candle_down: Color32::from_rgb(180, 0, 0),
+            bar_line: Color32::from_rgb(180, 180, 180),
+            volume_up: Color32::from_rgb(100, 180, 100),
+            volume_down: Color32::from_rgb(180, 100, 100),
+            grid: Color32::from_gray(60),
+            price_label_background: Color32::from_rgba_premultiplied(20, 20, 20, 220),
+            crosshair: Color32::from_rgba_unmultiplied(255, 255, 255, 100),
+            bar_highlight: Color32::from_rgb(100, 100, 100),
+            ui_scale: 1.0,
+            font_size: CHART_FONT_SIZE,
+        }
+    }
+}
+
+impl Theme {
+    /// Theme from `THEME_FILE` when it exists, otherwise the default one.
+    pub fn load_default() -> Self {
+        if Path::new(THEME_FILE).exists() {
+            match Self::load(Path::new(THEME_FILE)) {
+                Ok(theme) => return theme,
+                Err(e) => tracing::warn!("Unable to load theme from {}: {}", THEME_FILE, e),
+            }
+        }
+        Self::default()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Applies the dark/light mode and the UI scale to egui.
+    pub fn apply(&self, ctx: &egui::Context) {
+        let visuals = if self.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        ctx.set_visuals(visuals);
+        ctx.set_zoom_factor(self.ui_scale.clamp(UI_SCALE_RANGE.0, UI_SCALE_RANGE.1));
+    }
+
+    /// Font of the chart labels.
+    pub fn chart_font(&self) -> egui::FontId {
+        egui::FontId::proportional(
+            self.font_size
+                .clamp(CHART_FONT_SIZE_RANGE.0, CHART_FONT_SIZE_RANGE.1),
+        )
+    }
+
+    /// Extra room below the chart for time labels larger than the default font.
+    pub fn time_labels_extra(&self) -> f32 {
+        (self.chart_font().size - CHART_FONT_SIZE).max(0.0)
+    }
+
+    fn colors_mut(&mut self) -> [(&'static str, &mut Color32); 9] {
+        [
+            ("Candle up", &mut self.candle_up),
+            ("Candle down", &mut self.candle_down),
+            ("Bar lines", &mut self.bar_line),
+            ("Volume up", &mut self.volume_up),
+            ("Volume down", &mut self.volume_down),
+            ("Grid", &mut self.grid),
+            ("Price label background", &mut self.price_label_background),
+            ("Crosshair", &mut self.crosshair),
+            ("Bar under cursor", &mut self.bar_highlight),
+        ]
+    }
+}
+
+/// Theme menu state: the file used for import and export.
+pub struct ThemeEditor {
+    pub path: String,
+}
+
+impl Default for ThemeEditor {
+    fn default() -> Self {
+        Self {
+            path: THEME_FILE.to_string(),
+        }
+    }
+}
+
+impl ThemeEditor {
+    /// Edits `theme` in place; returns a status message after export or import.
+    pub fn show_menu(&mut self, ui: &mut egui::Ui, theme: &mut Theme) -> Option<String> {
+        let mut message = None;
+        if ui.checkbox(&mut theme.dark_mode, "Dark mode").changed() {
+            theme.apply(ui.ctx());
+        }
+        // масштаб применяется после отпускания слайдера, иначе он уезжает из-под курсора
+        let scale = ui.add(
+            egui::Slider::new(&mut theme.ui_scale, UI_SCALE_RANGE.0..=UI_SCALE_RANGE.1)
+                .text("UI scale")
+                .step_by(0.05),
+        );
+        if scale.drag_stopped() || (scale.changed() && !scale.dragged()) {
+            theme.apply(ui.ctx());
+        }
+        ui.add(
+            egui::Slider::new(
+                &mut theme.font_size,
+                CHART_FONT_SIZE_RANGE.0..=CHART_FONT_SIZE_RANGE.1,
+            )
+            .text("Chart font size")
+            .step_by(1.0),
+        );
+        egui::Grid::new("theme_colors")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (label, color) in theme.colors_mut() {
+                    ui.label(label);
+                    ui.color_edit_button_srgba(color);
+                    ui.end_row();
+                }
+            });
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(160.0));
+            let path = Path::new(&self.path);
+            if ui.button("export").clicked() {
+                message = Some(match theme.save(path) {
+                    Ok(()) => format!("Theme exported to {}", self.path),
+                    Err(e) => format!("Unable to export theme: {}", e),
+                });
+            }
+            if ui.button("import").clicked() {
+                message = Some(match Theme::load(path) {
+                    Ok(imported) => {
+                        *theme = imported;
+                        theme.apply(ui.ctx());
+                        format!("Theme imported from {}", self.path)
+                    }
+                    Err(e) => format!("Unable to import theme: {}", e),
+                });
+            }
+        });
+        if ui.button("Reset to default").clicked() {
+            *theme = Theme::default();
+            theme.apply(ui.ctx());
+        }
+        message
+    }
+}
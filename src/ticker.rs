@@ -0,0 +1,211 @@
+// ticker.rs - Live best bid/ask and recent trades of the active symbol from one Binance websocket connection
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::exchange::Market;
+use crate::settings::{BINANCE_FUTURES_STREAM_URL, BINANCE_STREAM_URL, TRADE_TAPE_SIZE};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::WebSocket;
+
+// how often a blocked read returns to check the stop flag
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+pub struct BookTicker {
+    pub bid: f64,
+    pub bid_qty: f64,
+    pub ask: f64,
+    pub ask_qty: f64,
+    pub received: Instant,
+}
+
+impl BookTicker {
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+
+    fn parse(value: &serde_json::Value) -> Option<Self> {
+        let field = |name: &str| value.get(name)?.as_str()?.parse::<f64>().ok();
+        Some(Self {
+            bid: field("b")?,
+            bid_qty: field("B")?,
+            ask: field("a")?,
+            ask_qty: field("A")?,
+            received: Instant::now(),
+        })
+    }
+}
+
+/// Executed trade from the `{symbol}@trade` stream (`@aggTrade` on futures).
+#[derive(Debug, Clone, Copy)]
+pub struct Trade {
+    pub price: f64,
+    pub qty: f64,
+    /// The buyer was the maker: a market sell.
+    pub sell: bool,
+    /// Trade time, ms.
+    pub time: i64,
+}
+
+impl Trade {
+    fn parse(value: &serde_json::Value) -> Option<Self> {
+        let field = |name: &str| value.get(name)?.as_str()?.parse::<f64>().ok();
+        Some(Self {
+            price: field("p")?,
+            qty: field("q")?,
+            sell: value.get("m")?.as_bool()?,
+            time: value.get("T")?.as_i64()?,
+        })
+    }
+}
+
+/// Opens a websocket with a read timeout so that reader threads can be stopped.
+pub fn connect_stream(url: &str) -> Result<WebSocket<MaybeTlsStream<TcpStream>>, Box<dyn Error>> {
+    let (socket, _) = tungstenite::connect(url)?;
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(READ_TIMEOUT))?,
+        MaybeTlsStream::NativeTls(stream) => {
+            stream.get_ref().set_read_timeout(Some(READ_TIMEOUT))?
+        }
+        _ => {}
+    }
+    Ok(socket)
+}
+
+/// `true` for the error a read returns when `READ_TIMEOUT` expires.
+pub fn is_timeout(error: &tungstenite::Error) -> bool {
+    matches!(error, tungstenite::Error::Io(e)
+        if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut))
+}
+
+/// Background reader of `{symbol}@bookTicker` and `{symbol}@trade` over one combined
+/// stream connection, reconnecting after errors. Independent of the bar pipeline;
+/// stopped when dropped.
+pub struct TickerStream {
+    pub symbol: String,
+    latest: Arc<Mutex<Option<BookTicker>>>,
+    // последние сделки, новые в начале
+    trades: Arc<Mutex<VecDeque<Trade>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl TickerStream {
+    pub fn start(symbol: &str) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let trades = Arc::new(Mutex::new(VecDeque::with_capacity(TRADE_TAPE_SIZE)));
+        let stop = Arc::new(AtomicBool::new(false));
+        // у фьючерсов нет потока @trade, только агрегированные сделки
+        let (market, pair) = Market::split(symbol);
+        let (base, trade_stream) = match market {
+            Market::Spot => (BINANCE_STREAM_URL, "trade"),
+            Market::Futures => (BINANCE_FUTURES_STREAM_URL, "aggTrade"),
+        };
+        let url = format!(
+            "{}?streams={pair}@bookTicker/{pair}@{}",
+            base,
+            trade_stream,
+            pair = pair.to_lowercase()
+        );
+        let thread_latest = Arc::clone(&latest);
+        let thread_trades = Arc::clone(&trades);
+        let thread_stop = Arc::clone(&stop);
+        let spawned = thread::Builder::new()
+            .name(format!("ticker-{}", symbol))
+            .spawn(move || Self::run(&url, &thread_latest, &thread_trades, &thread_stop));
+        if let Err(e) = spawned {
+            tracing::error!("Unable to start book ticker stream: {}", e);
+        }
+        Self {
+            symbol: symbol.to_string(),
+            latest,
+            trades,
+            stop,
+        }
+    }
+
+    pub fn latest(&self) -> Option<BookTicker> {
+        self.latest.lock().ok().and_then(|latest| *latest)
+    }
+
+    /// Recent trades, newest first.
+    pub fn trades(&self) -> Vec<Trade> {
+        self.trades
+            .lock()
+            .map(|trades| trades.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Dispatches a combined stream message `{"stream": ..., "data": ...}`.
+    fn handle_message(
+        text: &str,
+        latest: &Mutex<Option<BookTicker>>,
+        trades: &Mutex<VecDeque<Trade>>,
+    ) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(text) else {
+            return;
+        };
+        let (Some(stream), Some(data)) = (
+            value.get("stream").and_then(|s| s.as_str()),
+            value.get("data"),
+        ) else {
+            return;
+        };
+        if stream.ends_with("@bookTicker") {
+            if let Some(ticker) = BookTicker::parse(data) {
+                if let Ok(mut latest) = latest.lock() {
+                    *latest = Some(ticker);
+                }
+            }
+        } else if stream.ends_with("@trade") || stream.ends_with("@aggTrade") {
+            if let (Some(trade), Ok(mut trades)) = (Trade::parse(data), trades.lock()) {
+                trades.push_front(trade);
+                trades.truncate(TRADE_TAPE_SIZE);
+            }
+        }
+    }
+
+    fn run(
+        url: &str,
+        latest: &Mutex<Option<BookTicker>>,
+        trades: &Mutex<VecDeque<Trade>>,
+        stop: &AtomicBool,
+    ) {
+        while !stop.load(Ordering::Relaxed) {
+            let mut socket = match connect_stream(url) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    tracing::warn!("Book ticker connection failed: {}", e);
+                    thread::sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            };
+            while !stop.load(Ordering::Relaxed) {
+                match socket.read() {
+                    Ok(tungstenite::Message::Text(text)) => {
+                        Self::handle_message(&text, latest, trades);
+                    }
+                    Ok(_) => {}
+                    Err(e) if is_timeout(&e) => {}
+                    Err(e) => {
+                        tracing::warn!("Book ticker stream error: {}", e);
+                        break;
+                    }
+                }
+            }
+            let _ = socket.close(None);
+        }
+    }
+}
+
+impl Drop for TickerStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
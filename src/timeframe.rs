@@ -1,19 +1,24 @@
 // timeframe.rs - Data validation, consistency checks, database integration
 // See CONVENTIONS.md for project structure and workflow
 
+use crate::app_error::{self, AppError};
 use crate::compress;
 use crate::datawindow::DataWindow;
-use crate::db::Database;
-use crate::fetch::{KLine, PRICE_MULTIPLIER};
-use crate::rsi;
-use chrono::{Duration, Utc};
+use crate::db::{Database, WriteBatch};
+use crate::exchange;
+use crate::fetch::{volume_to_f64, KLine, PRICE_MULTIPLIER};
+use crate::settings::{SYNC_BATCH_BLOCKS, UPDATE_INTERVAL};
+use chrono::{DateTime, Datelike, Duration, Utc};
 use reqwest::blocking::Client;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
 use std::thread;
 use std::time;
 
-const BLOCK_SIZE: usize = 1000;
-const UPDATE_INTERVAL: u64 = 300;
+// Binance returns at most 1000 klines per request
+const FETCH_LIMIT: i64 = 1000;
 
 #[derive(Debug, Clone)]
 pub struct Bar {
@@ -23,35 +28,119 @@ pub struct Bar {
     pub low: f64,
     pub close: f64,
     pub volume: f64,
+    /// Sum of typical price * volume of the source klines, for VWAP.
+    pub pv: f64,
+}
+
+/// Progress of `sync_data` / `backfill_history`, passed to the progress callback
+/// after every fetched block and once more with `last_error` when a fetch fails.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncProgress {
+    pub blocks_done: usize,
+    pub blocks_total: usize,
+    /// Start of the block fetched last (or failed), ms.
+    pub current_time: i64,
+    /// Time left for the remaining blocks at the rate so far.
+    pub eta: Option<time::Duration>,
+    pub last_error: Option<String>,
+}
+
+impl SyncProgress {
+    fn new(blocks_total: usize) -> Self {
+        Self {
+            blocks_total,
+            ..Self::default()
+        }
+    }
+
+    /// Counts a fetched block; the ETA is extrapolated from `started`.
+    fn block_done(&mut self, current_time: i64, started: time::Instant) {
+        self.blocks_done += 1;
+        self.current_time = current_time;
+        let remaining = self.blocks_total.saturating_sub(self.blocks_done) as u32;
+        self.eta = (started.elapsed() / self.blocks_done as u32).checked_mul(remaining);
+    }
+
+    /// Fraction of the blocks fetched.
+    pub fn fraction(&self) -> f32 {
+        if self.blocks_total == 0 {
+            0.0
+        } else {
+            self.blocks_done as f32 / self.blocks_total as f32
+        }
+    }
+}
+
+/// Timeframe value of calendar-month bars (UTC), 30 days as the nominal length.
+pub const MONTH_MINUTES: i32 = 43_200;
+
+/// How the chart bars are built from the klines.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum BarType {
+    /// Fixed time buckets of the timeframe.
+    #[default]
+    Time,
+    /// Renko bricks, box size in percent of the price.
+    Renko(f64),
+    /// Bars closing once their low..high span reaches this percent of the price.
+    Range(f64),
+}
+
+impl BarType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BarType::Time => "time",
+            BarType::Renko(_) => "renko",
+            BarType::Range(_) => "range",
+        }
+    }
+
+    /// Box or range in price units at `price`; 0 for time bars.
+    pub fn size_at(&self, price: f64) -> f64 {
+        match self {
+            BarType::Time => 0.0,
+            BarType::Renko(percent) | BarType::Range(percent) => price * percent / 100.0,
+        }
+    }
+}
+
+fn to_price(value: u64) -> f64 {
+    value as f64 / 10f64.powi(PRICE_MULTIPLIER as i32)
+}
+
+fn typical_pv(kline: &KLine) -> f64 {
+    (to_price(kline.high) + to_price(kline.low) + to_price(kline.close)) / 3.0
+        * volume_to_f64(kline.volume)
 }
 
 pub struct Timeframe;
 
 impl Timeframe {
+    /// Fetches the last minutes of `symbol` every `UPDATE_INTERVAL` seconds and sends
+    /// them to `tx` until `stop` is set or the receiver is dropped. The chunks are
+    /// stored by the receiver with `process_data_chunk`, which owns the `DataWindow`.
     pub fn update_loop(
         client: &Client,
-        db: &Database,
         symbol: &str,
-        data_window: &mut DataWindow,
-    ) -> Result<(), Box<dyn Error>> {
-        let mut timer = time::Instant::now();
-
-        loop {
-            if timer.elapsed().as_secs() >= UPDATE_INTERVAL {
-                match Self::fetch_data_chunk(client, symbol) {
-                    Ok(data) => {
-                        Self::process_data_chunk(symbol, data, db, data_window)?;
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
+        tx: &mpsc::Sender<Result<Vec<KLine>, String>>,
+        stop: &AtomicBool,
+    ) {
+        let mut timer: Option<time::Instant> = None;
+        while !stop.load(Ordering::Relaxed) {
+            if timer.is_none_or(|t| t.elapsed().as_secs() >= UPDATE_INTERVAL) {
+                let chunk = Self::fetch_data_chunk(client, symbol).map_err(|e| e.to_string());
+                if tx.send(chunk).is_err() {
+                    return;
                 }
-                timer = time::Instant::now();
+                timer = Some(time::Instant::now());
             }
-            thread::sleep(time::Duration::from_secs(10));
+            thread::sleep(time::Duration::from_secs(1));
         }
     }
 
+    /// Fetches all blocks after `last_{symbol}` (or from `start_time` for a new symbol)
+    /// up to `end_time`, reporting each block to `progress`.
+    #[tracing::instrument(skip(pause_between_requests, db, data_window, progress))]
     pub fn sync_data(
         pause_between_requests: u64,
         db: &Database,
@@ -59,50 +148,389 @@ impl Timeframe {
         start_time: i64,
         end_time: i64,
         data_window: &mut DataWindow,
+        progress: &mut dyn FnMut(&SyncProgress),
     ) -> Result<(), Box<dyn Error>> {
         let client = Client::new();
         let mut current_time;
+        let block_ms = db.block_size() as i64 * 60_000;
         let last_timestamp = db.get_last_timestamp(symbol).unwrap_or(0);
         if last_timestamp == 0 {
-            println!("No data found for {}, initializing with data", symbol);
-            current_time = Self::get_dbtimestamp(start_time);
+            tracing::info!("No data found for {}, initializing with data", symbol);
+            current_time = db.block_start(start_time);
         } else {
-            current_time = last_timestamp + 60_000_000;
+            current_time = last_timestamp + block_ms;
         }
+        let blocks_total =
+            ((end_time - current_time).max(0) as u64).div_ceil(block_ms as u64) as usize;
+        let mut status = SyncProgress::new(blocks_total);
+        let started = time::Instant::now();
+        let mut batch = WriteBatch::default();
         while current_time < end_time {
             if current_time != start_time {
                 thread::sleep(std::time::Duration::from_secs(pause_between_requests));
             }
-            let klines = crate::fetch::fetch_klines(
+            let klines = match Self::fetch_block(
                 &client,
                 symbol,
-                "1m",
-                1000,
-                Some(current_time),
-                Some(current_time + 60_000_000),
-            )?;
-            Self::process_data_chunk(symbol, klines, db, data_window)?;
-            println!("Initialized data for {} from {}", symbol, current_time);
-            current_time += 60_000_000;
+                current_time,
+                db.block_size(),
+                pause_between_requests,
+            ) {
+                Ok(klines) => klines,
+                Err(e) => {
+                    status.current_time = current_time;
+                    status.last_error = Some(e.to_string());
+                    progress(&status);
+                    // Keep the complete blocks fetched so far
+                    Self::commit_batch(symbol, db, batch)?;
+                    return Err(e);
+                }
+            };
+            Self::stage_data_chunk(symbol, klines, db, data_window, &mut batch)?;
+            tracing::info!("Fetched data for {} from {}", symbol, current_time);
+            status.block_done(current_time, started);
+            progress(&status);
+            if batch.block_count() >= SYNC_BATCH_BLOCKS {
+                Self::commit_batch(symbol, db, std::mem::take(&mut batch))?;
+            }
+            current_time += block_ms;
         }
+        Self::commit_batch(symbol, db, batch)?;
 
         Ok(())
     }
 
+    /// Fetches up to `days` of history before the first stored block, newest block
+    /// first, and returns the number of blocks stored. Stops early at an empty
+    /// window (before the symbol was listed). Aggregation tiers are rebuilt after.
+    #[tracing::instrument(skip(pause_between_requests, db, progress))]
+    pub fn backfill_history(
+        pause_between_requests: u64,
+        db: &Database,
+        symbol: &str,
+        days: i64,
+        progress: &mut dyn FnMut(&SyncProgress),
+    ) -> Result<usize, Box<dyn Error>> {
+        let first_timestamp = db.get_first_timestamp(symbol)?;
+        if first_timestamp == 0 {
+            return Err(format!("No data for {}, nothing to extend", symbol).into());
+        }
+        let client = Client::new();
+        let block_ms = db.block_size() as i64 * 60_000;
+        let target = db.block_start(first_timestamp - Duration::days(days).num_milliseconds());
+        let blocks_total = ((first_timestamp - target) / block_ms) as usize;
+        // past windows never touch the live tail
+        let mut scratch = DataWindow::default();
+        let mut batch = WriteBatch::default();
+        let mut status = SyncProgress::new(blocks_total);
+        let started = time::Instant::now();
+        let mut stored = 0;
+        let mut current_time = first_timestamp - block_ms;
+        let mut result = Ok(());
+        while current_time >= target {
+            if status.blocks_done > 0 {
+                thread::sleep(std::time::Duration::from_secs(pause_between_requests));
+            }
+            let klines = match Self::fetch_block(
+                &client,
+                symbol,
+                current_time,
+                db.block_size(),
+                pause_between_requests,
+            ) {
+                Ok(klines) => klines,
+                Err(e) => {
+                    status.current_time = current_time;
+                    status.last_error = Some(e.to_string());
+                    progress(&status);
+                    result = Err(e);
+                    break;
+                }
+            };
+            if klines.is_empty() {
+                tracing::info!(
+                    "No {} data before {}, history start reached",
+                    symbol,
+                    current_time
+                );
+                break;
+            }
+            Self::stage_data_chunk(symbol, klines, db, &mut scratch, &mut batch)?;
+            tracing::info!("Fetched history for {} from {}", symbol, current_time);
+            status.block_done(current_time, started);
+            progress(&status);
+            if batch.block_count() >= SYNC_BATCH_BLOCKS {
+                stored += batch.block_count();
+                db.apply_batch(std::mem::take(&mut batch))?;
+            }
+            current_time -= block_ms;
+        }
+        stored += batch.block_count();
+        db.apply_batch(batch)?;
+
+        // Tiers only append after their last record, older data needs a rebuild
+        if stored > 0 {
+            db.invalidate_aggregation(symbol)?;
+            if let Err(e) = db.aggregate_ohlcv_data(symbol) {
+                app_error::report(AppError::Aggregation {
+                    symbol: symbol.to_string(),
+                    message: e.to_string(),
+                });
+            }
+        }
+        result.map(|_| stored)
+    }
+
+    /// Downloads the window of a corrupted block again and replaces the block with it
+    /// in one batch; a failed download leaves the stored block as it was.
+    #[tracing::instrument(skip(db, data_window))]
+    pub fn refetch_block(
+        db: &Database,
+        symbol: &str,
+        block_start: i64,
+        data_window: &mut DataWindow,
+    ) -> Result<(), Box<dyn Error>> {
+        tracing::info!("Re-fetching block {}_{}", symbol, block_start);
+        let klines = Self::fetch_block(&Client::new(), symbol, block_start, db.block_size(), 1)?;
+        let mut batch = WriteBatch::default();
+        batch.remove_block(symbol, block_start);
+        Self::stage_klines(symbol, klines, db, data_window, &mut batch, true)?;
+        Self::commit_batch(symbol, db, batch)
+    }
+
+    /// Downloads the blocks covering `[from, to]` again, replacing the stored ones and
+    /// removing the `stale` block keys (e.g. a misaligned one). Used for ranges flagged
+    /// by the anomaly scan; aggregation tiers are rebuilt. Nothing is changed unless
+    /// every block was downloaded. Returns the blocks written.
+    #[tracing::instrument(skip(db))]
+    pub fn refetch_range(
+        pause_between_requests: u64,
+        db: &Database,
+        symbol: &str,
+        stale: &[i64],
+        from: i64,
+        to: i64,
+    ) -> Result<usize, Box<dyn Error>> {
+        let client = Client::new();
+        let block_ms = db.block_size() as i64 * 60_000;
+        // past windows never touch the live tail
+        let mut scratch = DataWindow::default();
+        let mut batch = WriteBatch::default();
+        for block_start in stale {
+            batch.remove_block(symbol, *block_start);
+        }
+        let first = db.block_start(from);
+        let mut block_start = first;
+        while block_start <= db.block_start(to) {
+            if block_start != first {
+                thread::sleep(std::time::Duration::from_secs(pause_between_requests));
+            }
+            let klines = Self::fetch_block(
+                &client,
+                symbol,
+                block_start,
+                db.block_size(),
+                pause_between_requests,
+            )?;
+            // удаление и новый блок применяются вместе, после всех загрузок
+            batch.remove_block(symbol, block_start);
+            Self::stage_klines(symbol, klines, db, &mut scratch, &mut batch, true)?;
+            block_start += block_ms;
+        }
+        let stored = batch.block_count();
+        db.apply_batch(batch)?;
+        db.invalidate_aggregation(symbol)?;
+        if let Err(e) = db.aggregate_ohlcv_data(symbol) {
+            app_error::report(AppError::Aggregation {
+                symbol: symbol.to_string(),
+                message: e.to_string(),
+            });
+        }
+        Ok(stored)
+    }
+
+    /// Stores klines edited outside the application (Parquet import): they replace the
+    /// stored minutes with the same open time, other minutes of their blocks stay.
+    /// Minutes of a block whose window has not closed yet are not stored. Aggregation
+    /// tiers are rebuilt. Returns the blocks written.
+    pub fn import_klines(
+        db: &Database,
+        symbol: &str,
+        klines: Vec<KLine>,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut scratch = DataWindow::default();
+        let mut batch = WriteBatch::default();
+        Self::stage_data_chunk(symbol, klines, db, &mut scratch, &mut batch)?;
+        let stored = batch.block_count();
+        if stored == 0 {
+            return Ok(0);
+        }
+        db.apply_batch(batch)?;
+        db.invalidate_aggregation(symbol)?;
+        if let Err(e) = db.aggregate_ohlcv_data(symbol) {
+            app_error::report(AppError::Aggregation {
+                symbol: symbol.to_string(),
+                message: e.to_string(),
+            });
+        }
+        Ok(stored)
+    }
+
+    /// Downloads one block window, in several requests when the block size
+    /// exceeds the API limit.
+    fn fetch_block(
+        client: &Client,
+        symbol: &str,
+        block_start: i64,
+        block_size: usize,
+        pause_between_requests: u64,
+    ) -> Result<Vec<KLine>, Box<dyn Error>> {
+        let block_end = block_start + block_size as i64 * 60_000;
+        let mut klines = Vec::new();
+        let mut page_start = block_start;
+        while page_start < block_end {
+            if page_start != block_start {
+                thread::sleep(std::time::Duration::from_secs(pause_between_requests));
+            }
+            let page_end = (page_start + FETCH_LIMIT * 60_000).min(block_end);
+            klines.extend(exchange::active().fetch_klines(
+                client,
+                symbol,
+                "1m",
+                FETCH_LIMIT,
+                Some(page_start),
+                Some(page_end - 1),
+            )?);
+            page_start = page_end;
+        }
+        Ok(klines)
+    }
+
+    /// Renko bricks of `box_size` from the kline closes, continuing after the brick
+    /// `last` if given. A brick is stamped with the minute that completed it and
+    /// carries the volume since the previous brick; further bricks of the same minute
+    /// have none. Minutes after the last brick are not shown.
+    pub fn renko_bars(klines: &[KLine], box_size: f64, last: Option<&Bar>) -> Vec<Bar> {
+        let mut bars = Vec::new();
+        let Some(first) = klines.first().filter(|_| box_size > 0.0) else {
+            return bars;
+        };
+        // границы последнего кирпича
+        let (mut low, mut high) = match last {
+            Some(bar) => (bar.open.min(bar.close), bar.open.max(bar.close)),
+            None => (to_price(first.close), to_price(first.close)),
+        };
+        let mut volume = 0u64;
+        let mut pv = 0.0;
+        for kline in klines {
+            let close = to_price(kline.close);
+            volume = volume.saturating_add(kline.volume);
+            pv += typical_pv(kline);
+            while close >= high + box_size || close <= low - box_size {
+                let (open, brick_close) = if close >= high + box_size {
+                    (high, high + box_size)
+                } else {
+                    (low, low - box_size)
+                };
+                bars.push(Bar {
+                    time: kline.open_time,
+                    open,
+                    high: open.max(brick_close),
+                    low: open.min(brick_close),
+                    close: brick_close,
+                    volume: volume_to_f64(volume),
+                    pv,
+                });
+                volume = 0;
+                pv = 0.0;
+                low = open.min(brick_close);
+                high = open.max(brick_close);
+            }
+        }
+        bars
+    }
+
+    /// Bars closing once their low..high span reaches `range`; the last one may be
+    /// unfinished. Each bar starts with the minute after the previous bar.
+    pub fn range_bars(klines: &[KLine], range: f64) -> Vec<Bar> {
+        let mut bars = Vec::new();
+        let mut current: Option<Bar> = None;
+        for kline in klines {
+            let (high, low, close) = (
+                to_price(kline.high),
+                to_price(kline.low),
+                to_price(kline.close),
+            );
+            let bar = current.get_or_insert_with(|| Bar {
+                time: kline.open_time,
+                open: to_price(kline.open),
+                high,
+                low,
+                close,
+                volume: 0.0,
+                pv: 0.0,
+            });
+            bar.high = bar.high.max(high);
+            bar.low = bar.low.min(low);
+            bar.close = close;
+            bar.volume += volume_to_f64(kline.volume);
+            bar.pv += typical_pv(kline);
+            if bar.high - bar.low >= range {
+                bars.extend(current.take());
+            }
+        }
+        bars.extend(current);
+        bars
+    }
+
+    /// Calendar-month (UTC) bars from time-sorted daily bars; a month starting
+    /// inside the data is partial.
+    pub fn month_bars(daily: &[Bar]) -> Vec<Bar> {
+        let mut bars: Vec<Bar> = Vec::new();
+        let mut current_month = None;
+        for day in daily {
+            let month = DateTime::from_timestamp_millis(day.time).map(|dt| (dt.year(), dt.month()));
+            match bars.last_mut() {
+                Some(bar) if month == current_month => {
+                    bar.high = bar.high.max(day.high);
+                    bar.low = bar.low.min(day.low);
+                    bar.close = day.close;
+                    bar.volume += day.volume;
+                    bar.pv += day.pv;
+                }
+                _ => {
+                    current_month = month;
+                    bars.push(day.clone());
+                }
+            }
+        }
+        bars
+    }
+
+    /// Combines `kline_minutes` klines (1m blocks or aggregated records) into bars
+    /// of `timeframe_minutes`, a multiple of `kline_minutes`.
+    #[tracing::instrument(level = "trace", skip_all, fields(klines = klines.len(), timeframe_minutes))]
     pub fn convert_to_timeframe(
         mut klines: Vec<KLine>,
         timeframe_minutes: i32,
+        kline_minutes: i32,
         dolastbar: bool,
         data_window: &mut DataWindow,
-        rsi_calculator: &mut rsi::WilderRSI,
     ) -> Result<Vec<Bar>, Box<dyn Error>> {
         let mut result = Vec::new();
         let mut current_open_time = 0;
         let mut current_open = 0.0;
         let mut current_high = f64::MIN;
         let mut current_low = f64::MAX;
-        let mut current_volume = 0.0;
+        let mut current_volume = 0u64;
+        let mut current_close = 0.0;
+        let mut current_pv = 0.0;
         let mut count = 0;
+        let bar_duration = timeframe_minutes as i64 * 60_000;
+        // bars of non-24/7 markets end with their session
+        let calendar =
+            (!data_window.calendar.is_continuous()).then(|| data_window.calendar.clone());
+        let mut current_session = None;
         let mut current_processing_klines = std::mem::take(&mut data_window.timeframe_remainder);
         current_processing_klines.append(&mut klines);
         let total_len = current_processing_klines.len();
@@ -110,21 +538,41 @@ impl Timeframe {
         for kline in &current_processing_klines {
             let price_high = kline.high as f64 / 10f64.powi(PRICE_MULTIPLIER as i32);
             let price_low = kline.low as f64 / 10f64.powi(PRICE_MULTIPLIER as i32);
+            let session = calendar
+                .as_ref()
+                .and_then(|c| c.session_start(kline.open_time));
+            // After a data gap close the unfinished bar instead of stretching it over the gap
+            if count > 0
+                && (kline.open_time >= current_open_time + bar_duration
+                    || session != current_session)
+            {
+                result.push(Bar {
+                    time: current_open_time,
+                    open: current_open,
+                    high: current_high,
+                    low: current_low,
+                    close: current_close,
+                    volume: volume_to_f64(current_volume),
+                    pv: current_pv,
+                });
+                count = 0;
+            }
+            current_close = kline.close as f64 / 10f64.powi(PRICE_MULTIPLIER as i32);
+            let pv = (price_high + price_low + current_close) / 3.0 * volume_to_f64(kline.volume);
             if count == 0 {
                 current_open_time = kline.open_time;
+                current_session = session;
                 current_open = kline.open as f64 / 10f64.powi(PRICE_MULTIPLIER as i32);
                 current_high = price_high;
                 current_low = price_low;
                 current_volume = kline.volume;
+                current_pv = pv;
             } else {
                 current_high = current_high.max(price_high);
                 current_low = current_low.min(price_low);
-                current_volume += kline.volume;
+                current_volume = current_volume.saturating_add(kline.volume);
+                current_pv += pv;
             }
-            let _rsi_val = rsi_calculator.add_price(
-                current_open_time,
-                kline.close as f64 / 10f64.powi(PRICE_MULTIPLIER as i32),
-            );
             /*(&
             {
                 timestamp: current_open_time as u64,
@@ -135,7 +583,7 @@ impl Timeframe {
                 });*/
             items_processed_in_loop += 1;
             count += 1;
-            if count >= timeframe_minutes as usize
+            if count >= (timeframe_minutes / kline_minutes) as usize
                 || (dolastbar && items_processed_in_loop == total_len)
             {
                 result.push(Bar {
@@ -144,14 +592,15 @@ impl Timeframe {
                     high: current_high,
                     low: current_low,
                     close: kline.close as f64 / 10f64.powi(PRICE_MULTIPLIER as i32),
-                    volume: current_volume,
+                    volume: volume_to_f64(current_volume),
+                    pv: current_pv,
                 });
                 count = 0;
                 /* DEBUG
                 let time_str = DateTime::from_timestamp(current_open_time /1000 , 0)
                     .map(|dt: DateTime<Utc>| dt.format("%d %b %H:%M").to_string())
                     .unwrap_or_else(|| "Invalid timestamp".to_string());
-                println!(
+                tracing::info!(
                     "{} open: {:.2}, high: {:.2}, low: {:.2}, close: {:.2}, volume: {:.2}, rsi: {}",
                     time_str,
                     current_open,
@@ -173,13 +622,9 @@ impl Timeframe {
         Ok(result)
     }
 
-    pub fn get_dbtimestamp(timestamp_ms: i64) -> i64 {
-        timestamp_ms - timestamp_ms % (BLOCK_SIZE as i64 * 60_000)
-    }
-
     fn fetch_data_chunk(client: &Client, symbol: &str) -> Result<Vec<KLine>, Box<dyn Error>> {
         let now = Utc::now().timestamp_millis();
-        crate::fetch::fetch_klines(
+        exchange::active().fetch_klines(
             client,
             symbol,
             "1m",
@@ -189,35 +634,174 @@ impl Timeframe {
         )
     }
 
+    /// Finds missing minute ranges `[from, to)` inside a block window.
+    /// Whole-minute gaps (exchange downtime) are tolerated; duplicated or
+    /// misaligned timestamps still fail the consistency check.
+    fn find_gaps(
+        symbol: &str,
+        data: &[KLine],
+        block_start: i64,
+        block_end: i64,
+    ) -> Result<Vec<(i64, i64)>, Box<dyn Error>> {
+        let mut gaps = Vec::new();
+        let mut expected = block_start;
+        for kline in data {
+            let time_diff = kline.open_time - expected;
+            if time_diff < 0 || time_diff % 60_000 != 0 {
+                return Err(format!(
+                    "Consistency check failed for {}: kline at {} after {} is not aligned to 60000ms",
+                    symbol,
+                    kline.open_time,
+                    expected - 60_000
+                )
+                .into());
+            }
+            if time_diff > 0 {
+                gaps.push((expected, kline.open_time));
+            }
+            expected = kline.open_time + 60_000;
+        }
+        if expected < block_end {
+            gaps.push((expected, block_end));
+        }
+        Ok(gaps)
+    }
+
+    /// Merges klines by `open_time`, newer data winning, sorted ascending.
+    pub fn merge_klines(existing: Vec<KLine>, new: Vec<KLine>) -> Vec<KLine> {
+        let mut merged: BTreeMap<i64, KLine> =
+            existing.into_iter().map(|k| (k.open_time, k)).collect();
+        merged.extend(new.into_iter().map(|k| (k.open_time, k)));
+        merged.into_values().collect()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(symbol, klines = data.len()))]
     pub fn process_data_chunk(
         symbol: &str,
         data: Vec<KLine>,
         db: &Database,
         dw: &mut DataWindow,
     ) -> Result<(), Box<dyn Error>> {
-        if data.len() < 1000 {
-            dw.recent_data = data;
-            println!("DataWindow.recent_data len {}", dw.recent_data.len());
+        let mut batch = WriteBatch::default();
+        Self::stage_data_chunk(symbol, data, db, dw, &mut batch)?;
+        Self::commit_batch(symbol, db, batch)
+    }
+
+    /// Replaces `dw.recent_data` with the tail saved by `Database::save_recent` and
+    /// merges it with the stored blocks: minutes of blocks completed meanwhile (by the
+    /// scheduler or a window that has closed) are committed, the rest stays in memory.
+    pub fn restore_recent(
+        symbol: &str,
+        db: &Database,
+        dw: &mut DataWindow,
+    ) -> Result<(), Box<dyn Error>> {
+        dw.recent_data.clear();
+        let saved = db.load_recent(symbol)?;
+        Self::process_data_chunk(symbol, saved, db, dw)
+    }
+
+    /// Commits staged blocks atomically and runs aggregation if anything was written.
+    fn commit_batch(symbol: &str, db: &Database, batch: WriteBatch) -> Result<(), Box<dyn Error>> {
+        if batch.is_empty() {
             return Ok(());
         }
-        for i in 1..data.len() {
-            let time_diff = data[i].open_time - data[i - 1].open_time;
-            if time_diff != 60_000 {
-                return Err(format!(
-                    "Consistency check failed for {}: gap between {} and {} is {}ms (expected 60000ms)",
-                    symbol,
-                    data[i-1].open_time,
-                    data[i].open_time,
-                    time_diff
-                ).into());
-            }
-        }
-        let compressed_data = compress::compress_klines(&data)?;
-        db.insert_block(symbol, data[0].open_time, &compressed_data)?;
+        db.apply_batch(batch)?;
 
         // Запускаем агрегацию после добавления новых данных
         if let Err(e) = db.aggregate_ohlcv_data(symbol) {
-            eprintln!("Warning: Failed to aggregate data for {}: {}", symbol, e);
+            app_error::report(AppError::Aggregation {
+                symbol: symbol.to_string(),
+                message: e.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Validates and merges fetched klines into `batch`; the incomplete tail
+    /// goes to `dw.recent_data` instead of the DB.
+    fn stage_data_chunk(
+        symbol: &str,
+        data: Vec<KLine>,
+        db: &Database,
+        dw: &mut DataWindow,
+        batch: &mut WriteBatch,
+    ) -> Result<(), Box<dyn Error>> {
+        Self::stage_klines(symbol, data, db, dw, batch, false)
+    }
+
+    /// `stage_data_chunk`; with `replace` the stored blocks are not read and merged,
+    /// the fetched klines alone become the block (re-fetch of a corrupted block).
+    fn stage_klines(
+        symbol: &str,
+        data: Vec<KLine>,
+        db: &Database,
+        dw: &mut DataWindow,
+        batch: &mut WriteBatch,
+        replace: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        // Fetched ranges may straddle block boundaries (e.g. a resumed sync)
+        let mut blocks: BTreeMap<i64, Vec<KLine>> = BTreeMap::new();
+        for kline in data {
+            blocks
+                .entry(db.block_start(kline.open_time))
+                .or_default()
+                .push(kline);
+        }
+
+        let now = Utc::now().timestamp_millis();
+        for (block_start, block_data) in blocks {
+            let block_end = block_start + db.block_size() as i64 * 60_000;
+            let existing = if replace {
+                None
+            } else {
+                match db.get_block(symbol, block_start)? {
+                    Some(compressed) => Some(compress::decompress_klines(&compressed)?),
+                    None => None,
+                }
+            };
+            // Re-fetched minutes replace stored/recent ones instead of duplicating them
+            let (recent_in_block, recent_rest): (Vec<KLine>, Vec<KLine>) =
+                std::mem::take(&mut dw.recent_data)
+                    .into_iter()
+                    .partition(|k| k.open_time >= block_start && k.open_time < block_end);
+            dw.recent_data = recent_rest;
+            let base = Self::merge_klines(existing.clone().unwrap_or_default(), recent_in_block);
+            let merged = Self::merge_klines(base, block_data);
+
+            // A short block is still complete when its whole window lies in the past
+            // (exchange downtime); otherwise it is the live tail kept in memory
+            let window_closed = now >= block_end + 60_000;
+            if merged.len() < db.block_size() && !window_closed {
+                dw.recent_data = Self::merge_klines(std::mem::take(&mut dw.recent_data), merged);
+                tracing::debug!("DataWindow.recent_data len {}", dw.recent_data.len());
+                continue;
+            }
+            if existing
+                .as_ref()
+                .is_some_and(|e| e.as_slice() == merged.as_slice())
+            {
+                // Idempotent re-insert, nothing new in this block
+                continue;
+            }
+
+            let gaps = Self::find_gaps(symbol, &merged, block_start, block_end)?;
+            batch.clear_gaps(db, symbol, block_start, block_end)?;
+            if !gaps.is_empty() {
+                for (from, to) in &gaps {
+                    tracing::info!(
+                        "Gap in {} data: {} minutes missing from {}",
+                        symbol,
+                        (to - from) / 60_000,
+                        from
+                    );
+                }
+                batch.insert_gaps(symbol, &gaps);
+            }
+            let compressed_data = compress::compress_klines(&merged)?;
+            batch.insert_block(symbol, block_start, &compressed_data);
         }
 
         Ok(())
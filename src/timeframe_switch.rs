@@ -0,0 +1,198 @@
+// timeframe_switch.rs - Keyboard timeframe switching: 1-9 hotkeys and typed timeframes ("15", "4h" + Enter), bar type menu
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::settings::{TIMEFRAME_HOTKEYS, TIMEFRAME_HOTKEY_DELAY_MS};
+use crate::timeframe::{BarType, MONTH_MINUTES};
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+/// Minutes of a typed timeframe: a number of minutes or with an `h`/`d`/`w` suffix;
+/// "1M" is the calendar month.
+pub fn parse_timeframe(text: &str) -> Option<i32> {
+    if text.trim() == "1M" {
+        return Some(MONTH_MINUTES);
+    }
+    let text = text.trim().to_lowercase();
+    let (number, multiplier) = match text.chars().last()? {
+        'h' => (&text[..text.len() - 1], 60),
+        'd' => (&text[..text.len() - 1], 1440),
+        'w' => (&text[..text.len() - 1], 10080),
+        'm' => (&text[..text.len() - 1], 1),
+        _ => (text.as_str(), 1),
+    };
+    number
+        .parse::<i32>()
+        .ok()
+        .filter(|n| *n > 0)
+        .and_then(|n| n.checked_mul(multiplier))
+}
+
+/// Short label of a timeframe in minutes: 15m, 4h, 1d, 1w, 1M.
+pub fn timeframe_label(minutes: i32) -> String {
+    match minutes {
+        MONTH_MINUTES => "1M".to_string(),
+        m if m % 10080 == 0 => format!("{}w", m / 10080),
+        m if m % 1440 == 0 => format!("{}d", m / 1440),
+        m if m % 60 == 0 => format!("{}h", m / 60),
+        m => format!("{}m", m),
+    }
+}
+
+/// Typed keys not yet applied. A single digit left alone for
+/// `TIMEFRAME_HOTKEY_DELAY_MS` selects `TIMEFRAME_HOTKEYS[digit - 1]`; anything
+/// longer is applied with Enter.
+#[derive(Default)]
+pub struct TimeframeSwitch {
+    typed: String,
+    last_key: Option<Instant>,
+}
+
+impl TimeframeSwitch {
+    /// Reads the chart keyboard input; returns the selected timeframe.
+    pub fn handle_input(&mut self, ctx: &egui::Context) -> Option<i32> {
+        if ctx.wants_keyboard_input() {
+            self.typed.clear();
+            return None;
+        }
+        let (text, enter, escape, backspace) = ctx.input(|i| {
+            let text: String = i
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Text(text) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            (
+                text,
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+                i.key_pressed(egui::Key::Backspace),
+            )
+        });
+        for c in text.chars() {
+            let accepted = c.is_ascii_digit()
+                || (!self.typed.is_empty() && "mhdwMHDW".contains(c) && !self.has_suffix());
+            if accepted {
+                self.typed.push(c);
+                self.last_key = Some(Instant::now());
+            }
+        }
+        if self.typed.is_empty() {
+            return None;
+        }
+        if escape {
+            self.typed.clear();
+            return None;
+        }
+        if backspace {
+            self.typed.pop();
+            return None;
+        }
+        if enter {
+            return parse_timeframe(&std::mem::take(&mut self.typed));
+        }
+        let expired = self
+            .last_key
+            .is_some_and(|t| t.elapsed() >= Duration::from_millis(TIMEFRAME_HOTKEY_DELAY_MS));
+        if expired && self.typed.len() == 1 {
+            let digit = self.typed.parse::<usize>().unwrap_or(0);
+            self.typed.clear();
+            return digit
+                .checked_sub(1)
+                .and_then(|i| TIMEFRAME_HOTKEYS.get(i))
+                .copied();
+        }
+        if self.typed.len() == 1 {
+            // ждем следующую цифру или истечение задержки
+            ctx.request_repaint_after(Duration::from_millis(TIMEFRAME_HOTKEY_DELAY_MS));
+        }
+        None
+    }
+
+    fn has_suffix(&self) -> bool {
+        self.typed
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_ascii_alphabetic())
+    }
+
+    /// Popup in the middle of the screen with the typed timeframe.
+    pub fn show_overlay(&self, ctx: &egui::Context) {
+        if self.typed.is_empty() {
+            return;
+        }
+        let hint = match parse_timeframe(&self.typed) {
+            Some(minutes) => format!("{}  →  {}", self.typed, timeframe_label(minutes)),
+            None => self.typed.clone(),
+        };
+        egui::Area::new(egui::Id::new("timeframe_switch"))
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(egui::RichText::new(hint).size(20.0));
+                    ui.label("Enter - apply, Esc - cancel");
+                });
+            });
+    }
+
+    /// Quick-switch menu listing the hotkeys.
+    pub fn show_menu(ui: &mut egui::Ui, current: i32) -> Option<i32> {
+        let mut selected = None;
+        for (index, minutes) in TIMEFRAME_HOTKEYS.iter().enumerate() {
+            let label = format!("{}   {}", index + 1, timeframe_label(*minutes));
+            if ui.selectable_label(*minutes == current, label).clicked() {
+                selected = Some(*minutes);
+                ui.close_menu();
+            }
+        }
+        if ui
+            .selectable_label(current == MONTH_MINUTES, "1M")
+            .clicked()
+        {
+            selected = Some(MONTH_MINUTES);
+            ui.close_menu();
+        }
+        ui.separator();
+        ui.label("Or type a timeframe (15, 4h, 1d, 1M) and press Enter");
+        selected
+    }
+
+    /// Bar type choice; `percent` is the Renko box / range size, applied when the
+    /// edit ends. Returns the bar type to switch to.
+    pub fn show_bar_type_menu(
+        ui: &mut egui::Ui,
+        current: BarType,
+        percent: &mut f64,
+    ) -> Option<BarType> {
+        let mut selected = None;
+        for bar_type in [
+            BarType::Time,
+            BarType::Renko(*percent),
+            BarType::Range(*percent),
+        ] {
+            if ui
+                .selectable_label(bar_type.label() == current.label(), bar_type.label())
+                .clicked()
+            {
+                selected = Some(bar_type);
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        let response = ui.add(
+            egui::DragValue::new(percent)
+                .range(0.01..=10.0)
+                .speed(0.01)
+                .suffix("% of price"),
+        );
+        if response.drag_stopped() || response.lost_focus() {
+            selected = match current {
+                BarType::Renko(_) => Some(BarType::Renko(*percent)),
+                BarType::Range(_) => Some(BarType::Range(*percent)),
+                BarType::Time => None,
+            };
+        }
+        selected
+    }
+}
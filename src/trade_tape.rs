@@ -0,0 +1,48 @@
+// trade_tape.rs - Recent trades tape (time, price, size, side) docked to the right of the chart
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::{format_price, AxisTimezone};
+use crate::crosshair::format_volume;
+use crate::ticker::Trade;
+use eframe::egui::{self, Color32, RichText};
+
+const BUY_COLOR: Color32 = Color32::from_rgb(80, 190, 110);
+const SELL_COLOR: Color32 = Color32::from_rgb(220, 90, 90);
+
+/// Side panel with `trades` (newest first); must be shown before the central panel.
+pub fn show(ctx: &egui::Context, trades: &[Trade], decimals: usize, time_zone: AxisTimezone) {
+    egui::SidePanel::right("trade_tape")
+        .resizable(false)
+        .default_width(190.0)
+        .show(ctx, |ui| {
+            ui.strong("Trades");
+            ui.separator();
+            if trades.is_empty() {
+                ui.weak("waiting for trades");
+                return;
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("trade_tape_rows")
+                    .num_columns(3)
+                    .spacing([8.0, 1.0])
+                    .show(ui, |ui| {
+                        for trade in trades {
+                            let color = if trade.sell { SELL_COLOR } else { BUY_COLOR };
+                            ui.weak(
+                                time_zone
+                                    .datetime(trade.time)
+                                    .format("%H:%M:%S")
+                                    .to_string(),
+                            );
+                            ui.label(
+                                RichText::new(format_price(trade.price, decimals))
+                                    .monospace()
+                                    .color(color),
+                            );
+                            ui.label(RichText::new(format_volume(trade.qty)).monospace());
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+}
@@ -0,0 +1,45 @@
+// visible_profile.rs - Volume profile of the visible bars as a horizontal histogram at the right edge of the price chart
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::render::Renderer;
+use crate::settings::{VISIBLE_PROFILE_BINS, VISIBLE_PROFILE_WIDTH};
+use crate::theme::Theme;
+use eframe::egui::{self, Rect};
+
+/// Bins grow leftwards from the right edge of `price_rect`; the point of control is
+/// drawn stronger.
+pub fn draw(
+    renderer: &mut impl Renderer,
+    price_rect: Rect,
+    data_window: &mut DataWindow,
+    scale_price: &impl Fn(f64) -> f32,
+    theme: &Theme,
+) {
+    let Some(profile) = data_window.visible_volume_profile(VISIBLE_PROFILE_BINS) else {
+        return;
+    };
+    let max_volume = profile.max_volume();
+    if max_volume <= 0.0 {
+        return;
+    }
+    let poc = profile.poc_index();
+    let max_width = price_rect.width() * VISIBLE_PROFILE_WIDTH;
+    for (bin, volume) in profile.bins.iter().enumerate() {
+        let (low, high) = profile.bin_range(bin);
+        let width = (volume / max_volume) as f32 * max_width;
+        let color = if Some(bin) == poc {
+            theme.volume_up.gamma_multiply(0.6)
+        } else {
+            theme.volume_up.gamma_multiply(0.3)
+        };
+        renderer.rect_filled(
+            Rect::from_min_max(
+                egui::pos2(price_rect.right() - width, scale_price(high)),
+                egui::pos2(price_rect.right(), scale_price(low) - 1.0),
+            ),
+            0.0,
+            color,
+        );
+    }
+}
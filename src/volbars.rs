@@ -1,12 +1,18 @@
 // volbars.rs
 use crate::datawindow::DataWindow;
 use crate::drawing_util;
+use crate::render::Renderer;
+use crate::theme::Theme;
 use eframe::egui;
 
-pub fn draw(ui: &mut egui::Ui, rect: egui::Rect, data_window: &mut DataWindow) {
-    let painter = ui.painter();
-    let up_color = egui::Color32::from_rgb(100, 180, 100);
-    let down_color = egui::Color32::from_rgb(180, 100, 100);
+pub fn draw(
+    renderer: &mut impl Renderer,
+    rect: egui::Rect,
+    data_window: &mut DataWindow,
+    theme: &Theme,
+) {
+    let up_color = theme.volume_up;
+    let down_color = theme.volume_down;
 
     let volume_height = rect.height() * data_window.volume_height_ratio;
     let vol_rect =
@@ -45,7 +51,7 @@ pub fn draw(ui: &mut egui::Ui, rect: egui::Rect, data_window: &mut DataWindow) {
             down_color
         };
 
-        painter.rect_filled(
+        renderer.rect_filled(
             egui::Rect::from_min_max(
                 egui::pos2(x_left, y_top),
                 egui::pos2(x_right, vol_rect.bottom()),
@@ -0,0 +1,90 @@
+// volume_profile.rs - Volume-by-price histogram calculation from 1m klines or chart bars
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::fetch::{volume_to_f64, KLine, PRICE_MULTIPLIER};
+use crate::timeframe::Bar;
+
+#[derive(Debug, Clone)]
+pub struct VolumeProfile {
+    pub price_low: f64,
+    pub bin_height: f64,
+    pub bins: Vec<f64>,
+}
+
+impl VolumeProfile {
+    /// Builds a profile from 1m klines, spreading each kline's volume evenly
+    /// across the price bins its low..high range touches.
+    pub fn from_klines(klines: &[KLine], bin_count: usize) -> Option<Self> {
+        let divider = 10f64.powi(PRICE_MULTIPLIER as i32);
+        let ranges = klines.iter().map(|k| {
+            (
+                k.low as f64 / divider,
+                k.high as f64 / divider,
+                volume_to_f64(k.volume),
+            )
+        });
+        Self::from_ranges(ranges, bin_count, 1.0 / divider)
+    }
+
+    /// Same from chart bars, e.g. the visible range.
+    pub fn from_bars(bars: &[Bar], bin_count: usize) -> Option<Self> {
+        let ranges = bars.iter().map(|b| (b.low, b.high, b.volume));
+        Self::from_ranges(ranges, bin_count, f64::EPSILON)
+    }
+
+    /// (low, high, volume) items; bins are at least `min_bin_height` high.
+    fn from_ranges(
+        ranges: impl Iterator<Item = (f64, f64, f64)> + Clone,
+        bin_count: usize,
+        min_bin_height: f64,
+    ) -> Option<Self> {
+        let low = ranges.clone().map(|r| r.0).reduce(f64::min)?;
+        let high = ranges.clone().map(|r| r.1).reduce(f64::max)?;
+        let bin_count = bin_count.max(1);
+        let bin_height = ((high - low) / bin_count as f64).max(min_bin_height);
+        let mut bins = vec![0.0; bin_count];
+
+        for (r_low, r_high, volume) in ranges {
+            let first = (((r_low - low) / bin_height) as usize).min(bin_count - 1);
+            let last = (((r_high - low) / bin_height) as usize).min(bin_count - 1);
+            let share = volume / (last - first + 1) as f64;
+            for bin in &mut bins[first..=last] {
+                *bin += share;
+            }
+        }
+
+        Some(Self {
+            price_low: low,
+            bin_height,
+            bins,
+        })
+    }
+
+    pub fn max_volume(&self) -> f64 {
+        self.bins.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// Index of the bin with the highest volume (point of control).
+    pub fn poc_index(&self) -> Option<usize> {
+        self.bins
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Volume of the bin containing `price`, `None` outside the profile.
+    pub fn volume_at(&self, price: f64) -> Option<f64> {
+        let offset = (price - self.price_low) / self.bin_height;
+        if offset < 0.0 {
+            return None;
+        }
+        self.bins.get(offset as usize).copied()
+    }
+
+    /// Price range `(low, high)` covered by a bin.
+    pub fn bin_range(&self, index: usize) -> (f64, f64) {
+        let low = self.price_low + index as f64 * self.bin_height;
+        (low, low + self.bin_height)
+    }
+}
@@ -0,0 +1,59 @@
+// vwap.rs - VWAP (Volume Weighted Average Price) calculation: anchored at a bar or reset every session
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::timeframe::Bar;
+
+/// Typical price used as the VWAP weight base: (high + low + close) / 3.
+pub fn typical_price(bar: &Bar) -> f64 {
+    (bar.high + bar.low + bar.close) / 3.0
+}
+
+/// Calculates VWAP anchored at `anchor_index` and running to the end of `bars`.
+///
+/// Returns one value per bar starting at `anchor_index`; `None` entries appear
+/// while the accumulated volume is still zero.
+pub fn anchored_vwap(bars: &[Bar], anchor_index: usize) -> Vec<Option<f64>> {
+    let mut result = Vec::with_capacity(bars.len().saturating_sub(anchor_index));
+    let mut cum_pv = 0.0;
+    let mut cum_volume = 0.0;
+
+    for bar in bars.iter().skip(anchor_index) {
+        cum_pv += typical_price(bar) * bar.volume;
+        cum_volume += bar.volume;
+        result.push(if cum_volume > 0.0 {
+            Some(cum_pv / cum_volume)
+        } else {
+            None
+        });
+    }
+
+    result
+}
+
+/// VWAP restarting at every session: sessions of `session_hours` hours counted from
+/// `start_hour` UTC (24 - daily). Weighted by the minute typical prices in `Bar::pv`;
+/// NaN while the session has no volume.
+pub fn session_vwap(bars: &[Bar], session_hours: usize, start_hour: i64) -> Vec<f64> {
+    let session_ms = session_hours.max(1) as i64 * 3_600_000;
+    let offset = start_hour * 3_600_000;
+    let mut current_session = None;
+    let mut cum_pv = 0.0;
+    let mut cum_volume = 0.0;
+    bars.iter()
+        .map(|bar| {
+            let session = (bar.time - offset).div_euclid(session_ms);
+            if current_session != Some(session) {
+                current_session = Some(session);
+                cum_pv = 0.0;
+                cum_volume = 0.0;
+            }
+            cum_pv += bar.pv;
+            cum_volume += bar.volume;
+            if cum_volume > 0.0 {
+                cum_pv / cum_volume
+            } else {
+                f64::NAN
+            }
+        })
+        .collect()
+}
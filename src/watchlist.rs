@@ -0,0 +1,194 @@
+// watchlist.rs - Watchlist side panel: user symbols with last price and 24h change, click switches the chart
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::axes_util::format_price;
+use crate::db::Database;
+use crate::exchange::Market;
+use crate::fetch::{self, DayTicker};
+use crate::settings::WATCHLIST_POLL_SECS;
+use crate::symbol_info::guess_price_decimals;
+use eframe::egui::{self, Color32, RichText};
+use std::collections::HashMap;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const UP_COLOR: Color32 = Color32::from_rgb(80, 190, 110);
+const DOWN_COLOR: Color32 = Color32::from_rgb(220, 90, 90);
+
+#[derive(Default)]
+pub struct Watchlist {
+    pub open: bool,
+    symbols: Vec<String>,
+    loaded: bool,
+    quotes: HashMap<String, DayTicker>,
+    job: Option<JoinHandle<Result<Vec<DayTicker>, String>>>,
+    polled: Option<Instant>,
+    error: Option<String>,
+    adding: String,
+}
+
+impl Watchlist {
+    /// Loads the stored symbols once and polls the quotes every `WATCHLIST_POLL_SECS`
+    /// in a background thread while the panel is open.
+    pub fn refresh(&mut self, db: &Database) {
+        if !self.open {
+            return;
+        }
+        if !self.loaded {
+            self.loaded = true;
+            match db.get_watchlist() {
+                Ok(symbols) => self.symbols = symbols,
+                Err(e) => tracing::warn!("Unable to load the watchlist: {}", e),
+            }
+        }
+        if self.job.as_ref().is_some_and(|job| job.is_finished()) {
+            let result = self.job.take().map(|job| {
+                job.join()
+                    .unwrap_or_else(|_| Err("watchlist thread panicked".into()))
+            });
+            match result {
+                Some(Ok(quotes)) => {
+                    self.error = None;
+                    self.quotes = quotes.into_iter().map(|q| (q.symbol.clone(), q)).collect();
+                }
+                Some(Err(e)) => self.error = Some(e),
+                None => {}
+            }
+        }
+        let due = self
+            .polled
+            .is_none_or(|at| at.elapsed() >= Duration::from_secs(WATCHLIST_POLL_SECS));
+        if self.job.is_none() && due && !self.symbols.is_empty() {
+            let symbols = self.symbols.clone();
+            self.job = Some(std::thread::spawn(move || {
+                fetch_quotes(&reqwest::blocking::Client::new(), &symbols).map_err(|e| e.to_string())
+            }));
+            self.polled = Some(Instant::now());
+        }
+    }
+
+    fn save(&mut self, db: &Database) {
+        if let Err(e) = db.set_watchlist(&self.symbols) {
+            tracing::warn!("Unable to save the watchlist: {}", e);
+        }
+        // новые символы получают котировки сразу
+        self.polled = None;
+    }
+
+    /// Side panel with the symbols; must be shown before the central panel. Returns
+    /// the symbol of a clicked row.
+    pub fn show(&mut self, ctx: &egui::Context, db: &Database, current: &str) -> Option<String> {
+        let mut picked = None;
+        let mut remove = None;
+        let mut add = None;
+        egui::SidePanel::left("watchlist")
+            .resizable(false)
+            .default_width(210.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Watchlist");
+                    if self.job.is_some() {
+                        ui.spinner();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.adding)
+                            .hint_text("symbol")
+                            .desired_width(110.0),
+                    );
+                    let entered =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("add").clicked() || entered {
+                        let symbol = self.adding.trim().to_uppercase();
+                        if !symbol.is_empty() {
+                            add = Some(symbol);
+                        }
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.symbols.iter().any(|s| s == current),
+                            egui::Button::new("+ chart"),
+                        )
+                        .clicked()
+                    {
+                        add = Some(current.to_string());
+                    }
+                });
+                if let Some(e) = &self.error {
+                    ui.colored_label(DOWN_COLOR, e);
+                }
+                ui.separator();
+                egui::Grid::new("watchlist_rows")
+                    .num_columns(4)
+                    .spacing([8.0, 2.0])
+                    .show(ui, |ui| {
+                        for symbol in &self.symbols {
+                            if ui.selectable_label(symbol == current, symbol).clicked() {
+                                picked = Some(symbol.clone());
+                            }
+                            match self.quotes.get(symbol) {
+                                Some(quote) => {
+                                    let decimals = guess_price_decimals(quote.last_price);
+                                    ui.label(
+                                        RichText::new(format_price(quote.last_price, decimals))
+                                            .monospace(),
+                                    );
+                                    let color = if quote.change_percent >= 0.0 {
+                                        UP_COLOR
+                                    } else {
+                                        DOWN_COLOR
+                                    };
+                                    ui.label(
+                                        RichText::new(format!("{:+.2}%", quote.change_percent))
+                                            .monospace()
+                                            .color(color),
+                                    );
+                                }
+                                None => {
+                                    ui.weak("-");
+                                    ui.weak("-");
+                                }
+                            }
+                            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                                remove = Some(symbol.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(symbol) = add.filter(|s| !self.symbols.contains(s)) {
+            self.symbols.push(symbol);
+            self.adding.clear();
+            self.save(db);
+        }
+        if let Some(symbol) = remove {
+            self.symbols.retain(|s| *s != symbol);
+            self.quotes.remove(&symbol);
+            self.save(db);
+        }
+        picked
+    }
+}
+
+/// Spot pairs in one 24h ticker request, futures (`F:`) pairs one by one from the
+/// futures API; quotes are keyed by the chart symbol.
+fn fetch_quotes(
+    client: &reqwest::blocking::Client,
+    symbols: &[String],
+) -> Result<Vec<DayTicker>, Box<dyn std::error::Error>> {
+    let mut spot = Vec::new();
+    let mut quotes = Vec::new();
+    for symbol in symbols {
+        match Market::split(symbol) {
+            (Market::Spot, pair) => spot.push(pair.to_string()),
+            (Market::Futures, pair) => {
+                let mut quote = fetch::fetch_futures_day_ticker(client, pair)?;
+                quote.symbol = symbol.clone();
+                quotes.push(quote);
+            }
+        }
+    }
+    quotes.extend(fetch::fetch_day_tickers(client, &spot)?);
+    Ok(quotes)
+}
@@ -0,0 +1,79 @@
+// zoom_select.rs - Zoom-to-selection: drag on the time axis (or shift+drag on the chart) sets the visible range
+// See CONVENTIONS.md for project structure and workflow
+
+use crate::datawindow::DataWindow;
+use crate::drawing_util::bar_index_at_x;
+use eframe::egui::{self, Color32, Rect, Stroke};
+
+#[derive(Default)]
+pub struct ZoomSelection {
+    // x координаты начала и текущего конца выделения
+    span: Option<(f32, f32)>,
+}
+
+impl ZoomSelection {
+    /// A drag starting on `axis_rect`, or in `rect` with shift held, selects a span of
+    /// bars; on release the visible range becomes exactly the selected bars. Returns
+    /// `true` while the input is used by the selection.
+    pub fn handle_input(
+        &mut self,
+        response: &egui::Response,
+        rect: Rect,
+        axis_rect: Rect,
+        data_window: &mut DataWindow,
+    ) -> bool {
+        if response.drag_started() {
+            let (origin, shift) = response
+                .ctx
+                .input(|i| (i.pointer.press_origin(), i.modifiers.shift));
+            self.span = origin
+                .filter(|pos| axis_rect.contains(*pos) || (shift && rect.contains(*pos)))
+                .map(|pos| (pos.x, pos.x));
+        }
+        let Some((from, to)) = self.span else {
+            return false;
+        };
+        let x = response
+            .interact_pointer_pos()
+            .map_or(to, |pos| pos.x.clamp(rect.left(), rect.right() - 1.0));
+        if response.dragged() {
+            self.span = Some((from, x));
+            return true;
+        }
+        self.span = None;
+        let index_at = |x: f32| {
+            bar_index_at_x(
+                x,
+                data_window.visible_range,
+                data_window.bars.len(),
+                rect,
+                data_window.pixel_offset,
+            )
+        };
+        if let (Some(a), Some(b)) = (index_at(from.min(x)), index_at(from.max(x))) {
+            // как и при зуме колесом, не меньше двух баров
+            let end = (b as i64 + 1)
+                .max(a as i64 + 2)
+                .min(data_window.bars.len() as i64);
+            data_window.visible_range = ((end - 2).min(a as i64).max(0), end);
+            data_window.pixel_offset = 0.0;
+        }
+        true
+    }
+
+    /// Shaded band over the selected span.
+    pub fn draw(&self, painter: &egui::Painter, rect: Rect) {
+        let Some((from, to)) = self.span else {
+            return;
+        };
+        let band = Rect::from_x_y_ranges(from.min(to)..=from.max(to), rect.y_range());
+        let color = Color32::from_rgb(100, 150, 230);
+        painter.rect_filled(band, 0.0, color.gamma_multiply(0.15));
+        for x in [band.left(), band.right()] {
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                Stroke::new(1.0, color),
+            );
+        }
+    }
+}